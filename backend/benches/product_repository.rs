@@ -0,0 +1,162 @@
+//! Compares loading products through the SQLite repository with the old way of loading them, which queried the
+//! barcodes and stash items of every product separately. Run with `cargo bench`
+
+use actix_web::rt::Runtime;
+use chrono::{Days, NaiveDate};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rsstash::{
+    domain::{
+        entities::{Product, StashItem},
+        repositories::ProductRepository as ProductRepositoryTrait,
+        value_objects::{Brand, ExpiryDateKind, ProductId, Quantity, Unit},
+    },
+    infrastructure::persistence::sqlite::{
+        db::setup_db,
+        pool::{create_pool, ConnectionManager, Pool},
+        ProductRepository,
+    },
+};
+use rusqlite::named_params;
+use uuid::Uuid;
+
+/// Number of stash items each product gets
+const STASH_ITEMS_PER_PRODUCT: u64 = 5;
+
+/// Makes a repository holding the given number of products, each with a barcode and some stash items
+fn setup(runtime: &Runtime, products: usize) -> (Pool, ProductRepository) {
+    let pool = create_pool(ConnectionManager::memory(), 1).unwrap();
+    setup_db(&pool.get().unwrap()).unwrap();
+    let repository = ProductRepository::new(pool.clone());
+
+    let first_expiry_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let products = (0..products)
+        .map(|i| {
+            let stash_items = (0..STASH_ITEMS_PER_PRODUCT)
+                .map(|day| {
+                    StashItem::new(
+                        Uuid::new_v4(),
+                        1.try_into().unwrap(),
+                        first_expiry_date.checked_add_days(Days::new(day)),
+                    )
+                })
+                .collect();
+            let mut product = Product::new(
+                format!("{:013}", i).parse().unwrap(),
+                "Brand".parse().unwrap(),
+                format!("Product {}", i),
+                stash_items,
+            );
+            product.attach_barcode(format!("B{:012}", i).parse().unwrap());
+
+            product
+        })
+        .collect();
+    runtime.block_on(repository.save_all(products)).unwrap();
+
+    (pool, repository)
+}
+
+/// Loads all products with stash items the way the repository used to: collect the keys, then query each product,
+/// its barcodes and its stash items one at a time with freshly prepared statements
+fn find_all_with_stash_items_one_by_one(pool: &Pool) -> Vec<Product> {
+    let mut conn = pool.get().unwrap();
+    let tx = conn.transaction().unwrap();
+
+    let keys = tx
+        .prepare("SELECT DISTINCT product_key FROM stash_items")
+        .unwrap()
+        .query_map([], |row| row.get::<_, i64>(0))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let mut products = vec![];
+    for key in keys {
+        let mut product = tx
+            .prepare("SELECT id, brand, name, unit FROM products WHERE key = :key")
+            .unwrap()
+            .query_row(named_params! { ":key": key }, |row| {
+                let mut product = Product::new(
+                    row.get::<_, ProductId>("id")?,
+                    row.get::<_, Brand>("brand")?,
+                    row.get::<_, String>("name")?,
+                    vec![],
+                );
+                product.set_unit(row.get::<_, Unit>("unit")?);
+
+                Ok(product)
+            })
+            .unwrap();
+
+        let barcodes = tx
+            .prepare("SELECT barcode FROM product_barcodes WHERE product_key = :product_key")
+            .unwrap()
+            .query_map(named_params! { ":product_key": key }, |row| {
+                row.get::<_, ProductId>("barcode")
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        for barcode in barcodes {
+            product.attach_barcode(barcode);
+        }
+
+        let stash_items = tx
+            .prepare("SELECT id, quantity, expiry_date, expiry_date_kind, opened_on FROM stash_items WHERE product_key = :product_key")
+            .unwrap()
+            .query_map(named_params! { ":product_key": key }, |row| {
+                let mut stash_item = StashItem::new(
+                    row.get::<_, String>("id")?.parse().unwrap(),
+                    row.get::<_, Quantity>("quantity")?,
+                    row.get::<_, Option<NaiveDate>>("expiry_date")?,
+                );
+                stash_item.set_expiry_date_kind(row.get::<_, ExpiryDateKind>("expiry_date_kind")?);
+                stash_item.set_opened_on(row.get::<_, Option<NaiveDate>>("opened_on")?);
+
+                Ok(stash_item)
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        for stash_item in stash_items {
+            product.add_stash_item(stash_item).unwrap();
+        }
+
+        products.push(product);
+    }
+
+    tx.commit().unwrap();
+
+    products
+}
+
+fn find_all_with_stash_items(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_all_with_stash_items");
+    let runtime = Runtime::new().unwrap();
+
+    for products in [100, 1000] {
+        let (pool, repository) = setup(&runtime, products);
+
+        group.bench_with_input(
+            BenchmarkId::new("one_by_one", products),
+            &pool,
+            |b, pool| b.iter(|| find_all_with_stash_items_one_by_one(pool)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("set_based", products),
+            &repository,
+            |b, repository| {
+                b.iter(|| {
+                    runtime
+                        .block_on(repository.find_all_with_stash_items())
+                        .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, find_all_with_stash_items);
+criterion_main!(benches);
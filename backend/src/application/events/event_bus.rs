@@ -0,0 +1,285 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use getset::Getters;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::StashEvent;
+
+/// An event as it was published, along with the ID subscriptions are resumed by
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct PublishedEvent {
+    /// ID of the event. IDs are increasing, in the order events are published
+    #[getset(get = "pub")]
+    id: u64,
+
+    #[getset(get = "pub")]
+    event: StashEvent,
+}
+
+/// What a subscription delivers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    /// An event published since the subscription started, or since the event it was resumed from
+    Event(PublishedEvent),
+    /// Events were published which the subscription can not deliver, so the subscriber has to catch up by other means
+    Missed,
+}
+
+/// Events published most recently, to resume subscriptions from
+struct History {
+    /// Oldest first
+    events: VecDeque<PublishedEvent>,
+    /// ID of the event published last
+    last_id: u64,
+}
+
+/// Publishes events to everyone subscribed, remembering the most recent ones so subscriptions can be resumed
+pub struct EventBus {
+    history: Mutex<History>,
+    sender: broadcast::Sender<PublishedEvent>,
+    /// Number of events remembered
+    capacity: usize,
+}
+
+impl EventBus {
+    /// Creates an event bus remembering the given number of events. Subscribers falling further behind than that miss
+    /// events
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+
+        Self {
+            history: Mutex::new(History {
+                events: VecDeque::with_capacity(capacity),
+                last_id: 0,
+            }),
+            sender,
+            capacity,
+        }
+    }
+
+    /// Publishes an event to everyone subscribed
+    ///
+    /// # Returns
+    /// The ID of the published event
+    pub fn publish(&self, event: StashEvent) -> u64 {
+        let mut history = self.history.lock().unwrap();
+
+        history.last_id += 1;
+        let published = PublishedEvent {
+            id: history.last_id,
+            event,
+        };
+
+        if history.events.len() == self.capacity {
+            history.events.pop_front();
+        }
+        if self.capacity > 0 {
+            history.events.push_back(published.clone());
+        }
+
+        // Sending while holding the lock keeps subscriptions from getting events twice, or not at all. It only fails
+        // when nobody is subscribed
+        let _ = self.sender.send(published);
+
+        history.last_id
+    }
+
+    /// Subscribes to the events published from now on
+    ///
+    /// # Parameters
+    /// - `last_event_id`: ID of the last event seen, to also get the events published since. The subscription starts
+    ///   with a [`Delivery::Missed`] if some of them are no longer remembered
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> Subscription {
+        let history = self.history.lock().unwrap();
+        let receiver = self.sender.subscribe();
+
+        let oldest_id = history
+            .events
+            .front()
+            .map_or(history.last_id + 1, |event| event.id);
+        let (missed, replay) = match last_event_id {
+            None => (false, VecDeque::new()),
+            // The ID may also be from before a restart, when IDs started over
+            Some(last_event_id)
+                if last_event_id + 1 < oldest_id || last_event_id > history.last_id =>
+            {
+                (true, VecDeque::new())
+            }
+            Some(last_event_id) => (
+                false,
+                history
+                    .events
+                    .iter()
+                    .filter(|event| event.id > last_event_id)
+                    .cloned()
+                    .collect(),
+            ),
+        };
+
+        Subscription {
+            missed,
+            replay,
+            receiver,
+        }
+    }
+}
+
+/// A subscription to the events published to an [`EventBus`]
+pub struct Subscription {
+    /// Whether to start by telling events were missed
+    missed: bool,
+    /// Events published before the subscription started, which are delivered first
+    replay: VecDeque<PublishedEvent>,
+    receiver: broadcast::Receiver<PublishedEvent>,
+}
+
+impl Subscription {
+    /// Waits for what to deliver next
+    ///
+    /// # Returns
+    /// * `Some(delivery)` with the next event, or with the news that events were missed
+    /// * `None` if the event bus is gone
+    pub async fn next(&mut self) -> Option<Delivery> {
+        if std::mem::take(&mut self.missed) {
+            return Some(Delivery::Missed);
+        }
+
+        if let Some(event) = self.replay.pop_front() {
+            return Some(Delivery::Event(event));
+        }
+
+        match self.receiver.recv().await {
+            Ok(event) => Some(Delivery::Event(event)),
+            Err(RecvError::Lagged(_)) => Some(Delivery::Missed),
+            Err(RecvError::Closed) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn event() -> StashEvent {
+        StashEvent::StashItemRemoved {
+            product_id: "ID".parse().unwrap(),
+            stash_item_id: Uuid::new_v4(),
+        }
+    }
+
+    /// Publishes events, returning them as published
+    fn publish(event_bus: &EventBus, count: usize) -> Vec<PublishedEvent> {
+        (0..count)
+            .map(|_| {
+                let event = event();
+                let id = event_bus.publish(event.clone());
+                PublishedEvent { id, event }
+            })
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_subscribe() {
+        let event_bus = EventBus::new(4);
+        publish(&event_bus, 2);
+
+        let mut subscription = event_bus.subscribe(None);
+        let published = publish(&event_bus, 2);
+
+        assert_eq!(
+            subscription.next().await,
+            Some(Delivery::Event(published[0].clone()))
+        );
+        assert_eq!(
+            subscription.next().await,
+            Some(Delivery::Event(published[1].clone()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_ids_increase() {
+        let event_bus = EventBus::new(4);
+
+        let published = publish(&event_bus, 3);
+
+        assert_eq!(
+            published
+                .iter()
+                .map(|event| *event.id())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_resume() {
+        let event_bus = EventBus::new(4);
+        let published = publish(&event_bus, 3);
+
+        let mut subscription = event_bus.subscribe(Some(1));
+        let live = publish(&event_bus, 1);
+
+        assert_eq!(
+            subscription.next().await,
+            Some(Delivery::Event(published[1].clone()))
+        );
+        assert_eq!(
+            subscription.next().await,
+            Some(Delivery::Event(published[2].clone()))
+        );
+        assert_eq!(
+            subscription.next().await,
+            Some(Delivery::Event(live[0].clone()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_resume_from_forgotten_event() {
+        let event_bus = EventBus::new(2);
+        publish(&event_bus, 4);
+
+        let mut subscription = event_bus.subscribe(Some(1));
+        let live = publish(&event_bus, 1);
+
+        assert_eq!(subscription.next().await, Some(Delivery::Missed));
+        assert_eq!(
+            subscription.next().await,
+            Some(Delivery::Event(live[0].clone()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_resume_from_oldest_remembered_event() {
+        let event_bus = EventBus::new(2);
+        let published = publish(&event_bus, 4);
+
+        let mut subscription = event_bus.subscribe(Some(2));
+
+        assert_eq!(
+            subscription.next().await,
+            Some(Delivery::Event(published[2].clone()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_resume_from_unknown_event() {
+        let event_bus = EventBus::new(4);
+        publish(&event_bus, 1);
+
+        let mut subscription = event_bus.subscribe(Some(7));
+
+        assert_eq!(subscription.next().await, Some(Delivery::Missed));
+    }
+
+    #[actix_web::test]
+    async fn test_lagging_subscription_misses_events() {
+        let event_bus = EventBus::new(2);
+
+        let mut subscription = event_bus.subscribe(None);
+        publish(&event_bus, 3);
+
+        assert_eq!(subscription.next().await, Some(Delivery::Missed));
+    }
+}
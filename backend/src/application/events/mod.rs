@@ -0,0 +1,7 @@
+mod event_bus;
+mod product_event_forwarder;
+mod stash_event;
+
+pub use event_bus::{Delivery, EventBus, PublishedEvent, Subscription};
+pub use product_event_forwarder::ProductEventForwarder;
+pub use stash_event::StashEvent;
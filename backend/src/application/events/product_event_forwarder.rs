@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use crate::domain::events::{ProductEvent, ProductEventHandler};
+
+use super::{EventBus, StashEvent};
+
+/// Publishes the events of products to an [`EventBus`] once they are saved, so whoever displays the stash learns of
+/// the changes to its stash items
+pub struct ProductEventForwarder {
+    event_bus: Arc<EventBus>,
+}
+
+impl ProductEventForwarder {
+    /// Creates a forwarder publishing to the given event bus
+    pub fn new(event_bus: Arc<EventBus>) -> Self {
+        Self { event_bus }
+    }
+}
+
+impl ProductEventHandler for ProductEventForwarder {
+    fn handle(&self, event: &ProductEvent) {
+        self.event_bus.publish(StashEvent::from(event.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{application::events::Delivery, domain::entities::FakeStashItem};
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_handle_publishes_event() {
+        let event_bus = Arc::new(EventBus::new(4));
+        let mut subscription = event_bus.subscribe(None);
+        let forwarder = ProductEventForwarder::new(event_bus);
+        let stash_item = FakeStashItem::new().build();
+
+        forwarder.handle(&ProductEvent::StashItemAdded {
+            product_id: "ID".parse().unwrap(),
+            stash_item: stash_item.clone(),
+        });
+
+        let Some(Delivery::Event(published)) = subscription.next().await else {
+            panic!("No event published");
+        };
+        assert_eq!(
+            published.event(),
+            &StashEvent::StashItemAdded {
+                product_id: "ID".parse().unwrap(),
+                stash_item,
+            }
+        );
+    }
+}
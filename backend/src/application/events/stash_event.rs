@@ -0,0 +1,169 @@
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::{Product, StashItem},
+    events::ProductEvent,
+    value_objects::{ExpiryDateKind, ProductId, Quantity},
+};
+
+/// Something which changed in the stash, published to keep everyone displaying it up to date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StashEvent {
+    /// A product was created, or restored from the trash
+    ProductCreated { product: Box<Product> },
+    /// A product changed as a whole, like when it was edited or its stash items were split
+    ProductUpdated { product: Box<Product> },
+    /// A product was deleted, along with its stash items
+    ProductDeleted { product_id: ProductId },
+    /// A stash item was added to a product, or restored from the trash
+    StashItemAdded {
+        product_id: ProductId,
+        stash_item: StashItem,
+    },
+    /// A stash item was removed from a product
+    StashItemRemoved {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+    },
+    /// The quantity of a stash item changed
+    QuantityChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: Quantity,
+        to: Quantity,
+    },
+    /// The expiry date of a stash item changed
+    ExpiryDateChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    /// What the expiry date of a stash item means changed
+    ExpiryDateKindChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: ExpiryDateKind,
+        to: ExpiryDateKind,
+    },
+    /// The date a stash item was opened on changed
+    OpenedOnChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+}
+
+impl From<ProductEvent> for StashEvent {
+    fn from(event: ProductEvent) -> Self {
+        match event {
+            ProductEvent::StashItemAdded {
+                product_id,
+                stash_item,
+            } => Self::StashItemAdded {
+                product_id,
+                stash_item,
+            },
+            ProductEvent::StashItemRemoved {
+                product_id,
+                stash_item,
+            } => Self::StashItemRemoved {
+                product_id,
+                stash_item_id: *stash_item.id(),
+            },
+            ProductEvent::QuantityChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::QuantityChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            },
+            ProductEvent::ExpiryDateChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::ExpiryDateChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            },
+            ProductEvent::ExpiryDateKindChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::ExpiryDateKindChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            },
+            ProductEvent::OpenedOnChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::OpenedOnChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::entities::FakeStashItem;
+
+    use super::*;
+
+    #[test]
+    fn test_from_stash_item_removed() {
+        let stash_item = FakeStashItem::new().build();
+
+        let event = StashEvent::from(ProductEvent::StashItemRemoved {
+            product_id: "ID".parse().unwrap(),
+            stash_item: stash_item.clone(),
+        });
+
+        assert_eq!(
+            event,
+            StashEvent::StashItemRemoved {
+                product_id: "ID".parse().unwrap(),
+                stash_item_id: *stash_item.id(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_quantity_changed() {
+        let stash_item_id = Uuid::new_v4();
+
+        let event = StashEvent::from(ProductEvent::QuantityChanged {
+            product_id: "ID".parse().unwrap(),
+            stash_item_id,
+            from: Quantity::new(2).unwrap(),
+            to: Quantity::new(1).unwrap(),
+        });
+
+        assert_eq!(
+            event,
+            StashEvent::QuantityChanged {
+                product_id: "ID".parse().unwrap(),
+                stash_item_id,
+                from: Quantity::new(2).unwrap(),
+                to: Quantity::new(1).unwrap(),
+            }
+        );
+    }
+}
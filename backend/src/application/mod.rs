@@ -1,2 +1,4 @@
+pub mod events;
+pub mod queries;
 pub mod services;
 pub mod use_cases;
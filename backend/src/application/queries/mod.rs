@@ -0,0 +1,16 @@
+mod stash_item_cursor;
+mod stash_item_filter;
+mod stash_item_page;
+mod stash_item_query;
+mod stash_item_sort;
+mod stash_item_view;
+
+pub use stash_item_cursor::StashItemCursor;
+pub use stash_item_filter::StashItemFilter;
+pub use stash_item_page::StashItemPage;
+pub use stash_item_query::StashItemQuery;
+pub use stash_item_sort::StashItemSort;
+pub use stash_item_view::StashItemView;
+
+#[cfg(test)]
+pub use stash_item_query::MockStashItemQuery;
@@ -0,0 +1,19 @@
+use getset::Getters;
+use uuid::Uuid;
+
+/// Position in the results of a [`StashItemQuery`](super::StashItemQuery), just after the last stash item of a page
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct StashItemCursor {
+    /// Value of the sort key of the last stash item
+    key: String,
+    /// ID of the last stash item
+    id: Uuid,
+}
+
+impl StashItemCursor {
+    /// Creates a new [`StashItemCursor`]
+    pub fn new(key: String, id: Uuid) -> Self {
+        Self { key, id }
+    }
+}
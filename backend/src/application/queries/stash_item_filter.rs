@@ -0,0 +1,16 @@
+use chrono::NaiveDate;
+
+use crate::domain::value_objects::{Brand, ProductId};
+
+/// Which stash items a [`StashItemQuery`](super::StashItemQuery) includes. Unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StashItemFilter {
+    /// Only include stash items of the product with this ID or barcode
+    pub product_id: Option<ProductId>,
+    /// Only include stash items of products of this brand
+    pub brand: Option<Brand>,
+    /// Only include stash items expiring on or after this date
+    pub expiring_after: Option<NaiveDate>,
+    /// Only include stash items expiring before this date
+    pub expiring_before: Option<NaiveDate>,
+}
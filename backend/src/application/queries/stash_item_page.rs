@@ -0,0 +1,23 @@
+use getset::Getters;
+
+use super::{StashItemCursor, StashItemView};
+
+/// A page of stash items from a [`StashItemQuery`](super::StashItemQuery)
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct StashItemPage {
+    /// The stash items on the page
+    stash_items: Vec<StashItemView>,
+    /// Where the next page starts. `None` on the last page
+    next_cursor: Option<StashItemCursor>,
+}
+
+impl StashItemPage {
+    /// Creates a new [`StashItemPage`]
+    pub fn new(stash_items: Vec<StashItemView>, next_cursor: Option<StashItemCursor>) -> Self {
+        Self {
+            stash_items,
+            next_cursor,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+
+use crate::domain::errors::ProductRepositoryError;
+
+use super::{StashItemCursor, StashItemFilter, StashItemPage, StashItemSort};
+
+/// Read model listing stash items directly, without loading the products they belong to
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait StashItemQuery: Sync + Send {
+    /// Finds a page of stash items matching a filter
+    ///
+    /// # Parameters
+    /// * `filter` - Which stash items to include
+    /// * `sort` - Order of the stash items
+    /// * `cursor` - Where the previous page ended, if this is not the first page
+    /// * `limit` - Maximum number of stash items on the page
+    ///
+    /// # Returns
+    /// * `Ok(page)` with the stash items, and a cursor to the next page if there are more
+    /// * `Err(_)` if the query fails
+    async fn find_stash_items(
+        &self,
+        filter: &StashItemFilter,
+        sort: StashItemSort,
+        cursor: Option<StashItemCursor>,
+        limit: usize,
+    ) -> Result<StashItemPage, ProductRepositoryError>;
+}
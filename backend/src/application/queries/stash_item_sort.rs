@@ -0,0 +1,11 @@
+/// Order of the stash items from a [`StashItemQuery`](super::StashItemQuery). Ties are broken by stash item ID
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StashItemSort {
+    /// Soonest effective expiry date first. Stash items which do not expire come last
+    #[default]
+    ExpiryDate,
+    /// Alphabetically by product name
+    Name,
+    /// Alphabetically by brand
+    Brand,
+}
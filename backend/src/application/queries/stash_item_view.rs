@@ -0,0 +1,41 @@
+use chrono::NaiveDate;
+use getset::Getters;
+
+use crate::domain::{
+    entities::StashItem,
+    value_objects::{Brand, ProductId, Unit},
+};
+
+/// A stash item along with the product info needed to show it on its own
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct StashItemView {
+    product_id: ProductId,
+    brand: Brand,
+    name: String,
+    unit: Unit,
+    stash_item: StashItem,
+    /// Date the stash item actually expires, taking opening into account
+    effective_expiry_date: Option<NaiveDate>,
+}
+
+impl StashItemView {
+    /// Creates a new [`StashItemView`]
+    pub fn new(
+        product_id: ProductId,
+        brand: Brand,
+        name: String,
+        unit: Unit,
+        stash_item: StashItem,
+        effective_expiry_date: Option<NaiveDate>,
+    ) -> Self {
+        Self {
+            product_id,
+            brand,
+            name,
+            unit,
+            stash_item,
+            effective_expiry_date,
+        }
+    }
+}
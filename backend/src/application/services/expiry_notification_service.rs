@@ -0,0 +1,457 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use actix_web::rt::task::spawn_blocking;
+use async_trait::async_trait;
+use chrono::{Days, NaiveDate};
+
+use crate::{
+    application::use_cases::NotifyExpiringStashItems,
+    domain::{
+        errors::NotificationError,
+        notifiers::Notifier,
+        repositories::{NotificationRepository, ProductRepository},
+        value_objects::{ExpiryDigest, ExpiryDigestEntry},
+    },
+};
+
+pub struct ExpiryNotificationService {
+    product_repository: Arc<Box<dyn ProductRepository>>,
+    notification_repository: Arc<Box<dyn NotificationRepository>>,
+    notifiers: Arc<Vec<Box<dyn Notifier>>>,
+    /// Number of days ahead to notify about expiring stash items, narrowest first
+    windows: Vec<u32>,
+}
+
+impl ExpiryNotificationService {
+    pub fn new(
+        product_repository: Arc<Box<dyn ProductRepository>>,
+        notification_repository: Arc<Box<dyn NotificationRepository>>,
+        notifiers: Vec<Box<dyn Notifier>>,
+        mut windows: Vec<u32>,
+    ) -> Self {
+        windows.sort();
+        windows.dedup();
+
+        Self {
+            product_repository,
+            notification_repository,
+            notifiers: Arc::new(notifiers),
+            windows,
+        }
+    }
+}
+
+#[async_trait]
+impl NotifyExpiringStashItems for ExpiryNotificationService {
+    async fn notify_expiring_stash_items(
+        &self,
+        today: NaiveDate,
+    ) -> Result<usize, NotificationError> {
+        let Some(widest) = self.windows.last() else {
+            return Ok(0);
+        };
+
+        // Stash items expiring on the last day of the widest window are included
+        let before = today
+            .checked_add_days(Days::new(u64::from(*widest) + 1))
+            .unwrap_or(NaiveDate::MAX);
+        let products = self
+            .product_repository
+            .find_expiring_in_interval(None, Some(before))
+            .await?;
+
+        // Put each stash item in the narrowest window it expires within
+        let mut entries = vec![];
+        for product in &products {
+            for stash_item in product.stash_items() {
+                let Some(entry) = ExpiryDigestEntry::new(product, stash_item, today) else {
+                    continue;
+                };
+                let days_left = (*entry.expiry_date() - today).num_days();
+                let Some(window) = self
+                    .windows
+                    .iter()
+                    .find(|window| days_left <= i64::from(**window))
+                else {
+                    continue;
+                };
+
+                entries.push((*window, entry));
+            }
+        }
+        let stash_item_ids = entries
+            .iter()
+            .map(|(_, entry)| *entry.stash_item_id())
+            .collect::<Vec<_>>();
+
+        // Each notifier keeps track of what it delivered, so one failing neither holds up the others nor has them
+        // deliver again when it is retried. The first failure is returned once every notifier has had its turn
+        let mut notified = HashSet::new();
+        let mut failure = None;
+        for (index, notifier) in self.notifiers.iter().enumerate() {
+            // Leave out the stash items the notifier has already notified about in the window or a narrower one
+            let notified_windows = self
+                .notification_repository
+                .find_notified_windows(notifier.name(), &stash_item_ids)
+                .await?;
+            let mut digests: BTreeMap<u32, Vec<ExpiryDigestEntry>> = BTreeMap::new();
+            for (window, entry) in &entries {
+                if notified_windows
+                    .get(entry.stash_item_id())
+                    .is_some_and(|notified| notified <= window)
+                {
+                    continue;
+                }
+
+                digests.entry(*window).or_default().push(entry.clone());
+            }
+
+            for (window, entries) in digests {
+                let digest = ExpiryDigest::new(window, entries);
+
+                // Notifiers do blocking I/O, so they run on the blocking thread pool
+                let notifiers = self.notifiers.clone();
+                let delivered = digest.clone();
+                let delivery = spawn_blocking(move || notifiers[index].notify(&delivered))
+                    .await
+                    .unwrap_or_else(|err| Err(NotificationError::DeliveryError(err.to_string())));
+                if let Err(err) = delivery {
+                    failure.get_or_insert(err);
+                    continue;
+                }
+
+                let stash_item_ids = digest
+                    .entries()
+                    .iter()
+                    .map(|entry| *entry.stash_item_id())
+                    .collect::<Vec<_>>();
+                self.notification_repository
+                    .mark_notified(notifier.name(), &stash_item_ids, window)
+                    .await?;
+
+                notified.extend(stash_item_ids);
+            }
+        }
+
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(notified.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use mockall::predicate::eq;
+
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem, Product, StashItem},
+        errors::ProductRepositoryError,
+        notifiers::MockNotifier,
+        repositories::{MockNotificationRepository, MockProductRepository},
+    };
+
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 11, 10).unwrap()
+    }
+
+    /// Makes a notifier with a name
+    fn notifier(name: &'static str) -> MockNotifier {
+        let mut notifier = MockNotifier::new();
+        notifier.expect_name().return_const(name);
+        notifier
+    }
+
+    /// Makes a product repository finding a single product with stash items expiring
+    fn finding(product: Product) -> MockProductRepository {
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_expiring_in_interval()
+            .returning(move |_, _| Ok(vec![product.clone()]));
+        product_repository
+    }
+
+    /// Makes a stash item expiring tomorrow
+    fn expiring_tomorrow() -> StashItem {
+        FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 11).unwrap())
+            .build()
+    }
+
+    #[actix_web::test]
+    async fn test_notify_expiring_stash_items() {
+        let tomorrow = expiring_tomorrow();
+        let in_three_days = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 13).unwrap())
+            .build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![tomorrow.clone(), in_three_days.clone()])
+            .build();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_expiring_in_interval()
+            .with(eq(None), eq(NaiveDate::from_ymd_opt(2023, 11, 18)))
+            .returning(move |_, _| Ok(vec![product.clone()]));
+
+        // The notified windows of all stash items are found at once
+        let mut notification_repository = MockNotificationRepository::new();
+        notification_repository
+            .expect_find_notified_windows()
+            .withf(|notifier, stash_item_ids| notifier == "webhook" && stash_item_ids.len() == 2)
+            .times(1)
+            .returning(|_, _| Ok(HashMap::new()));
+        let tomorrow_id = *tomorrow.id();
+        notification_repository
+            .expect_mark_notified()
+            .withf(move |notifier, stash_item_ids, window| {
+                notifier == "webhook" && stash_item_ids == [tomorrow_id] && *window == 1
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let in_three_days_id = *in_three_days.id();
+        notification_repository
+            .expect_mark_notified()
+            .withf(move |notifier, stash_item_ids, window| {
+                notifier == "webhook" && stash_item_ids == [in_three_days_id] && *window == 7
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let mut notifier = notifier("webhook");
+        notifier
+            .expect_notify()
+            .withf(|digest| *digest.window_days() == 1 && digest.entries().len() == 1)
+            .times(1)
+            .returning(|_| Ok(()));
+        notifier
+            .expect_notify()
+            .withf(|digest| *digest.window_days() == 7 && digest.entries().len() == 1)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let service = ExpiryNotificationService::new(
+            Arc::new(Box::new(product_repository)),
+            Arc::new(Box::new(notification_repository)),
+            vec![Box::new(notifier)],
+            vec![7, 1],
+        );
+
+        assert_eq!(service.notify_expiring_stash_items(today()).await, Ok(2));
+    }
+
+    #[actix_web::test]
+    async fn test_notify_expiring_stash_items_already_notified() {
+        let stash_item = expiring_tomorrow();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item])
+            .build();
+
+        let mut notification_repository = MockNotificationRepository::new();
+        notification_repository
+            .expect_find_notified_windows()
+            .withf(move |_, stash_item_ids| stash_item_ids == [stash_item_id])
+            .returning(move |_, _| Ok(HashMap::from([(stash_item_id, 1)])));
+        notification_repository.expect_mark_notified().never();
+
+        let mut notifier = notifier("webhook");
+        notifier.expect_notify().never();
+
+        let service = ExpiryNotificationService::new(
+            Arc::new(Box::new(finding(product))),
+            Arc::new(Box::new(notification_repository)),
+            vec![Box::new(notifier)],
+            vec![1, 7],
+        );
+
+        assert_eq!(service.notify_expiring_stash_items(today()).await, Ok(0));
+    }
+
+    #[actix_web::test]
+    async fn test_notify_expiring_stash_items_narrower_window() {
+        let stash_item = expiring_tomorrow();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item])
+            .build();
+
+        // Notified about a week ahead, but not yet about the day before
+        let mut notification_repository = MockNotificationRepository::new();
+        notification_repository
+            .expect_find_notified_windows()
+            .returning(move |_, _| Ok(HashMap::from([(stash_item_id, 7)])));
+        notification_repository
+            .expect_mark_notified()
+            .withf(move |_, stash_item_ids, window| {
+                stash_item_ids == [stash_item_id] && *window == 1
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let mut notifier = notifier("webhook");
+        notifier.expect_notify().times(1).returning(|_| Ok(()));
+
+        let service = ExpiryNotificationService::new(
+            Arc::new(Box::new(finding(product))),
+            Arc::new(Box::new(notification_repository)),
+            vec![Box::new(notifier)],
+            vec![1, 7],
+        );
+
+        assert_eq!(service.notify_expiring_stash_items(today()).await, Ok(1));
+    }
+
+    #[actix_web::test]
+    async fn test_notify_expiring_stash_items_delivery_failed() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![expiring_tomorrow()])
+            .build();
+
+        let mut notification_repository = MockNotificationRepository::new();
+        notification_repository
+            .expect_find_notified_windows()
+            .returning(|_, _| Ok(HashMap::new()));
+        notification_repository.expect_mark_notified().never();
+
+        let mut notifier = notifier("webhook");
+        notifier
+            .expect_notify()
+            .returning(|_| Err(NotificationError::DeliveryError("down".to_string())));
+
+        let service = ExpiryNotificationService::new(
+            Arc::new(Box::new(finding(product))),
+            Arc::new(Box::new(notification_repository)),
+            vec![Box::new(notifier)],
+            vec![1],
+        );
+
+        assert_eq!(
+            service.notify_expiring_stash_items(today()).await,
+            Err(NotificationError::DeliveryError("down".to_string()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_notify_expiring_stash_items_other_notifiers_deliver() {
+        let in_three_days = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 13).unwrap())
+            .build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![expiring_tomorrow(), in_three_days])
+            .build();
+
+        // The failing notifier is retried later without the others delivering again, and its failure in one window
+        // does not keep it from delivering in the next
+        let mut notification_repository = MockNotificationRepository::new();
+        notification_repository
+            .expect_find_notified_windows()
+            .returning(|_, _| Ok(HashMap::new()));
+        notification_repository
+            .expect_mark_notified()
+            .withf(|notifier, _, window| notifier == "webhook" && *window == 7)
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        notification_repository
+            .expect_mark_notified()
+            .withf(|notifier, _, _| notifier == "email")
+            .times(2)
+            .returning(|_, _, _| Ok(()));
+
+        let mut failing = notifier("webhook");
+        failing
+            .expect_notify()
+            .withf(|digest| *digest.window_days() == 1)
+            .times(1)
+            .returning(|_| Err(NotificationError::DeliveryError("down".to_string())));
+        failing
+            .expect_notify()
+            .withf(|digest| *digest.window_days() == 7)
+            .times(1)
+            .returning(|_| Ok(()));
+        let mut delivering = notifier("email");
+        delivering.expect_notify().times(2).returning(|_| Ok(()));
+
+        let service = ExpiryNotificationService::new(
+            Arc::new(Box::new(finding(product))),
+            Arc::new(Box::new(notification_repository)),
+            vec![Box::new(failing), Box::new(delivering)],
+            vec![1, 7],
+        );
+
+        assert_eq!(
+            service.notify_expiring_stash_items(today()).await,
+            Err(NotificationError::DeliveryError("down".to_string()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_notify_expiring_stash_items_per_notifier() {
+        let stash_item = expiring_tomorrow();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item])
+            .build();
+
+        // Only the notifier which has not delivered yet delivers
+        let mut notification_repository = MockNotificationRepository::new();
+        notification_repository
+            .expect_find_notified_windows()
+            .withf(|notifier, _| notifier == "webhook")
+            .returning(move |_, _| Ok(HashMap::from([(stash_item_id, 1)])));
+        notification_repository
+            .expect_find_notified_windows()
+            .withf(|notifier, _| notifier == "email")
+            .returning(|_, _| Ok(HashMap::new()));
+        notification_repository
+            .expect_mark_notified()
+            .withf(move |notifier, stash_item_ids, window| {
+                notifier == "email" && stash_item_ids == [stash_item_id] && *window == 1
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let mut delivered = notifier("webhook");
+        delivered.expect_notify().never();
+        let mut retried = notifier("email");
+        retried.expect_notify().times(1).returning(|_| Ok(()));
+
+        let service = ExpiryNotificationService::new(
+            Arc::new(Box::new(finding(product))),
+            Arc::new(Box::new(notification_repository)),
+            vec![Box::new(delivered), Box::new(retried)],
+            vec![1],
+        );
+
+        assert_eq!(service.notify_expiring_stash_items(today()).await, Ok(1));
+    }
+
+    #[actix_web::test]
+    async fn test_notify_expiring_stash_items_repository_error() {
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_expiring_in_interval()
+            .returning(|_, _| Err(ProductRepositoryError::PersisteneError("error".to_string())));
+
+        let service = ExpiryNotificationService::new(
+            Arc::new(Box::new(product_repository)),
+            Arc::new(Box::new(MockNotificationRepository::new())),
+            vec![],
+            vec![1],
+        );
+
+        assert_eq!(
+            service.notify_expiring_stash_items(today()).await,
+            Err(NotificationError::ProductRepositoryError(
+                ProductRepositoryError::PersisteneError("error".to_string())
+            ))
+        );
+    }
+}
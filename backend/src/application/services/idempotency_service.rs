@@ -0,0 +1,194 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::{
+    application::use_cases::{
+        ClaimIdempotencyKey, RecordIdempotentResponse, ReleaseIdempotencyKey,
+    },
+    domain::{
+        errors::IdempotencyError,
+        repositories::IdempotencyRepository,
+        value_objects::{IdempotencyClaim, IdempotentResponse},
+    },
+};
+
+pub struct IdempotencyService {
+    idempotency_repository: Arc<Box<dyn IdempotencyRepository>>,
+    /// How long idempotency keys are remembered after they were first used
+    window: chrono::Duration,
+    /// How long a request may take before its idempotency key can be claimed again, in case it never completes
+    lease: chrono::Duration,
+}
+
+impl IdempotencyService {
+    pub fn new(
+        idempotency_repository: Arc<Box<dyn IdempotencyRepository>>,
+        window: Duration,
+        lease: Duration,
+    ) -> Self {
+        Self {
+            idempotency_repository,
+            window: chrono::Duration::from_std(window).expect("Idempotency window out of range"),
+            lease: chrono::Duration::from_std(lease).expect("Idempotency lease out of range"),
+        }
+    }
+}
+
+#[async_trait]
+impl ClaimIdempotencyKey for IdempotencyService {
+    async fn claim_idempotency_key(
+        &self,
+        key: &str,
+        fingerprint: &str,
+    ) -> Result<IdempotencyClaim, IdempotencyError> {
+        let now = chrono::Utc::now().naive_utc();
+        let token = Uuid::new_v4();
+
+        let Some(record) = self
+            .idempotency_repository
+            .claim(
+                key,
+                &token,
+                fingerprint,
+                now,
+                now - self.window,
+                now - self.lease,
+            )
+            .await?
+        else {
+            return Ok(IdempotencyClaim::Claimed(token));
+        };
+
+        if record.fingerprint() != fingerprint {
+            return Ok(IdempotencyClaim::Mismatch);
+        }
+
+        Ok(match record.response() {
+            Some(response) => IdempotencyClaim::Replay(response.clone()),
+            None => IdempotencyClaim::InProgress,
+        })
+    }
+}
+
+#[async_trait]
+impl RecordIdempotentResponse for IdempotencyService {
+    async fn record_idempotent_response(
+        &self,
+        key: &str,
+        token: &Uuid,
+        response: IdempotentResponse,
+    ) -> Result<(), IdempotencyError> {
+        self.idempotency_repository
+            .complete(key, token, response)
+            .await
+    }
+}
+
+#[async_trait]
+impl ReleaseIdempotencyKey for IdempotencyService {
+    async fn release_idempotency_key(
+        &self,
+        key: &str,
+        token: &Uuid,
+    ) -> Result<(), IdempotencyError> {
+        self.idempotency_repository.release(key, token).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::{always, eq};
+
+    use crate::domain::{
+        repositories::MockIdempotencyRepository, value_objects::IdempotencyRecord,
+    };
+
+    use super::*;
+
+    fn service(idempotency_repository: MockIdempotencyRepository) -> IdempotencyService {
+        IdempotencyService::new(
+            Arc::new(Box::new(idempotency_repository)),
+            Duration::from_secs(60 * 60),
+            Duration::from_secs(60),
+        )
+    }
+
+    /// Makes a repository in which the key was already claimed for a request
+    fn claimed(
+        fingerprint: &str,
+        response: Option<IdempotentResponse>,
+    ) -> MockIdempotencyRepository {
+        let record = IdempotencyRecord::new(
+            "key".to_string(),
+            fingerprint.to_string(),
+            response,
+            chrono::Utc::now().naive_utc(),
+        );
+
+        let mut idempotency_repository = MockIdempotencyRepository::new();
+        idempotency_repository
+            .expect_claim()
+            .returning(move |_, _, _, _, _, _| Ok(Some(record.clone())));
+        idempotency_repository
+    }
+
+    #[actix_web::test]
+    async fn test_claim_idempotency_key() {
+        let mut idempotency_repository = MockIdempotencyRepository::new();
+        idempotency_repository
+            .expect_claim()
+            .with(
+                eq("key"),
+                always(),
+                eq("fingerprint"),
+                always(),
+                always(),
+                always(),
+            )
+            .withf(|_, _, _, now, expired_before, stale_before| {
+                *now - *expired_before == chrono::Duration::hours(1)
+                    && *now - *stale_before == chrono::Duration::minutes(1)
+            })
+            .times(1)
+            .returning(|_, _, _, _, _, _| Ok(None));
+
+        let claim = service(idempotency_repository)
+            .claim_idempotency_key("key", "fingerprint")
+            .await;
+
+        assert!(matches!(claim, Ok(IdempotencyClaim::Claimed(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_claim_idempotency_key_replay() {
+        let response = IdempotentResponse::new(204, vec![], vec![]);
+
+        let claim = service(claimed("fingerprint", Some(response.clone())))
+            .claim_idempotency_key("key", "fingerprint")
+            .await;
+
+        assert_eq!(claim, Ok(IdempotencyClaim::Replay(response)));
+    }
+
+    #[actix_web::test]
+    async fn test_claim_idempotency_key_in_progress() {
+        let claim = service(claimed("fingerprint", None))
+            .claim_idempotency_key("key", "fingerprint")
+            .await;
+
+        assert_eq!(claim, Ok(IdempotencyClaim::InProgress));
+    }
+
+    #[actix_web::test]
+    async fn test_claim_idempotency_key_mismatch() {
+        let response = IdempotentResponse::new(204, vec![], vec![]);
+
+        let claim = service(claimed("other", Some(response)))
+            .claim_idempotency_key("key", "fingerprint")
+            .await;
+
+        assert_eq!(claim, Ok(IdempotencyClaim::Mismatch));
+    }
+}
@@ -1,3 +1,7 @@
+mod expiry_notification_service;
+mod idempotency_service;
 mod product_service;
 
+pub use expiry_notification_service::ExpiryNotificationService;
+pub use idempotency_service::IdempotencyService;
 pub use product_service::ProductService;
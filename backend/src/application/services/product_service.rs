@@ -1,556 +1,3353 @@
+use async_trait::async_trait;
 use std::{collections::HashSet, sync::Arc};
+use uuid::Uuid;
 
 use crate::{
-    application::use_cases::{
-        AddStashItem, CreateProduct, DeleteProduct, DeleteStashItem, GetAllProductsWithStashItems,
-        GetProduct, GetProductByStashItemId, GetProductsExpiringBefore, GetStashItems,
-        UpdateProduct, UpdateStashItem,
+    application::{
+        events::{EventBus, StashEvent},
+        use_cases::{
+            AddStashItem, AttachBarcode, ConsumeStashItem, CreateProduct, DeleteProduct,
+            DeleteStashItem, DetachBarcode, ExecuteBatch, GetAllProductsWithStashItems,
+            GetExpiringStashItems, GetProduct, GetProductByStashItemId, GetProductsExpiringBefore,
+            GetProductsExpiringInInterval, GetProductsUnsafeBefore, GetStashItems, GetTrash,
+            MergeProducts, MoveStashItem, OpenStashItem, PurgeTrash, RestoreFromTrash,
+            SplitStashItem, UndoOperation, UpdateProduct, UpdateStashItem,
+        },
     },
     domain::{
         entities::{Product, StashItem},
-        errors::ProductRepositoryError,
-        repositories::ProductRepository,
-        value_objects::ProductId,
+        errors::{BatchError, ProductRepositoryError, StashItemDoesntExistError},
+        repositories::{ProductRepository, ProductTransaction, UnitOfWork},
+        value_objects::{
+            BatchOperation, InverseOperation, Operation, ProductId, Quantity, TrashEntry, Unit,
+        },
     },
 };
 
 pub struct ProductService {
     product_repository: Arc<Box<dyn ProductRepository>>,
+    unit_of_work: Arc<Box<dyn UnitOfWork>>,
+    event_bus: Arc<EventBus>,
 }
 
 impl ProductService {
-    pub fn new(product_repository: Arc<Box<dyn ProductRepository>>) -> Self {
-        Self { product_repository }
+    pub fn new(
+        product_repository: Arc<Box<dyn ProductRepository>>,
+        unit_of_work: Arc<Box<dyn UnitOfWork>>,
+        event_bus: Arc<EventBus>,
+    ) -> Self {
+        Self {
+            product_repository,
+            unit_of_work,
+            event_bus,
+        }
+    }
+
+    /// Publishes that something changed in the stash
+    fn publish(&self, event: StashEvent) {
+        self.event_bus.publish(event);
+    }
+
+    /// Publishes that a product was restored from the trash
+    ///
+    /// # Parameters
+    /// - `product_id`: ID of the product
+    async fn publish_restored_product(
+        &self,
+        product_id: &ProductId,
+    ) -> Result<(), ProductRepositoryError> {
+        if let Some(product) = self.product_repository.find_by_id(product_id).await? {
+            self.publish(StashEvent::ProductCreated {
+                product: Box::new(product),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Publishes that a stash item was restored from the trash
+    ///
+    /// # Parameters
+    /// - `stash_item_id`: ID of the stash item
+    async fn publish_restored_stash_item(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let product = self
+            .product_repository
+            .find_by_stash_item_id(stash_item_id)
+            .await?;
+
+        if let Some(product) = product {
+            if let Some(stash_item) = product.stash_item(stash_item_id) {
+                self.publish(StashEvent::StashItemAdded {
+                    product_id: product.id().clone(),
+                    stash_item: stash_item.clone(),
+                });
+            }
+        }
+
+        Ok(())
     }
 }
 
+#[async_trait]
 impl GetProduct for ProductService {
-    fn get_product(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
-        self.product_repository.find_by_id(id)
+    async fn get_product(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
+        self.product_repository.find_by_id(id).await
     }
 }
 
+#[async_trait]
 impl CreateProduct for ProductService {
-    fn create_product(&self, product: Product) -> Result<Product, ProductRepositoryError> {
-        // Clone the ID so we can use it to fetch the product after saving it
-        let product_id = product.id().clone();
+    async fn create_product(
+        &self,
+        product: Product,
+    ) -> Result<(Product, Uuid), ProductRepositoryError> {
+        // Check and save in one transaction, so concurrent requests cannot both create the product
+        let (product, operation_id) = self
+            .unit_of_work
+            .run(move |tx| {
+                if tx.exists_by_id(product.id())? {
+                    return Err(ProductRepositoryError::ProductAlreadyExists);
+                }
 
-        if self.product_repository.exists_by_id(&product.id())? {
-            return Err(ProductRepositoryError::ProductAlreadyExists);
-        }
+                // Clone the ID so we can use it to fetch the product after saving it
+                let product_id = product.id().clone();
 
-        match self.product_repository.save(product) {
-            Ok(()) => match self.product_repository.find_by_id(&product_id) {
-                Ok(Some(product)) => Ok(product),
-                // This should never happen; we just created it!
-                Ok(None) => panic!("Product not found after saving"),
-                Err(e) => Err(e),
-            },
-            Err(e) => Err(e),
-        }
+                tx.save(product)?;
+
+                let product = match tx.find_by_id(&product_id)? {
+                    Some(product) => product,
+                    // This should never happen; we just created it!
+                    None => panic!("Product not found after saving"),
+                };
+
+                let operation_id = record(
+                    tx,
+                    InverseOperation::DeleteProduct {
+                        product: Box::new(product.clone()),
+                    },
+                )?;
+
+                Ok((product, operation_id))
+            })
+            .await?;
+
+        self.publish(StashEvent::ProductCreated {
+            product: Box::new(product.clone()),
+        });
+
+        Ok((product, operation_id))
     }
 }
 
+#[async_trait]
 impl UpdateProduct for ProductService {
-    fn update_product(
+    async fn update_product(
         &self,
         id: &ProductId,
         product: Product,
+    ) -> Result<(Product, Uuid), ProductRepositoryError> {
+        let id = id.clone();
+
+        let (product, operation_id) = self
+            .unit_of_work
+            .run(move |tx| {
+                let existing = find_product(tx, &id)?;
+
+                // The ID may be a barcode of the existing product. Keep its own ID and barcodes, which are managed
+                // separately
+                let mut updated = Product::new(
+                    existing.id().clone(),
+                    product.brand().clone(),
+                    product.name().to_string(),
+                    product.stash_items().into_iter().cloned().collect(),
+                );
+                updated.set_unit(*product.unit());
+                updated.set_shelf_life_after_opening_days(*product.shelf_life_after_opening_days());
+                updated.set_best_before_grace_period_days(*product.best_before_grace_period_days());
+                for barcode in existing.barcodes() {
+                    updated.attach_barcode(barcode.clone());
+                }
+
+                // Clone the ID so we can use it to fetch the product after saving it
+                let product_id = updated.id().clone();
+
+                tx.save(updated)?;
+
+                let product = match tx.find_by_id(&product_id)? {
+                    Some(product) => product,
+                    // This should never happen; we just saved it!
+                    None => panic!("Product not found after saving"),
+                };
+
+                let operation_id = record(
+                    tx,
+                    InverseOperation::RevertProduct {
+                        before: Box::new(existing),
+                        after: Box::new(product.clone()),
+                    },
+                )?;
+
+                Ok::<_, ProductRepositoryError>((product, operation_id))
+            })
+            .await?;
+
+        self.publish(StashEvent::ProductUpdated {
+            product: Box::new(product.clone()),
+        });
+
+        Ok((product, operation_id))
+    }
+}
+
+#[async_trait]
+impl AttachBarcode for ProductService {
+    async fn attach_barcode(
+        &self,
+        product_id: &ProductId,
+        barcode: ProductId,
     ) -> Result<Product, ProductRepositoryError> {
-        // Clone the ID so we can use it to fetch the product after saving it
-        let product_id = product.id().clone();
+        let product_id = product_id.clone();
 
-        if !self.product_repository.exists_by_id(id)? {
-            return Err(ProductRepositoryError::ProductNotFound);
-        }
+        // Check and save in one transaction, so two products can not be given the same barcode at once
+        let (product, attached) = self
+            .unit_of_work
+            .run(move |tx| {
+                let mut product = find_product(tx, &product_id)?;
 
-        match self.product_repository.save(product) {
-            Ok(()) => match self.product_repository.find_by_id(&product_id) {
-                Ok(Some(product)) => Ok(product),
-                // This should never happen; we just created it!
-                Ok(None) => panic!("Product not found after saving"),
-                Err(e) => Err(e),
-            },
-            Err(e) => Err(e),
+                // Attaching a barcode the product already has is a no-op, but it can not be taken from another product
+                if product.has_barcode(&barcode) {
+                    return Ok((product, false));
+                }
+                if tx.exists_by_id(&barcode)? {
+                    return Err(ProductRepositoryError::ProductAlreadyExists);
+                }
+
+                product.attach_barcode(barcode);
+                tx.save(product.clone())?;
+
+                Ok((product, true))
+            })
+            .await?;
+
+        if attached {
+            self.publish(StashEvent::ProductUpdated {
+                product: Box::new(product.clone()),
+            });
         }
+
+        Ok(product)
+    }
+}
+
+#[async_trait]
+impl DetachBarcode for ProductService {
+    async fn detach_barcode(
+        &self,
+        product_id: &ProductId,
+        barcode: &ProductId,
+    ) -> Result<(), ProductRepositoryError> {
+        let product_id = product_id.clone();
+        let barcode = barcode.clone();
+
+        let product = self
+            .unit_of_work
+            .run(move |tx| {
+                let mut product = find_product(tx, &product_id)?;
+
+                product.detach_barcode(&barcode)?;
+
+                tx.save(product.clone())?;
+
+                Ok::<_, ProductRepositoryError>(product)
+            })
+            .await?;
+
+        self.publish(StashEvent::ProductUpdated {
+            product: Box::new(product),
+        });
+
+        Ok(())
     }
 }
 
+#[async_trait]
 impl DeleteProduct for ProductService {
-    fn delete_product(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
-        self.product_repository.delete_by_id(id)
+    async fn delete_product(&self, id: &ProductId) -> Result<Option<Uuid>, ProductRepositoryError> {
+        let id = id.clone();
+
+        let deleted = self
+            .unit_of_work
+            .run(move |tx| {
+                let product_id = match tx.find_by_id(&id)? {
+                    Some(product) => product.id().clone(),
+                    None => return Ok(None),
+                };
+
+                // The product goes to the trash, so it is restored from there when undone
+                tx.delete_by_id(&product_id)?;
+
+                let operation_id = record(
+                    tx,
+                    InverseOperation::RestoreProduct {
+                        product_id: product_id.clone(),
+                    },
+                )?;
+
+                Ok::<_, ProductRepositoryError>(Some((product_id, operation_id)))
+            })
+            .await?;
+
+        let Some((product_id, operation_id)) = deleted else {
+            return Ok(None);
+        };
+
+        self.publish(StashEvent::ProductDeleted { product_id });
+
+        Ok(Some(operation_id))
     }
 }
 
+#[async_trait]
 impl AddStashItem for ProductService {
-    fn add_stash_item(
+    async fn add_stash_item(
         &self,
         product_id: &ProductId,
         stash_item: StashItem,
-    ) -> Result<(), ProductRepositoryError> {
-        let mut product = match self.product_repository.find_by_id(product_id)? {
-            Some(product) => product,
-            None => return Err(ProductRepositoryError::ProductNotFound),
-        };
+    ) -> Result<Uuid, ProductRepositoryError> {
+        let product_id = product_id.clone();
+        let added = stash_item.clone();
+
+        // The product records that the stash item was added, which is published once it is saved
+        self.unit_of_work
+            .run(move |tx| {
+                let mut product = find_product(tx, &product_id)?;
+
+                product.add_stash_item(added)?;
+
+                let product_id = product.id().clone();
+                tx.save(product)?;
+
+                record(
+                    tx,
+                    InverseOperation::RemoveStashItem {
+                        product_id,
+                        stash_item,
+                    },
+                )
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl UpdateStashItem for ProductService {
+    async fn update_stash_item(
+        &self,
+        product_id: &ProductId,
+        stash_item: StashItem,
+    ) -> Result<(StashItem, Uuid), ProductRepositoryError> {
+        let product_id = product_id.clone();
+
+        // The product records what changed about the stash item, which is published once it is saved
+        self.unit_of_work
+            .run(move |tx| {
+                // Clone the ID so we can find the stash item after saving it
+                let stash_item_id = *stash_item.id();
+
+                let mut product = find_product(tx, &product_id)?;
+                let before = product
+                    .stash_item(&stash_item_id)
+                    .cloned()
+                    .ok_or(StashItemDoesntExistError)?;
+
+                product.update_stash_item(stash_item)?;
+
+                tx.save(product)?;
+
+                let (product_id, after) = match tx.find_by_id(&product_id)? {
+                    Some(product) => match product.stash_item(&stash_item_id) {
+                        Some(after) => (product.id().clone(), after.clone()),
+                        None => panic!("Stash item not found after saving"),
+                    },
+                    None => panic!("Product not found after saving"),
+                };
+
+                let operation_id = record(
+                    tx,
+                    InverseOperation::RevertStashItem {
+                        product_id,
+                        before,
+                        after: after.clone(),
+                    },
+                )?;
+
+                Ok((after, operation_id))
+            })
+            .await
+    }
+}
 
-        product.add_stash_item(stash_item)?;
+#[async_trait]
+impl DeleteStashItem for ProductService {
+    async fn delete_stash_item(
+        &self,
+        product_id: &ProductId,
+        stash_item_id: &uuid::Uuid,
+    ) -> Result<Uuid, ProductRepositoryError> {
+        let product_id = product_id.clone();
+        let stash_item_id = *stash_item_id;
 
-        self.product_repository.save(product)?;
+        let (product_id, operation_id) = self
+            .unit_of_work
+            .run(move |tx| {
+                let product = find_product(tx, &product_id)?;
 
-        // TODO No return value?
-        Ok(())
+                if !product.has_stash_item(&stash_item_id) {
+                    return Err(StashItemDoesntExistError.into());
+                }
+
+                // The stash item goes to the trash, so it is restored from there when undone
+                tx.delete_stash_item_by_id(&stash_item_id)?;
+
+                let operation_id =
+                    record(tx, InverseOperation::RestoreStashItem { stash_item_id })?;
+
+                Ok::<_, ProductRepositoryError>((product.id().clone(), operation_id))
+            })
+            .await?;
+
+        // Moving the stash item to the trash happens outside the product, so nothing was recorded about it
+        self.publish(StashEvent::StashItemRemoved {
+            product_id,
+            stash_item_id,
+        });
+
+        Ok(operation_id)
     }
 }
 
-impl UpdateStashItem for ProductService {
-    fn update_stash_item(
+#[async_trait]
+impl SplitStashItem for ProductService {
+    async fn split_stash_item(
         &self,
         product_id: &ProductId,
-        stash_item: StashItem,
-    ) -> Result<StashItem, ProductRepositoryError> {
-        // Clone the ID so we can find the stash item after saving it
-        let stash_item_id = stash_item.id().clone();
+        stash_item_id: &uuid::Uuid,
+        split: StashItem,
+    ) -> Result<Product, ProductRepositoryError> {
+        let product_id = product_id.clone();
+        let stash_item_id = *stash_item_id;
 
-        let mut product = match self.product_repository.find_by_id(product_id)? {
-            Some(product) => product,
-            None => return Err(ProductRepositoryError::ProductNotFound),
-        };
+        // The product records what the split did to its stash items, which is published once it is saved
+        self.unit_of_work
+            .run(move |tx| {
+                let mut product = find_product(tx, &product_id)?;
 
-        product.update_stash_item(stash_item)?;
+                product.split_stash_item(&stash_item_id, split)?;
 
-        self.product_repository.save(product)?;
+                tx.save(product.clone())?;
 
-        match self.product_repository.find_by_id(product_id) {
-            Ok(Some(product)) => {
-                let si = product
-                    .stash_items()
-                    .iter()
-                    .find(|x| x.id() == &stash_item_id)
-                    .map(|x| *x);
+                Ok(product)
+            })
+            .await
+    }
+}
 
-                match si {
-                    Some(stash_item) => Ok(stash_item.clone()),
-                    None => panic!("Stash item not found after saving"),
-                }
-            }
-            Ok(None) => panic!("Product not found after saving"),
-            Err(e) => Err(e),
-        }
+#[async_trait]
+impl ConsumeStashItem for ProductService {
+    async fn consume_stash_item(
+        &self,
+        product_id: &ProductId,
+        stash_item_id: &uuid::Uuid,
+        amount: Quantity,
+        unit: Option<Unit>,
+    ) -> Result<(Option<StashItem>, Uuid), ProductRepositoryError> {
+        let product_id = product_id.clone();
+        let stash_item_id = *stash_item_id;
+
+        // The product records the new quantity, or that the stash item is gone, which is published once it is saved
+        self.unit_of_work
+            .run(move |tx| {
+                let mut product = find_product(tx, &product_id)?;
+                let before = product.clone();
+
+                // Without a unit, the amount is in the unit of the product
+                let unit = unit.unwrap_or(*product.unit());
+
+                let consumed = product.consume_stash_item(&stash_item_id, amount, unit)?;
+
+                tx.save(product.clone())?;
+
+                // A stash item consumed completely is gone for good, so the product is put back as it was
+                let operation_id = record(
+                    tx,
+                    InverseOperation::RevertProduct {
+                        before: Box::new(before),
+                        after: Box::new(product),
+                    },
+                )?;
+
+                Ok((consumed, operation_id))
+            })
+            .await
     }
 }
 
-impl DeleteStashItem for ProductService {
-    fn delete_stash_item(
+#[async_trait]
+impl OpenStashItem for ProductService {
+    async fn open_stash_item(
         &self,
         product_id: &ProductId,
         stash_item_id: &uuid::Uuid,
-    ) -> Result<(), ProductRepositoryError> {
-        let mut product = match self.product_repository.find_by_id(product_id)? {
-            Some(product) => product,
-            None => return Err(ProductRepositoryError::ProductNotFound),
-        };
+        opened_on: chrono::NaiveDate,
+    ) -> Result<StashItem, ProductRepositoryError> {
+        let product_id = product_id.clone();
+        let stash_item_id = *stash_item_id;
 
-        product.remove_stash_item(stash_item_id)?;
+        // Opening part of a stash item splits it, so the product may record more than the opened stash item changing,
+        // which is published once it is saved
+        self.unit_of_work
+            .run(move |tx| {
+                let mut product = find_product(tx, &product_id)?;
 
-        self.product_repository.save(product)?;
+                let opened = product.open_stash_item(&stash_item_id, opened_on)?;
 
-        Ok(())
+                tx.save(product)?;
+
+                Ok(opened)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl MoveStashItem for ProductService {
+    async fn move_stash_item(
+        &self,
+        stash_item_id: &uuid::Uuid,
+        target_product_id: &ProductId,
+        merge: bool,
+    ) -> Result<StashItem, ProductRepositoryError> {
+        let stash_item_id = *stash_item_id;
+        let target_product_id = target_product_id.clone();
+
+        // Both products record what the move did to them, which is published once they are saved
+        self.unit_of_work
+            .run(move |tx| {
+                let mut source = tx
+                    .find_by_stash_item_id(&stash_item_id)?
+                    .ok_or(ProductRepositoryError::StashItemNotFound)?;
+
+                // Moving a stash item to the product it already belongs to is a no-op
+                if source.id() == &target_product_id {
+                    return source
+                        .stash_item(&stash_item_id)
+                        .cloned()
+                        .ok_or(ProductRepositoryError::StashItemNotFound);
+                }
+
+                let mut target = find_product(tx, &target_product_id)?;
+
+                let stash_item = source.move_stash_item(&stash_item_id, &mut target, merge)?;
+
+                tx.save(source)?;
+                tx.save(target)?;
+
+                Ok(stash_item)
+            })
+            .await
     }
 }
 
+#[async_trait]
+impl MergeProducts for ProductService {
+    async fn merge_products(
+        &self,
+        source_id: &ProductId,
+        target_id: &ProductId,
+    ) -> Result<Product, ProductRepositoryError> {
+        let source_id = source_id.clone();
+        let target_id = target_id.clone();
+
+        let (product, merged) = self
+            .unit_of_work
+            .run(move |tx| {
+                let source = find_product(tx, &source_id)?;
+                let mut target = find_product(tx, &target_id)?;
+
+                // Either of the IDs may be a barcode. If both lead to the same product, there is nothing to merge
+                if source.id() == target.id() {
+                    return Ok::<_, ProductRepositoryError>((target, None));
+                }
+
+                // Clone the IDs so we can use them to fetch the product after saving it, and tell what was merged
+                let merged_id = target.id().clone();
+                let source_id = source.id().clone();
+
+                source.merge_into(&mut target)?;
+
+                tx.merge_into(&source_id, target)?;
+
+                match tx.find_by_id(&merged_id)? {
+                    Some(product) => Ok((product, Some(source_id))),
+                    // This should never happen; we just saved it!
+                    None => panic!("Product not found after merging"),
+                }
+            })
+            .await?;
+
+        let Some(source_id) = merged else {
+            return Ok(product);
+        };
+
+        self.publish(StashEvent::ProductDeleted {
+            product_id: source_id,
+        });
+        self.publish(StashEvent::ProductUpdated {
+            product: Box::new(product.clone()),
+        });
+
+        Ok(product)
+    }
+}
+
+#[async_trait]
 impl GetStashItems for ProductService {
-    fn get_stash_items(
+    async fn get_stash_items(
         &self,
         product_id: &ProductId,
     ) -> Result<HashSet<StashItem>, ProductRepositoryError> {
-        let product = match self.product_repository.find_by_id(product_id)? {
+        let product = match self.product_repository.find_by_id(product_id).await? {
             Some(product) => product,
             None => return Err(ProductRepositoryError::ProductNotFound),
         };
 
-        let stash_items = product
-            .stash_items()
-            .into_iter()
-            .map(|x| x.clone())
-            .collect();
+        let stash_items = product.stash_items().into_iter().cloned().collect();
 
         Ok(stash_items)
     }
 }
 
+#[async_trait]
 impl GetProductByStashItemId for ProductService {
-    fn get_product_by_stash_item_id(
+    async fn get_product_by_stash_item_id(
         &self,
         stash_item_id: &uuid::Uuid,
     ) -> Result<Option<Product>, ProductRepositoryError> {
-        self.product_repository.find_by_stash_item_id(stash_item_id)
+        self.product_repository
+            .find_by_stash_item_id(stash_item_id)
+            .await
     }
 }
 
+#[async_trait]
 impl GetProductsExpiringBefore for ProductService {
-    fn products_expiring_before(
+    async fn products_expiring_before(
         &self,
         before: chrono::NaiveDate,
     ) -> Result<Vec<Product>, ProductRepositoryError> {
         self.product_repository
             .find_expiring_in_interval(None, Some(before))
+            .await
+    }
+}
+
+#[async_trait]
+impl GetProductsExpiringInInterval for ProductService {
+    async fn products_expiring_in_interval(
+        &self,
+        after: Option<chrono::NaiveDate>,
+        before: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        match (after, before) {
+            (None, None) => self.product_repository.find_all_with_stash_items().await,
+            (after, before) => {
+                self.product_repository
+                    .find_expiring_in_interval(after, before)
+                    .await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl GetExpiringStashItems for ProductService {
+    async fn expiring_stash_items(
+        &self,
+        after: Option<chrono::NaiveDate>,
+        before: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        let mut products = self.products_expiring_in_interval(after, before).await?;
+
+        for product in products.iter_mut() {
+            let outside = product
+                .stash_items()
+                .into_iter()
+                .filter(
+                    |stash_item| match product.effective_expiry_date(stash_item) {
+                        Some(date) => {
+                            after.is_some_and(|after| date < after)
+                                || before.is_some_and(|before| date >= before)
+                        }
+                        None => true,
+                    },
+                )
+                .map(|stash_item| *stash_item.id())
+                .collect::<Vec<_>>();
+
+            for stash_item_id in outside {
+                product.remove_stash_item(&stash_item_id)?;
+            }
+        }
+        products.retain(|product| !product.stash_items().is_empty());
+
+        Ok(products)
+    }
+}
+
+#[async_trait]
+impl GetProductsUnsafeBefore for ProductService {
+    async fn products_unsafe_before(
+        &self,
+        before: chrono::NaiveDate,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.product_repository
+            .find_unsafe_in_interval(None, Some(before))
+            .await
     }
 }
 
+#[async_trait]
 impl GetAllProductsWithStashItems for ProductService {
-    fn get_all_products_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError> {
-        self.product_repository.find_all_with_stash_items()
+    async fn get_all_products_with_stash_items(
+        &self,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.product_repository.find_all_with_stash_items().await
+    }
+}
+
+#[async_trait]
+impl GetTrash for ProductService {
+    async fn get_trash(&self) -> Result<Vec<TrashEntry>, ProductRepositoryError> {
+        self.product_repository.find_trash().await
+    }
+}
+
+#[async_trait]
+impl RestoreFromTrash for ProductService {
+    async fn restore_from_trash(&self, id: &str) -> Result<(), ProductRepositoryError> {
+        // Stash items are identified by UUIDs, while products may have any ID, so try the stash items first
+        if let Ok(stash_item_id) = id.parse() {
+            match self
+                .product_repository
+                .restore_stash_item_by_id(&stash_item_id)
+                .await
+            {
+                Err(ProductRepositoryError::StashItemNotFound) => {}
+                Err(err) => return Err(err),
+                Ok(()) => return self.publish_restored_stash_item(&stash_item_id).await,
+            }
+        }
+
+        let product_id = id
+            .parse()
+            .map_err(|_| ProductRepositoryError::ProductNotFound)?;
+        self.product_repository.restore_by_id(&product_id).await?;
+
+        self.publish_restored_product(&product_id).await
+    }
+}
+
+#[async_trait]
+impl UndoOperation for ProductService {
+    async fn undo_operation(&self, operation_id: &Uuid) -> Result<(), ProductRepositoryError> {
+        let operation_id = *operation_id;
+
+        // Check the stash is as the operation left it, undo it and forget it in one transaction, so nothing changes in
+        // between and an operation is only undone once
+        let events = self
+            .unit_of_work
+            .run(move |tx| {
+                let operation = tx
+                    .find_operation_by_id(&operation_id)?
+                    .ok_or(ProductRepositoryError::OperationNotFound)?;
+
+                let events = undo(tx, operation.inverse().clone())?;
+
+                tx.delete_operation_by_id(&operation_id)?;
+
+                Ok::<_, ProductRepositoryError>(events)
+            })
+            .await?;
+
+        for event in events {
+            self.publish(event);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExecuteBatch for ProductService {
+    async fn execute_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+    ) -> Result<(Vec<Product>, Option<Uuid>), BatchError> {
+        let (results, operation_id) = self
+            .unit_of_work
+            .run(move |tx| {
+                let results = operations
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, operation)| {
+                        apply(tx, operation)
+                            .map_err(|error| BatchError::OperationFailed { index, error })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // The batch is undone as a whole, like it was applied. An empty batch did nothing to undo
+                let operation_id = if results.is_empty() {
+                    None
+                } else {
+                    let inverse = InverseOperation::Batch {
+                        operations: results
+                            .iter()
+                            .map(|(_, _, inverse)| inverse.clone())
+                            .collect(),
+                    };
+                    Some(record(tx, inverse)?)
+                };
+
+                Ok::<_, BatchError>((results, operation_id))
+            })
+            .await?;
+
+        // Only once the batch is committed is there anything to tell. What the products recorded about their stash
+        // items was published as they were committed, so products created in the batch are published as the batch
+        // left them, for their stash items to not go missing
+        let mut products = Vec::with_capacity(results.len());
+        for (product, event, _) in &results {
+            if let Some(event) = event {
+                self.publish(match event {
+                    StashEvent::ProductCreated { product } => StashEvent::ProductCreated {
+                        product: Box::new(last_state_of(&results, product.id())),
+                    },
+                    event => event.clone(),
+                });
+            }
+            products.push(product.clone());
+        }
+
+        Ok((products, operation_id))
+    }
+}
+
+/// Records an operation within a transaction, so it is only kept if the changes it undoes are committed
+///
+/// # Parameters
+/// - `tx`: The transaction the operation is done in
+/// - `inverse`: What to do to undo the operation
+///
+/// # Returns
+/// The ID of the recorded operation
+fn record(
+    tx: &mut dyn ProductTransaction,
+    inverse: InverseOperation,
+) -> Result<Uuid, ProductRepositoryError> {
+    let operation = Operation::new(inverse);
+    let operation_id = *operation.id();
+
+    tx.save_operation(operation)?;
+
+    Ok(operation_id)
+}
+
+/// Gets a product as the last operation of a batch applied to it left it
+///
+/// # Parameters
+/// - `results`: What the operations of the batch resulted in, in the order they were applied
+/// - `product_id`: ID of the product
+fn last_state_of(
+    results: &[(Product, Option<StashEvent>, InverseOperation)],
+    product_id: &ProductId,
+) -> Product {
+    results
+        .iter()
+        .rev()
+        .map(|(product, _, _)| product)
+        .find(|product| product.id() == product_id)
+        .cloned()
+        .expect("Product of the batch not found among its results")
+}
+
+/// Applies an operation of a batch
+///
+/// # Parameters
+/// - `tx`: The transaction the batch is applied in
+/// - `operation`: The operation to apply
+///
+/// # Returns
+/// The product the operation was applied to, as it is right after the operation, along with what to publish about it
+/// once the batch is committed, unless the product records it itself, and what to do to undo it
+fn apply(
+    tx: &mut dyn ProductTransaction,
+    operation: BatchOperation,
+) -> Result<(Product, Option<StashEvent>, InverseOperation), ProductRepositoryError> {
+    let (product, event, inverse) = match operation {
+        BatchOperation::CreateProduct { product } => {
+            if tx.exists_by_id(product.id())? {
+                return Err(ProductRepositoryError::ProductAlreadyExists);
+            }
+
+            let event = StashEvent::ProductCreated {
+                product: product.clone(),
+            };
+            let inverse = InverseOperation::DeleteProduct {
+                product: product.clone(),
+            };
+            (*product, Some(event), inverse)
+        }
+        BatchOperation::AddStashItem {
+            product_id,
+            stash_item,
+        } => {
+            let mut product = find_product(tx, &product_id)?;
+            product.add_stash_item(stash_item.clone())?;
+
+            let inverse = InverseOperation::RemoveStashItem {
+                product_id: product.id().clone(),
+                stash_item,
+            };
+            (product, None, inverse)
+        }
+        BatchOperation::UpdateStashItem {
+            product_id,
+            stash_item,
+        } => {
+            let mut product = find_product(tx, &product_id)?;
+            let before = product
+                .stash_item(stash_item.id())
+                .cloned()
+                .ok_or(StashItemDoesntExistError)?;
+            product.update_stash_item(stash_item.clone())?;
+
+            let inverse = InverseOperation::RevertStashItem {
+                product_id: product.id().clone(),
+                before,
+                after: stash_item,
+            };
+            (product, None, inverse)
+        }
+        BatchOperation::DeleteStashItem {
+            product_id,
+            stash_item_id,
+        } => {
+            let mut product = find_product(tx, &product_id)?;
+            product.remove_stash_item(&stash_item_id)?;
+
+            // The stash item goes to the trash, like when it is deleted on its own
+            tx.delete_stash_item_by_id(&stash_item_id)?;
+
+            let event = StashEvent::StashItemRemoved {
+                product_id: product.id().clone(),
+                stash_item_id,
+            };
+            return Ok((
+                product,
+                Some(event),
+                InverseOperation::RestoreStashItem { stash_item_id },
+            ));
+        }
+        BatchOperation::ConsumeStashItem {
+            product_id,
+            stash_item_id,
+            amount,
+            unit,
+        } => {
+            let mut product = find_product(tx, &product_id)?;
+            let before = product.clone();
+            let unit = unit.unwrap_or(*product.unit());
+            product.consume_stash_item(&stash_item_id, amount, unit)?;
+
+            // A stash item consumed completely is gone for good, so the product is put back as it was
+            let inverse = InverseOperation::RevertProduct {
+                before: Box::new(before),
+                after: Box::new(product.clone()),
+            };
+            (product, None, inverse)
+        }
+    };
+
+    tx.save(product.clone())?;
+
+    Ok((product, event, inverse))
+}
+
+/// Undoes an operation, as long as what it changed has not changed since
+///
+/// # Parameters
+/// - `tx`: The transaction the operation is undone in
+/// - `inverse`: What to do to undo the operation
+///
+/// # Returns
+/// What to publish once the operation is undone, besides what the products record themselves, or `UndoConflict` if
+/// the stash has changed since
+fn undo(
+    tx: &mut dyn ProductTransaction,
+    inverse: InverseOperation,
+) -> Result<Vec<StashEvent>, ProductRepositoryError> {
+    let events = match inverse {
+        InverseOperation::RemoveStashItem {
+            product_id,
+            stash_item,
+        } => {
+            let mut product = find_undo_target(tx, &product_id)?;
+            if product.stash_item(stash_item.id()) != Some(&stash_item) {
+                return Err(ProductRepositoryError::UndoConflict);
+            }
+
+            // The product records that the stash item was removed, which is published once it is saved
+            product.remove_stash_item(stash_item.id())?;
+            tx.save(product)?;
+
+            vec![]
+        }
+        InverseOperation::RevertStashItem {
+            product_id,
+            before,
+            after,
+        } => {
+            let mut product = find_undo_target(tx, &product_id)?;
+            if product.stash_item(after.id()) != Some(&after) {
+                return Err(ProductRepositoryError::UndoConflict);
+            }
+
+            // Another stash item may have taken the dates the stash item had since. The product records what changed
+            // back, which is published once it is saved
+            product
+                .update_stash_item(before)
+                .map_err(|_| ProductRepositoryError::UndoConflict)?;
+            tx.save(product)?;
+
+            vec![]
+        }
+        InverseOperation::RestoreStashItem { stash_item_id } => {
+            match tx.restore_stash_item_by_id(&stash_item_id) {
+                Err(
+                    ProductRepositoryError::StashItemNotFound
+                    | ProductRepositoryError::DuplicateExpiryDateError,
+                ) => return Err(ProductRepositoryError::UndoConflict),
+                result => result?,
+            }
+
+            let product = tx
+                .find_by_stash_item_id(&stash_item_id)?
+                .ok_or(ProductRepositoryError::StashItemNotFound)?;
+            let stash_item = product
+                .stash_item(&stash_item_id)
+                .cloned()
+                .ok_or(ProductRepositoryError::StashItemNotFound)?;
+
+            vec![StashEvent::StashItemAdded {
+                product_id: product.id().clone(),
+                stash_item,
+            }]
+        }
+        InverseOperation::RevertProduct { before, after } => {
+            if tx.find_by_id(after.id())? != Some(*after) {
+                return Err(ProductRepositoryError::UndoConflict);
+            }
+
+            tx.save(*before.clone())?;
+
+            vec![StashEvent::ProductUpdated { product: before }]
+        }
+        InverseOperation::RestoreProduct { product_id } => {
+            match tx.restore_by_id(&product_id) {
+                Err(
+                    ProductRepositoryError::ProductNotFound
+                    | ProductRepositoryError::ProductAlreadyExists,
+                ) => return Err(ProductRepositoryError::UndoConflict),
+                result => result?,
+            }
+
+            vec![StashEvent::ProductCreated {
+                product: Box::new(find_product(tx, &product_id)?),
+            }]
+        }
+        InverseOperation::DeleteProduct { product } => {
+            if tx.find_by_id(product.id())?.as_ref() != Some(&*product) {
+                return Err(ProductRepositoryError::UndoConflict);
+            }
+
+            // The product goes to the trash, like when it is deleted on its own
+            tx.delete_by_id(product.id())?;
+
+            vec![StashEvent::ProductDeleted {
+                product_id: product.id().clone(),
+            }]
+        }
+        InverseOperation::Batch { operations } => {
+            // Each operation finds the stash as the one after it left it once that one is undone
+            let mut events = vec![];
+            for inverse in operations.into_iter().rev() {
+                events.extend(undo(tx, inverse)?);
+            }
+
+            events
+        }
+    };
+
+    Ok(events)
+}
+
+/// Finds the product an operation changed, as long as it is still around
+///
+/// # Parameters
+/// - `tx`: The transaction the operation is undone in
+/// - `product_id`: ID of the product
+fn find_undo_target(
+    tx: &mut dyn ProductTransaction,
+    product_id: &ProductId,
+) -> Result<Product, ProductRepositoryError> {
+    tx.find_by_id(product_id)?
+        .ok_or(ProductRepositoryError::UndoConflict)
+}
+
+/// Finds a product within a transaction, failing if it does not exist
+///
+/// # Parameters
+/// - `tx`: The transaction to find the product in
+/// - `product_id`: ID of the product, or a barcode attached to it
+fn find_product(
+    tx: &mut dyn ProductTransaction,
+    product_id: &ProductId,
+) -> Result<Product, ProductRepositoryError> {
+    tx.find_by_id(product_id)?
+        .ok_or(ProductRepositoryError::ProductNotFound)
+}
+
+#[async_trait]
+impl PurgeTrash for ProductService {
+    async fn purge_trash(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError> {
+        self.product_repository.purge_trash(deleted_before).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use chrono::NaiveDate;
+    use futures_util::FutureExt;
     use mockall::predicate::eq;
     use uuid::Uuid;
 
-    use crate::domain::{
-        entities::{FakeProduct, FakeStashItem},
-        repositories::MockProductRepository,
-    };
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem},
+        events::{ProductEvent, ProductEventDispatcher},
+        repositories::{MockProductRepository, MockProductTransaction, MockUnitOfWork},
+        value_objects::ExpiryDateKind,
+    };
+
+    use crate::{
+        application::events::{Delivery, ProductEventForwarder},
+        infrastructure::persistence::memory,
+    };
+
+    use super::*;
+
+    fn service(product_repository: MockProductRepository) -> ProductService {
+        ProductService::new(
+            Arc::new(Box::new(product_repository)),
+            Arc::new(Box::new(MockUnitOfWork::new())),
+            Arc::new(EventBus::new(16)),
+        )
+    }
+
+    /// Makes a service running units of work in a transaction
+    fn transactional(transaction: MockProductTransaction) -> ProductService {
+        let transaction = std::sync::Mutex::new(transaction);
+
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work
+            .expect_run_boxed()
+            .returning(move |work| Ok(work(&mut *transaction.lock().unwrap())));
+
+        ProductService::new(
+            Arc::new(Box::new(MockProductRepository::new())),
+            Arc::new(Box::new(unit_of_work)),
+            Arc::new(EventBus::new(16)),
+        )
+    }
+
+    /// Gets the events a service has published so far
+    fn published(product_service: &ProductService) -> Vec<StashEvent> {
+        let mut subscription = product_service.event_bus.subscribe(Some(0));
+
+        std::iter::from_fn(|| match subscription.next().now_or_never() {
+            Some(Some(Delivery::Event(published))) => Some(published.event().clone()),
+            _ => None,
+        })
+        .collect()
+    }
+
+    /// Makes a transaction expect an operation to be recorded
+    fn recording(transaction: &mut MockProductTransaction, inverse: InverseOperation) {
+        transaction
+            .expect_save_operation()
+            .withf(move |operation| operation.inverse() == &inverse)
+            .times(1)
+            .returning(|_| Ok(()));
+    }
+
+    /// Makes a transaction hold an operation, which is expected to be forgotten once it is undone
+    fn holding(
+        transaction: &mut MockProductTransaction,
+        inverse: InverseOperation,
+        undone: bool,
+    ) -> Uuid {
+        let operation = Operation::new(inverse);
+        let operation_id = *operation.id();
+
+        transaction
+            .expect_find_operation_by_id()
+            .with(eq(operation_id))
+            .returning(move |_| Ok(Some(operation.clone())));
+        transaction
+            .expect_delete_operation_by_id()
+            .with(eq(operation_id))
+            .times(usize::from(undone))
+            .returning(|_| Ok(()));
+
+        operation_id
+    }
+
+    #[actix_web::test]
+    async fn test_get_product_by_id() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(returned_product.clone())));
+
+        let product_service = service(product_repository);
+
+        let found_product = product_service
+            .get_product(&product_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(found_product, product);
+    }
+
+    #[actix_web::test]
+    async fn test_get_product_by_id_not_found() {
+        let product_id: ProductId = "ID".parse().unwrap();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(|_| Ok(None));
+
+        let product_service = service(product_repository);
+
+        let found_product = product_service.get_product(&product_id).await.unwrap();
+
+        assert!(found_product.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_create_product() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+        let returned_product = product.clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_exists_by_id()
+            .with(eq(product_id.clone()))
+            .returning(|_| Ok(false));
+        transaction
+            .expect_save()
+            .with(eq(product.clone()))
+            .returning(|_| Ok(()));
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(returned_product.clone())));
+        recording(
+            &mut transaction,
+            InverseOperation::DeleteProduct {
+                product: Box::new(product.clone()),
+            },
+        );
+
+        let product_service = transactional(transaction);
+
+        let (created_product, _) = product_service
+            .create_product(product.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(created_product, product);
+    }
+
+    #[actix_web::test]
+    async fn test_create_product_already_exists() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_exists_by_id()
+            .with(eq(product_id.clone()))
+            .returning(|_| Ok(true));
+
+        let product_service = transactional(transaction);
+
+        let created_product = product_service.create_product(product.clone()).await;
+
+        assert_eq!(
+            created_product.unwrap_err(),
+            ProductRepositoryError::ProductAlreadyExists
+        );
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_update_product() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+        let returned_product = product.clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_save()
+            .with(eq(product.clone()))
+            .returning(|_| Ok(()));
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(returned_product.clone())));
+
+        recording(
+            &mut transaction,
+            InverseOperation::RevertProduct {
+                before: Box::new(product.clone()),
+                after: Box::new(product.clone()),
+            },
+        );
+
+        let product_service = transactional(transaction);
+
+        let (updated_product, _) = product_service
+            .update_product(&product_id, product.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(updated_product, product);
+    }
+
+    #[actix_web::test]
+    async fn test_update_product_keeps_barcodes() {
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+        let existing = FakeProduct::new()
+            .with_barcodes(vec![barcode.clone()])
+            .build();
+        let product_id = existing.id().clone();
+        let product = FakeProduct::new().with_id(product_id.clone()).build();
+        let before = existing.clone();
+
+        let mut expected = product.clone();
+        expected.attach_barcode(barcode.clone());
+        let saved = expected.clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(barcode.clone()))
+            .returning(move |_| Ok(Some(existing.clone())));
+        transaction
+            .expect_save()
+            .with(eq(expected.clone()))
+            .times(1)
+            .returning(|_| Ok(()));
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(saved.clone())));
+
+        recording(
+            &mut transaction,
+            InverseOperation::RevertProduct {
+                before: Box::new(before),
+                after: Box::new(expected.clone()),
+            },
+        );
+
+        let product_service = transactional(transaction);
+
+        let (updated_product, _) = product_service
+            .update_product(&barcode, product)
+            .await
+            .unwrap();
+
+        assert_eq!(updated_product, expected);
+    }
+
+    #[actix_web::test]
+    async fn test_update_product_not_found() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(|_| Ok(None));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let updated_product = product_service
+            .update_product(&product_id, product.clone())
+            .await;
+
+        assert_eq!(
+            updated_product.unwrap_err(),
+            ProductRepositoryError::ProductNotFound
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_delete_product() {
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+        let product = FakeProduct::new()
+            .with_barcodes(vec![barcode.clone()])
+            .build();
+        let product_id = product.id().clone();
+        let deleted_id = product_id.clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(barcode.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_delete_by_id()
+            .with(eq(product_id.clone()))
+            .times(1)
+            .returning(|_| Ok(()));
+        recording(
+            &mut transaction,
+            InverseOperation::RestoreProduct { product_id },
+        );
+
+        let product_service = transactional(transaction);
+
+        let deleted_product = product_service.delete_product(&barcode).await;
+
+        assert!(matches!(deleted_product, Ok(Some(_))));
+        assert_eq!(
+            published(&product_service),
+            vec![StashEvent::ProductDeleted {
+                product_id: deleted_id
+            }]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_delete_product_not_found() {
+        let product_id: ProductId = "ID".parse().unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(|_| Ok(None));
+        transaction.expect_delete_by_id().never();
+        transaction.expect_save_operation().never();
+
+        let product_service = transactional(transaction);
+
+        let deleted_product = product_service.delete_product(&product_id).await;
+
+        assert_eq!(deleted_product, Ok(None));
+    }
+
+    #[actix_web::test]
+    async fn test_add_stash_item() {
+        let product = FakeProduct::new().with_stash_items(Vec::new()).build();
+        let product_id = product.id().clone();
+        let stash_item = FakeStashItem::new().build();
+        let added = ProductEvent::StashItemAdded {
+            product_id: product_id.clone(),
+            stash_item: stash_item.clone(),
+        };
+
+        // The saved product records the stash item being added, to be published once it is committed
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_save()
+            .withf(move |product| {
+                product.stash_items().len() == 1 && product.events() == [added.clone()]
+            })
+            .returning(|_| Ok(()));
+
+        recording(
+            &mut transaction,
+            InverseOperation::RemoveStashItem {
+                product_id: product_id.clone(),
+                stash_item: stash_item.clone(),
+            },
+        );
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .add_stash_item(&product_id, stash_item.clone())
+            .await;
+
+        assert!(result.is_ok());
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_update_stash_item() {
+        let stash_item_id = Uuid::new_v4();
+        let before = FakeStashItem::new().with_id(stash_item_id).build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![before.clone()])
+            .build();
+        let product_id = product.id().clone();
+        let stash_item = FakeStashItem::new().with_id(stash_item_id).build();
+
+        let mut updated = product.clone();
+        updated.update_stash_item(stash_item.clone()).unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .times(1)
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction.expect_save().returning(|_| Ok(()));
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(updated.clone())));
+
+        recording(
+            &mut transaction,
+            InverseOperation::RevertStashItem {
+                product_id: product_id.clone(),
+                before,
+                after: stash_item.clone(),
+            },
+        );
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .update_stash_item(&product_id, stash_item.clone())
+            .await;
+
+        assert!(matches!(result, Ok((updated, _)) if updated == stash_item));
+        // The product records what changed, to be published once it is committed
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_stash_item() {
+        let stash_item_id = Uuid::new_v4();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new().with_id(stash_item_id).build()])
+            .build();
+        let product_id = product.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_delete_stash_item_by_id()
+            .with(eq(stash_item_id))
+            .returning(|_| Ok(()));
+
+        recording(
+            &mut transaction,
+            InverseOperation::RestoreStashItem { stash_item_id },
+        );
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .delete_stash_item(&product_id, &stash_item_id)
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_stash_item_not_found() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction.expect_delete_stash_item_by_id().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .delete_stash_item(&product_id, &Uuid::new_v4())
+            .await;
+
+        assert_eq!(result, Err(ProductRepositoryError::StashItemNotFound));
+    }
+
+    #[actix_web::test]
+    async fn test_split_stash_item() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(4.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 26).unwrap())
+            .build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let product_id = product.id().clone();
+        let split = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2024, 5, 26).unwrap())
+            .build();
+
+        let mut expected = product.clone();
+        expected
+            .split_stash_item(stash_item.id(), split.clone())
+            .unwrap();
+
+        // The saved product records what the split did, to be published once it is committed
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        let saved = expected.clone();
+        transaction
+            .expect_save()
+            .withf(move |product| product == &saved && product.events() == saved.events())
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .split_stash_item(&product_id, stash_item.id(), split)
+            .await;
+
+        assert_eq!(result.unwrap(), expected);
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_split_stash_item_not_found() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .split_stash_item(&product_id, &Uuid::new_v4(), FakeStashItem::new().build())
+            .await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::StashItemNotFound
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_consume_stash_item() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .build();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_unit(Unit::Kilograms)
+            .with_stash_items(vec![stash_item])
+            .build();
+        let product_id = product.id().clone();
+
+        let mut expected = product.clone();
+        expected
+            .consume_stash_item(&stash_item_id, "0.5".parse().unwrap(), Unit::Kilograms)
+            .unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        let found = product.clone();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(found.clone())));
+        transaction
+            .expect_save()
+            .with(eq(expected.clone()))
+            .times(1)
+            .returning(|_| Ok(()));
+        recording(
+            &mut transaction,
+            InverseOperation::RevertProduct {
+                before: Box::new(product),
+                after: Box::new(expected),
+            },
+        );
+
+        let product_service = transactional(transaction);
+
+        let (consumed, _) = product_service
+            .consume_stash_item(&product_id, &stash_item_id, "0.5".parse().unwrap(), None)
+            .await
+            .unwrap();
+        let consumed = consumed.unwrap();
+
+        assert_eq!(consumed.quantity(), &"1.5".parse().unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_open_stash_item() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .build();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item])
+            .build();
+        let product_id = product.id().clone();
+        let opened_on = NaiveDate::from_ymd_opt(2023, 11, 1).unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_save()
+            .withf(move |product| {
+                product.stash_items().len() == 2
+                    && product
+                        .stash_items()
+                        .iter()
+                        .any(|item| item.opened_on() == &Some(opened_on))
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let product_service = transactional(transaction);
+
+        let opened = product_service
+            .open_stash_item(&product_id, &stash_item_id, opened_on)
+            .await
+            .unwrap();
+
+        assert_eq!(opened.opened_on(), &Some(opened_on));
+        assert_eq!(opened.quantity(), &1.try_into().unwrap());
+        // The product records the stash item being split, to be published once it is committed
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_move_stash_item() {
+        let stash_item = FakeStashItem::new().build();
+        let stash_item_id = *stash_item.id();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let source_id = source.id().clone();
+        let target = FakeProduct::new().with_stash_items(vec![]).build();
+        let target_id = target.id().clone();
+        let removed = ProductEvent::StashItemRemoved {
+            product_id: source_id.clone(),
+            stash_item: stash_item.clone(),
+        };
+        let added = ProductEvent::StashItemAdded {
+            product_id: target_id.clone(),
+            stash_item: stash_item.clone(),
+        };
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_stash_item_id()
+            .with(eq(stash_item_id))
+            .returning(move |_| Ok(Some(source.clone())));
+        transaction
+            .expect_find_by_id()
+            .with(eq(target_id.clone()))
+            .returning(move |_| Ok(Some(target.clone())));
+        transaction
+            .expect_save()
+            .withf(move |product| {
+                product.id() == &source_id
+                    && !product.has_stash_item(&stash_item_id)
+                    && product.events() == [removed.clone()]
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let saved_target_id = target_id.clone();
+        transaction
+            .expect_save()
+            .withf(move |product| {
+                product.id() == &saved_target_id
+                    && product.has_stash_item(&stash_item_id)
+                    && product.events() == [added.clone()]
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .move_stash_item(&stash_item_id, &target_id, false)
+            .await;
+
+        // Both products record what the move did to them, to be published once they are committed
+        assert_eq!(result.unwrap(), stash_item);
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_move_stash_item_not_found() {
+        let stash_item_id = Uuid::new_v4();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_stash_item_id()
+            .with(eq(stash_item_id))
+            .returning(|_| Ok(None));
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .move_stash_item(&stash_item_id, &"ID".parse().unwrap(), false)
+            .await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::StashItemNotFound
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_move_stash_item_target_not_found() {
+        let stash_item = FakeStashItem::new().build();
+        let stash_item_id = *stash_item.id();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![stash_item])
+            .build();
+        let target_id: ProductId = "ID".parse().unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_stash_item_id()
+            .with(eq(stash_item_id))
+            .returning(move |_| Ok(Some(source.clone())));
+        transaction
+            .expect_find_by_id()
+            .with(eq(target_id.clone()))
+            .returning(|_| Ok(None));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .move_stash_item(&stash_item_id, &target_id, false)
+            .await;
+
+        assert_eq!(result.unwrap_err(), ProductRepositoryError::ProductNotFound);
+    }
+
+    #[actix_web::test]
+    async fn test_move_stash_item_duplicate_expiry_date() {
+        let expiry_date = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let stash_item = FakeStashItem::new().with_expiry_date(expiry_date).build();
+        let stash_item_id = *stash_item.id();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![stash_item])
+            .build();
+        let target = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_expiry_date(expiry_date)
+                .build()])
+            .build();
+        let target_id = target.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_stash_item_id()
+            .with(eq(stash_item_id))
+            .returning(move |_| Ok(Some(source.clone())));
+        transaction
+            .expect_find_by_id()
+            .with(eq(target_id.clone()))
+            .returning(move |_| Ok(Some(target.clone())));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .move_stash_item(&stash_item_id, &target_id, false)
+            .await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::DuplicateExpiryDateError
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_merge_products() {
+        let source = FakeProduct::new().build();
+        let source_id = source.id().clone();
+        let target = FakeProduct::new().with_stash_items(vec![]).build();
+        let target_id = target.id().clone();
+
+        let mut expected = target.clone();
+        source.clone().merge_into(&mut expected).unwrap();
+        let merged = expected.clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(source_id.clone()))
+            .returning(move |_| Ok(Some(source.clone())));
+        transaction
+            .expect_find_by_id()
+            .with(eq(target_id.clone()))
+            .times(1)
+            .returning(move |_| Ok(Some(target.clone())));
+        transaction
+            .expect_merge_into()
+            .with(eq(source_id.clone()), eq(expected.clone()))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        transaction
+            .expect_find_by_id()
+            .with(eq(target_id.clone()))
+            .returning(move |_| Ok(Some(merged.clone())));
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.merge_products(&source_id, &target_id).await;
+
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_merge_products_not_found() {
+        let target = FakeProduct::new().build();
+        let target_id = target.id().clone();
+        let source_id: ProductId = "ID".parse().unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(source_id.clone()))
+            .returning(|_| Ok(None));
+        transaction
+            .expect_find_by_id()
+            .with(eq(target_id.clone()))
+            .returning(move |_| Ok(Some(target.clone())));
+        transaction.expect_merge_into().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.merge_products(&source_id, &target_id).await;
+
+        assert_eq!(result.unwrap_err(), ProductRepositoryError::ProductNotFound);
+    }
+
+    #[actix_web::test]
+    async fn test_merge_products_into_itself() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+        let returned_product = product.clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(returned_product.clone())));
+        transaction.expect_merge_into().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .merge_products(&product_id, &product_id)
+            .await;
+
+        assert_eq!(result.unwrap(), product);
+    }
+
+    #[actix_web::test]
+    async fn test_attach_barcode() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+
+        let mut expected = product.clone();
+        expected.attach_barcode(barcode.clone());
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .times(1)
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_exists_by_id()
+            .with(eq(barcode.clone()))
+            .returning(|_| Ok(false));
+        transaction
+            .expect_save()
+            .with(eq(expected.clone()))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.attach_barcode(&product_id, barcode).await;
+
+        assert_eq!(result.unwrap(), expected);
+        assert_eq!(
+            published(&product_service),
+            vec![StashEvent::ProductUpdated {
+                product: Box::new(expected)
+            }]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_attach_barcode_already_attached() {
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+        let product = FakeProduct::new()
+            .with_barcodes(vec![barcode.clone()])
+            .build();
+        let product_id = product.id().clone();
+        let expected = product.clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.attach_barcode(&product_id, barcode).await;
+
+        assert_eq!(result.unwrap(), expected);
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_attach_barcode_of_other_product() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_exists_by_id()
+            .with(eq(barcode.clone()))
+            .returning(|_| Ok(true));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.attach_barcode(&product_id, barcode).await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::ProductAlreadyExists
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_detach_barcode() {
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+        let product = FakeProduct::new()
+            .with_barcodes(vec![barcode.clone()])
+            .build();
+        let product_id = product.id().clone();
+
+        let mut expected = product.clone();
+        expected.detach_barcode(&barcode).unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_save()
+            .with(eq(expected))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let product_service = transactional(transaction);
+
+        product_service
+            .detach_barcode(&product_id, &barcode)
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_detach_barcode_not_found() {
+        let product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.detach_barcode(&product_id, &barcode).await;
+
+        assert_eq!(result.unwrap_err(), ProductRepositoryError::BarcodeNotFound);
+    }
+
+    #[actix_web::test]
+    async fn test_get_stash_items() {
+        let expected_stash_items = vec![FakeStashItem::new().build(), FakeStashItem::new().build()];
+        let product = FakeProduct::new()
+            .with_stash_items(expected_stash_items.clone())
+            .build();
+        let product_id = product.id().clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+
+        let product_service = service(product_repository);
+
+        let result = product_service.get_stash_items(&product_id).await;
+
+        assert!(result.is_ok());
+
+        let stash_items = result.unwrap();
+
+        for stash_item in expected_stash_items {
+            assert!(stash_items.contains(&stash_item));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_product_by_stash_item_id() {
+        let stash_item = FakeStashItem::new().build();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item])
+            .build();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_by_stash_item_id()
+            .with(eq(stash_item_id))
+            .returning(move |_| Ok(Some(returned_product.clone())));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .get_product_by_stash_item_id(&stash_item_id)
+            .await;
+
+        let found_product = result.unwrap().unwrap();
+
+        assert_eq!(found_product, product);
+    }
+
+    #[actix_web::test]
+    async fn test_get_products_expiring_before() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+                .build()])
+            .build();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_expiring_in_interval()
+            .with(
+                eq(None),
+                eq(Some(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())),
+            )
+            .returning(move |_, _| Ok(vec![returned_product.clone()]));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .products_expiring_before(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], product);
+    }
+
+    #[actix_web::test]
+    async fn test_get_products_expiring_in_interval() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+                .build()])
+            .build();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_expiring_in_interval()
+            .with(
+                eq(NaiveDate::from_ymd_opt(2023, 1, 1)),
+                eq(NaiveDate::from_ymd_opt(2023, 1, 2)),
+            )
+            .returning(move |_, _| Ok(vec![returned_product.clone()]));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .products_expiring_in_interval(
+                NaiveDate::from_ymd_opt(2023, 1, 1),
+                NaiveDate::from_ymd_opt(2023, 1, 2),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result, vec![product]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_products_expiring_in_interval_unbounded() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new().build()])
+            .build();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_all_with_stash_items()
+            .returning(move || Ok(vec![returned_product.clone()]));
+        product_repository
+            .expect_find_expiring_in_interval()
+            .never();
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .products_expiring_in_interval(None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(result, vec![product]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_products_unsafe_before() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+                .with_expiry_date_kind(ExpiryDateKind::UseBy)
+                .build()])
+            .build();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_unsafe_in_interval()
+            .with(
+                eq(None),
+                eq(Some(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())),
+            )
+            .returning(move |_, _| Ok(vec![returned_product.clone()]));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .products_unsafe_before(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(result, vec![product]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_expiring_stash_items() {
+        let expiring = |day| {
+            FakeStashItem::new()
+                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, day).unwrap())
+                .build()
+        };
+        let matching = expiring(10);
+        let product = FakeProduct::new()
+            .with_stash_items(vec![
+                expiring(9),
+                matching.clone(),
+                expiring(20),
+                FakeStashItem::new().without_expiry_date().build(),
+            ])
+            .build();
+        let other_product = FakeProduct::new()
+            .with_stash_items(vec![expiring(25)])
+            .build();
+        let returned_products = vec![product.clone(), other_product];
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_expiring_in_interval()
+            .with(
+                eq(NaiveDate::from_ymd_opt(2023, 1, 10)),
+                eq(NaiveDate::from_ymd_opt(2023, 1, 20)),
+            )
+            .returning(move |_, _| Ok(returned_products.clone()));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .expiring_stash_items(
+                NaiveDate::from_ymd_opt(2023, 1, 10),
+                NaiveDate::from_ymd_opt(2023, 1, 20),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id(), product.id());
+        assert_eq!(result[0].stash_items(), HashSet::from([&matching]));
+    }
+
+    #[actix_web::test]
+    async fn test_get_expiring_stash_items_unbounded() {
+        let expiring = FakeStashItem::new().build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![
+                expiring.clone(),
+                FakeStashItem::new().without_expiry_date().build(),
+            ])
+            .build();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_all_with_stash_items()
+            .returning(move || Ok(vec![returned_product.clone()]));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .expiring_stash_items(None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].stash_items(), HashSet::from([&expiring]));
+    }
+
+    #[actix_web::test]
+    async fn test_get_all_products_with_stash_items() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new().build()])
+            .build();
+        let returned_product = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_find_all_with_stash_items()
+            .returning(move || Ok(vec![returned_product.clone()]));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .get_all_products_with_stash_items()
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], product);
+    }
+
+    #[actix_web::test]
+    async fn test_restore_from_trash_stash_item() {
+        let stash_item = FakeStashItem::new().build();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let product_id = product.id().clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_restore_stash_item_by_id()
+            .with(eq(stash_item_id))
+            .returning(|_| Ok(()));
+        product_repository.expect_restore_by_id().never();
+        product_repository
+            .expect_find_by_stash_item_id()
+            .with(eq(stash_item_id))
+            .returning(move |_| Ok(Some(product.clone())));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .restore_from_trash(&stash_item_id.to_string())
+            .await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            published(&product_service),
+            vec![StashEvent::StashItemAdded {
+                product_id,
+                stash_item
+            }]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_restore_from_trash_product() {
+        let product_id: ProductId = "7038010000737".parse().unwrap();
+        let product = FakeProduct::new().with_id(product_id.clone()).build();
+        let restored = product.clone();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository.expect_restore_stash_item_by_id().never();
+        product_repository
+            .expect_restore_by_id()
+            .with(eq(product_id.clone()))
+            .returning(|_| Ok(()));
+        product_repository
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(restored.clone())));
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .restore_from_trash(&product_id.to_string())
+            .await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            published(&product_service),
+            vec![StashEvent::ProductCreated {
+                product: Box::new(product)
+            }]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_restore_from_trash_product_with_uuid_id() {
+        let id = Uuid::new_v4().to_string();
+        let product_id: ProductId = id.parse().unwrap();
+
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_restore_stash_item_by_id()
+            .returning(|_| Err(ProductRepositoryError::StashItemNotFound));
+        product_repository
+            .expect_restore_by_id()
+            .with(eq(product_id))
+            .returning(|_| Err(ProductRepositoryError::ProductNotFound));
+
+        let product_service = service(product_repository);
+
+        let result = product_service.restore_from_trash(&id).await;
+
+        assert_eq!(result, Err(ProductRepositoryError::ProductNotFound));
+    }
+
+    #[actix_web::test]
+    async fn test_restore_from_trash_stash_item_conflict() {
+        let mut product_repository = MockProductRepository::new();
+        product_repository
+            .expect_restore_stash_item_by_id()
+            .returning(|_| Err(ProductRepositoryError::DuplicateExpiryDateError));
+        product_repository.expect_restore_by_id().never();
+
+        let product_service = service(product_repository);
+
+        let result = product_service
+            .restore_from_trash(&Uuid::new_v4().to_string())
+            .await;
+
+        assert_eq!(
+            result,
+            Err(ProductRepositoryError::DuplicateExpiryDateError)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_undo_operation_not_found() {
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_operation_by_id()
+            .returning(|_| Ok(None));
+        transaction.expect_delete_operation_by_id().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.undo_operation(&Uuid::new_v4()).await;
+
+        assert_eq!(result, Err(ProductRepositoryError::OperationNotFound));
+    }
+
+    #[actix_web::test]
+    async fn test_undo_add_stash_item() {
+        let stash_item = FakeStashItem::new().build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let product_id = product.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_save()
+            .withf(|product| product.stash_items().is_empty())
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RemoveStashItem {
+                product_id,
+                stash_item,
+            },
+            true,
+        );
+        let product_service = transactional(transaction);
+
+        let result = product_service.undo_operation(&operation_id).await;
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[actix_web::test]
+    async fn test_undo_add_stash_item_changed_since() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let product_id = product.id().clone();
+        product
+            .update_stash_item(
+                FakeStashItem::new()
+                    .with_id(*stash_item.id())
+                    .with_quantity(1.try_into().unwrap())
+                    .build(),
+            )
+            .unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction.expect_save().never();
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RemoveStashItem {
+                product_id,
+                stash_item,
+            },
+            false,
+        );
+        let product_service = transactional(transaction);
+
+        let result = product_service.undo_operation(&operation_id).await;
+
+        assert_eq!(result, Err(ProductRepositoryError::UndoConflict));
+    }
+
+    #[actix_web::test]
+    async fn test_undo_update_stash_item() {
+        let before = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .build();
+        let after = FakeStashItem::new()
+            .with_id(*before.id())
+            .with_quantity(2.try_into().unwrap())
+            .build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![after.clone()])
+            .build();
+        let product_id = product.id().clone();
+
+        let mut expected = product.clone();
+        expected.update_stash_item(before.clone()).unwrap();
+
+        // The saved product records the quantity changing back, to be published once it is committed
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
+            .with(eq(product_id.clone()))
+            .returning(move |_| Ok(Some(product.clone())));
+        transaction
+            .expect_save()
+            .withf(move |product| product == &expected && product.events() == expected.events())
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RevertStashItem {
+                product_id,
+                before,
+                after,
+            },
+            true,
+        );
+        let product_service = transactional(transaction);
+
+        let result = product_service.undo_operation(&operation_id).await;
+
+        assert_eq!(result, Ok(()));
+        assert!(published(&product_service).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_undo_delete_stash_item() {
+        let stash_item = FakeStashItem::new().build();
+        let stash_item_id = *stash_item.id();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let product_id = product.id().clone();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_restore_stash_item_by_id()
+            .with(eq(stash_item_id))
+            .times(1)
+            .returning(|_| Ok(()));
+        transaction
+            .expect_find_by_stash_item_id()
+            .with(eq(stash_item_id))
+            .returning(move |_| Ok(Some(product.clone())));
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RestoreStashItem { stash_item_id },
+            true,
+        );
+        let product_service = transactional(transaction);
+
+        let result = product_service.undo_operation(&operation_id).await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            published(&product_service),
+            vec![StashEvent::StashItemAdded {
+                product_id,
+                stash_item
+            }]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_undo_delete_stash_item_already_restored() {
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_restore_stash_item_by_id()
+            .returning(|_| Err(ProductRepositoryError::StashItemNotFound));
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RestoreStashItem {
+                stash_item_id: Uuid::new_v4(),
+            },
+            false,
+        );
+        let product_service = transactional(transaction);
+
+        let result = product_service.undo_operation(&operation_id).await;
 
-    use super::*;
+        assert_eq!(result, Err(ProductRepositoryError::UndoConflict));
+    }
 
-    #[test]
-    fn test_get_product_by_id() {
-        let product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-        let returned_product = product.clone();
+    #[actix_web::test]
+    async fn test_undo_update_product() {
+        let before = FakeProduct::new().build();
+        let after = FakeProduct::new().with_id(before.id().clone()).build();
+        let product_id = before.id().clone();
+        let current = after.clone();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
+        let mut transaction = MockProductTransaction::new();
+        transaction
             .expect_find_by_id()
-            .with(eq(product_id.clone()))
-            .returning(move |_| Ok(Some(returned_product.clone())));
+            .with(eq(product_id))
+            .returning(move |_| Ok(Some(current.clone())));
+        transaction
+            .expect_save()
+            .with(eq(before.clone()))
+            .times(1)
+            .returning(|_| Ok(()));
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RevertProduct {
+                before: Box::new(before),
+                after: Box::new(after),
+            },
+            true,
+        );
+        let product_service = transactional(transaction);
 
-        let found_product = product_service.get_product(&product_id).unwrap().unwrap();
+        let result = product_service.undo_operation(&operation_id).await;
 
-        assert_eq!(found_product, product);
+        assert_eq!(result, Ok(()));
     }
 
-    #[test]
-    fn test_get_product_by_id_not_found() {
-        let product_id: ProductId = "ID".parse().unwrap();
+    #[actix_web::test]
+    async fn test_undo_update_product_changed_since() {
+        let before = FakeProduct::new().build();
+        let after = FakeProduct::new().with_id(before.id().clone()).build();
+        let product_id = before.id().clone();
+        let current = FakeProduct::new().with_id(before.id().clone()).build();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
+        let mut transaction = MockProductTransaction::new();
+        transaction
             .expect_find_by_id()
-            .with(eq(product_id.clone()))
-            .returning(|_| Ok(None));
-
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+            .with(eq(product_id))
+            .returning(move |_| Ok(Some(current.clone())));
+        transaction.expect_save().never();
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RevertProduct {
+                before: Box::new(before),
+                after: Box::new(after),
+            },
+            false,
+        );
+        let product_service = transactional(transaction);
 
-        let found_product = product_service.get_product(&product_id).unwrap();
+        let result = product_service.undo_operation(&operation_id).await;
 
-        assert!(found_product.is_none());
+        assert_eq!(result, Err(ProductRepositoryError::UndoConflict));
     }
 
-    #[test]
-    fn test_create_product() {
+    #[actix_web::test]
+    async fn test_undo_delete_product() {
         let product = FakeProduct::new().build();
         let product_id = product.id().clone();
-        let returned_product = product.clone();
+        let restored = product.clone();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_exists_by_id()
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_restore_by_id()
             .with(eq(product_id.clone()))
-            .returning(|_| Ok(false));
-        product_repository
-            .expect_save()
-            .with(eq(product.clone()))
+            .times(1)
             .returning(|_| Ok(()));
-        product_repository
+        transaction
             .expect_find_by_id()
             .with(eq(product_id.clone()))
-            .returning(move |_| Ok(Some(returned_product.clone())));
+            .returning(move |_| Ok(Some(restored.clone())));
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RestoreProduct { product_id },
+            true,
+        );
+        let product_service = transactional(transaction);
 
-        let created_product = product_service.create_product(product.clone()).unwrap();
+        let result = product_service.undo_operation(&operation_id).await;
 
-        assert_eq!(created_product, product);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            published(&product_service),
+            vec![StashEvent::ProductCreated {
+                product: Box::new(product)
+            }]
+        );
     }
 
-    #[test]
-    fn test_create_product_already_exists() {
-        let product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_exists_by_id()
-            .with(eq(product_id.clone()))
-            .returning(|_| Ok(true));
-
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+    #[actix_web::test]
+    async fn test_undo_delete_product_id_taken() {
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_restore_by_id()
+            .returning(|_| Err(ProductRepositoryError::ProductAlreadyExists));
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::RestoreProduct {
+                product_id: "ID".parse().unwrap(),
+            },
+            false,
+        );
+        let product_service = transactional(transaction);
 
-        let created_product = product_service.create_product(product.clone());
+        let result = product_service.undo_operation(&operation_id).await;
 
-        assert_eq!(
-            created_product.unwrap_err(),
-            ProductRepositoryError::ProductAlreadyExists
-        );
+        assert_eq!(result, Err(ProductRepositoryError::UndoConflict));
     }
 
-    #[test]
-    fn test_update_product() {
-        let product = FakeProduct::new().build();
+    #[actix_web::test]
+    async fn test_undo_batch() {
+        let product = FakeProduct::new().with_stash_items(vec![]).build();
         let product_id = product.id().clone();
-        let returned_product = product.clone();
+        let stash_item = FakeStashItem::new().build();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_exists_by_id()
+        let mut with_stash_item = product.clone();
+        with_stash_item.add_stash_item(stash_item.clone()).unwrap();
+        with_stash_item.take_events();
+
+        // The stash item added last is removed first, after which the product is as it was created
+        let mut transaction = MockProductTransaction::new();
+        let mut sequence = mockall::Sequence::new();
+        let found = with_stash_item.clone();
+        transaction
+            .expect_find_by_id()
             .with(eq(product_id.clone()))
-            .returning(|_| Ok(true));
-        product_repository
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(move |_| Ok(Some(found.clone())));
+        let saved = product.clone();
+        let removed = ProductEvent::StashItemRemoved {
+            product_id: product_id.clone(),
+            stash_item: stash_item.clone(),
+        };
+        transaction
             .expect_save()
-            .with(eq(product.clone()))
+            .withf(move |product| product == &saved && product.events() == [removed.clone()])
+            .times(1)
+            .in_sequence(&mut sequence)
             .returning(|_| Ok(()));
-        product_repository
+        let found = product.clone();
+        transaction
             .expect_find_by_id()
             .with(eq(product_id.clone()))
-            .returning(move |_| Ok(Some(returned_product.clone())));
-
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
-
-        let updated_product = product_service
-            .update_product(&product_id, product.clone())
-            .unwrap();
-
-        assert_eq!(updated_product, product);
-    }
-
-    #[test]
-    fn test_update_product_not_found() {
-        let product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_exists_by_id()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(move |_| Ok(Some(found.clone())));
+        transaction
+            .expect_delete_by_id()
             .with(eq(product_id.clone()))
-            .returning(|_| Ok(false));
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(()));
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::Batch {
+                operations: vec![
+                    InverseOperation::DeleteProduct {
+                        product: Box::new(product),
+                    },
+                    InverseOperation::RemoveStashItem {
+                        product_id: product_id.clone(),
+                        stash_item: stash_item.clone(),
+                    },
+                ],
+            },
+            true,
+        );
+        let product_service = transactional(transaction);
 
-        let updated_product = product_service.update_product(&product_id, product.clone());
+        let result = product_service.undo_operation(&operation_id).await;
 
+        // The product records the stash item being removed, to be published once it is committed
+        assert_eq!(result, Ok(()));
         assert_eq!(
-            updated_product.unwrap_err(),
-            ProductRepositoryError::ProductNotFound
+            published(&product_service),
+            vec![StashEvent::ProductDeleted { product_id }]
         );
     }
 
-    #[test]
-    fn test_delete_product() {
-        let product_id: ProductId = "ID".parse().unwrap();
+    #[actix_web::test]
+    async fn test_undo_batch_changed_since() {
+        let product = FakeProduct::new().with_stash_items(vec![]).build();
+        let product_id = product.id().clone();
+        let changed = FakeProduct::new().with_id(product_id.clone()).build();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_delete_by_id()
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_find_by_id()
             .with(eq(product_id.clone()))
-            .returning(|_| Ok(()));
-
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+            .returning(move |_| Ok(Some(changed.clone())));
+        transaction.expect_delete_by_id().never();
+
+        let operation_id = holding(
+            &mut transaction,
+            InverseOperation::Batch {
+                operations: vec![InverseOperation::DeleteProduct {
+                    product: Box::new(product),
+                }],
+            },
+            false,
+        );
+        let product_service = transactional(transaction);
 
-        let deleted_product = product_service.delete_product(&product_id);
+        let result = product_service.undo_operation(&operation_id).await;
 
-        assert!(deleted_product.is_ok());
+        assert_eq!(result, Err(ProductRepositoryError::UndoConflict));
+        assert!(published(&product_service).is_empty());
     }
 
-    #[test]
-    fn test_delete_product_not_found() {
-        let product_id: ProductId = "ID".parse().unwrap();
+    #[actix_web::test]
+    async fn test_execute_batch() {
+        let product = FakeProduct::new().with_stash_items(vec![]).build();
+        let product_id = product.id().clone();
+        let first = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 1).unwrap())
+            .build();
+        let second = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 2).unwrap())
+            .build();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_delete_by_id()
+        let mut with_first = product.clone();
+        with_first.add_stash_item(first.clone()).unwrap();
+        let mut with_both = with_first.clone();
+        with_both.add_stash_item(second.clone()).unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
+            .expect_exists_by_id()
             .with(eq(product_id.clone()))
+            .returning(|_| Ok(false));
+        transaction
+            .expect_save()
+            .with(eq(product.clone()))
+            .times(1)
             .returning(|_| Ok(()));
-
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
-
-        let deleted_product = product_service.delete_product(&product_id);
-
-        assert!(deleted_product.is_ok());
-    }
-
-    #[test]
-    fn test_add_stash_item() {
-        let product = FakeProduct::new().with_stash_items(Vec::new()).build();
-        let product_id = product.id().clone();
-        let stash_item = FakeStashItem::new().build();
-
-        let mut product_repository = MockProductRepository::new();
-        product_repository
+        let found = with_first.clone();
+        transaction
             .expect_find_by_id()
             .with(eq(product_id.clone()))
-            .returning(move |_| Ok(Some(product.clone())));
-        product_repository
+            .times(1)
+            .returning(move |_| Ok(Some(found.clone())));
+        transaction
             .expect_save()
-            .withf(move |product| product.stash_items().len() == 1)
+            .with(eq(with_both.clone()))
+            .times(1)
             .returning(|_| Ok(()));
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        recording(
+            &mut transaction,
+            InverseOperation::Batch {
+                operations: vec![
+                    InverseOperation::DeleteProduct {
+                        product: Box::new(product.clone()),
+                    },
+                    InverseOperation::RemoveStashItem {
+                        product_id: product_id.clone(),
+                        stash_item: second.clone(),
+                    },
+                ],
+            },
+        );
+        let product_service = transactional(transaction);
 
-        let result = product_service.add_stash_item(&product_id, stash_item);
+        let result = product_service
+            .execute_batch(vec![
+                BatchOperation::CreateProduct {
+                    product: Box::new(product.clone()),
+                },
+                BatchOperation::AddStashItem {
+                    product_id,
+                    stash_item: second,
+                },
+            ])
+            .await;
 
-        assert!(result.is_ok());
+        assert_eq!(
+            result.map(|(products, _)| products),
+            Ok(vec![product, with_both.clone()])
+        );
+        // The stash item added is published as the product saves it, so the product is published as the batch left it
+        assert_eq!(
+            published(&product_service),
+            vec![StashEvent::ProductCreated {
+                product: Box::new(with_both)
+            }]
+        );
     }
 
-    #[test]
-    fn test_update_stash_item() {
-        let stash_item_id = Uuid::new_v4();
+    #[actix_web::test]
+    async fn test_execute_batch_delete_stash_item() {
+        let stash_item = FakeStashItem::new().build();
         let product = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new().with_id(stash_item_id).build()])
+            .with_stash_items(vec![stash_item.clone()])
             .build();
         let product_id = product.id().clone();
-        let stash_item = FakeStashItem::new().with_id(stash_item_id).build();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
+        let mut expected = product.clone();
+        expected.remove_stash_item(stash_item.id()).unwrap();
+
+        let mut transaction = MockProductTransaction::new();
+        transaction
             .expect_find_by_id()
             .with(eq(product_id.clone()))
             .returning(move |_| Ok(Some(product.clone())));
-        product_repository.expect_save().returning(|_| Ok(()));
-
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        transaction
+            .expect_delete_stash_item_by_id()
+            .with(eq(*stash_item.id()))
+            .times(1)
+            .returning(|_| Ok(()));
+        transaction.expect_save().never();
+
+        recording(
+            &mut transaction,
+            InverseOperation::Batch {
+                operations: vec![InverseOperation::RestoreStashItem {
+                    stash_item_id: *stash_item.id(),
+                }],
+            },
+        );
+        let product_service = transactional(transaction);
 
-        let result = product_service.update_stash_item(&product_id, stash_item.clone());
+        let result = product_service
+            .execute_batch(vec![BatchOperation::DeleteStashItem {
+                product_id,
+                stash_item_id: *stash_item.id(),
+            }])
+            .await;
 
-        assert!(result.is_ok());
+        assert_eq!(result.map(|(products, _)| products), Ok(vec![expected]));
     }
 
-    #[test]
-    fn test_delete_stash_item() {
-        let stash_item_id = Uuid::new_v4();
+    #[actix_web::test]
+    async fn test_execute_batch_update_stash_item() {
+        let before = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .build();
+        let after = FakeStashItem::new()
+            .with_id(*before.id())
+            .with_quantity(2.try_into().unwrap())
+            .build();
         let product = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new().with_id(stash_item_id).build()])
+            .with_stash_items(vec![before.clone()])
             .build();
         let product_id = product.id().clone();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
+        let mut expected = product.clone();
+        expected.update_stash_item(after.clone()).unwrap();
+
+        // The saved product records the quantity changing, to be published once it is committed
+        let mut transaction = MockProductTransaction::new();
+        transaction
             .expect_find_by_id()
             .with(eq(product_id.clone()))
             .returning(move |_| Ok(Some(product.clone())));
-        product_repository
+        let saved = expected.clone();
+        transaction
             .expect_save()
-            .withf(move |product| product.stash_items().len() == 0)
+            .withf(move |product| product == &saved && product.events() == saved.events())
+            .times(1)
             .returning(|_| Ok(()));
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
-
-        let result = product_service.delete_stash_item(&product_id, &stash_item_id);
+        recording(
+            &mut transaction,
+            InverseOperation::Batch {
+                operations: vec![InverseOperation::RevertStashItem {
+                    product_id: product_id.clone(),
+                    before,
+                    after: after.clone(),
+                }],
+            },
+        );
+        let product_service = transactional(transaction);
 
-        assert!(result.is_ok());
+        let result = product_service
+            .execute_batch(vec![BatchOperation::UpdateStashItem {
+                product_id,
+                stash_item: after,
+            }])
+            .await;
+
+        assert_eq!(result.map(|(products, _)| products), Ok(vec![expected]));
+        assert!(published(&product_service).is_empty());
     }
 
-    #[test]
-    fn test_get_stash_items() {
-        let expected_stash_items = vec![FakeStashItem::new().build(), FakeStashItem::new().build()];
-        let product = FakeProduct::new()
-            .with_stash_items(expected_stash_items.clone())
-            .build();
-        let product_id = product.id().clone();
+    #[actix_web::test]
+    async fn test_execute_batch_stops_at_failed_operation() {
+        let product_id: ProductId = "ID".parse().unwrap();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
+        let mut transaction = MockProductTransaction::new();
+        transaction
             .expect_find_by_id()
-            .with(eq(product_id.clone()))
-            .returning(move |_| Ok(Some(product.clone())));
+            .times(1)
+            .returning(|_| Ok(None));
+        transaction.expect_save().never();
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        let product_service = transactional(transaction);
 
-        let result = product_service.get_stash_items(&product_id);
+        let result = product_service
+            .execute_batch(vec![
+                BatchOperation::AddStashItem {
+                    product_id: product_id.clone(),
+                    stash_item: FakeStashItem::new().build(),
+                },
+                BatchOperation::DeleteStashItem {
+                    product_id,
+                    stash_item_id: Uuid::new_v4(),
+                },
+            ])
+            .await;
 
-        assert!(result.is_ok());
+        assert_eq!(
+            result,
+            Err(BatchError::OperationFailed {
+                index: 0,
+                error: ProductRepositoryError::ProductNotFound
+            })
+        );
+    }
 
-        let stash_items = result.unwrap();
+    #[actix_web::test]
+    async fn test_execute_batch_product_already_exists() {
+        let product = FakeProduct::new().build();
 
-        for stash_item in expected_stash_items {
-            assert!(stash_items.contains(&stash_item));
-        }
+        let mut transaction = MockProductTransaction::new();
+        transaction.expect_exists_by_id().returning(|_| Ok(true));
+        transaction.expect_save().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service
+            .execute_batch(vec![BatchOperation::CreateProduct {
+                product: Box::new(product),
+            }])
+            .await;
+
+        assert_eq!(
+            result,
+            Err(BatchError::OperationFailed {
+                index: 0,
+                error: ProductRepositoryError::ProductAlreadyExists
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_execute_batch_empty() {
+        let mut transaction = MockProductTransaction::new();
+        transaction.expect_save_operation().never();
+
+        let product_service = transactional(transaction);
+
+        let result = product_service.execute_batch(vec![]).await;
+
+        // An empty batch did nothing, so there is nothing to undo
+        assert_eq!(result, Ok((vec![], None)));
+    }
+
+    /// Makes a service keeping products and operations in memory, which publishes the events of the products it saves
+    fn in_memory() -> ProductService {
+        let event_bus = Arc::new(EventBus::new(16));
+        let mut event_dispatcher = ProductEventDispatcher::new();
+        event_dispatcher.register(Box::new(ProductEventForwarder::new(event_bus.clone())));
+        let event_dispatcher = Arc::new(event_dispatcher);
+
+        let product_repository =
+            memory::ProductRepository::new().with_event_dispatcher(event_dispatcher.clone());
+        let unit_of_work =
+            memory::UnitOfWork::new(&product_repository).with_event_dispatcher(event_dispatcher);
+
+        ProductService::new(
+            Arc::new(Box::new(product_repository)),
+            Arc::new(Box::new(unit_of_work)),
+            event_bus,
+        )
     }
 
-    #[test]
-    fn test_get_product_by_stash_item_id() {
+    #[actix_web::test]
+    async fn test_add_stash_item_and_undo_in_memory() {
+        let product_service = in_memory();
+        let product = FakeProduct::new().with_stash_items(vec![]).build();
+        let product_id = product.id().clone();
         let stash_item = FakeStashItem::new().build();
-        let stash_item_id = stash_item.id().clone();
-        let product = FakeProduct::new()
-            .with_stash_items(vec![stash_item])
-            .build();
-        let returned_product = product.clone();
+        product_service.create_product(product).await.unwrap();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_find_by_stash_item_id()
-            .with(eq(stash_item_id.clone()))
-            .returning(move |_| Ok(Some(returned_product.clone())));
+        let operation_id = product_service
+            .add_stash_item(&product_id, stash_item.clone())
+            .await
+            .unwrap();
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        let found = product_service.get_product(&product_id).await.unwrap();
+        assert!(found.unwrap().has_stash_item(stash_item.id()));
 
-        let result = product_service.get_product_by_stash_item_id(&stash_item_id);
+        product_service.undo_operation(&operation_id).await.unwrap();
 
-        let found_product = result.unwrap().unwrap();
+        let found = product_service.get_product(&product_id).await.unwrap();
+        assert!(found.unwrap().stash_items().is_empty());
+        assert_eq!(
+            published(&product_service)[1..],
+            [
+                StashEvent::StashItemAdded {
+                    product_id: product_id.clone(),
+                    stash_item: stash_item.clone()
+                },
+                StashEvent::StashItemRemoved {
+                    product_id,
+                    stash_item_id: *stash_item.id()
+                }
+            ]
+        );
 
-        assert_eq!(found_product, product);
+        // An operation is only undone once
+        assert_eq!(
+            product_service.undo_operation(&operation_id).await,
+            Err(ProductRepositoryError::OperationNotFound)
+        );
     }
 
-    #[test]
-    fn test_get_products_expiring_before() {
-        let product = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 01, 01).unwrap())
-                .build()])
+    #[actix_web::test]
+    async fn test_move_stash_item_in_memory() {
+        let product_service = in_memory();
+        let stash_item = FakeStashItem::new().build();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
             .build();
-        let returned_product = product.clone();
+        let target = FakeProduct::new().with_stash_items(vec![]).build();
+        product_service
+            .create_product(source.clone())
+            .await
+            .unwrap();
+        product_service
+            .create_product(target.clone())
+            .await
+            .unwrap();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_find_expiring_in_interval()
-            .with(
-                eq(None),
-                eq(Some(NaiveDate::from_ymd_opt(2023, 01, 02).unwrap())),
-            )
-            .returning(move |_, _| Ok(vec![returned_product.clone()]));
+        let moved = product_service
+            .move_stash_item(stash_item.id(), target.id(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(moved, stash_item);
+        let source = product_service.get_product(source.id()).await.unwrap();
+        assert!(source.unwrap().stash_items().is_empty());
+        let target = product_service.get_product(target.id()).await.unwrap();
+        assert!(target.unwrap().has_stash_item(stash_item.id()));
+    }
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+    #[actix_web::test]
+    async fn test_create_product_and_undo_in_memory() {
+        let product_service = in_memory();
+        let product = FakeProduct::new().build();
 
-        let result = product_service
-            .products_expiring_before(NaiveDate::from_ymd_opt(2023, 01, 02).unwrap())
+        let (_, operation_id) = product_service
+            .create_product(product.clone())
+            .await
             .unwrap();
+        product_service.undo_operation(&operation_id).await.unwrap();
 
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0], product);
+        assert_eq!(product_service.get_product(product.id()).await, Ok(None));
     }
 
-    #[test]
-    fn test_get_all_products_with_stash_items() {
+    #[actix_web::test]
+    async fn test_consume_stash_item_and_undo_in_memory() {
+        let product_service = in_memory();
+        let stash_item = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .build();
         let product = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new().build()])
+            .with_stash_items(vec![stash_item.clone()])
             .build();
-        let returned_product = product.clone();
+        product_service
+            .create_product(product.clone())
+            .await
+            .unwrap();
 
-        let mut product_repository = MockProductRepository::new();
-        product_repository
-            .expect_find_all_with_stash_items()
-            .returning(move || Ok(vec![returned_product.clone()]));
+        let (consumed, operation_id) = product_service
+            .consume_stash_item(product.id(), stash_item.id(), 2.try_into().unwrap(), None)
+            .await
+            .unwrap();
+        assert_eq!(consumed, None);
 
-        let product_service = ProductService::new(Arc::new(Box::new(product_repository)));
+        product_service.undo_operation(&operation_id).await.unwrap();
 
-        let result = product_service.get_all_products_with_stash_items().unwrap();
+        let found = product_service.get_product(product.id()).await.unwrap();
+        assert_eq!(
+            found.unwrap().stash_item(stash_item.id()),
+            Some(&stash_item)
+        );
+    }
 
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0], product);
+    #[actix_web::test]
+    async fn test_undo_conflict_keeps_operation_in_memory() {
+        let product_service = in_memory();
+        let product = FakeProduct::new().with_stash_items(vec![]).build();
+        let stash_item = FakeStashItem::new().build();
+        product_service
+            .create_product(product.clone())
+            .await
+            .unwrap();
+        let operation_id = product_service
+            .add_stash_item(product.id(), stash_item.clone())
+            .await
+            .unwrap();
+
+        // The stash item changes since, so adding it can not be undone until it is as the operation left it
+        let mut changed = stash_item.clone();
+        changed.set_quantity(3.try_into().unwrap());
+        product_service
+            .update_stash_item(product.id(), changed)
+            .await
+            .unwrap();
+        assert_eq!(
+            product_service.undo_operation(&operation_id).await,
+            Err(ProductRepositoryError::UndoConflict)
+        );
+
+        product_service
+            .update_stash_item(product.id(), stash_item)
+            .await
+            .unwrap();
+        assert_eq!(product_service.undo_operation(&operation_id).await, Ok(()));
     }
 }
@@ -1,7 +1,10 @@
 use crate::domain::{
     entities::StashItem, errors::ProductRepositoryError, value_objects::ProductId,
 };
+use async_trait::async_trait;
+use uuid::Uuid;
 
+#[async_trait]
 pub trait AddStashItem {
     /// Add a stash item to a product.
     ///
@@ -10,10 +13,10 @@ pub trait AddStashItem {
     /// - `stash_item` - The stash item to add.
     ///
     /// # Returns
-    /// Nothing if successful, otherwise an error is returned.
-    fn add_stash_item(
+    /// The ID of the operation, to undo it by, if successful, otherwise an error is returned.
+    async fn add_stash_item(
         &self,
         product_id: &ProductId,
         stash_item: StashItem,
-    ) -> Result<(), ProductRepositoryError>;
+    ) -> Result<Uuid, ProductRepositoryError>;
 }
@@ -0,0 +1,21 @@
+use crate::domain::{entities::Product, errors::ProductRepositoryError, value_objects::ProductId};
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait AttachBarcode {
+    /// Attaches a barcode to a product, so the product can also be found by it
+    ///
+    /// # Parameters
+    /// - `product_id` - The ID or a barcode of the product
+    /// - `barcode` - The barcode to attach
+    ///
+    /// # Returns
+    /// `Ok(Product)` with the barcode attached
+    /// `Err(ProductRepositoryError::ProductNotFound)` if the product does not exist
+    /// `Err(ProductRepositoryError::ProductAlreadyExists)` if the barcode belongs to another product
+    async fn attach_barcode(
+        &self,
+        product_id: &ProductId,
+        barcode: ProductId,
+    ) -> Result<Product, ProductRepositoryError>;
+}
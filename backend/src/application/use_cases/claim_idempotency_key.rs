@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+
+use crate::domain::{errors::IdempotencyError, value_objects::IdempotencyClaim};
+
+#[async_trait]
+pub trait ClaimIdempotencyKey {
+    /// Claims an idempotency key for a request, telling whether the request is to be handled or was already handled
+    ///
+    /// # Parameters
+    /// * `key` - The idempotency key the client sent along with the request
+    /// * `fingerprint` - Fingerprint of the request
+    ///
+    /// # Returns
+    /// * `Ok(claim)` with what to do with the request
+    /// * `Err(_)` if the underlying data store fails to claim the key
+    async fn claim_idempotency_key(
+        &self,
+        key: &str,
+        fingerprint: &str,
+    ) -> Result<IdempotencyClaim, IdempotencyError>;
+}
@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::StashItem,
+    errors::ProductRepositoryError,
+    value_objects::{ProductId, Quantity, Unit},
+};
+
+#[async_trait]
+pub trait ConsumeStashItem {
+    /// Consumes an amount of a stash item. A stash item consumed completely is removed.
+    ///
+    /// # Parameters
+    /// - `product_id` - ID of the product the stash item belongs to.
+    /// - `stash_item_id` - ID of the stash item to consume from.
+    /// - `amount` - Amount to consume.
+    /// - `unit` - Unit of the amount. Must be compatible with the unit of the product. Defaults to the unit of the
+    ///   product.
+    ///
+    /// # Returns
+    /// `Ok((Some(StashItem), operation_id))` with what is left of the stash item and the ID of the operation, to undo
+    ///   it by
+    /// `Ok((None, operation_id))` if the stash item was consumed completely
+    /// `Err(ProductRepositoryError::ProductNotFound)` if the product does not exist
+    /// `Err(ProductRepositoryError::StashItemNotFound)` if the stash item does not exist on the product
+    /// `Err(ProductRepositoryError::UnitError)` if the unit is not compatible with the unit of the product
+    /// `Err(ProductRepositoryError::QuantityError)` if the amount is larger than what is left of the stash item
+    async fn consume_stash_item(
+        &self,
+        product_id: &ProductId,
+        stash_item_id: &Uuid,
+        amount: Quantity,
+        unit: Option<Unit>,
+    ) -> Result<(Option<StashItem>, Uuid), ProductRepositoryError>;
+}
@@ -1,5 +1,8 @@
 use crate::domain::{entities::Product, errors::ProductRepositoryError};
+use async_trait::async_trait;
+use uuid::Uuid;
 
+#[async_trait]
 pub trait CreateProduct {
     /// Creates a new product
     ///
@@ -7,7 +10,10 @@ pub trait CreateProduct {
     /// - `product` - The product to create
     ///
     /// # Returns
-    /// `Ok(ProductId)` if the product was created successfully
+    /// `Ok((product, operation_id))` with the created product and the ID of the operation, to undo it by
     /// `Err(String)` if the product could not be created
-    fn create_product(&self, product: Product) -> Result<Product, ProductRepositoryError>;
+    async fn create_product(
+        &self,
+        product: Product,
+    ) -> Result<(Product, Uuid), ProductRepositoryError>;
 }
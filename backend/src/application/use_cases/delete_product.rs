@@ -1,5 +1,8 @@
 use crate::domain::{errors::ProductRepositoryError, value_objects::ProductId};
+use async_trait::async_trait;
+use uuid::Uuid;
 
+#[async_trait]
 pub trait DeleteProduct {
     /// Deletes a product by id
     ///
@@ -7,7 +10,8 @@ pub trait DeleteProduct {
     /// * `id` - The id of the product to delete
     ///
     /// # Returns
-    /// * `Ok(())` if the product was deleted, or was not there in the first place
+    /// * `Ok(Some(operation_id))` with the ID of the operation, to undo it by, if the product was deleted
+    /// * `Ok(None)` if the product was not there in the first place
     /// * `Err(_)` if the underlying data store fails to delete the product
-    fn delete_product(&self, id: &ProductId) -> Result<(), ProductRepositoryError>;
+    async fn delete_product(&self, id: &ProductId) -> Result<Option<Uuid>, ProductRepositoryError>;
 }
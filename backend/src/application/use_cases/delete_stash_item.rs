@@ -1,7 +1,9 @@
+use async_trait::async_trait;
 use uuid::Uuid;
 
 use crate::domain::{errors::ProductRepositoryError, value_objects::ProductId};
 
+#[async_trait]
 pub trait DeleteStashItem {
     /// Delete a stash item from a product.
     ///
@@ -10,10 +12,10 @@ pub trait DeleteStashItem {
     /// - `stash_item_id` - The stash item id.
     ///
     /// # Returns
-    /// Nothing if successful, otherwise an error is returned.
-    fn delete_stash_item(
+    /// The ID of the operation, to undo it by, if successful, otherwise an error is returned.
+    async fn delete_stash_item(
         &self,
         product_id: &ProductId,
         stash_item_id: &Uuid,
-    ) -> Result<(), ProductRepositoryError>;
+    ) -> Result<Uuid, ProductRepositoryError>;
 }
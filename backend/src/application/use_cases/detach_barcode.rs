@@ -0,0 +1,22 @@
+use crate::domain::{errors::ProductRepositoryError, value_objects::ProductId};
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait DetachBarcode {
+    /// Detaches a barcode from a product
+    ///
+    /// # Parameters
+    /// - `product_id` - The ID or a barcode of the product
+    /// - `barcode` - The barcode to detach
+    ///
+    /// # Returns
+    /// `Ok(())` if the barcode was detached
+    /// `Err(ProductRepositoryError::ProductNotFound)` if the product does not exist
+    /// `Err(ProductRepositoryError::BarcodeNotFound)` if the barcode is not attached to the product
+    /// `Err(ProductRepositoryError::CannotDetachPrimaryBarcode)` if the barcode is the ID of the product
+    async fn detach_barcode(
+        &self,
+        product_id: &ProductId,
+        barcode: &ProductId,
+    ) -> Result<(), ProductRepositoryError>;
+}
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::{entities::Product, errors::BatchError, value_objects::BatchOperation};
+
+#[async_trait]
+pub trait ExecuteBatch {
+    /// Applies a batch of operations in order, all together or not at all
+    ///
+    /// # Parameters
+    /// * `operations` - The operations to apply
+    ///
+    /// # Returns
+    /// * `Ok((products, operation_id))` with the product each operation was applied to, as it was right after the
+    ///   operation, and the ID to undo the whole batch by. An empty batch has nothing to undo, so it has no ID
+    /// * `Err(BatchError::OperationFailed { .. })` if one of the operations failed, so none of them were applied
+    /// * `Err(BatchError::BatchFailed(_))` if the underlying data store fails to apply the batch
+    async fn execute_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+    ) -> Result<(Vec<Product>, Option<Uuid>), BatchError>;
+}
@@ -1,6 +1,10 @@
 use crate::domain::{entities::Product, errors::ProductRepositoryError};
+use async_trait::async_trait;
 
+#[async_trait]
 pub trait GetAllProductsWithStashItems {
     /// Gets all products with stash items
-    fn get_all_products_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError>;
+    async fn get_all_products_with_stash_items(
+        &self,
+    ) -> Result<Vec<Product>, ProductRepositoryError>;
 }
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+
+use crate::domain::{entities::Product, errors::ProductRepositoryError};
+
+#[async_trait]
+pub trait GetExpiringStashItems {
+    /// Gets the stash items expiring within the given date interval, along with their products. Stash items expiring
+    /// outside the interval, and stash items which do not expire, are left out of the products
+    ///
+    /// # Parameters
+    /// - `after` - The start of the date range, inclusive
+    /// - `before` - The end of the date range, exclusive
+    ///
+    /// # Returns
+    /// A list of products holding only the stash items expiring within the given date interval
+    async fn expiring_stash_items(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError>;
+}
@@ -1,5 +1,7 @@
 use crate::domain::{entities::Product, errors::ProductRepositoryError, value_objects::ProductId};
+use async_trait::async_trait;
 
+#[async_trait]
 pub trait GetProduct {
     /// Gets a product by id
     ///
@@ -10,5 +12,5 @@ pub trait GetProduct {
     /// * `Ok(Some(product))` if the product was found
     /// * `Ok(None)` if the product was not found
     /// * `Err(_)` if the underlying data store fails to get the product
-    fn get_product(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError>;
+    async fn get_product(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError>;
 }
@@ -1,7 +1,9 @@
+use async_trait::async_trait;
 use uuid::Uuid;
 
 use crate::domain::{entities::Product, errors::ProductRepositoryError};
 
+#[async_trait]
 pub trait GetProductByStashItemId {
     /// Get a product by the ID of a stash item that belongs to it
     ///
@@ -10,7 +12,7 @@ pub trait GetProductByStashItemId {
     ///
     /// # Returns
     /// The product that the stash item belongs to, if it exists
-    fn get_product_by_stash_item_id(
+    async fn get_product_by_stash_item_id(
         &self,
         stash_item_id: &Uuid,
     ) -> Result<Option<Product>, ProductRepositoryError>;
@@ -1,7 +1,9 @@
+use async_trait::async_trait;
 use chrono::NaiveDate;
 
 use crate::domain::{entities::Product, errors::ProductRepositoryError};
 
+#[async_trait]
 pub trait GetProductsExpiringBefore {
     /// Gets all products with at least one stash item expiring before the given date
     ///
@@ -10,7 +12,7 @@ pub trait GetProductsExpiringBefore {
     ///
     /// # Returns
     /// A list of products with at least one stash item expiring before the given date
-    fn products_expiring_before(
+    async fn products_expiring_before(
         &self,
         before: NaiveDate,
     ) -> Result<Vec<Product>, ProductRepositoryError>;
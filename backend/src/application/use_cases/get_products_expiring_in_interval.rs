@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+
+use crate::domain::{entities::Product, errors::ProductRepositoryError};
+
+#[async_trait]
+pub trait GetProductsExpiringInInterval {
+    /// Gets all products with at least one stash item expiring within the given date interval. Without any bounds,
+    /// all products with stash items are returned
+    ///
+    /// # Parameters
+    /// - `after` - The start of the date range, inclusive
+    /// - `before` - The end of the date range, exclusive
+    ///
+    /// # Returns
+    /// A list of products with at least one stash item expiring within the given date interval
+    async fn products_expiring_in_interval(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError>;
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+
+use crate::domain::{entities::Product, errors::ProductRepositoryError};
+
+#[async_trait]
+pub trait GetProductsUnsafeBefore {
+    /// Gets all products with at least one stash item becoming unsafe before the given date. Stash items past their
+    /// best-before date, but still within the grace period of the product, are not included
+    ///
+    /// # Parameters
+    /// - `before` - The end of the date range, exclusive
+    ///
+    /// # Returns
+    /// A list of products with at least one stash item becoming unsafe before the given date
+    async fn products_unsafe_before(
+        &self,
+        before: NaiveDate,
+    ) -> Result<Vec<Product>, ProductRepositoryError>;
+}
@@ -1,9 +1,11 @@
+use async_trait::async_trait;
 use std::collections::HashSet;
 
 use crate::domain::{
     entities::StashItem, errors::ProductRepositoryError, value_objects::ProductId,
 };
 
+#[async_trait]
 pub trait GetStashItems {
     /// Get all stash items for a product.
     ///
@@ -12,7 +14,7 @@ pub trait GetStashItems {
     ///
     /// # Returns
     /// The stash items if successful, otherwise an error is returned.
-    fn get_stash_items(
+    async fn get_stash_items(
         &self,
         product_id: &ProductId,
     ) -> Result<HashSet<StashItem>, ProductRepositoryError>;
@@ -0,0 +1,13 @@
+use async_trait::async_trait;
+
+use crate::domain::{errors::ProductRepositoryError, value_objects::TrashEntry};
+
+#[async_trait]
+pub trait GetTrash {
+    /// Gets the deleted products and stash items which can still be restored
+    ///
+    /// # Returns
+    /// * `Ok(entries)` with the entries in the trash, most recently deleted first
+    /// * `Err(_)` if the underlying data store fails to get the trash
+    async fn get_trash(&self) -> Result<Vec<TrashEntry>, ProductRepositoryError>;
+}
@@ -0,0 +1,22 @@
+use crate::domain::{entities::Product, errors::ProductRepositoryError, value_objects::ProductId};
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait MergeProducts {
+    /// Merges a product into another product. All stash items of the source are moved to the target, merging
+    /// quantities of stash items with the same expiry date. The source is deleted, and its ID and barcodes become barcodes of the
+    /// target.
+    ///
+    /// # Parameters
+    /// - `source_id` - The ID of the product to merge.
+    /// - `target_id` - The ID of the product to merge into.
+    ///
+    /// # Returns
+    /// `Ok(Product)` with the target product after the merge
+    /// `Err(ProductRepositoryError::ProductNotFound)` if either of the products does not exist
+    async fn merge_products(
+        &self,
+        source_id: &ProductId,
+        target_id: &ProductId,
+    ) -> Result<Product, ProductRepositoryError>;
+}
@@ -1,23 +1,61 @@
 mod add_stash_item;
+mod attach_barcode;
+mod claim_idempotency_key;
+mod consume_stash_item;
 mod create_product;
 mod delete_product;
 mod delete_stash_item;
+mod detach_barcode;
+mod execute_batch;
 mod get_all_products_with_stash_items;
+mod get_expiring_stash_items;
 mod get_product;
 mod get_product_by_stash_item_id;
 mod get_products_expiring_before;
+mod get_products_expiring_in_interval;
+mod get_products_unsafe_before;
 mod get_stash_items;
+mod get_trash;
+mod merge_products;
+mod move_stash_item;
+mod notify_expiring_stash_items;
+mod open_stash_item;
+mod purge_trash;
+mod record_idempotent_response;
+mod release_idempotency_key;
+mod restore_from_trash;
+mod split_stash_item;
+mod undo_operation;
 mod update_product;
 mod update_stash_item;
 
 pub use add_stash_item::AddStashItem;
+pub use attach_barcode::AttachBarcode;
+pub use claim_idempotency_key::ClaimIdempotencyKey;
+pub use consume_stash_item::ConsumeStashItem;
 pub use create_product::CreateProduct;
 pub use delete_product::DeleteProduct;
 pub use delete_stash_item::DeleteStashItem;
+pub use detach_barcode::DetachBarcode;
+pub use execute_batch::ExecuteBatch;
 pub use get_all_products_with_stash_items::GetAllProductsWithStashItems;
+pub use get_expiring_stash_items::GetExpiringStashItems;
 pub use get_product::GetProduct;
 pub use get_product_by_stash_item_id::GetProductByStashItemId;
 pub use get_products_expiring_before::GetProductsExpiringBefore;
+pub use get_products_expiring_in_interval::GetProductsExpiringInInterval;
+pub use get_products_unsafe_before::GetProductsUnsafeBefore;
 pub use get_stash_items::GetStashItems;
+pub use get_trash::GetTrash;
+pub use merge_products::MergeProducts;
+pub use move_stash_item::MoveStashItem;
+pub use notify_expiring_stash_items::NotifyExpiringStashItems;
+pub use open_stash_item::OpenStashItem;
+pub use purge_trash::PurgeTrash;
+pub use record_idempotent_response::RecordIdempotentResponse;
+pub use release_idempotency_key::ReleaseIdempotencyKey;
+pub use restore_from_trash::RestoreFromTrash;
+pub use split_stash_item::SplitStashItem;
+pub use undo_operation::UndoOperation;
 pub use update_product::UpdateProduct;
 pub use update_stash_item::UpdateStashItem;
@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::StashItem, errors::ProductRepositoryError, value_objects::ProductId,
+};
+
+#[async_trait]
+pub trait MoveStashItem {
+    /// Move a stash item from the product it belongs to, to another product.
+    ///
+    /// # Parameters
+    /// - `stash_item_id` - ID of the stash item to move.
+    /// - `target_product_id` - ID of the product to move the stash item to.
+    /// - `merge` - Whether to merge the stash item into a stash item on the target product with the same expiry date.
+    ///
+    /// # Returns
+    /// The stash item as it is on the target product if successful, otherwise an error is returned.
+    /// If the target product already has a stash item with the same expiry date and `merge` is false, a
+    /// `ProductRepositoryError::DuplicateExpiryDateError` is returned.
+    async fn move_stash_item(
+        &self,
+        stash_item_id: &Uuid,
+        target_product_id: &ProductId,
+        merge: bool,
+    ) -> Result<StashItem, ProductRepositoryError>;
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+
+use crate::domain::errors::NotificationError;
+
+#[async_trait]
+pub trait NotifyExpiringStashItems {
+    /// Sends digests of stash items expiring within the notification windows. Each notifier only notifies about each
+    /// stash item once per window, and a notifier failing does not keep the others from delivering
+    ///
+    /// # Parameters
+    /// - `today` - The day to count the windows from
+    ///
+    /// # Returns
+    /// The number of stash items notified about, or the first error once every notifier has been tried
+    async fn notify_expiring_stash_items(
+        &self,
+        today: NaiveDate,
+    ) -> Result<usize, NotificationError>;
+}
@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::StashItem, errors::ProductRepositoryError, value_objects::ProductId,
+};
+
+#[async_trait]
+pub trait OpenStashItem {
+    /// Opens one unit of a stash item, splitting it off into an opened stash item.
+    ///
+    /// # Parameters
+    /// - `product_id` - ID of the product the stash item belongs to.
+    /// - `stash_item_id` - ID of the stash item to open a unit of.
+    /// - `opened_on` - Date the unit was opened.
+    ///
+    /// # Returns
+    /// `Ok(StashItem)` with the stash item holding the opened unit
+    /// `Err(ProductRepositoryError::ProductNotFound)` if the product does not exist
+    /// `Err(ProductRepositoryError::StashItemNotFound)` if the stash item does not exist on the product
+    /// `Err(ProductRepositoryError::StashItemAlreadyOpened)` if the stash item is already opened
+    async fn open_stash_item(
+        &self,
+        product_id: &ProductId,
+        stash_item_id: &Uuid,
+        opened_on: NaiveDate,
+    ) -> Result<StashItem, ProductRepositoryError>;
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::domain::errors::ProductRepositoryError;
+
+#[async_trait]
+pub trait PurgeTrash {
+    /// Permanently deletes the products and stash items which went to the trash before the given time
+    ///
+    /// # Parameters
+    /// * `deleted_before` - Entries deleted before this time are purged
+    ///
+    /// # Returns
+    /// * `Ok(count)` with the number of products and stash items purged
+    /// * `Err(_)` if the underlying data store fails to purge the trash
+    async fn purge_trash(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError>;
+}
@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::{errors::IdempotencyError, value_objects::IdempotentResponse};
+
+#[async_trait]
+pub trait RecordIdempotentResponse {
+    /// Records the response to a request an idempotency key was claimed for, to replay it when the request is retried
+    ///
+    /// # Parameters
+    /// * `key` - The idempotency key
+    /// * `token` - Token the key was claimed with
+    /// * `response` - The response to the request
+    ///
+    /// # Returns
+    /// * `Ok(())` if the response was recorded
+    /// * `Err(IdempotencyError::ClaimLost)` if the key was claimed again for a retry of the request
+    /// * `Err(_)` if the underlying data store fails to record the response
+    async fn record_idempotent_response(
+        &self,
+        key: &str,
+        token: &Uuid,
+        response: IdempotentResponse,
+    ) -> Result<(), IdempotencyError>;
+}
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::errors::IdempotencyError;
+
+#[async_trait]
+pub trait ReleaseIdempotencyKey {
+    /// Releases an idempotency key claimed for a request which could not be handled, so the request can be retried
+    ///
+    /// # Parameters
+    /// * `key` - The idempotency key
+    /// * `token` - Token the key was claimed with
+    ///
+    /// # Returns
+    /// * `Ok(())` if the key was released, or was claimed again for a retry of the request
+    /// * `Err(_)` if the underlying data store fails to release the key
+    async fn release_idempotency_key(
+        &self,
+        key: &str,
+        token: &Uuid,
+    ) -> Result<(), IdempotencyError>;
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use crate::domain::errors::ProductRepositoryError;
+
+#[async_trait]
+pub trait RestoreFromTrash {
+    /// Restores a deleted product or stash item from the trash
+    ///
+    /// # Parameters
+    /// * `id` - The id of the entry in the trash, which is the id of the deleted product or stash item
+    ///
+    /// # Returns
+    /// * `Ok(())` if the product or stash item was restored
+    /// * `Err(ProductRepositoryError::ProductNotFound)` if nothing with the id is in the trash
+    /// * `Err(ProductRepositoryError::ProductAlreadyExists)` if another product has taken the id of the product since
+    /// * `Err(ProductRepositoryError::DuplicateExpiryDateError)` if the product of the stash item has gotten another
+    ///   stash item with the same dates since
+    /// * `Err(_)` if the underlying data store fails to restore the entry
+    async fn restore_from_trash(&self, id: &str) -> Result<(), ProductRepositoryError>;
+}
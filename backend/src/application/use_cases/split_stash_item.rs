@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::{Product, StashItem},
+    errors::ProductRepositoryError,
+    value_objects::ProductId,
+};
+
+#[async_trait]
+pub trait SplitStashItem {
+    /// Splits part of a stash item off into a new stash item with its own expiry date. The quantity of the new stash
+    /// item is subtracted from the original.
+    ///
+    /// # Parameters
+    /// - `product_id` - ID of the product the stash item belongs to.
+    /// - `stash_item_id` - ID of the stash item to split.
+    /// - `split` - The new stash item.
+    ///
+    /// # Returns
+    /// `Ok(Product)` with both stash items if successful
+    /// `Err(ProductRepositoryError::ProductNotFound)` if the product does not exist
+    /// `Err(ProductRepositoryError::StashItemNotFound)` if the stash item does not exist on the product
+    /// `Err(ProductRepositoryError::DuplicateExpiryDateError)` if the product already has a stash item with the
+    /// expiry date of the new one
+    /// `Err(ProductRepositoryError::QuantityError)` if the quantity of the new stash item is not less than the
+    /// quantity of the original
+    async fn split_stash_item(
+        &self,
+        product_id: &ProductId,
+        stash_item_id: &Uuid,
+        split: StashItem,
+    ) -> Result<Product, ProductRepositoryError>;
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::errors::ProductRepositoryError;
+
+#[async_trait]
+pub trait UndoOperation {
+    /// Undoes an operation, as long as what it changed has not been changed again since
+    ///
+    /// # Parameters
+    /// * `operation_id` - The id of the operation, as returned by the use case which performed it
+    ///
+    /// # Returns
+    /// * `Ok(())` if the operation was undone
+    /// * `Err(ProductRepositoryError::OperationNotFound)` if the operation does not exist, has already been undone or
+    ///   is too old to be undone
+    /// * `Err(ProductRepositoryError::UndoConflict)` if what the operation changed has been changed again since
+    /// * `Err(_)` if the underlying data store fails to undo the operation
+    async fn undo_operation(&self, operation_id: &Uuid) -> Result<(), ProductRepositoryError>;
+}
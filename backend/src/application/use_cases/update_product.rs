@@ -1,5 +1,8 @@
 use crate::domain::{entities::Product, errors::ProductRepositoryError, value_objects::ProductId};
+use async_trait::async_trait;
+use uuid::Uuid;
 
+#[async_trait]
 pub trait UpdateProduct {
     /// Updates a product by its ID
     ///
@@ -8,11 +11,12 @@ pub trait UpdateProduct {
     /// - `product` - The product to update
     ///
     /// # Returns
-    /// `Ok(Product)` if the product was updated
+    /// `Ok((Product, Uuid))` with the updated product and the ID of the operation, to undo it by, if the product was
+    /// updated
     /// `Err(String)` if the product could not be updated
-    fn update_product(
+    async fn update_product(
         &self,
         id: &ProductId,
         product: Product,
-    ) -> Result<Product, ProductRepositoryError>;
+    ) -> Result<(Product, Uuid), ProductRepositoryError>;
 }
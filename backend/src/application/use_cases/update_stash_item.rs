@@ -1,7 +1,10 @@
 use crate::domain::{
     entities::StashItem, errors::ProductRepositoryError, value_objects::ProductId,
 };
+use async_trait::async_trait;
+use uuid::Uuid;
 
+#[async_trait]
 pub trait UpdateStashItem {
     /// Update a stash item in a product.
     ///
@@ -10,11 +13,12 @@ pub trait UpdateStashItem {
     /// - `stash_item` - The stash item to update.
     ///
     /// # Returns
-    /// Nothing if successful, otherwise an error is returned.
+    /// The updated stash item and the ID of the operation, to undo it by, if successful, otherwise an error is
+    /// returned.
     /// If the stash item does not exist, a `ProductRepositoryError::StashItemNotFound` is returned.
-    fn update_stash_item(
+    async fn update_stash_item(
         &self,
         product_id: &ProductId,
         stash_item: StashItem,
-    ) -> Result<StashItem, ProductRepositoryError>;
+    ) -> Result<(StashItem, Uuid), ProductRepositoryError>;
 }
@@ -1,17 +1,21 @@
 use crate::domain::{
     entities::FakeStashItem,
-    value_objects::{Brand, ProductId},
+    value_objects::{Brand, ProductId, Unit},
 };
 
 use super::{Product, StashItem};
 
 /// A fake product builder
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct FakeProduct {
     id: Option<ProductId>,
     brand: Option<Brand>,
     name: Option<String>,
+    unit: Option<Unit>,
     stash_items: Option<Vec<StashItem>>,
+    barcodes: Option<Vec<ProductId>>,
+    shelf_life_after_opening_days: Option<u32>,
+    best_before_grace_period_days: Option<u32>,
 }
 
 impl FakeProduct {
@@ -20,7 +24,11 @@ impl FakeProduct {
             id: None,
             brand: None,
             name: None,
+            unit: None,
             stash_items: None,
+            barcodes: None,
+            shelf_life_after_opening_days: None,
+            best_before_grace_period_days: None,
         }
     }
 
@@ -39,11 +47,31 @@ impl FakeProduct {
         self
     }
 
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
     pub fn with_stash_items(mut self, stash_items: Vec<StashItem>) -> Self {
         self.stash_items = Some(stash_items);
         self
     }
 
+    pub fn with_barcodes(mut self, barcodes: Vec<ProductId>) -> Self {
+        self.barcodes = Some(barcodes);
+        self
+    }
+
+    pub fn with_shelf_life_after_opening_days(mut self, days: u32) -> Self {
+        self.shelf_life_after_opening_days = Some(days);
+        self
+    }
+
+    pub fn with_best_before_grace_period_days(mut self, days: u32) -> Self {
+        self.best_before_grace_period_days = Some(days);
+        self
+    }
+
     fn random_name() -> String {
         use rand::distributions::Alphanumeric;
         use rand::{thread_rng, Rng};
@@ -63,21 +91,37 @@ impl FakeProduct {
         let mut rng = rand::thread_rng();
         let range = Uniform::new_inclusive(1, 3);
         let length = rng.sample(range);
-        let mut stash_items = Vec::with_capacity(length);
-        for _ in 0..length {
+        let mut stash_items: Vec<StashItem> = Vec::with_capacity(length);
+        while stash_items.len() < length {
+            // A product cannot have two stash items with the same expiry date
             let stash_item = FakeStashItem::new().build();
-            stash_items.push(stash_item);
+            if stash_items
+                .iter()
+                .all(|other| other.expiry_date() != stash_item.expiry_date())
+            {
+                stash_items.push(stash_item);
+            }
         }
         stash_items
     }
 
     pub fn build(self) -> Product {
-        Product::new(
+        let mut product = Product::new(
             self.id.unwrap_or_else(ProductId::random),
             self.brand.unwrap_or_else(Brand::random),
             self.name.unwrap_or_else(FakeProduct::random_name),
             self.stash_items
                 .unwrap_or_else(FakeProduct::random_stash_items),
-        )
+        );
+
+        for barcode in self.barcodes.unwrap_or_default() {
+            product.attach_barcode(barcode);
+        }
+
+        product.set_unit(self.unit.unwrap_or_default());
+        product.set_shelf_life_after_opening_days(self.shelf_life_after_opening_days);
+        product.set_best_before_grace_period_days(self.best_before_grace_period_days);
+
+        product
     }
 }
@@ -1,16 +1,18 @@
 use chrono::NaiveDate;
 use uuid::Uuid;
 
-use crate::domain::value_objects::Quantity;
+use crate::domain::value_objects::{ExpiryDateKind, Quantity};
 
 use super::StashItem;
 
 /// A fake stash item builder
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct FakeStashItem {
     id: Option<Uuid>,
     quantity: Option<Quantity>,
-    expiry_date: Option<NaiveDate>,
+    expiry_date: Option<Option<NaiveDate>>,
+    expiry_date_kind: Option<ExpiryDateKind>,
+    opened_on: Option<NaiveDate>,
 }
 
 impl FakeStashItem {
@@ -19,6 +21,8 @@ impl FakeStashItem {
             id: None,
             quantity: None,
             expiry_date: None,
+            expiry_date_kind: None,
+            opened_on: None,
         }
     }
 
@@ -33,7 +37,22 @@ impl FakeStashItem {
     }
 
     pub fn with_expiry_date(mut self, expiry_date: NaiveDate) -> Self {
-        self.expiry_date = Some(expiry_date);
+        self.expiry_date = Some(Some(expiry_date));
+        self
+    }
+
+    pub fn without_expiry_date(mut self) -> Self {
+        self.expiry_date = Some(None);
+        self
+    }
+
+    pub fn with_expiry_date_kind(mut self, expiry_date_kind: ExpiryDateKind) -> Self {
+        self.expiry_date_kind = Some(expiry_date_kind);
+        self
+    }
+
+    pub fn with_opened_on(mut self, opened_on: NaiveDate) -> Self {
+        self.opened_on = Some(opened_on);
         self
     }
 
@@ -52,10 +71,14 @@ impl FakeStashItem {
     }
 
     pub fn build(self) -> StashItem {
-        StashItem::new(
+        let mut stash_item = StashItem::new(
             self.id.unwrap_or_else(Uuid::new_v4),
             self.quantity.unwrap_or_else(Quantity::random),
-            self.expiry_date.unwrap_or_else(FakeStashItem::random_date),
-        )
+            self.expiry_date
+                .unwrap_or_else(|| Some(FakeStashItem::random_date())),
+        );
+        stash_item.set_expiry_date_kind(self.expiry_date_kind.unwrap_or_default());
+        stash_item.set_opened_on(self.opened_on);
+        stash_item
     }
 }
@@ -6,11 +6,11 @@ pub use entity::Entity;
 pub use product::Product;
 pub use stash_item::StashItem;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 mod fake_stash_item;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 pub use fake_stash_item::FakeStashItem;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 mod fake_product;
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
 pub use fake_product::FakeProduct;
@@ -1,17 +1,20 @@
 use std::collections::{HashMap, HashSet};
 
-use chrono::NaiveDate;
+use chrono::{Days, NaiveDate};
 use getset::{Getters, Setters};
 use uuid::Uuid;
 
 use crate::domain::{
-    errors::{ProductRepositoryError, StashItemDoesntExistError, StashItemExistsError},
-    value_objects::{Brand, ProductId},
+    errors::{
+        ProductRepositoryError, QuantityError, StashItemDoesntExistError, StashItemExistsError,
+    },
+    events::ProductEvent,
+    value_objects::{Brand, ExpiryDateKind, ExpiryStatus, ProductId, Quantity, Unit},
 };
 
 use super::{Entity, StashItem};
 
-#[derive(Debug, Clone, PartialEq, Eq, Getters, Setters)]
+#[derive(Debug, Clone, Getters, Setters)]
 pub struct Product {
     /// ID of the product
     #[getset(get = "pub")]
@@ -25,8 +28,28 @@ pub struct Product {
     #[getset(get = "pub", set = "pub")]
     name: String,
 
+    /// Unit the quantities of the product's stash items are in. Changing it does not convert existing quantities
+    #[getset(get = "pub", set = "pub")]
+    unit: Unit,
+
     /// Stash items of this product
     stash_items: HashMap<Uuid, StashItem>,
+
+    /// Barcodes the product can also be found by, in addition to its ID
+    barcodes: HashSet<ProductId>,
+
+    /// Number of days the product keeps after being opened, if it expires sooner once opened
+    #[getset(get = "pub", set = "pub")]
+    shelf_life_after_opening_days: Option<u32>,
+
+    /// Number of days stash items of the product are still safe after their best-before date. Without it, they are
+    /// never considered unsafe
+    #[getset(get = "pub", set = "pub")]
+    best_before_grace_period_days: Option<u32>,
+
+    /// What happened to the stash items of the product since the events were last taken. They are not part of the state
+    /// of the product, so they are left out when comparing products
+    events: Vec<ProductEvent>,
 }
 
 impl Product {
@@ -41,32 +64,224 @@ impl Product {
     /// # Returns
     ///
     /// * A new Product with the given data
+    ///
+    /// # Panics
+    ///
+    /// If two of the stash items have the same ID or the same dates. Use [`Product::try_new`] for stash items which
+    /// are not known to be distinct
     pub fn new(id: ProductId, brand: Brand, name: String, stash_items: Vec<StashItem>) -> Self {
+        Self::try_new(id, brand, name, stash_items)
+            .expect("Stash items of the product are not distinct")
+    }
+
+    /// Create a new Product, unless its stash items clash
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the product
+    /// * `brand` - Brand of the product
+    /// * `name` - Name of the product
+    /// * `stash_items` - Stash items of the product
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Product) A new Product with the given data
+    /// * Err(StashItemExistsError) if two of the stash items have the same ID or the same dates
+    pub fn try_new(
+        id: ProductId,
+        brand: Brand,
+        name: String,
+        stash_items: Vec<StashItem>,
+    ) -> Result<Self, StashItemExistsError> {
         let mut product = Self {
             id,
             brand,
             name,
+            unit: Unit::default(),
             stash_items: HashMap::new(),
+            barcodes: HashSet::new(),
+            shelf_life_after_opening_days: None,
+            best_before_grace_period_days: None,
+            events: vec![],
         };
 
         for stash_item in stash_items {
-            product.add_stash_item(stash_item).unwrap();
+            product.add_stash_item(stash_item)?;
         }
 
-        product
+        // Products are also made like this when they are loaded, so their stash items are nothing new
+        product.take_events();
+
+        Ok(product)
+    }
+
+    /// Gets the events recorded since they were last taken, oldest first
+    pub fn events(&self) -> &[ProductEvent] {
+        &self.events
+    }
+
+    /// Takes the events recorded so far, for them to be dispatched once the product is saved
+    ///
+    /// # Returns
+    /// * The events recorded since they were last taken, oldest first
+    pub fn take_events(&mut self) -> Vec<ProductEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Puts a stash item on the product, replacing the stash item with the same ID, and records what changed
+    ///
+    /// # Arguments
+    /// * `stash_item` - Stash item to put on the product
+    fn put_stash_item(&mut self, stash_item: StashItem) {
+        let product_id = self.id.clone();
+        let stash_item_id = *stash_item.id();
+
+        match self.stash_items.insert(stash_item_id, stash_item.clone()) {
+            None => self.events.push(ProductEvent::StashItemAdded {
+                product_id,
+                stash_item,
+            }),
+            Some(previous) => {
+                if previous.quantity() != stash_item.quantity() {
+                    self.events.push(ProductEvent::QuantityChanged {
+                        product_id: product_id.clone(),
+                        stash_item_id,
+                        from: *previous.quantity(),
+                        to: *stash_item.quantity(),
+                    });
+                }
+                if previous.expiry_date() != stash_item.expiry_date() {
+                    self.events.push(ProductEvent::ExpiryDateChanged {
+                        product_id: product_id.clone(),
+                        stash_item_id,
+                        from: *previous.expiry_date(),
+                        to: *stash_item.expiry_date(),
+                    });
+                }
+                if previous.expiry_date_kind() != stash_item.expiry_date_kind() {
+                    self.events.push(ProductEvent::ExpiryDateKindChanged {
+                        product_id: product_id.clone(),
+                        stash_item_id,
+                        from: *previous.expiry_date_kind(),
+                        to: *stash_item.expiry_date_kind(),
+                    });
+                }
+                if previous.opened_on() != stash_item.opened_on() {
+                    self.events.push(ProductEvent::OpenedOnChanged {
+                        product_id,
+                        stash_item_id,
+                        from: *previous.opened_on(),
+                        to: *stash_item.opened_on(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Takes a stash item off the product, and records that it was removed
+    ///
+    /// # Arguments
+    /// * `stash_item_id` - ID of the stash item to take off
+    ///
+    /// # Returns
+    /// * The stash item, if the product had it
+    fn take_stash_item(&mut self, stash_item_id: &Uuid) -> Option<StashItem> {
+        let stash_item = self.stash_items.remove(stash_item_id)?;
+
+        self.events.push(ProductEvent::StashItemRemoved {
+            product_id: self.id.clone(),
+            stash_item: stash_item.clone(),
+        });
+
+        Some(stash_item)
+    }
+
+    /// Gets an item with the same expiry date and opened date as the given item, if one exists. Such items can not
+    /// exist side by side, as there would be no telling them apart
+    ///
+    /// # Arguments
+    /// * `stash_item` - Item to find a matching item for
+    ///
+    /// # Returns
+    /// * The matching item, if one exists. This may be the given item itself
+    fn stash_item_like(&self, stash_item: &StashItem) -> Option<&StashItem> {
+        self.stash_items.values().find(|item| {
+            item.expiry_date() == stash_item.expiry_date()
+                && item.opened_on() == stash_item.opened_on()
+        })
+    }
+
+    /// Gets the date a stash item of this product actually expires. Once opened, that is the printed expiry date or
+    /// the end of the shelf life after opening, whichever comes first
+    ///
+    /// # Arguments
+    /// * `stash_item` - Stash item to get the expiry date of
+    ///
+    /// # Returns
+    /// * The effective expiry date of the stash item, if it expires at all
+    pub fn effective_expiry_date(&self, stash_item: &StashItem) -> Option<NaiveDate> {
+        let expires_after_opening =
+            match (stash_item.opened_on(), self.shelf_life_after_opening_days) {
+                (Some(opened_on), Some(days)) => opened_on.checked_add_days(Days::new(days.into())),
+                _ => None,
+            };
+
+        match (*stash_item.expiry_date(), expires_after_opening) {
+            (Some(printed), Some(opened)) => Some(printed.min(opened)),
+            (printed, opened) => printed.or(opened),
+        }
+    }
+
+    /// Gets the barcodes attached to the product, in addition to its ID. Note: No order is guaranteed.
+    ///
+    /// # Returns
+    ///
+    /// * The barcodes attached to the product
+    pub fn barcodes(&self) -> HashSet<&ProductId> {
+        self.barcodes.iter().collect()
+    }
+
+    /// Checks if the product can be found by the given barcode, either because it is the ID of the product or because
+    /// it is attached to it
+    ///
+    /// # Arguments
+    /// * `barcode` - The barcode to check for
+    ///
+    /// # Returns
+    /// * True if the product has the given barcode, false otherwise
+    pub fn has_barcode(&self, barcode: &ProductId) -> bool {
+        &self.id == barcode || self.barcodes.contains(barcode)
+    }
+
+    /// Attaches a barcode to the product, so it can also be found by it. Attaching a barcode the product already has
+    /// does nothing
+    ///
+    /// # Arguments
+    /// * `barcode` - The barcode to attach
+    pub fn attach_barcode(&mut self, barcode: ProductId) {
+        if !self.has_barcode(&barcode) {
+            self.barcodes.insert(barcode);
+        }
     }
 
-    /// Gets an item with the given expiry date, if one exists
+    /// Detaches a barcode from the product
     ///
     /// # Arguments
-    /// * `expiry_date` - Expiry date of the item to get
+    /// * `barcode` - The barcode to detach
     ///
     /// # Returns
-    /// * The item with the given expiry date, if one exists
-    fn stash_item_with_expiry_date(&self, expiry_date: &NaiveDate) -> Option<&StashItem> {
-        self.stash_items
-            .values()
-            .find(|item| item.expiry_date() == expiry_date)
+    /// * Ok(()) if the barcode was detached
+    /// * Err(ProductRepositoryError::CannotDetachPrimaryBarcode) if the barcode is the ID of the product
+    /// * Err(ProductRepositoryError::BarcodeNotFound) if the barcode is not attached to the product
+    pub fn detach_barcode(&mut self, barcode: &ProductId) -> Result<(), ProductRepositoryError> {
+        if &self.id == barcode {
+            return Err(ProductRepositoryError::CannotDetachPrimaryBarcode);
+        }
+
+        match self.barcodes.remove(barcode) {
+            true => Ok(()),
+            false => Err(ProductRepositoryError::BarcodeNotFound),
+        }
     }
 
     /// Gets the list of stash items associated with the product. Note: No order is guaranteed.
@@ -107,18 +322,18 @@ impl Product {
     ///
     /// # Returns
     /// * Ok(()) if the item was added
-    /// Err(StashItemExistsError) if an item with the same ID already exists
+    /// * Err(StashItemExistsError) if an item with the same ID already exists
     pub fn add_stash_item(&mut self, stash_item: StashItem) -> Result<(), StashItemExistsError> {
         if self.has_stash_item(stash_item.id()) {
             return Err(StashItemExistsError);
         }
 
-        if let Some(_) = self.stash_item_with_expiry_date(stash_item.expiry_date()) {
+        if self.stash_item_like(&stash_item).is_some() {
             // TODO Other error type
             return Err(StashItemExistsError);
         }
 
-        self.stash_items.insert(stash_item.id().clone(), stash_item);
+        self.put_stash_item(stash_item);
 
         Ok(())
     }
@@ -135,10 +350,8 @@ impl Product {
         &mut self,
         stash_item_id: &Uuid,
     ) -> Result<StashItem, StashItemDoesntExistError> {
-        match self.stash_items.remove(stash_item_id) {
-            Some(stash_item) => Ok(stash_item),
-            None => Err(StashItemDoesntExistError),
-        }
+        self.take_stash_item(stash_item_id)
+            .ok_or(StashItemDoesntExistError)
     }
 
     /// Updates a stash item in the product
@@ -159,27 +372,322 @@ impl Product {
         }
 
         // Check if a stash item on the product has the same expiry date
-        match self.stash_item_with_expiry_date(stash_item.expiry_date()) {
-            Some(si) => {
-                // ...but not the same ID
-                if si.id() != stash_item.id() {
-                    return Err(ProductRepositoryError::DuplicateExpiryDateError);
+        if let Some(si) = self.stash_item_like(&stash_item) {
+            // ...but not the same ID
+            if si.id() != stash_item.id() {
+                return Err(ProductRepositoryError::DuplicateExpiryDateError);
+            }
+        }
+
+        self.put_stash_item(stash_item);
+
+        Ok(())
+    }
+
+    /// Splits part of a stash item off into a new stash item, for example when some of it is frozen and gets a new
+    /// expiry date. The quantity of the new stash item is subtracted from the original
+    ///
+    /// # Arguments
+    /// * `stash_item_id` - ID of the stash item to split
+    /// * `split` - The new stash item
+    ///
+    /// # Returns
+    /// * Ok(()) if the stash item was split
+    /// * Err(ProductRepositoryError::StashItemNotFound) if no stash item with the given ID exists
+    /// * Err(ProductRepositoryError::StashItemExists) if a stash item with the ID of the new one already exists
+    /// * Err(ProductRepositoryError::DuplicateExpiryDateError) if a stash item with the expiry date of the new one
+    ///   already exists
+    /// * Err(ProductRepositoryError::QuantityError) if the quantity of the new stash item is not less than the
+    ///   quantity of the original
+    pub fn split_stash_item(
+        &mut self,
+        stash_item_id: &Uuid,
+        split: StashItem,
+    ) -> Result<(), ProductRepositoryError> {
+        let mut original = self
+            .stash_item(stash_item_id)
+            .ok_or(ProductRepositoryError::StashItemNotFound)?
+            .clone();
+
+        if self.has_stash_item(split.id()) {
+            return Err(ProductRepositoryError::StashItemExists);
+        }
+
+        if self.stash_item_like(&split).is_some() {
+            return Err(ProductRepositoryError::DuplicateExpiryDateError);
+        }
+
+        original.set_quantity(original.quantity().checked_sub(*split.quantity())?);
+
+        self.put_stash_item(original);
+        self.put_stash_item(split);
+
+        Ok(())
+    }
+
+    /// Gets the date a stash item of this product is no longer safe to eat. For use-by dates, that is the effective
+    /// expiry date. For best-before dates, it is the end of the grace period after the effective expiry date
+    ///
+    /// # Arguments
+    /// * `stash_item` - Stash item to get the date for
+    ///
+    /// # Returns
+    /// * The date the stash item is no longer safe, if it ever becomes unsafe
+    pub fn unsafe_date(&self, stash_item: &StashItem) -> Option<NaiveDate> {
+        let expiry_date = self.effective_expiry_date(stash_item)?;
+
+        match stash_item.expiry_date_kind() {
+            ExpiryDateKind::UseBy => Some(expiry_date),
+            ExpiryDateKind::BestBefore => self
+                .best_before_grace_period_days
+                .and_then(|days| expiry_date.checked_add_days(Days::new(days.into()))),
+        }
+    }
+
+    /// Gets how a stash item of this product is doing with regard to its expiry date on the given day
+    ///
+    /// # Arguments
+    /// * `stash_item` - Stash item to get the status of
+    /// * `today` - The day to get the status for
+    ///
+    /// # Returns
+    /// * The expiry status of the stash item
+    pub fn expiry_status(&self, stash_item: &StashItem, today: NaiveDate) -> ExpiryStatus {
+        if self
+            .unsafe_date(stash_item)
+            .is_some_and(|unsafe_date| today >= unsafe_date)
+        {
+            ExpiryStatus::Unsafe
+        } else if self
+            .effective_expiry_date(stash_item)
+            .is_some_and(|expiry_date| today >= expiry_date)
+        {
+            ExpiryStatus::PastBestBefore
+        } else {
+            ExpiryStatus::Fresh
+        }
+    }
+
+    /// Consumes an amount of a stash item. The amount may be in any unit compatible with the unit of the product. A
+    /// stash item consumed completely is removed
+    ///
+    /// # Arguments
+    /// * `stash_item_id` - ID of the stash item to consume from
+    /// * `amount` - Amount to consume
+    /// * `unit` - Unit of the amount
+    ///
+    /// # Returns
+    /// * Ok(Some(StashItem)) The stash item with the amount subtracted
+    /// * Ok(None) if the stash item was consumed completely
+    /// * Err(ProductRepositoryError::StashItemNotFound) if no stash item with the given ID exists
+    /// * Err(ProductRepositoryError::UnitError) if the unit can not be converted to the unit of the product
+    /// * Err(ProductRepositoryError::QuantityError) if the amount is larger than the quantity of the stash item
+    pub fn consume_stash_item(
+        &mut self,
+        stash_item_id: &Uuid,
+        amount: Quantity,
+        unit: Unit,
+    ) -> Result<Option<StashItem>, ProductRepositoryError> {
+        let mut stash_item = self
+            .stash_item(stash_item_id)
+            .ok_or(ProductRepositoryError::StashItemNotFound)?
+            .clone();
+
+        let amount = unit.convert(amount, self.unit)?;
+
+        match stash_item.quantity().checked_sub(amount) {
+            Ok(quantity) => {
+                stash_item.set_quantity(quantity);
+                self.put_stash_item(stash_item.clone());
+                Ok(Some(stash_item))
+            }
+            Err(QuantityError::ZeroError) => {
+                self.take_stash_item(stash_item_id);
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Opens one unit of a stash item. The opened unit becomes a stash item of its own, unless it is the only unit,
+    /// in which case the stash item itself is marked as opened. A unit opened on the same day as an existing opened
+    /// stash item with the same expiry date is added to that stash item
+    ///
+    /// # Arguments
+    /// * `stash_item_id` - ID of the stash item to open a unit of
+    /// * `opened_on` - Date the unit was opened
+    ///
+    /// # Returns
+    /// * Ok(StashItem) The stash item holding the opened unit
+    /// * Err(ProductRepositoryError::StashItemNotFound) if no stash item with the given ID exists
+    /// * Err(ProductRepositoryError::StashItemAlreadyOpened) if the stash item is already opened
+    /// * Err(ProductRepositoryError::QuantityError) if the quantity of the opened stash item is too large
+    pub fn open_stash_item(
+        &mut self,
+        stash_item_id: &Uuid,
+        opened_on: NaiveDate,
+    ) -> Result<StashItem, ProductRepositoryError> {
+        let original = self
+            .stash_item(stash_item_id)
+            .ok_or(ProductRepositoryError::StashItemNotFound)?
+            .clone();
+
+        if original.opened_on().is_some() {
+            return Err(ProductRepositoryError::StashItemAlreadyOpened);
+        }
+
+        let one = Quantity::new(1)?;
+
+        let mut opened = StashItem::new(Uuid::new_v4(), one, *original.expiry_date());
+        opened.set_opened_on(Some(opened_on));
+
+        // Figure out what the opened stash item looks like before touching anything
+        let opened = match self.stash_item_like(&opened) {
+            Some(existing) => {
+                let mut existing = existing.clone();
+                existing.set_quantity(existing.quantity().checked_add(one)?);
+                existing
+            }
+            None if original.quantity() == &one => {
+                let mut opened = original.clone();
+                opened.set_opened_on(Some(opened_on));
+                opened
+            }
+            None => opened,
+        };
+
+        if opened.id() != original.id() {
+            match original.quantity().checked_sub(one) {
+                Ok(quantity) => {
+                    let mut original = original;
+                    original.set_quantity(quantity);
+                    self.put_stash_item(original);
+                }
+                Err(_) => {
+                    self.take_stash_item(original.id());
                 }
             }
-            None => (),
         }
 
-        self.remove_stash_item(stash_item.id())?;
-        self.add_stash_item(stash_item)
-            .expect("If it already exists now, the deletion didn't work");
+        self.put_stash_item(opened.clone());
+
+        Ok(opened)
+    }
+
+    /// Moves a stash item from this product to another product
+    ///
+    /// # Arguments
+    /// * `stash_item_id` - ID of the stash item to move
+    /// * `target` - Product to move the stash item to
+    /// * `merge` - Whether to merge the stash item into a stash item on the target with the same expiry date
+    ///
+    /// # Returns
+    /// * Ok(StashItem) The stash item as it is on the target product after the move
+    /// * Err(ProductRepositoryError::StashItemNotFound) if no stash item with the given ID exists on this product
+    /// * Err(ProductRepositoryError::StashItemExists) if the target already has a stash item with the same ID
+    /// * Err(ProductRepositoryError::DuplicateExpiryDateError) if the target has a stash item with the same expiry
+    ///   date, and `merge` is false
+    /// * Err(ProductRepositoryError::QuantityError) if the merged quantity is too large
+    pub fn move_stash_item(
+        &mut self,
+        stash_item_id: &Uuid,
+        target: &mut Product,
+        merge: bool,
+    ) -> Result<StashItem, ProductRepositoryError> {
+        let stash_item = self
+            .stash_item(stash_item_id)
+            .ok_or(ProductRepositoryError::StashItemNotFound)?;
+
+        if target.has_stash_item(stash_item_id) {
+            return Err(ProductRepositoryError::StashItemExists);
+        }
+
+        // Figure out what the stash item looks like on the target before touching either product
+        let moved = match target.stash_item_like(stash_item) {
+            Some(_) if !merge => return Err(ProductRepositoryError::DuplicateExpiryDateError),
+            Some(existing) => {
+                let mut merged = existing.clone();
+                merged.set_quantity(existing.quantity().checked_add(*stash_item.quantity())?);
+                merged
+            }
+            None => stash_item.clone(),
+        };
+
+        self.remove_stash_item(stash_item_id)?;
+        target.put_stash_item(moved.clone());
+
+        Ok(moved)
+    }
+
+    /// Merges this product into another product by moving all its stash items to the target. Stash items with an
+    /// expiry date the target already has a stash item for are merged into that stash item. The ID and barcodes of
+    /// this product are attached to the target, and the events this product recorded are carried over to it, so they
+    /// are dispatched when the target is saved
+    ///
+    /// # Arguments
+    /// * `target` - Product to merge this product into
+    ///
+    /// # Returns
+    /// * Ok(()) if the product was merged into the target
+    /// * Err(ProductRepositoryError::StashItemExists) if the target already has a stash item with the same ID as one
+    ///   of this product's stash items
+    /// * Err(ProductRepositoryError::QuantityError) if a merged quantity is too large
+    ///
+    /// The target is left untouched if merging fails
+    pub fn merge_into(mut self, target: &mut Product) -> Result<(), ProductRepositoryError> {
+        let mut merged = target.clone();
+        merged.events.append(&mut self.events);
+
+        let stash_item_ids = self.stash_items.keys().copied().collect::<Vec<_>>();
+        for stash_item_id in stash_item_ids {
+            // Keep the removal from this product ahead of what it caused on the target
+            let moved_at = merged.events.len();
+            self.move_stash_item(&stash_item_id, &mut merged, true)?;
+            merged.events.splice(moved_at..moved_at, self.take_events());
+        }
+
+        merged.attach_barcode(self.id);
+        for barcode in self.barcodes {
+            merged.attach_barcode(barcode);
+        }
+
+        *target = merged;
 
         Ok(())
     }
 }
 
+impl PartialEq for Product {
+    fn eq(&self, other: &Self) -> bool {
+        // Destructured so new fields can not be forgotten here. Recorded events are deliberately left out
+        let Self {
+            id,
+            brand,
+            name,
+            unit,
+            stash_items,
+            barcodes,
+            shelf_life_after_opening_days,
+            best_before_grace_period_days,
+            events: _,
+        } = self;
+
+        *id == other.id
+            && *brand == other.brand
+            && *name == other.name
+            && *unit == other.unit
+            && *stash_items == other.stash_items
+            && *barcodes == other.barcodes
+            && *shelf_life_after_opening_days == other.shelf_life_after_opening_days
+            && *best_before_grace_period_days == other.best_before_grace_period_days
+    }
+}
+
+impl Eq for Product {}
+
 impl Entity<ProductId> for Product {
     fn id(&self) -> &ProductId {
-        &self.id()
+        self.id()
     }
 }
 
@@ -187,11 +695,29 @@ impl Entity<ProductId> for Product {
 mod tests {
     use crate::domain::{
         entities::{FakeProduct, FakeStashItem},
-        value_objects::Quantity,
+        errors::UnitError,
     };
 
     use super::*;
 
+    #[test]
+    fn test_try_new_duplicate_dates() {
+        // Neither stash item has an expiry date, so they can not be told apart
+        let stash_items = vec![
+            FakeStashItem::new().without_expiry_date().build(),
+            FakeStashItem::new().without_expiry_date().build(),
+        ];
+
+        let product = Product::try_new(
+            "ID".parse().unwrap(),
+            "Brand".parse().unwrap(),
+            "Name".to_string(),
+            stash_items,
+        );
+
+        assert_eq!(product.unwrap_err(), StashItemExistsError);
+    }
+
     #[test]
     fn test_product_id() {
         let product_id: ProductId = "ID".parse().unwrap();
@@ -285,15 +811,12 @@ mod tests {
         let expiry_date = NaiveDate::from_ymd_opt(2023, 11, 26).unwrap();
         let mut product = FakeProduct::new()
             .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(expiry_date.clone())
+                .with_expiry_date(expiry_date)
                 .build()])
             .build();
 
-        let result = product.add_stash_item(
-            FakeStashItem::new()
-                .with_expiry_date(expiry_date.clone())
-                .build(),
-        );
+        let result =
+            product.add_stash_item(FakeStashItem::new().with_expiry_date(expiry_date).build());
 
         // TODO Check error type
         assert!(result.is_err());
@@ -325,9 +848,7 @@ mod tests {
         let mut product = FakeProduct::new()
             .with_stash_items(vec![stash_item_1.clone()])
             .build();
-        let stash_item_2 = FakeStashItem::new()
-            .with_id(stash_item_1.id().clone())
-            .build();
+        let stash_item_2 = FakeStashItem::new().with_id(*stash_item_1.id()).build();
         let result = product.update_stash_item(stash_item_2.clone());
 
         assert!(result.is_ok());
@@ -350,22 +871,913 @@ mod tests {
         let stash_item_id = Uuid::new_v4();
         let expiry_date = NaiveDate::from_ymd_opt(2023, 11, 26).unwrap();
         let mut product = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_id(stash_item_id.clone())
-                .with_expiry_date(expiry_date.clone())
-                .with_quantity(Quantity::new(2).unwrap())
-                .build()])
+            .with_stash_items(vec![
+                FakeStashItem::new()
+                    .with_id(stash_item_id)
+                    .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 25).unwrap())
+                    .with_quantity(Quantity::new(2).unwrap())
+                    .build(),
+                FakeStashItem::new().with_expiry_date(expiry_date).build(),
+            ])
             .build();
 
         let result = product.update_stash_item(
             FakeStashItem::new()
-                .with_id(stash_item_id.clone())
-                .with_expiry_date(expiry_date.clone())
+                .with_id(stash_item_id)
+                .with_expiry_date(expiry_date)
                 .with_quantity(Quantity::new(3).unwrap())
                 .build(),
         );
 
-        // TODO Check error type
-        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::DuplicateExpiryDateError
+        );
+    }
+
+    #[test]
+    fn test_split_stash_item() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(4.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 26).unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let split = FakeStashItem::new()
+            .with_quantity(3.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2024, 5, 26).unwrap())
+            .build();
+
+        product
+            .split_stash_item(stash_item.id(), split.clone())
+            .unwrap();
+
+        assert_eq!(
+            product.stash_item(stash_item.id()).unwrap().quantity(),
+            &Quantity::new(1).unwrap()
+        );
+        assert_eq!(product.stash_item(split.id()), Some(&split));
+        assert_eq!(product.stash_items().len(), 2);
+    }
+
+    #[test]
+    fn test_split_stash_item_doesnt_exist() {
+        let mut product = FakeProduct::new().build();
+
+        let result = product.split_stash_item(&Uuid::new_v4(), FakeStashItem::new().build());
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::StashItemNotFound
+        );
+    }
+
+    #[test]
+    fn test_split_stash_item_existing_expiry_date() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(4.try_into().unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let split = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .with_expiry_date(stash_item.expiry_date().unwrap())
+            .build();
+
+        let result = product.split_stash_item(stash_item.id(), split);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::DuplicateExpiryDateError
+        );
+    }
+
+    #[test]
+    fn test_split_stash_item_whole_quantity() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(4.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 26).unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let split = FakeStashItem::new()
+            .with_quantity(4.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2024, 5, 26).unwrap())
+            .build();
+
+        let result = product.split_stash_item(stash_item.id(), split);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::QuantityError(QuantityError::ZeroError)
+        );
+        assert_eq!(product.stash_item(stash_item.id()), Some(&stash_item));
+    }
+
+    #[test]
+    fn test_effective_expiry_date() {
+        let product = FakeProduct::new()
+            .with_shelf_life_after_opening_days(14)
+            .build();
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+            .with_opened_on(NaiveDate::from_ymd_opt(2023, 11, 1).unwrap())
+            .build();
+
+        assert_eq!(
+            product.effective_expiry_date(&stash_item),
+            NaiveDate::from_ymd_opt(2023, 11, 15)
+        );
+    }
+
+    #[test]
+    fn test_effective_expiry_date_printed_date_first() {
+        let product = FakeProduct::new()
+            .with_shelf_life_after_opening_days(14)
+            .build();
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 10).unwrap())
+            .with_opened_on(NaiveDate::from_ymd_opt(2023, 11, 1).unwrap())
+            .build();
+
+        assert_eq!(
+            product.effective_expiry_date(&stash_item),
+            NaiveDate::from_ymd_opt(2023, 11, 10)
+        );
+    }
+
+    #[test]
+    fn test_effective_expiry_date_not_opened() {
+        let product = FakeProduct::new()
+            .with_shelf_life_after_opening_days(14)
+            .build();
+        let stash_item = FakeStashItem::new().build();
+
+        assert_eq!(
+            product.effective_expiry_date(&stash_item),
+            *stash_item.expiry_date()
+        );
+    }
+
+    #[test]
+    fn test_expiry_status_best_before() {
+        let product = FakeProduct::new()
+            .with_best_before_grace_period_days(7)
+            .build();
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 10).unwrap())
+            .with_expiry_date_kind(ExpiryDateKind::BestBefore)
+            .build();
+
+        let status_on = |day| {
+            product.expiry_status(&stash_item, NaiveDate::from_ymd_opt(2023, 11, day).unwrap())
+        };
+
+        assert_eq!(status_on(9), ExpiryStatus::Fresh);
+        assert_eq!(status_on(10), ExpiryStatus::PastBestBefore);
+        assert_eq!(status_on(16), ExpiryStatus::PastBestBefore);
+        assert_eq!(status_on(17), ExpiryStatus::Unsafe);
+    }
+
+    #[test]
+    fn test_expiry_status_best_before_without_grace_period() {
+        let product = FakeProduct::new().build();
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 10).unwrap())
+            .build();
+
+        assert_eq!(product.unsafe_date(&stash_item), None);
+        assert_eq!(
+            product.expiry_status(&stash_item, NaiveDate::from_ymd_opt(2024, 11, 10).unwrap()),
+            ExpiryStatus::PastBestBefore
+        );
+    }
+
+    #[test]
+    fn test_effective_expiry_date_without_expiry_date() {
+        let product = FakeProduct::new().build();
+        let stash_item = FakeStashItem::new().without_expiry_date().build();
+
+        assert_eq!(product.effective_expiry_date(&stash_item), None);
+    }
+
+    #[test]
+    fn test_effective_expiry_date_opened_without_expiry_date() {
+        let product = FakeProduct::new()
+            .with_shelf_life_after_opening_days(5)
+            .build();
+        let stash_item = FakeStashItem::new()
+            .without_expiry_date()
+            .with_opened_on(NaiveDate::from_ymd_opt(2023, 11, 1).unwrap())
+            .build();
+
+        assert_eq!(
+            product.effective_expiry_date(&stash_item),
+            NaiveDate::from_ymd_opt(2023, 11, 6)
+        );
+    }
+
+    #[test]
+    fn test_expiry_status_without_expiry_date() {
+        let product = FakeProduct::new()
+            .with_best_before_grace_period_days(7)
+            .build();
+        let stash_item = FakeStashItem::new()
+            .without_expiry_date()
+            .with_expiry_date_kind(ExpiryDateKind::UseBy)
+            .build();
+
+        assert_eq!(product.unsafe_date(&stash_item), None);
+        assert_eq!(
+            product.expiry_status(&stash_item, NaiveDate::MAX),
+            ExpiryStatus::Fresh
+        );
+    }
+
+    #[test]
+    fn test_expiry_status_use_by() {
+        let product = FakeProduct::new()
+            .with_best_before_grace_period_days(7)
+            .build();
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 10).unwrap())
+            .with_expiry_date_kind(ExpiryDateKind::UseBy)
+            .build();
+
+        assert_eq!(
+            product.expiry_status(&stash_item, NaiveDate::from_ymd_opt(2023, 11, 9).unwrap()),
+            ExpiryStatus::Fresh
+        );
+        assert_eq!(
+            product.expiry_status(&stash_item, NaiveDate::from_ymd_opt(2023, 11, 10).unwrap()),
+            ExpiryStatus::Unsafe
+        );
+    }
+
+    #[test]
+    fn test_consume_stash_item() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity("1.5".parse().unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_unit(Unit::Kilograms)
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let consumed = product
+            .consume_stash_item(stash_item.id(), Quantity::new(250).unwrap(), Unit::Grams)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(consumed.quantity(), &"1.25".parse().unwrap());
+        assert_eq!(product.stash_item(stash_item.id()), Some(&consumed));
+    }
+
+    #[test]
+    fn test_consume_stash_item_completely() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity("0.5".parse().unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_unit(Unit::Litres)
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let consumed = product
+            .consume_stash_item(
+                stash_item.id(),
+                Quantity::new(500).unwrap(),
+                Unit::Millilitres,
+            )
+            .unwrap();
+
+        assert_eq!(consumed, None);
+        assert!(!product.has_stash_item(stash_item.id()));
+    }
+
+    #[test]
+    fn test_consume_stash_item_too_much() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let result =
+            product.consume_stash_item(stash_item.id(), Quantity::new(3).unwrap(), Unit::Pieces);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::QuantityError(QuantityError::UnderflowError)
+        );
+        assert_eq!(product.stash_item(stash_item.id()), Some(&stash_item));
+    }
+
+    #[test]
+    fn test_consume_stash_item_incompatible_unit() {
+        let stash_item = FakeStashItem::new().build();
+        let mut product = FakeProduct::new()
+            .with_unit(Unit::Grams)
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let result =
+            product.consume_stash_item(stash_item.id(), Quantity::new(1).unwrap(), Unit::Litres);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::UnitError(UnitError::IncompatibleUnitsError)
+        );
+    }
+
+    #[test]
+    fn test_open_stash_item() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(4.try_into().unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let opened_on = NaiveDate::from_ymd_opt(2023, 11, 1).unwrap();
+
+        let opened = product.open_stash_item(stash_item.id(), opened_on).unwrap();
+
+        assert_ne!(opened.id(), stash_item.id());
+        assert_eq!(opened.quantity(), &Quantity::new(1).unwrap());
+        assert_eq!(opened.expiry_date(), stash_item.expiry_date());
+        assert_eq!(opened.opened_on(), &Some(opened_on));
+        assert_eq!(
+            product.stash_item(stash_item.id()).unwrap().quantity(),
+            &Quantity::new(3).unwrap()
+        );
+        assert_eq!(product.stash_item(opened.id()), Some(&opened));
+    }
+
+    #[test]
+    fn test_open_stash_item_last_unit() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let opened_on = NaiveDate::from_ymd_opt(2023, 11, 1).unwrap();
+
+        let opened = product.open_stash_item(stash_item.id(), opened_on).unwrap();
+
+        assert_eq!(opened.id(), stash_item.id());
+        assert_eq!(opened.opened_on(), &Some(opened_on));
+        assert_eq!(product.stash_items().len(), 1);
+    }
+
+    #[test]
+    fn test_open_stash_item_adds_to_opened() {
+        let expiry_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let opened_on = NaiveDate::from_ymd_opt(2023, 11, 1).unwrap();
+        let stash_item = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .with_expiry_date(expiry_date)
+            .build();
+        let existing = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .with_expiry_date(expiry_date)
+            .with_opened_on(opened_on)
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone(), existing.clone()])
+            .build();
+
+        let opened = product.open_stash_item(stash_item.id(), opened_on).unwrap();
+
+        assert_eq!(opened.id(), existing.id());
+        assert_eq!(opened.quantity(), &Quantity::new(3).unwrap());
+        assert!(!product.has_stash_item(stash_item.id()));
+        assert_eq!(product.stash_items().len(), 1);
+    }
+
+    #[test]
+    fn test_open_stash_item_already_opened() {
+        let stash_item = FakeStashItem::new()
+            .with_opened_on(NaiveDate::from_ymd_opt(2023, 11, 1).unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let result = product.open_stash_item(
+            stash_item.id(),
+            NaiveDate::from_ymd_opt(2023, 11, 2).unwrap(),
+        );
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::StashItemAlreadyOpened
+        );
+    }
+
+    #[test]
+    fn test_move_stash_item() {
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 26).unwrap())
+            .build();
+        let mut source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let mut target = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 27).unwrap())
+                .build()])
+            .build();
+
+        let moved = source
+            .move_stash_item(stash_item.id(), &mut target, false)
+            .unwrap();
+
+        assert_eq!(moved, stash_item);
+        assert!(!source.has_stash_item(stash_item.id()));
+        assert_eq!(target.stash_item(stash_item.id()), Some(&stash_item));
+        assert_eq!(target.stash_items().len(), 2);
+    }
+
+    #[test]
+    fn test_move_stash_item_doesnt_exist() {
+        let mut source = FakeProduct::new().build();
+        let mut target = FakeProduct::new().build();
+
+        let result = source.move_stash_item(&Uuid::new_v4(), &mut target, false);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::StashItemNotFound
+        );
+    }
+
+    #[test]
+    fn test_move_stash_item_existing_expiry_date() {
+        let expiry_date = NaiveDate::from_ymd_opt(2023, 11, 26).unwrap();
+        let stash_item = FakeStashItem::new().with_expiry_date(expiry_date).build();
+        let mut source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let mut target = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_expiry_date(expiry_date)
+                .build()])
+            .build();
+        let original_target = target.clone();
+
+        let result = source.move_stash_item(stash_item.id(), &mut target, false);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::DuplicateExpiryDateError
+        );
+        assert!(source.has_stash_item(stash_item.id()));
+        assert_eq!(target, original_target);
+    }
+
+    #[test]
+    fn test_move_stash_item_merge() {
+        let expiry_date = NaiveDate::from_ymd_opt(2023, 11, 26).unwrap();
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(expiry_date)
+            .with_quantity(Quantity::new(2).unwrap())
+            .build();
+        let existing = FakeStashItem::new()
+            .with_expiry_date(expiry_date)
+            .with_quantity(Quantity::new(3).unwrap())
+            .build();
+        let mut source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let mut target = FakeProduct::new()
+            .with_stash_items(vec![existing.clone()])
+            .build();
+
+        let moved = source
+            .move_stash_item(stash_item.id(), &mut target, true)
+            .unwrap();
+
+        assert_eq!(moved.id(), existing.id());
+        assert_eq!(moved.quantity(), &Quantity::new(5).unwrap());
+        assert!(!source.has_stash_item(stash_item.id()));
+        assert!(!target.has_stash_item(stash_item.id()));
+        assert_eq!(target.stash_item(existing.id()), Some(&moved));
+    }
+
+    #[test]
+    fn test_merge_into() {
+        let expiry_date = NaiveDate::from_ymd_opt(2023, 11, 26).unwrap();
+        let merged_stash_item = FakeStashItem::new()
+            .with_expiry_date(expiry_date)
+            .with_quantity(Quantity::new(2).unwrap())
+            .build();
+        let moved_stash_item = FakeStashItem::new()
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 27).unwrap())
+            .build();
+        let existing = FakeStashItem::new()
+            .with_expiry_date(expiry_date)
+            .with_quantity(Quantity::new(3).unwrap())
+            .build();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![merged_stash_item.clone(), moved_stash_item.clone()])
+            .build();
+        let mut target = FakeProduct::new()
+            .with_stash_items(vec![existing.clone()])
+            .build();
+
+        source.merge_into(&mut target).unwrap();
+
+        assert_eq!(target.stash_items().len(), 2);
+        assert_eq!(
+            target.stash_item(existing.id()).unwrap().quantity(),
+            &Quantity::new(5).unwrap()
+        );
+        assert_eq!(
+            target.stash_item(moved_stash_item.id()),
+            Some(&moved_stash_item)
+        );
+        assert!(!target.has_stash_item(merged_stash_item.id()));
+    }
+
+    #[test]
+    fn test_merge_into_attaches_barcodes() {
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+        let source = FakeProduct::new()
+            .with_barcodes(vec![barcode.clone()])
+            .build();
+        let mut target = FakeProduct::new().build();
+
+        source.clone().merge_into(&mut target).unwrap();
+
+        assert!(target.has_barcode(source.id()));
+        assert!(target.has_barcode(&barcode));
+        assert_eq!(target.barcodes().len(), 2);
+    }
+
+    #[test]
+    fn test_merge_into_failure_leaves_target_untouched() {
+        let expiry_date = NaiveDate::from_ymd_opt(2023, 11, 26).unwrap();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![
+                FakeStashItem::new()
+                    .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 25).unwrap())
+                    .build(),
+                FakeStashItem::new()
+                    .with_expiry_date(expiry_date)
+                    .with_quantity(Quantity::try_from(rust_decimal::Decimal::MAX).unwrap())
+                    .build(),
+            ])
+            .build();
+        let mut target = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_expiry_date(expiry_date)
+                .build()])
+            .build();
+        let original_target = target.clone();
+
+        let result = source.merge_into(&mut target);
+
+        assert!(result.is_err());
+        assert_eq!(target, original_target);
+    }
+
+    #[test]
+    fn test_attach_barcode() {
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+        let mut product = FakeProduct::new().build();
+
+        product.attach_barcode(barcode.clone());
+
+        assert!(product.has_barcode(&barcode));
+        assert!(product.barcodes().contains(&barcode));
+    }
+
+    #[test]
+    fn test_attach_barcode_own_id() {
+        let mut product = FakeProduct::new().build();
+
+        product.attach_barcode(product.id().clone());
+
+        assert!(product.barcodes().is_empty());
+    }
+
+    #[test]
+    fn test_has_barcode_own_id() {
+        let product = FakeProduct::new().build();
+
+        assert!(product.has_barcode(product.id()));
+    }
+
+    #[test]
+    fn test_detach_barcode() {
+        let barcode: ProductId = "BARCODE".parse().unwrap();
+        let mut product = FakeProduct::new()
+            .with_barcodes(vec![barcode.clone()])
+            .build();
+
+        let result = product.detach_barcode(&barcode);
+
+        assert!(result.is_ok());
+        assert!(!product.has_barcode(&barcode));
+    }
+
+    #[test]
+    fn test_detach_barcode_not_attached() {
+        let mut product = FakeProduct::new().build();
+
+        let result = product.detach_barcode(&"BARCODE".parse().unwrap());
+
+        assert_eq!(result.unwrap_err(), ProductRepositoryError::BarcodeNotFound);
+    }
+
+    #[test]
+    fn test_detach_barcode_own_id() {
+        let mut product = FakeProduct::new().build();
+        let product_id = product.id().clone();
+
+        let result = product.detach_barcode(&product_id);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ProductRepositoryError::CannotDetachPrimaryBarcode
+        );
+        assert!(product.has_barcode(&product_id));
+    }
+
+    #[test]
+    fn test_new_records_no_events() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new().build()])
+            .build();
+
+        assert!(product.events().is_empty());
+    }
+
+    #[test]
+    fn test_add_stash_item_records_event() {
+        let mut product = FakeProduct::new().with_stash_items(vec![]).build();
+        let stash_item = FakeStashItem::new().build();
+
+        product.add_stash_item(stash_item.clone()).unwrap();
+
+        assert_eq!(
+            product.events(),
+            [ProductEvent::StashItemAdded {
+                product_id: product.id().clone(),
+                stash_item
+            }]
+        );
+    }
+
+    #[test]
+    fn test_add_existing_stash_item_records_no_event() {
+        let stash_item = FakeStashItem::new().build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        assert!(product.add_stash_item(stash_item).is_err());
+        assert!(product.events().is_empty());
+    }
+
+    #[test]
+    fn test_remove_stash_item_records_event() {
+        let stash_item = FakeStashItem::new().build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        product.remove_stash_item(stash_item.id()).unwrap();
+
+        assert_eq!(
+            product.events(),
+            [ProductEvent::StashItemRemoved {
+                product_id: product.id().clone(),
+                stash_item
+            }]
+        );
+    }
+
+    #[test]
+    fn test_update_stash_item_records_changes() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(Quantity::new(2).unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let updated = FakeStashItem::new()
+            .with_id(*stash_item.id())
+            .with_quantity(Quantity::new(3).unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 2, 1).unwrap())
+            .build();
+        product.update_stash_item(updated).unwrap();
+
+        assert_eq!(
+            product.events(),
+            [
+                ProductEvent::QuantityChanged {
+                    product_id: product.id().clone(),
+                    stash_item_id: *stash_item.id(),
+                    from: Quantity::new(2).unwrap(),
+                    to: Quantity::new(3).unwrap(),
+                },
+                ProductEvent::ExpiryDateChanged {
+                    product_id: product.id().clone(),
+                    stash_item_id: *stash_item.id(),
+                    from: NaiveDate::from_ymd_opt(2023, 1, 1),
+                    to: NaiveDate::from_ymd_opt(2023, 2, 1),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_stash_item_records_kind_and_opened_on_changes() {
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date_kind(ExpiryDateKind::BestBefore)
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let mut updated = stash_item.clone();
+        updated.set_expiry_date_kind(ExpiryDateKind::UseBy);
+        updated.set_opened_on(NaiveDate::from_ymd_opt(2023, 1, 1));
+        product.update_stash_item(updated).unwrap();
+
+        assert_eq!(
+            product.events(),
+            [
+                ProductEvent::ExpiryDateKindChanged {
+                    product_id: product.id().clone(),
+                    stash_item_id: *stash_item.id(),
+                    from: ExpiryDateKind::BestBefore,
+                    to: ExpiryDateKind::UseBy,
+                },
+                ProductEvent::OpenedOnChanged {
+                    product_id: product.id().clone(),
+                    stash_item_id: *stash_item.id(),
+                    from: None,
+                    to: NaiveDate::from_ymd_opt(2023, 1, 1),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_only_stash_item_records_opened_on_change() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(Quantity::new(1).unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        product
+            .open_stash_item(
+                stash_item.id(),
+                NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            product.events(),
+            [ProductEvent::OpenedOnChanged {
+                product_id: product.id().clone(),
+                stash_item_id: *stash_item.id(),
+                from: None,
+                to: NaiveDate::from_ymd_opt(2023, 1, 1),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_update_stash_item_unchanged_records_no_event() {
+        let stash_item = FakeStashItem::new().build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        product.update_stash_item(stash_item).unwrap();
+
+        assert!(product.events().is_empty());
+    }
+
+    #[test]
+    fn test_consume_stash_item_completely_records_event() {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(Quantity::new(1).unwrap())
+            .build();
+        let mut product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        product
+            .consume_stash_item(stash_item.id(), Quantity::new(1).unwrap(), Unit::Pieces)
+            .unwrap();
+
+        assert_eq!(
+            product.events(),
+            [ProductEvent::StashItemRemoved {
+                product_id: product.id().clone(),
+                stash_item
+            }]
+        );
+    }
+
+    #[test]
+    fn test_move_stash_item_records_events_on_both_products() {
+        let stash_item = FakeStashItem::new().build();
+        let mut source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let mut target = FakeProduct::new().with_stash_items(vec![]).build();
+
+        source
+            .move_stash_item(stash_item.id(), &mut target, false)
+            .unwrap();
+
+        assert_eq!(
+            source.events(),
+            [ProductEvent::StashItemRemoved {
+                product_id: source.id().clone(),
+                stash_item: stash_item.clone()
+            }]
+        );
+        assert_eq!(
+            target.events(),
+            [ProductEvent::StashItemAdded {
+                product_id: target.id().clone(),
+                stash_item
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge_into_carries_events_over_to_target() {
+        let stash_item = FakeStashItem::new().build();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let source_id = source.id().clone();
+        let mut target = FakeProduct::new().with_stash_items(vec![]).build();
+
+        source.merge_into(&mut target).unwrap();
+
+        assert_eq!(
+            target.events(),
+            [
+                ProductEvent::StashItemRemoved {
+                    product_id: source_id,
+                    stash_item: stash_item.clone()
+                },
+                ProductEvent::StashItemAdded {
+                    product_id: target.id().clone(),
+                    stash_item
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_take_events() {
+        let mut product = FakeProduct::new().with_stash_items(vec![]).build();
+        product
+            .add_stash_item(FakeStashItem::new().build())
+            .unwrap();
+
+        assert_eq!(product.take_events().len(), 1);
+        assert!(product.events().is_empty());
+    }
+
+    #[test]
+    fn test_events_do_not_make_products_unequal() {
+        let stash_item = FakeStashItem::new().build();
+        let mut product = FakeProduct::new().with_stash_items(vec![]).build();
+        let mut unchanged = product.clone();
+
+        product.add_stash_item(stash_item.clone()).unwrap();
+        unchanged.add_stash_item(stash_item).unwrap();
+        unchanged.take_events();
+
+        assert_eq!(product, unchanged);
+    }
+
+    #[test]
+    fn test_state_makes_products_unequal() {
+        let product = FakeProduct::new().build();
+        let mut changed = product.clone();
+
+        changed.set_name(format!("{} changed", product.name()));
+
+        assert_ne!(product, changed);
     }
 }
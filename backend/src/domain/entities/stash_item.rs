@@ -2,7 +2,7 @@ use chrono::NaiveDate;
 use getset::{Getters, Setters};
 use uuid::Uuid;
 
-use crate::domain::value_objects::Quantity;
+use crate::domain::value_objects::{ExpiryDateKind, Quantity};
 
 use super::Entity;
 
@@ -17,24 +17,34 @@ pub struct StashItem {
     #[getset(get = "pub", set = "pub")]
     quantity: Quantity,
 
-    /// Date when this stash item expires
+    /// Date when this stash item expires, if it expires at all
     #[getset(get = "pub", set = "pub")]
-    expiry_date: NaiveDate,
+    expiry_date: Option<NaiveDate>,
+
+    /// What the expiry date means
+    #[getset(get = "pub", set = "pub")]
+    expiry_date_kind: ExpiryDateKind,
+
+    /// Date when this stash item was opened, if it has been
+    #[getset(get = "pub", set = "pub")]
+    opened_on: Option<NaiveDate>,
 }
 
 impl StashItem {
-    pub fn new(id: Uuid, quantity: Quantity, expiry_date: NaiveDate) -> Self {
+    pub fn new(id: Uuid, quantity: Quantity, expiry_date: Option<NaiveDate>) -> Self {
         Self {
             id,
             quantity,
             expiry_date,
+            expiry_date_kind: ExpiryDateKind::default(),
+            opened_on: None,
         }
     }
 }
 
 impl Entity<Uuid> for StashItem {
     fn id(&self) -> &Uuid {
-        &self.id()
+        self.id()
     }
 }
 
@@ -63,6 +73,38 @@ mod tests {
     fn test_expiry_date() {
         let expires = NaiveDate::from_ymd_opt(2023, 11, 26).unwrap();
         let item = FakeStashItem::new().with_expiry_date(expires).build();
-        assert_eq!(item.expiry_date(), &expires);
+        assert_eq!(item.expiry_date(), &Some(expires));
+    }
+
+    #[test]
+    fn test_without_expiry_date() {
+        let item = FakeStashItem::new().without_expiry_date().build();
+        assert_eq!(item.expiry_date(), &None);
+    }
+
+    #[test]
+    fn test_expiry_date_kind() {
+        let item = FakeStashItem::new()
+            .with_expiry_date_kind(ExpiryDateKind::UseBy)
+            .build();
+        assert_eq!(item.expiry_date_kind(), &ExpiryDateKind::UseBy);
+    }
+
+    #[test]
+    fn test_opened_on() {
+        let opened_on = NaiveDate::from_ymd_opt(2023, 11, 20).unwrap();
+        let item = FakeStashItem::new().with_opened_on(opened_on).build();
+        assert_eq!(item.opened_on(), &Some(opened_on));
+    }
+
+    #[test]
+    fn test_not_opened() {
+        let item = StashItem::new(
+            Uuid::new_v4(),
+            Quantity::new(1).unwrap(),
+            Some(NaiveDate::from_ymd_opt(2023, 11, 26).unwrap()),
+        );
+        assert_eq!(item.opened_on(), &None);
+        assert_eq!(item.expiry_date_kind(), &ExpiryDateKind::BestBefore);
     }
 }
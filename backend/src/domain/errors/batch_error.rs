@@ -0,0 +1,33 @@
+use super::ProductRepositoryError;
+
+/// Error signalling that a batch of operations failed, so none of them were applied
+#[derive(Debug, PartialEq, Eq)]
+pub enum BatchError {
+    /// One of the operations failed
+    OperationFailed {
+        /// Index of the operation in the batch
+        index: usize,
+        error: ProductRepositoryError,
+    },
+    /// The batch as a whole failed, like when it could not be committed
+    BatchFailed(ProductRepositoryError),
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OperationFailed { index, error } => {
+                write!(f, "Operation {} failed: {}", index, error)
+            }
+            Self::BatchFailed(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<ProductRepositoryError> for BatchError {
+    fn from(error: ProductRepositoryError) -> Self {
+        Self::BatchFailed(error)
+    }
+}
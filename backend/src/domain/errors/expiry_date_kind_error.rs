@@ -0,0 +1,14 @@
+/// Possible errors when parsing the kind of an expiry date
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExpiryDateKindError {
+    /// The kind is not one of the known kinds
+    UnknownKindError,
+}
+
+impl std::fmt::Display for ExpiryDateKindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown expiry date kind")
+    }
+}
+
+impl std::error::Error for ExpiryDateKindError {}
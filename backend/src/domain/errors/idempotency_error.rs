@@ -0,0 +1,19 @@
+/// Possible errors when keeping track of requests made with an idempotency key
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdempotencyError {
+    /// Error related to storing the requests and their responses
+    PersistenceError(String),
+    /// The key was claimed again for a retry of the request, after the lease on it ran out
+    ClaimLost,
+}
+
+impl std::fmt::Display for IdempotencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdempotencyError::PersistenceError(error) => write!(f, "{}", error),
+            IdempotencyError::ClaimLost => write!(f, "Idempotency key was claimed again"),
+        }
+    }
+}
+
+impl std::error::Error for IdempotencyError {}
@@ -1,15 +1,25 @@
+mod batch_error;
 mod brand_error;
 mod duplicate_expiry_date_error;
+mod expiry_date_kind_error;
+mod idempotency_error;
+mod notification_error;
 mod product_id_error;
 mod product_repository_error;
 mod quantity_error;
 mod stash_item_doesnt_exist_error;
 mod stash_item_exists_error;
+mod unit_error;
 
+pub use batch_error::BatchError;
 pub use brand_error::BrandError;
 pub use duplicate_expiry_date_error::DuplicateExpiryDateError;
+pub use expiry_date_kind_error::ExpiryDateKindError;
+pub use idempotency_error::IdempotencyError;
+pub use notification_error::NotificationError;
 pub use product_id_error::ProductIdError;
 pub use product_repository_error::ProductRepositoryError;
 pub use quantity_error::QuantityError;
 pub use stash_item_doesnt_exist_error::StashItemDoesntExistError;
 pub use stash_item_exists_error::StashItemExistsError;
+pub use unit_error::UnitError;
@@ -0,0 +1,37 @@
+use super::ProductRepositoryError;
+
+/// Possible errors when notifying about expiring stash items
+#[derive(Debug, PartialEq, Eq)]
+pub enum NotificationError {
+    /// Error when finding the stash items to notify about
+    ProductRepositoryError(ProductRepositoryError),
+    /// A notifier is missing settings, or they are invalid
+    ConfigurationError(String),
+    /// A notifier failed to deliver the notification
+    DeliveryError(String),
+    /// Error related to keeping track of what has been notified about
+    PersistenceError(String),
+}
+
+impl std::fmt::Display for NotificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationError::ProductRepositoryError(error) => error.fmt(f),
+            NotificationError::ConfigurationError(error) => {
+                write!(f, "Invalid notifier configuration: {}", error)
+            }
+            NotificationError::DeliveryError(error) => {
+                write!(f, "Failed to deliver notification: {}", error)
+            }
+            NotificationError::PersistenceError(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for NotificationError {}
+
+impl From<ProductRepositoryError> for NotificationError {
+    fn from(error: ProductRepositoryError) -> Self {
+        Self::ProductRepositoryError(error)
+    }
+}
@@ -1,6 +1,6 @@
 use super::{
     BrandError, DuplicateExpiryDateError, ProductIdError, QuantityError, StashItemDoesntExistError,
-    StashItemExistsError,
+    StashItemExistsError, UnitError,
 };
 
 /// Error type for ProductRepository
@@ -14,6 +14,8 @@ pub enum ProductRepositoryError {
     BrandError(BrandError),
     /// Error related to quantity
     QuantityError(QuantityError),
+    /// Error related to units
+    UnitError(UnitError),
     /// Error related to expiry date
     ExpiryDateError(chrono::ParseError),
     /// Error signalling that a product with the same expiry date already exists
@@ -26,8 +28,18 @@ pub enum ProductRepositoryError {
     StashItemExists,
     /// The stash item does not exist
     StashItemNotFound,
+    /// The stash item has already been opened
+    StashItemAlreadyOpened,
     /// The provided date interval is invalid
     InvalidDateInterval,
+    /// The barcode is not attached to the product
+    BarcodeNotFound,
+    /// The ID of a product can not be detached from it
+    CannotDetachPrimaryBarcode,
+    /// The operation to undo does not exist, or has already been undone or forgotten
+    OperationNotFound,
+    /// The operation can not be undone, as what it changed has been changed again since
+    UndoConflict,
     /// Error related to the implementation of the repository
     PersisteneError(String),
 }
@@ -39,6 +51,7 @@ impl std::fmt::Display for ProductRepositoryError {
             ProductRepositoryError::StashItemIdError(error) => error.fmt(f),
             ProductRepositoryError::BrandError(error) => error.fmt(f),
             ProductRepositoryError::QuantityError(error) => error.fmt(f),
+            ProductRepositoryError::UnitError(error) => error.fmt(f),
             ProductRepositoryError::ExpiryDateError(error) => {
                 write!(f, "Expiry date error: {}", error)
             }
@@ -49,7 +62,18 @@ impl std::fmt::Display for ProductRepositoryError {
             ProductRepositoryError::ProductNotFound => write!(f, "Product not found"),
             ProductRepositoryError::StashItemExists => write!(f, "Stash item already exists"),
             ProductRepositoryError::StashItemNotFound => write!(f, "Stash item not found"),
+            ProductRepositoryError::StashItemAlreadyOpened => {
+                write!(f, "Stash item is already opened")
+            }
             ProductRepositoryError::InvalidDateInterval => write!(f, "Invalid date interval"),
+            ProductRepositoryError::BarcodeNotFound => write!(f, "Barcode not found"),
+            ProductRepositoryError::CannotDetachPrimaryBarcode => {
+                write!(f, "The ID of a product can not be detached from it")
+            }
+            ProductRepositoryError::OperationNotFound => write!(f, "Operation not found"),
+            ProductRepositoryError::UndoConflict => {
+                write!(f, "The operation has been overtaken by later changes")
+            }
             ProductRepositoryError::PersisteneError(error) => write!(f, "{}", error),
         }
     }
@@ -75,6 +99,12 @@ impl From<BrandError> for ProductRepositoryError {
     }
 }
 
+impl From<UnitError> for ProductRepositoryError {
+    fn from(error: UnitError) -> Self {
+        Self::UnitError(error)
+    }
+}
+
 impl From<QuantityError> for ProductRepositoryError {
     fn from(error: QuantityError) -> Self {
         Self::QuantityError(error)
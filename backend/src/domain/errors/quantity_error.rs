@@ -3,6 +3,14 @@
 pub enum QuantityError {
     /// A quantity can not be zero
     ZeroError,
+    /// A quantity can not be negative
+    NegativeError,
+    /// The quantity is not a valid number
+    InvalidError,
+    /// The quantity is too large to be represented
+    OverflowError,
+    /// The quantity is too small to be represented
+    UnderflowError,
 }
 
 impl std::error::Error for QuantityError {}
@@ -11,6 +19,10 @@ impl std::fmt::Display for QuantityError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             QuantityError::ZeroError => write!(f, "Quantity can not be zero"),
+            QuantityError::NegativeError => write!(f, "Quantity can not be negative"),
+            QuantityError::InvalidError => write!(f, "Quantity is not a valid number"),
+            QuantityError::OverflowError => write!(f, "Quantity is too large"),
+            QuantityError::UnderflowError => write!(f, "Quantity is too small"),
         }
     }
 }
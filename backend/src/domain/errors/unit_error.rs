@@ -0,0 +1,24 @@
+/// Possible errors when working with units
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UnitError {
+    /// The unit is not one of the known units
+    UnknownUnitError,
+    /// The units measure different things, like mass and volume, and can not be converted between
+    IncompatibleUnitsError,
+    /// The quantity can not be represented in the unit it is converted to
+    ConversionError,
+}
+
+impl std::fmt::Display for UnitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnitError::UnknownUnitError => write!(f, "Unknown unit"),
+            UnitError::IncompatibleUnitsError => write!(f, "The units are not compatible"),
+            UnitError::ConversionError => {
+                write!(f, "The quantity can not be converted to the unit")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnitError {}
@@ -0,0 +1,10 @@
+mod product_event;
+mod product_event_dispatcher;
+mod product_event_handler;
+
+pub use product_event::ProductEvent;
+pub use product_event_dispatcher::ProductEventDispatcher;
+pub use product_event_handler::ProductEventHandler;
+
+#[cfg(test)]
+pub use product_event_handler::MockProductEventHandler;
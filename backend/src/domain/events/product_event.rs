@@ -0,0 +1,51 @@
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::StashItem,
+    value_objects::{ExpiryDateKind, ProductId, Quantity},
+};
+
+/// Something which happened to the stash items of a [`Product`](crate::domain::entities::Product). Products record
+/// these as they are changed, and repositories dispatch them once the changes are saved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProductEvent {
+    /// A stash item was added to a product
+    StashItemAdded {
+        product_id: ProductId,
+        stash_item: StashItem,
+    },
+    /// A stash item was removed from a product
+    StashItemRemoved {
+        product_id: ProductId,
+        stash_item: StashItem,
+    },
+    /// The quantity of a stash item changed
+    QuantityChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: Quantity,
+        to: Quantity,
+    },
+    /// The expiry date of a stash item changed
+    ExpiryDateChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    /// What the expiry date of a stash item means changed
+    ExpiryDateKindChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: ExpiryDateKind,
+        to: ExpiryDateKind,
+    },
+    /// The date a stash item was opened on changed, like when it was opened
+    OpenedOnChanged {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+}
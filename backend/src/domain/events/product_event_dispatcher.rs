@@ -0,0 +1,124 @@
+use super::{ProductEvent, ProductEventHandler};
+
+/// Dispatches the events of products to the handlers registered for them
+#[derive(Default)]
+pub struct ProductEventDispatcher {
+    handlers: Vec<Box<dyn ProductEventHandler>>,
+}
+
+impl ProductEventDispatcher {
+    /// Creates a dispatcher without any handlers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a handler, which gets every event dispatched from now on
+    ///
+    /// # Parameters
+    /// * `handler` - The handler to register
+    pub fn register(&mut self, handler: Box<dyn ProductEventHandler>) {
+        self.handlers.push(handler);
+    }
+
+    /// Dispatches events to all registered handlers, in the order they were registered
+    ///
+    /// # Parameters
+    /// * `events` - The events to dispatch, in the order they happened
+    pub fn dispatch(&self, events: &[ProductEvent]) {
+        for event in events {
+            for handler in &self.handlers {
+                handler.handle(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl ProductEventDispatcher {
+    /// Creates a dispatcher with a handler collecting every event dispatched, for testing purposes
+    ///
+    /// # Returns
+    /// * The dispatcher, and the events it has dispatched
+    pub fn collecting() -> (
+        std::sync::Arc<Self>,
+        std::sync::Arc<std::sync::Mutex<Vec<ProductEvent>>>,
+    ) {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+
+        let collected = events.clone();
+        let mut handler = super::MockProductEventHandler::new();
+        handler
+            .expect_handle()
+            .returning(move |event| collected.lock().unwrap().push(event.clone()));
+
+        let mut dispatcher = Self::new();
+        dispatcher.register(Box::new(handler));
+
+        (std::sync::Arc::new(dispatcher), events)
+    }
+}
+
+impl std::fmt::Debug for ProductEventDispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProductEventDispatcher")
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use mockall::{predicate::eq, Sequence};
+
+    use crate::domain::{entities::FakeStashItem, events::MockProductEventHandler};
+
+    use super::*;
+
+    #[test]
+    fn test_dispatch_without_handlers() {
+        let dispatcher = ProductEventDispatcher::new();
+
+        dispatcher.dispatch(&[ProductEvent::StashItemAdded {
+            product_id: "7038010000737".parse().unwrap(),
+            stash_item: FakeStashItem::new().build(),
+        }]);
+    }
+
+    #[test]
+    fn test_dispatch_to_every_handler_in_order() {
+        let added = ProductEvent::StashItemAdded {
+            product_id: "7038010000737".parse().unwrap(),
+            stash_item: FakeStashItem::new().build(),
+        };
+        let removed = ProductEvent::StashItemRemoved {
+            product_id: "7038010000737".parse().unwrap(),
+            stash_item: FakeStashItem::new().build(),
+        };
+
+        let mut dispatcher = ProductEventDispatcher::new();
+        let handled = Arc::new(Mutex::new(vec![]));
+        for name in ["first", "second"] {
+            let mut sequence = Sequence::new();
+            let mut handler = MockProductEventHandler::new();
+            for event in [added.clone(), removed.clone()] {
+                let handled = handled.clone();
+                handler
+                    .expect_handle()
+                    .with(eq(event))
+                    .times(1)
+                    .in_sequence(&mut sequence)
+                    .returning(move |_| handled.lock().unwrap().push(name));
+            }
+            dispatcher.register(Box::new(handler));
+        }
+
+        dispatcher.dispatch(&[added, removed]);
+
+        assert_eq!(
+            *handled.lock().unwrap(),
+            vec!["first", "second", "first", "second"]
+        );
+    }
+}
@@ -0,0 +1,11 @@
+use super::ProductEvent;
+
+/// Handles the events of products once the changes they stem from are saved
+#[cfg_attr(test, mockall::automock)]
+pub trait ProductEventHandler: Sync + Send {
+    /// Handles an event. The change the event stems from is already saved, so there is no failing it
+    ///
+    /// # Parameters
+    /// * `event` - The event to handle
+    fn handle(&self, event: &ProductEvent);
+}
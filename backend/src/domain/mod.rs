@@ -1,4 +1,6 @@
 pub mod entities;
 pub mod errors;
+pub mod events;
+pub mod notifiers;
 pub mod repositories;
 pub mod value_objects;
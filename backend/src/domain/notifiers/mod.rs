@@ -0,0 +1,6 @@
+mod notifier;
+
+pub use notifier::Notifier;
+
+#[cfg(test)]
+pub use notifier::MockNotifier;
@@ -0,0 +1,17 @@
+use crate::domain::{errors::NotificationError, value_objects::ExpiryDigest};
+
+#[cfg_attr(test, mockall::automock)]
+pub trait Notifier: Sync + Send {
+    /// Name telling the notifier apart from the others, under which what it delivered is kept track of
+    fn name(&self) -> &'static str;
+
+    /// Delivers a digest of expiring stash items
+    ///
+    /// # Parameters
+    /// * `digest` - The digest to deliver
+    ///
+    /// # Returns
+    /// * `Ok(())` if the digest was delivered
+    /// * `Err(_)` if the digest could not be delivered
+    fn notify(&self, digest: &ExpiryDigest) -> Result<(), NotificationError>;
+}
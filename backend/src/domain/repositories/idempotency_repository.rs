@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::domain::{
+    errors::IdempotencyError,
+    value_objects::{IdempotencyRecord, IdempotentResponse},
+};
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait IdempotencyRepository: Sync + Send {
+    /// Claims an idempotency key for a request, unless it has already been claimed. Keys claimed before
+    /// `expired_before` are forgotten first, so they can be claimed again. So are keys claimed before `stale_before`
+    /// whose requests were never completed, like when the server stopped while handling them. The key is claimed with
+    /// a token, which only the request holding the claim knows, so a request whose claim went stale cannot complete
+    /// or release the key once a retry has claimed it again
+    ///
+    /// # Parameters
+    /// * `key` - The idempotency key
+    /// * `token` - Token to claim the key with
+    /// * `fingerprint` - Fingerprint of the request
+    /// * `now` - When the key is claimed
+    /// * `expired_before` - When the oldest keys still remembered were claimed
+    /// * `stale_before` - When the oldest keys still being handled were claimed
+    ///
+    /// # Returns
+    /// * `Ok(None)` if the key has been claimed for the request
+    /// * `Ok(Some(record))` with the request the key was already claimed for
+    /// * `Err(_)` if the repository fails to claim the key
+    async fn claim(
+        &self,
+        key: &str,
+        token: &Uuid,
+        fingerprint: &str,
+        now: NaiveDateTime,
+        expired_before: NaiveDateTime,
+        stale_before: NaiveDateTime,
+    ) -> Result<Option<IdempotencyRecord>, IdempotencyError>;
+
+    /// Records the response to the request a key was claimed for
+    ///
+    /// # Parameters
+    /// * `key` - The idempotency key
+    /// * `token` - Token the key was claimed with
+    /// * `response` - The response to the request
+    ///
+    /// # Returns
+    /// * `Ok(())` if the response was recorded
+    /// * `Err(IdempotencyError::ClaimLost)` if the key is no longer claimed with the token
+    /// * `Err(_)` if the repository fails to record the response
+    async fn complete(
+        &self,
+        key: &str,
+        token: &Uuid,
+        response: IdempotentResponse,
+    ) -> Result<(), IdempotencyError>;
+
+    /// Releases a key which has been claimed, but not completed, so the request can be retried
+    ///
+    /// # Parameters
+    /// * `key` - The idempotency key
+    /// * `token` - Token the key was claimed with
+    ///
+    /// # Returns
+    /// * `Ok(())` if the key was released, or is not claimed with the token
+    /// * `Err(_)` if the repository fails to release the key
+    async fn release(&self, key: &str, token: &Uuid) -> Result<(), IdempotencyError>;
+}
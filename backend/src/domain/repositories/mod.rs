@@ -1,6 +1,20 @@
+mod idempotency_repository;
+mod notification_repository;
 mod product_repository;
+mod unit_of_work;
 
+pub use idempotency_repository::IdempotencyRepository;
+pub use notification_repository::NotificationRepository;
 pub use product_repository::ProductRepository;
+pub use unit_of_work::{
+    ProductTransaction, UnitOfWork, Work, WorkResult, DEFAULT_UNDO_HISTORY_SIZE,
+};
 
+#[cfg(test)]
+pub use idempotency_repository::MockIdempotencyRepository;
+#[cfg(test)]
+pub use notification_repository::MockNotificationRepository;
 #[cfg(test)]
 pub use product_repository::MockProductRepository;
+#[cfg(test)]
+pub use unit_of_work::{MockProductTransaction, MockUnitOfWork};
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::errors::NotificationError;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait NotificationRepository: Sync + Send {
+    /// Gets the narrowest notification windows stash items have been notified about in by a notifier
+    ///
+    /// # Parameters
+    /// * `notifier` - Name of the notifier
+    /// * `stash_item_ids` - IDs of the stash items
+    ///
+    /// # Returns
+    /// * `Ok(windows)` with the narrowest window of each stash item the notifier has notified about. Stash items it
+    ///   has not notified about are left out
+    /// * `Err(_)` if the repository fails to get the notifications
+    async fn find_notified_windows(
+        &self,
+        notifier: &str,
+        stash_item_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, u32>, NotificationError>;
+
+    /// Records that a notifier has notified about stash items in a notification window
+    ///
+    /// # Parameters
+    /// * `notifier` - Name of the notifier
+    /// * `stash_item_ids` - IDs of the stash items
+    /// * `window_days` - The notification window the stash items were notified about in
+    ///
+    /// # Returns
+    /// * `Ok(())` if the notifications were recorded
+    /// * `Err(_)` if the repository fails to record the notifications
+    async fn mark_notified(
+        &self,
+        notifier: &str,
+        stash_item_ids: &[Uuid],
+        window_days: u32,
+    ) -> Result<(), NotificationError>;
+}
@@ -1,18 +1,26 @@
-use chrono::NaiveDate;
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
 use uuid::Uuid;
 
-use crate::domain::{entities::Product, errors::ProductRepositoryError, value_objects::ProductId};
+use crate::domain::{
+    entities::Product,
+    errors::ProductRepositoryError,
+    value_objects::{ProductId, TrashEntry},
+};
 
+/// Stores [`Product`]s. Deleted products and stash items go to the trash, where they can be restored from until they
+/// are purged. Apart from the methods dealing with the trash, a repository acts as if they are gone
 #[cfg_attr(test, mockall::automock)]
+#[async_trait]
 pub trait ProductRepository: Sync + Send {
     /// Gets all products with stash items
     ///
     /// # Returns
     /// * `Ok(products)` if the products were found
     /// * `Err(_)` if the repository fails to get the products
-    fn find_all_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError>;
+    async fn find_all_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError>;
 
-    /// Gets one product by id, if it exists
+    /// Gets one product by id, if it exists. The id may also be any barcode attached to the product
     ///
     /// # Parameters
     /// * `id` - The id of the product to get
@@ -21,7 +29,7 @@ pub trait ProductRepository: Sync + Send {
     /// * `Ok(Some(product))` if the product was found
     /// * `Ok(None)` if the product was not found
     /// * `Err(_)` if the repository fails to get the product
-    fn find_by_id(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError>;
+    async fn find_by_id(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError>;
 
     /// Gets a list of products by their ids
     ///
@@ -31,7 +39,7 @@ pub trait ProductRepository: Sync + Send {
     /// # Returns
     /// * `Ok(products)` if the products were found
     /// * `Err(_)` if the repository fails to get the products
-    fn find_by_ids(&self, ids: &[ProductId]) -> Result<Vec<Product>, ProductRepositoryError>;
+    async fn find_by_ids(&self, ids: &[ProductId]) -> Result<Vec<Product>, ProductRepositoryError>;
 
     /// Finds a product by the ID of a stash item that belongs to it
     ///
@@ -40,7 +48,7 @@ pub trait ProductRepository: Sync + Send {
     ///
     /// # Returns
     /// The product that the stash item belongs to, if it exists
-    fn find_by_stash_item_id(
+    async fn find_by_stash_item_id(
         &self,
         stash_item_id: &Uuid,
     ) -> Result<Option<Product>, ProductRepositoryError>;
@@ -53,13 +61,28 @@ pub trait ProductRepository: Sync + Send {
     ///
     /// # Returns
     /// A list of products with at least one stash item expiring within the given date interval
-    fn find_expiring_in_interval(
+    async fn find_expiring_in_interval(
         &self,
         after: Option<NaiveDate>,
         before: Option<NaiveDate>,
     ) -> Result<Vec<Product>, ProductRepositoryError>;
 
-    /// Returns whether a product exists
+    /// Finds all products with at least one stash item becoming unsafe within the given date interval. See
+    /// [`Product::unsafe_date`]
+    ///
+    /// # Parameters
+    /// - `after` - The start of the date range, inclusive
+    /// - `before` - The end of the date range, exclusive
+    ///
+    /// # Returns
+    /// A list of products with at least one stash item becoming unsafe within the given date interval
+    async fn find_unsafe_in_interval(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError>;
+
+    /// Returns whether a product exists. The id may also be any barcode attached to a product
     ///
     /// # Parameters
     /// * `id` - The id of the product to check
@@ -68,7 +91,7 @@ pub trait ProductRepository: Sync + Send {
     /// * `Ok(true)` if the product exists
     /// * `Ok(false)` if the product does not exist
     /// * `Err(_)` if the repository fails to check the product
-    fn exists_by_id(&self, id: &ProductId) -> Result<bool, ProductRepositoryError>;
+    async fn exists_by_id(&self, id: &ProductId) -> Result<bool, ProductRepositoryError>;
 
     /// Saves a product to the repository, or updates it if it already exists
     ///
@@ -78,9 +101,36 @@ pub trait ProductRepository: Sync + Send {
     /// # Returns
     /// * `Ok(())` if the product was saved
     /// * `Err(_)` if the repository fails to save the product
-    fn save(&self, product: Product) -> Result<(), ProductRepositoryError>;
+    async fn save(&self, product: Product) -> Result<(), ProductRepositoryError>;
 
-    /// Deletes a product by id
+    /// Saves several products to the repository in one go, or updates them if they already exist. Either all
+    /// products are saved, or none are
+    ///
+    /// # Parameters
+    /// * `products` - The products to save
+    ///
+    /// # Returns
+    /// * `Ok(())` if the products were saved
+    /// * `Err(_)` if the repository fails to save any of the products
+    async fn save_all(&self, products: Vec<Product>) -> Result<(), ProductRepositoryError>;
+
+    /// Merges a product into another product. The target product is saved, which moves the barcodes it took over from
+    /// the source to it, and the source product is deleted along with any stash items still belonging to it
+    ///
+    /// # Parameters
+    /// * `source_id` - The id of the product being merged
+    /// * `target` - The product being merged into, with the stash items of the source already moved to it
+    ///
+    /// # Returns
+    /// * `Ok(())` if the products were merged
+    /// * `Err(_)` if the repository fails to merge the products
+    async fn merge_into(
+        &self,
+        source_id: &ProductId,
+        target: Product,
+    ) -> Result<(), ProductRepositoryError>;
+
+    /// Deletes a product by id, moving it to the trash along with its stash items
     ///
     /// # Parameters
     /// * `id` - The id of the product to delete
@@ -88,5 +138,68 @@ pub trait ProductRepository: Sync + Send {
     /// # Returns
     /// * `Ok(())` if the product was deleted, or was not there in the first place
     /// * `Err(_)` if the repository fails to delete the product
-    fn delete_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError>;
+    async fn delete_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError>;
+
+    /// Deletes a stash item by id, moving it to the trash
+    ///
+    /// # Parameters
+    /// * `stash_item_id` - The id of the stash item to delete
+    ///
+    /// # Returns
+    /// * `Ok(())` if the stash item was deleted, or was not there in the first place
+    /// * `Err(_)` if the repository fails to delete the stash item
+    async fn delete_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError>;
+
+    /// Gets everything in the trash. Stash items deleted along with their product are part of the entry of the
+    /// product, not entries of their own
+    ///
+    /// # Returns
+    /// * `Ok(entries)` with the entries in the trash, most recently deleted first
+    /// * `Err(_)` if the repository fails to get the trash
+    async fn find_trash(&self) -> Result<Vec<TrashEntry>, ProductRepositoryError>;
+
+    /// Restores a product from the trash, along with the stash items it had when it was deleted. Barcodes taken by
+    /// other products since are not restored
+    ///
+    /// # Parameters
+    /// * `id` - The id of the deleted product
+    ///
+    /// # Returns
+    /// * `Ok(())` if the product was restored
+    /// * `Err(ProductRepositoryError::ProductNotFound)` if no product with the id is in the trash
+    /// * `Err(ProductRepositoryError::ProductAlreadyExists)` if another product has taken the id since
+    /// * `Err(_)` if the repository fails to restore the product
+    async fn restore_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError>;
+
+    /// Restores a stash item from the trash, back into its product
+    ///
+    /// # Parameters
+    /// * `stash_item_id` - The id of the deleted stash item
+    ///
+    /// # Returns
+    /// * `Ok(())` if the stash item was restored
+    /// * `Err(ProductRepositoryError::StashItemNotFound)` if no stash item with the id is in the trash
+    /// * `Err(ProductRepositoryError::DuplicateExpiryDateError)` if the product has gotten another stash item with the
+    ///   same dates since
+    /// * `Err(_)` if the repository fails to restore the stash item
+    async fn restore_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError>;
+
+    /// Permanently deletes everything that went to the trash before the given time
+    ///
+    /// # Parameters
+    /// * `deleted_before` - Entries deleted before this time are purged
+    ///
+    /// # Returns
+    /// * `Ok(count)` with the number of products and stash items purged
+    /// * `Err(_)` if the repository fails to purge the trash
+    async fn purge_trash(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError>;
 }
@@ -0,0 +1,167 @@
+use std::any::Any;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::Product,
+    errors::ProductRepositoryError,
+    value_objects::{Operation, ProductId},
+};
+
+/// How many of the most recent operations a [`UnitOfWork`] keeps by default, so they can be undone
+pub const DEFAULT_UNDO_HISTORY_SIZE: usize = 100;
+
+/// The products as seen from within a [`UnitOfWork`]. Changes made through it are only seen by the rest of the
+/// application once the unit of work is committed
+#[cfg_attr(test, mockall::automock)]
+pub trait ProductTransaction {
+    /// Finds a product by its ID, or by a barcode attached to it
+    ///
+    /// # Parameters
+    /// * `id` - The ID of the product, or a barcode attached to it
+    fn find_by_id(&mut self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError>;
+
+    /// Checks if a product exists by its ID, or by a barcode attached to it
+    ///
+    /// # Parameters
+    /// * `id` - The ID of the product, or a barcode attached to it
+    fn exists_by_id(&mut self, id: &ProductId) -> Result<bool, ProductRepositoryError>;
+
+    /// Finds the product a stash item belongs to
+    ///
+    /// # Parameters
+    /// * `stash_item_id` - The ID of the stash item
+    fn find_by_stash_item_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<Option<Product>, ProductRepositoryError>;
+
+    /// Saves a product, creating it if it does not exist
+    ///
+    /// # Parameters
+    /// * `product` - The product to save
+    fn save(&mut self, product: Product) -> Result<(), ProductRepositoryError>;
+
+    /// Merges a product into another, deleting it for good. The target is saved with what it took from the source
+    ///
+    /// # Parameters
+    /// * `source_id` - The ID of the product being merged
+    /// * `target` - The product being merged into, with the source's stash items and barcodes already added
+    fn merge_into(
+        &mut self,
+        source_id: &ProductId,
+        target: Product,
+    ) -> Result<(), ProductRepositoryError>;
+
+    /// Deletes a product by its ID, moving it to the trash along with its stash items
+    ///
+    /// # Parameters
+    /// * `id` - The ID of the product to delete, or a barcode attached to it
+    fn delete_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError>;
+
+    /// Deletes a stash item by its ID, moving it to the trash
+    ///
+    /// # Parameters
+    /// * `stash_item_id` - The ID of the stash item to delete
+    fn delete_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError>;
+
+    /// Restores a product from the trash. Fails with `ProductNotFound` if it is not in the trash, and with
+    /// `ProductAlreadyExists` if another product has taken its ID since
+    ///
+    /// # Parameters
+    /// * `id` - The ID of the product to restore
+    fn restore_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError>;
+
+    /// Restores a stash item from the trash. Fails with `StashItemNotFound` if it is not in the trash, and with
+    /// `DuplicateExpiryDateError` if its product has gotten another stash item with the same dates since
+    ///
+    /// # Parameters
+    /// * `stash_item_id` - The ID of the stash item to restore
+    fn restore_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError>;
+
+    /// Records an operation, so it can be undone. Only the most recent operations are kept, so the oldest one may be
+    /// forgotten to make room for it
+    ///
+    /// # Parameters
+    /// * `operation` - The operation to record
+    fn save_operation(&mut self, operation: Operation) -> Result<(), ProductRepositoryError>;
+
+    /// Finds an operation which can still be undone by its ID
+    ///
+    /// # Parameters
+    /// * `id` - The ID of the operation
+    fn find_operation_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Operation>, ProductRepositoryError>;
+
+    /// Forgets an operation, once it has been undone. Does nothing if the operation is not there in the first place
+    ///
+    /// # Parameters
+    /// * `id` - The ID of the operation
+    fn delete_operation_by_id(&mut self, id: &Uuid) -> Result<(), ProductRepositoryError>;
+}
+
+/// Work run by a [`UnitOfWork`], with its result and error boxed so units of work can be trait objects
+pub type Work = Box<dyn FnOnce(&mut dyn ProductTransaction) -> WorkResult + Send>;
+
+/// The boxed result or error of [`Work`]
+pub type WorkResult = Result<Box<dyn Any + Send>, Box<dyn Any + Send>>;
+
+/// Runs several repository operations in one transaction, so they are applied all together or not at all
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait UnitOfWork: Sync + Send {
+    /// Runs work in a transaction, which is committed if the work succeeds and rolled back if it fails. Use
+    /// [`run`](#method.run) rather than calling this directly
+    ///
+    /// # Parameters
+    /// * `work` - The work to run
+    ///
+    /// # Returns
+    /// * `Ok(result)` with what the work returned, whether it succeeded or not
+    /// * `Err(_)` if the transaction could not be started or committed
+    async fn run_boxed(&self, work: Work) -> Result<WorkResult, ProductRepositoryError>;
+}
+
+impl dyn UnitOfWork {
+    /// Runs work in a transaction, which is committed if the work succeeds and rolled back if it fails
+    ///
+    /// # Parameters
+    /// * `work` - The work to run, given the products as seen from within the transaction
+    ///
+    /// # Returns
+    /// * `Ok(value)` with what the work returned, once it is committed
+    /// * `Err(error)` with the error of the work, or the error of starting or committing the transaction
+    pub async fn run<T, E>(
+        &self,
+        work: impl FnOnce(&mut dyn ProductTransaction) -> Result<T, E> + Send + 'static,
+    ) -> Result<T, E>
+    where
+        T: Send + 'static,
+        E: From<ProductRepositoryError> + Send + 'static,
+    {
+        let result = self
+            .run_boxed(Box::new(move |transaction| match work(transaction) {
+                Ok(value) => Ok(Box::new(value)),
+                Err(error) => Err(Box::new(error)),
+            }))
+            .await?;
+
+        match result {
+            Ok(value) => Ok(*value
+                .downcast()
+                .expect("Unit of work returned a value of another type")),
+            Err(error) => Err(*error
+                .downcast()
+                .expect("Unit of work returned an error of another type")),
+        }
+    }
+}
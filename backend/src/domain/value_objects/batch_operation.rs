@@ -0,0 +1,34 @@
+use uuid::Uuid;
+
+use crate::domain::entities::{Product, StashItem};
+
+use super::{ProductId, Quantity, Unit};
+
+/// One of the operations in a batch, which are applied all together or not at all
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOperation {
+    /// Creates a product, failing if it already exists
+    CreateProduct { product: Box<Product> },
+    /// Adds a stash item to a product
+    AddStashItem {
+        product_id: ProductId,
+        stash_item: StashItem,
+    },
+    /// Updates a stash item of a product
+    UpdateStashItem {
+        product_id: ProductId,
+        stash_item: StashItem,
+    },
+    /// Deletes a stash item of a product, moving it to the trash
+    DeleteStashItem {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+    },
+    /// Consumes an amount of a stash item of a product. Without a unit, the amount is in the unit of the product
+    ConsumeStashItem {
+        product_id: ProductId,
+        stash_item_id: Uuid,
+        amount: Quantity,
+        unit: Option<Unit>,
+    },
+}
@@ -25,7 +25,7 @@ impl Brand {
     }
 
     /// Create a random brand, for testing purposes
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn random() -> Self {
         use rand::distributions::Alphanumeric;
         use rand::Rng;
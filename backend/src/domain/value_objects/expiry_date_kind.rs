@@ -0,0 +1,60 @@
+use std::str::FromStr;
+
+use crate::domain::errors::ExpiryDateKindError;
+
+/// What the expiry date printed on a stash item means
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ExpiryDateKind {
+    /// The item is at its best before the date, but usually fine for a while after
+    #[default]
+    BestBefore,
+    /// The item is not safe to eat after the date
+    UseBy,
+}
+
+impl std::fmt::Display for ExpiryDateKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpiryDateKind::BestBefore => write!(f, "best_before"),
+            ExpiryDateKind::UseBy => write!(f, "use_by"),
+        }
+    }
+}
+
+impl FromStr for ExpiryDateKind {
+    type Err = ExpiryDateKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "best_before" => Ok(ExpiryDateKind::BestBefore),
+            "use_by" => Ok(ExpiryDateKind::UseBy),
+            _ => Err(ExpiryDateKindError::UnknownKindError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("best_before".parse(), Ok(ExpiryDateKind::BestBefore));
+        assert_eq!("use_by".parse(), Ok(ExpiryDateKind::UseBy));
+    }
+
+    #[test]
+    fn test_from_str_unknown() {
+        assert_eq!(
+            "sell_by".parse::<ExpiryDateKind>(),
+            Err(ExpiryDateKindError::UnknownKindError)
+        );
+    }
+
+    #[test]
+    fn test_to_string() {
+        for kind in [ExpiryDateKind::BestBefore, ExpiryDateKind::UseBy] {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::NaiveDate;
+use getset::Getters;
+use uuid::Uuid;
+
+use crate::domain::entities::{Product, StashItem};
+
+use super::{Brand, ExpiryStatus, ProductId, Quantity, Unit};
+
+/// A summary of the stash items expiring within a notification window
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct ExpiryDigest {
+    /// Number of days ahead the stash items in the digest expire within
+    #[getset(get = "pub")]
+    window_days: u32,
+
+    /// The stash items in the digest, soonest expiring first
+    #[getset(get = "pub")]
+    entries: Vec<ExpiryDigestEntry>,
+}
+
+impl ExpiryDigest {
+    pub fn new(window_days: u32, mut entries: Vec<ExpiryDigestEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.expiry_date);
+
+        Self {
+            window_days,
+            entries,
+        }
+    }
+
+    /// Gets a short, human readable summary of the digest, suitable as a subject or title
+    pub fn title(&self) -> String {
+        let items = match self.entries.len() {
+            1 => "1 stash item".to_string(),
+            count => format!("{} stash items", count),
+        };
+        let days = match self.window_days {
+            1 => "1 day".to_string(),
+            days => format!("{} days", days),
+        };
+
+        format!("{} expiring within {}", items, days)
+    }
+}
+
+/// Lists the stash items in the digest, one per line
+impl std::fmt::Display for ExpiryDigest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A stash item in an [`ExpiryDigest`], along with what is needed to tell what it is
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct ExpiryDigestEntry {
+    /// ID of the product of the stash item
+    product_id: ProductId,
+    /// Brand of the product
+    brand: Brand,
+    /// Name of the product
+    name: String,
+    /// ID of the stash item
+    stash_item_id: Uuid,
+    /// Quantity of the stash item
+    quantity: Quantity,
+    /// Unit of the quantity
+    unit: Unit,
+    /// Effective expiry date of the stash item
+    expiry_date: NaiveDate,
+    /// Expiry status of the stash item on the day the digest was made
+    status: ExpiryStatus,
+}
+
+impl ExpiryDigestEntry {
+    /// Creates an entry for a stash item of a product
+    ///
+    /// # Arguments
+    /// * `product` - The product the stash item belongs to
+    /// * `stash_item` - The stash item
+    /// * `today` - The day the digest is made
+    ///
+    /// # Returns
+    /// * The entry, or `None` if the stash item does not expire
+    pub fn new(product: &Product, stash_item: &StashItem, today: NaiveDate) -> Option<Self> {
+        Some(Self {
+            product_id: product.id().clone(),
+            brand: product.brand().clone(),
+            name: product.name().clone(),
+            stash_item_id: *stash_item.id(),
+            quantity: *stash_item.quantity(),
+            unit: *product.unit(),
+            expiry_date: product.effective_expiry_date(stash_item)?,
+            status: product.expiry_status(stash_item, today),
+        })
+    }
+}
+
+impl std::fmt::Display for ExpiryDigestEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}), {} {}: {}",
+            self.name, self.brand, self.quantity, self.unit, self.expiry_date
+        )?;
+
+        match self.status {
+            ExpiryStatus::Fresh => Ok(()),
+            ExpiryStatus::PastBestBefore => write!(f, " (past best-before)"),
+            ExpiryStatus::Unsafe => write!(f, " (unsafe)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem},
+        value_objects::ExpiryDateKind,
+    };
+
+    use super::*;
+
+    fn entry(name: &str, expiry_date: NaiveDate, kind: ExpiryDateKind) -> ExpiryDigestEntry {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .with_expiry_date(expiry_date)
+            .with_expiry_date_kind(kind)
+            .build();
+        let product = FakeProduct::new()
+            .with_brand("Tine".parse().unwrap())
+            .with_name(name.to_string())
+            .with_unit(Unit::Litres)
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        ExpiryDigestEntry::new(
+            &product,
+            &stash_item,
+            NaiveDate::from_ymd_opt(2023, 11, 10).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_entry_without_expiry_date() {
+        let stash_item = FakeStashItem::new().without_expiry_date().build();
+        let product = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let entry = ExpiryDigestEntry::new(
+            &product,
+            &stash_item,
+            NaiveDate::from_ymd_opt(2023, 11, 10).unwrap(),
+        );
+
+        assert_eq!(entry, None);
+    }
+
+    #[test]
+    fn test_digest_sorted_by_expiry_date() {
+        let later = entry(
+            "Milk",
+            NaiveDate::from_ymd_opt(2023, 11, 12).unwrap(),
+            ExpiryDateKind::BestBefore,
+        );
+        let sooner = entry(
+            "Cream",
+            NaiveDate::from_ymd_opt(2023, 11, 11).unwrap(),
+            ExpiryDateKind::BestBefore,
+        );
+
+        let digest = ExpiryDigest::new(3, vec![later.clone(), sooner.clone()]);
+
+        assert_eq!(digest.entries(), &vec![sooner, later]);
+    }
+
+    #[test]
+    fn test_title() {
+        let milk = entry(
+            "Milk",
+            NaiveDate::from_ymd_opt(2023, 11, 11).unwrap(),
+            ExpiryDateKind::BestBefore,
+        );
+
+        assert_eq!(
+            ExpiryDigest::new(1, vec![milk.clone()]).title(),
+            "1 stash item expiring within 1 day"
+        );
+        assert_eq!(
+            ExpiryDigest::new(3, vec![milk.clone(), milk]).title(),
+            "2 stash items expiring within 3 days"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let digest = ExpiryDigest::new(
+            1,
+            vec![
+                entry(
+                    "Milk",
+                    NaiveDate::from_ymd_opt(2023, 11, 11).unwrap(),
+                    ExpiryDateKind::BestBefore,
+                ),
+                entry(
+                    "Cream",
+                    NaiveDate::from_ymd_opt(2023, 11, 9).unwrap(),
+                    ExpiryDateKind::BestBefore,
+                ),
+                entry(
+                    "Chicken",
+                    NaiveDate::from_ymd_opt(2023, 11, 10).unwrap(),
+                    ExpiryDateKind::UseBy,
+                ),
+            ],
+        );
+
+        assert_eq!(
+            digest.to_string(),
+            "Cream (Tine), 2 l: 2023-11-09 (past best-before)\n\
+            Chicken (Tine), 2 l: 2023-11-10 (unsafe)\n\
+            Milk (Tine), 2 l: 2023-11-11\n"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+/// How a stash item is doing with regard to its expiry date on a given day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExpiryStatus {
+    /// The item has not reached its expiry date
+    Fresh,
+    /// The item is past its best-before date, but still within the grace period of the product
+    PastBestBefore,
+    /// The item is past its use-by date, or past the grace period after its best-before date
+    Unsafe,
+}
+
+impl std::fmt::Display for ExpiryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpiryStatus::Fresh => write!(f, "fresh"),
+            ExpiryStatus::PastBestBefore => write!(f, "past_best_before"),
+            ExpiryStatus::Unsafe => write!(f, "unsafe"),
+        }
+    }
+}
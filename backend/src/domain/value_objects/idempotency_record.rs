@@ -0,0 +1,78 @@
+use chrono::NaiveDateTime;
+use getset::Getters;
+use uuid::Uuid;
+
+/// A request made with an idempotency key, along with the response to it once it has been handled
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct IdempotencyRecord {
+    /// Key the client sent along with the request
+    #[getset(get = "pub")]
+    key: String,
+
+    /// Fingerprint of the request, telling retries of it apart from other requests reusing the key
+    #[getset(get = "pub")]
+    fingerprint: String,
+
+    /// Response to the request. Missing while the request is being handled
+    #[getset(get = "pub")]
+    response: Option<IdempotentResponse>,
+
+    /// When the key was first used
+    #[getset(get = "pub")]
+    created_at: NaiveDateTime,
+}
+
+impl IdempotencyRecord {
+    pub fn new(
+        key: String,
+        fingerprint: String,
+        response: Option<IdempotentResponse>,
+        created_at: NaiveDateTime,
+    ) -> Self {
+        Self {
+            key,
+            fingerprint,
+            response,
+            created_at,
+        }
+    }
+}
+
+/// A response to a request made with an idempotency key, which is replayed when the request is retried
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct IdempotentResponse {
+    /// HTTP status code
+    #[getset(get = "pub")]
+    status: u16,
+
+    /// Names and values of the headers
+    #[getset(get = "pub")]
+    headers: Vec<(String, String)>,
+
+    #[getset(get = "pub")]
+    body: Vec<u8>,
+}
+
+impl IdempotentResponse {
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers,
+            body,
+        }
+    }
+}
+
+/// What to do with a request made with an idempotency key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// The key is new, so the request is to be handled, and its response recorded with the token the key was claimed
+    /// with
+    Claimed(Uuid),
+    /// The request was already handled, so its response is to be replayed
+    Replay(IdempotentResponse),
+    /// The request is still being handled
+    InProgress,
+    /// The key was already used for another request
+    Mismatch,
+}
@@ -1,7 +1,23 @@
+mod batch_operation;
 mod brand;
+mod expiry_date_kind;
+mod expiry_digest;
+mod expiry_status;
+mod idempotency_record;
+mod operation;
 mod product_id;
 mod quantity;
+mod trash_entry;
+mod unit;
 
+pub use batch_operation::BatchOperation;
 pub use brand::Brand;
+pub use expiry_date_kind::ExpiryDateKind;
+pub use expiry_digest::{ExpiryDigest, ExpiryDigestEntry};
+pub use expiry_status::ExpiryStatus;
+pub use idempotency_record::{IdempotencyClaim, IdempotencyRecord, IdempotentResponse};
+pub use operation::{InverseOperation, Operation};
 pub use product_id::ProductId;
 pub use quantity::Quantity;
+pub use trash_entry::TrashEntry;
+pub use unit::Unit;
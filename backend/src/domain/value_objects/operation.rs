@@ -0,0 +1,70 @@
+use getset::Getters;
+use uuid::Uuid;
+
+use crate::domain::entities::{Product, StashItem};
+
+use super::ProductId;
+
+/// A change made to the stash, recorded along with how to undo it
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct Operation {
+    /// ID the operation is undone by
+    #[getset(get = "pub")]
+    id: Uuid,
+
+    /// What to do to undo the operation
+    #[getset(get = "pub")]
+    inverse: InverseOperation,
+}
+
+impl Operation {
+    /// Records an operation with a new ID
+    ///
+    /// # Parameters
+    /// - `inverse`: What to do to undo the operation
+    pub fn new(inverse: InverseOperation) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            inverse,
+        }
+    }
+
+    /// Recreates an operation which was recorded before
+    ///
+    /// # Parameters
+    /// - `id`: ID the operation is undone by
+    /// - `inverse`: What to do to undo the operation
+    pub fn with_id(id: Uuid, inverse: InverseOperation) -> Self {
+        Self { id, inverse }
+    }
+}
+
+/// What to do to undo an operation. Each of them only applies as long as what the operation changed has not been
+/// changed again since
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InverseOperation {
+    /// Removes a stash item which was added
+    RemoveStashItem {
+        product_id: ProductId,
+        stash_item: StashItem,
+    },
+    /// Puts back a stash item as it was before it was updated
+    RevertStashItem {
+        product_id: ProductId,
+        before: StashItem,
+        after: StashItem,
+    },
+    /// Restores a deleted stash item from the trash
+    RestoreStashItem { stash_item_id: Uuid },
+    /// Puts back a product as it was before it was updated
+    RevertProduct {
+        before: Box<Product>,
+        after: Box<Product>,
+    },
+    /// Restores a deleted product from the trash
+    RestoreProduct { product_id: ProductId },
+    /// Deletes a product which was created, moving it to the trash
+    DeleteProduct { product: Box<Product> },
+    /// Undoes the operations of a batch, the last one first
+    Batch { operations: Vec<InverseOperation> },
+}
@@ -15,7 +15,7 @@ impl ProductId {
     /// # Errors
     /// - `ProductIdError::EmptyStringError` - The value is empty
     pub fn new(value: String) -> Result<Self, ProductIdError> {
-        if value.len() == 0 {
+        if value.is_empty() {
             Err(ProductIdError::EmptyStringError)
         } else {
             Ok(ProductId(value))
@@ -23,7 +23,7 @@ impl ProductId {
     }
 
     /// Create a random product ID, for testing purposes
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn random() -> Self {
         use rand::distributions::Alphanumeric;
         use rand::Rng;
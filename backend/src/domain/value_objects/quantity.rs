@@ -1,9 +1,13 @@
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
 use crate::domain::errors::QuantityError;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Quantity(u64);
+pub struct Quantity(Decimal);
 
-/// A quantity of a product
+/// A quantity of a product, in the unit of the product. Quantities are decimal, so they can be fractions of a unit
 impl Quantity {
     /// Create a new quantity from a positive integer
     ///
@@ -17,21 +21,51 @@ impl Quantity {
     }
 
     /// Create a random quantity, for testing purposes
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn random() -> Self {
         use rand::distributions::Uniform;
         use rand::Rng;
 
         let mut rng = rand::thread_rng();
         let range = Uniform::new_inclusive(1, 100);
-        let value = rng.sample(range);
-        Self(value)
+        let value: u64 = rng.sample(range);
+        Self(value.into())
     }
 
     /// Get the value of the quantity
-    pub fn value(&self) -> u64 {
+    pub fn value(&self) -> Decimal {
         self.0
     }
+
+    /// Adds two quantities together
+    ///
+    /// # Parameters
+    /// - `other` - The quantity to add to this one
+    ///
+    /// # Errors
+    /// - `QuantityError::OverflowError` - The sum is too large to be represented
+    pub fn checked_add(&self, other: Quantity) -> Result<Self, QuantityError> {
+        self.0
+            .checked_add(other.0)
+            .ok_or(QuantityError::OverflowError)
+            .and_then(Quantity::try_from)
+    }
+
+    /// Subtracts a quantity from this one
+    ///
+    /// # Parameters
+    /// - `other` - The quantity to subtract from this one
+    ///
+    /// # Errors
+    /// - `QuantityError::ZeroError` - The quantities are equal, so the difference is zero
+    /// - `QuantityError::UnderflowError` - The other quantity is larger than this one
+    pub fn checked_sub(&self, other: Quantity) -> Result<Self, QuantityError> {
+        if other.0 > self.0 {
+            return Err(QuantityError::UnderflowError);
+        }
+
+        Quantity::try_from(self.0 - other.0)
+    }
 }
 
 impl std::fmt::Display for Quantity {
@@ -41,7 +75,7 @@ impl std::fmt::Display for Quantity {
 }
 
 impl std::ops::Deref for Quantity {
-    type Target = u64;
+    type Target = Decimal;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -52,23 +86,44 @@ impl TryFrom<u64> for Quantity {
     type Error = QuantityError;
 
     fn try_from(value: u64) -> Result<Self, Self::Error> {
-        if value == 0 {
+        Quantity::try_from(Decimal::from(value))
+    }
+}
+
+impl TryFrom<Decimal> for Quantity {
+    type Error = QuantityError;
+
+    fn try_from(value: Decimal) -> Result<Self, Self::Error> {
+        if value.is_zero() {
             Err(QuantityError::ZeroError)
+        } else if value.is_sign_negative() {
+            Err(QuantityError::NegativeError)
         } else {
-            Ok(Self(value))
+            // Normalize so that equal quantities look the same, e.g. 0.5 and not 0.50
+            Ok(Self(value.normalize()))
         }
     }
 }
 
+impl FromStr for Quantity {
+    type Err = QuantityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Decimal::from_str(s)
+            .map_err(|_| QuantityError::InvalidError)
+            .and_then(Quantity::try_from)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_new_quantity() {
-        assert_eq!(Quantity::new(1), Ok(Quantity(1)));
-        assert_eq!(Quantity::new(2), Ok(Quantity(2)));
-        assert_eq!(Quantity::new(3), Ok(Quantity(3)));
+        assert_eq!(Quantity::new(1), Ok(Quantity(1.into())));
+        assert_eq!(Quantity::new(2), Ok(Quantity(2.into())));
+        assert_eq!(Quantity::new(3), Ok(Quantity(3.into())));
     }
 
     #[test]
@@ -80,13 +135,86 @@ mod tests {
     fn test_value() {
         let quantity = Quantity::new(1).unwrap();
 
-        assert_eq!(quantity.value(), 1);
+        assert_eq!(quantity.value(), Decimal::ONE);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let quantity: Quantity = "0.5".parse().unwrap();
+
+        assert_eq!(quantity.value(), Decimal::new(5, 1));
+        assert_eq!(quantity, "0.50".parse().unwrap());
+        assert_eq!(quantity.to_string(), "0.5");
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        assert_eq!("half".parse::<Quantity>(), Err(QuantityError::InvalidError));
+        assert_eq!("-1".parse::<Quantity>(), Err(QuantityError::NegativeError));
+        assert_eq!("0.0".parse::<Quantity>(), Err(QuantityError::ZeroError));
+    }
+
+    #[test]
+    fn test_checked_add() {
+        let quantity = Quantity::new(2).unwrap();
+
+        assert_eq!(
+            quantity.checked_add(Quantity(3.into())),
+            Ok(Quantity(5.into()))
+        );
+    }
+
+    #[test]
+    fn test_checked_add_fractions() {
+        let quantity: Quantity = "0.1".parse().unwrap();
+
+        assert_eq!(quantity.checked_add("0.2".parse().unwrap()), "0.3".parse());
+    }
+
+    #[test]
+    fn test_checked_add_overflow() {
+        let quantity = Quantity(Decimal::MAX);
+
+        assert_eq!(
+            quantity.checked_add(Quantity(1.into())),
+            Err(QuantityError::OverflowError)
+        );
+    }
+
+    #[test]
+    fn test_checked_sub() {
+        let quantity = Quantity::new(5).unwrap();
+
+        assert_eq!(
+            quantity.checked_sub(Quantity(3.into())),
+            Ok(Quantity(2.into()))
+        );
+    }
+
+    #[test]
+    fn test_checked_sub_zero() {
+        let quantity = Quantity::new(3).unwrap();
+
+        assert_eq!(
+            quantity.checked_sub(Quantity(3.into())),
+            Err(QuantityError::ZeroError)
+        );
+    }
+
+    #[test]
+    fn test_checked_sub_underflow() {
+        let quantity = Quantity::new(2).unwrap();
+
+        assert_eq!(
+            quantity.checked_sub(Quantity(3.into())),
+            Err(QuantityError::UnderflowError)
+        );
     }
 
     #[test]
     fn test_deref() {
         let quantity = Quantity::new(1).unwrap();
 
-        assert_eq!(*quantity, 1);
+        assert_eq!(*quantity, Decimal::ONE);
     }
 }
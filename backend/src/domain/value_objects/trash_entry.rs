@@ -0,0 +1,40 @@
+use chrono::NaiveDateTime;
+
+use crate::domain::entities::{Product, StashItem};
+
+use super::ProductId;
+
+/// Something that was deleted, and can be restored until it is purged from the trash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrashEntry {
+    /// A deleted product, along with the stash items it had when it was deleted
+    Product {
+        product: Product,
+        deleted_at: NaiveDateTime,
+    },
+    /// A deleted stash item of a product which is still around
+    StashItem {
+        product_id: ProductId,
+        stash_item: StashItem,
+        deleted_at: NaiveDateTime,
+    },
+}
+
+impl TrashEntry {
+    /// Gets the ID the entry is restored by, which is the ID of the deleted product or stash item
+    pub fn id(&self) -> String {
+        match self {
+            TrashEntry::Product { product, .. } => product.id().to_string(),
+            TrashEntry::StashItem { stash_item, .. } => stash_item.id().to_string(),
+        }
+    }
+
+    /// Gets when the product or stash item was deleted
+    pub fn deleted_at(&self) -> &NaiveDateTime {
+        match self {
+            TrashEntry::Product { deleted_at, .. } | TrashEntry::StashItem { deleted_at, .. } => {
+                deleted_at
+            }
+        }
+    }
+}
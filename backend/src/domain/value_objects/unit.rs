@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use crate::domain::errors::UnitError;
+
+use super::Quantity;
+
+/// Unit a product is measured in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// Whole items, like cans or jars
+    #[default]
+    Pieces,
+    Grams,
+    Kilograms,
+    Millilitres,
+    Litres,
+}
+
+/// What a unit measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Count,
+    Mass,
+    Volume,
+}
+
+impl Unit {
+    /// What the unit measures. Only units measuring the same thing can be converted between
+    fn dimension(&self) -> Dimension {
+        match self {
+            Unit::Pieces => Dimension::Count,
+            Unit::Grams | Unit::Kilograms => Dimension::Mass,
+            Unit::Millilitres | Unit::Litres => Dimension::Volume,
+        }
+    }
+
+    /// How many of the smallest unit measuring the same thing one of this unit is
+    fn factor(&self) -> Decimal {
+        match self {
+            Unit::Pieces | Unit::Grams | Unit::Millilitres => Decimal::ONE,
+            Unit::Kilograms | Unit::Litres => Decimal::ONE_THOUSAND,
+        }
+    }
+
+    /// Checks if quantities in this unit can be converted to the other unit
+    ///
+    /// # Parameters
+    /// * `other` - The unit to check against
+    ///
+    /// # Returns
+    /// `true` if the units measure the same thing, `false` otherwise
+    pub fn is_compatible_with(&self, other: Unit) -> bool {
+        self.dimension() == other.dimension()
+    }
+
+    /// Converts a quantity in this unit to another unit
+    ///
+    /// # Parameters
+    /// * `quantity` - The quantity to convert, in this unit
+    /// * `to` - The unit to convert to
+    ///
+    /// # Returns
+    /// `Ok(Quantity)` with the quantity in the other unit
+    /// `Err(UnitError::IncompatibleUnitsError)` if the units measure different things
+    /// `Err(UnitError::ConversionError)` if the quantity can not be represented in the other unit
+    pub fn convert(&self, quantity: Quantity, to: Unit) -> Result<Quantity, UnitError> {
+        if !self.is_compatible_with(to) {
+            return Err(UnitError::IncompatibleUnitsError);
+        }
+
+        quantity
+            .value()
+            .checked_mul(self.factor())
+            .and_then(|value| value.checked_div(to.factor()))
+            .ok_or(UnitError::ConversionError)
+            .and_then(|value| Quantity::try_from(value).map_err(|_| UnitError::ConversionError))
+    }
+}
+
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Pieces => write!(f, "pieces"),
+            Unit::Grams => write!(f, "g"),
+            Unit::Kilograms => write!(f, "kg"),
+            Unit::Millilitres => write!(f, "ml"),
+            Unit::Litres => write!(f, "l"),
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = UnitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pieces" => Ok(Unit::Pieces),
+            "g" => Ok(Unit::Grams),
+            "kg" => Ok(Unit::Kilograms),
+            "ml" => Ok(Unit::Millilitres),
+            "l" => Ok(Unit::Litres),
+            _ => Err(UnitError::UnknownUnitError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("pieces".parse(), Ok(Unit::Pieces));
+        assert_eq!("g".parse(), Ok(Unit::Grams));
+        assert_eq!("kg".parse(), Ok(Unit::Kilograms));
+        assert_eq!("ml".parse(), Ok(Unit::Millilitres));
+        assert_eq!("l".parse(), Ok(Unit::Litres));
+    }
+
+    #[test]
+    fn test_from_str_unknown() {
+        assert_eq!("lbs".parse::<Unit>(), Err(UnitError::UnknownUnitError));
+    }
+
+    #[test]
+    fn test_to_string() {
+        for unit in [
+            Unit::Pieces,
+            Unit::Grams,
+            Unit::Kilograms,
+            Unit::Millilitres,
+            Unit::Litres,
+        ] {
+            assert_eq!(unit.to_string().parse(), Ok(unit));
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let quantity: Quantity = "0.75".parse().unwrap();
+
+        assert_eq!(
+            Unit::Litres.convert(quantity, Unit::Millilitres),
+            Ok(Quantity::new(750).unwrap())
+        );
+        assert_eq!(
+            Unit::Grams.convert(Quantity::new(250).unwrap(), Unit::Kilograms),
+            Ok("0.25".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_convert_incompatible() {
+        assert_eq!(
+            Unit::Grams.convert(Quantity::new(1).unwrap(), Unit::Litres),
+            Err(UnitError::IncompatibleUnitsError)
+        );
+        assert_eq!(
+            Unit::Pieces.convert(Quantity::new(1).unwrap(), Unit::Grams),
+            Err(UnitError::IncompatibleUnitsError)
+        );
+    }
+}
@@ -1 +1,2 @@
+pub mod notifiers;
 pub mod persistence;
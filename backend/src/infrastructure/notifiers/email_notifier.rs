@@ -0,0 +1,289 @@
+use std::{str::FromStr, time::Duration};
+
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+
+use crate::domain::{errors::NotificationError, notifiers::Notifier, value_objects::ExpiryDigest};
+
+/// How to secure the connection to the SMTP server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain text. Only meant for local mail servers
+    None,
+    /// Upgrade the connection with STARTTLS
+    #[default]
+    StartTls,
+    /// TLS from the start
+    Tls,
+}
+
+impl FromStr for SmtpSecurity {
+    type Err = NotificationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(SmtpSecurity::None),
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            _ => Err(NotificationError::ConfigurationError(format!(
+                "Unknown SMTP security '{}', expected 'none', 'starttls' or 'tls'",
+                s
+            ))),
+        }
+    }
+}
+
+/// A notifier sending digests by email
+pub struct EmailNotifier {
+    /// Connection to the SMTP server
+    transport: SmtpTransport,
+    /// Sender of the emails
+    from: Mailbox,
+    /// Recipients of the emails
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    /// Creates a new [`EmailNotifier`]
+    ///
+    /// # Parameters
+    /// - `host`: Host of the SMTP server
+    /// - `port`: Port of the SMTP server. Defaults to the usual port for the security
+    /// - `security`: How to secure the connection to the SMTP server
+    /// - `credentials`: Username and password for the SMTP server, if it needs them
+    /// - `from`: Sender of the emails
+    /// - `to`: Recipients of the emails
+    ///
+    /// # Errors
+    /// Returns an error if an address is invalid, or if there are no recipients
+    pub fn new(
+        host: &str,
+        port: Option<u16>,
+        security: SmtpSecurity,
+        credentials: Option<(String, String)>,
+        from: &str,
+        to: &[String],
+    ) -> Result<Self, NotificationError> {
+        let mut builder = match security {
+            SmtpSecurity::None => SmtpTransport::builder_dangerous(host),
+            SmtpSecurity::StartTls => SmtpTransport::starttls_relay(host).map_err(invalid)?,
+            SmtpSecurity::Tls => SmtpTransport::relay(host).map_err(invalid)?,
+        }
+        .timeout(Some(Duration::from_secs(30)));
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let to = to
+            .iter()
+            .map(|address| address.parse())
+            .collect::<Result<Vec<Mailbox>, _>>()
+            .map_err(invalid)?;
+        if to.is_empty() {
+            return Err(NotificationError::ConfigurationError(
+                "No recipients".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: from.parse().map_err(invalid)?,
+            to,
+        })
+    }
+
+    /// Builds the email for a digest
+    fn message(&self, digest: &ExpiryDigest) -> Result<Message, NotificationError> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(digest.title())
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+
+        builder
+            .body(digest.to_string())
+            .map_err(|error| NotificationError::DeliveryError(error.to_string()))
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn notify(&self, digest: &ExpiryDigest) -> Result<(), NotificationError> {
+        self.transport
+            .send(&self.message(digest)?)
+            .map_err(|error| NotificationError::DeliveryError(error.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// Shortcut for turning errors in the settings into [`NotificationError::ConfigurationError`]s
+fn invalid(error: impl std::fmt::Display) -> NotificationError {
+    NotificationError::ConfigurationError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use chrono::NaiveDate;
+
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem},
+        value_objects::ExpiryDigestEntry,
+    };
+
+    use super::*;
+
+    fn digest() -> ExpiryDigest {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 11).unwrap())
+            .build();
+        let product = FakeProduct::new()
+            .with_brand("Tine".parse().unwrap())
+            .with_name("Milk".to_string())
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let entry = ExpiryDigestEntry::new(
+            &product,
+            &stash_item,
+            NaiveDate::from_ymd_opt(2023, 11, 10).unwrap(),
+        )
+        .unwrap();
+
+        ExpiryDigest::new(1, vec![entry])
+    }
+
+    /// Runs a bare-bones SMTP server accepting a single email, and returns its port along with a handle giving the
+    /// received message
+    fn smtp_server() -> (u16, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut message = String::new();
+
+            writer.write_all(b"220 localhost\r\n").unwrap();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+
+                match line.get(..4).unwrap_or_default().to_uppercase().as_str() {
+                    "DATA" => {
+                        writer.write_all(b"354 Go ahead\r\n").unwrap();
+                        loop {
+                            let mut line = String::new();
+                            reader.read_line(&mut line).unwrap();
+                            if line == ".\r\n" {
+                                break;
+                            }
+                            message.push_str(&line);
+                        }
+                        writer.write_all(b"250 Queued\r\n").unwrap();
+                    }
+                    "QUIT" => {
+                        writer.write_all(b"221 Bye\r\n").unwrap();
+                        break;
+                    }
+                    _ => writer.write_all(b"250 OK\r\n").unwrap(),
+                }
+            }
+
+            message
+        });
+
+        (port, handle)
+    }
+
+    #[test]
+    fn test_smtp_security_from_str() {
+        assert_eq!("none".parse(), Ok(SmtpSecurity::None));
+        assert_eq!("starttls".parse(), Ok(SmtpSecurity::StartTls));
+        assert_eq!("tls".parse(), Ok(SmtpSecurity::Tls));
+        assert_eq!(
+            "ssl".parse::<SmtpSecurity>(),
+            Err(NotificationError::ConfigurationError(
+                "Unknown SMTP security 'ssl', expected 'none', 'starttls' or 'tls'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_new_invalid_address() {
+        let result = EmailNotifier::new(
+            "localhost",
+            None,
+            SmtpSecurity::None,
+            None,
+            "not an address",
+            &["me@example.com".to_string()],
+        );
+
+        assert!(matches!(
+            result,
+            Err(NotificationError::ConfigurationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_new_no_recipients() {
+        let result = EmailNotifier::new(
+            "localhost",
+            None,
+            SmtpSecurity::None,
+            None,
+            "stash@example.com",
+            &[],
+        );
+
+        assert!(matches!(
+            result,
+            Err(NotificationError::ConfigurationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_notify() {
+        let (port, server) = smtp_server();
+
+        let notifier = EmailNotifier::new(
+            "127.0.0.1",
+            Some(port),
+            SmtpSecurity::None,
+            None,
+            "stash@example.com",
+            &["me@example.com".to_string()],
+        )
+        .unwrap();
+
+        notifier.notify(&digest()).unwrap();
+        drop(notifier);
+
+        let message = server.join().unwrap();
+        assert!(message.contains("Subject: 1 stash item expiring within 1 day"));
+        assert!(message.contains("To: me@example.com"));
+        assert!(message.contains("Milk (Tine), 1 pieces: 2023-11-11"));
+    }
+}
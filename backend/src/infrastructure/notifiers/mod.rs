@@ -0,0 +1,16 @@
+mod email_notifier;
+mod push_notifier;
+mod webhook_notifier;
+
+pub use email_notifier::{EmailNotifier, SmtpSecurity};
+pub use push_notifier::{PushNotifier, PushService};
+pub use webhook_notifier::WebhookNotifier;
+
+use crate::domain::errors::NotificationError;
+
+/// Converts a failed HTTP request into a [`NotificationError`]
+impl From<ureq::Error> for NotificationError {
+    fn from(error: ureq::Error) -> Self {
+        Self::DeliveryError(error.to_string())
+    }
+}
@@ -0,0 +1,214 @@
+use std::{str::FromStr, time::Duration};
+
+use serde_json::json;
+
+use crate::domain::{
+    errors::NotificationError,
+    notifiers::Notifier,
+    value_objects::{ExpiryDigest, ExpiryStatus},
+};
+
+/// The push notification services a [`PushNotifier`] can talk to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PushService {
+    /// ntfy. The URL is that of the topic, like `https://ntfy.sh/my-stash`
+    #[default]
+    Ntfy,
+    /// Gotify. The URL is that of the message endpoint, like `https://gotify.example.com/message`
+    Gotify,
+}
+
+impl FromStr for PushService {
+    type Err = NotificationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ntfy" => Ok(PushService::Ntfy),
+            "gotify" => Ok(PushService::Gotify),
+            _ => Err(NotificationError::ConfigurationError(format!(
+                "Unknown push service '{}', expected 'ntfy' or 'gotify'",
+                s
+            ))),
+        }
+    }
+}
+
+/// A notifier sending digests as push notifications
+pub struct PushNotifier {
+    /// Service the URL belongs to
+    service: PushService,
+    /// URL to post the notifications to
+    url: String,
+    /// Access token for the service, if it needs one
+    token: Option<String>,
+    /// HTTP client
+    agent: ureq::Agent,
+}
+
+impl PushNotifier {
+    /// Creates a new [`PushNotifier`]
+    ///
+    /// # Parameters
+    /// - `service`: Service the URL belongs to
+    /// - `url`: URL to post the notifications to
+    /// - `token`: Access token for the service, if it needs one
+    pub fn new(service: PushService, url: String, token: Option<String>) -> Self {
+        Self {
+            service,
+            url,
+            token,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+}
+
+impl Notifier for PushNotifier {
+    fn name(&self) -> &'static str {
+        "push"
+    }
+
+    fn notify(&self, digest: &ExpiryDigest) -> Result<(), NotificationError> {
+        // Unsafe stash items are worth more attention than the rest
+        let urgent = digest
+            .entries()
+            .iter()
+            .any(|entry| *entry.status() == ExpiryStatus::Unsafe);
+
+        match self.service {
+            PushService::Ntfy => {
+                let mut request = self
+                    .agent
+                    .post(&self.url)
+                    .set("Title", &digest.title())
+                    .set("Priority", if urgent { "high" } else { "default" });
+                if let Some(token) = &self.token {
+                    request = request.set("Authorization", &format!("Bearer {}", token));
+                }
+
+                request.send_string(&digest.to_string())?;
+            }
+            PushService::Gotify => {
+                let mut request = self.agent.post(&self.url);
+                if let Some(token) = &self.token {
+                    request = request.set("X-Gotify-Key", token);
+                }
+
+                request.send_json(json!({
+                    "title": digest.title(),
+                    "message": digest.to_string(),
+                    "priority": if urgent { 8 } else { 5 },
+                }))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use mockito::Matcher;
+
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem},
+        value_objects::{ExpiryDateKind, ExpiryDigestEntry},
+    };
+
+    use super::*;
+
+    fn digest(kind: ExpiryDateKind) -> ExpiryDigest {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(1.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 10).unwrap())
+            .with_expiry_date_kind(kind)
+            .build();
+        let product = FakeProduct::new()
+            .with_brand("Tine".parse().unwrap())
+            .with_name("Milk".to_string())
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let entry = ExpiryDigestEntry::new(
+            &product,
+            &stash_item,
+            NaiveDate::from_ymd_opt(2023, 11, 10).unwrap(),
+        )
+        .unwrap();
+
+        ExpiryDigest::new(1, vec![entry])
+    }
+
+    #[test]
+    fn test_push_service_from_str() {
+        assert_eq!("ntfy".parse(), Ok(PushService::Ntfy));
+        assert_eq!("gotify".parse(), Ok(PushService::Gotify));
+        assert_eq!(
+            "pigeon".parse::<PushService>(),
+            Err(NotificationError::ConfigurationError(
+                "Unknown push service 'pigeon', expected 'ntfy' or 'gotify'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_notify_ntfy() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/stash")
+            .match_header("title", "1 stash item expiring within 1 day")
+            .match_header("priority", "high")
+            .match_header("authorization", "Bearer secret")
+            .match_body("Milk (Tine), 1 pieces: 2023-11-10 (unsafe)\n")
+            .create();
+
+        let notifier = PushNotifier::new(
+            PushService::Ntfy,
+            format!("{}/stash", server.url()),
+            Some("secret".to_string()),
+        );
+
+        notifier.notify(&digest(ExpiryDateKind::UseBy)).unwrap();
+
+        mock.assert();
+    }
+
+    #[test]
+    fn test_notify_gotify() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/message")
+            .match_header("x-gotify-key", "secret")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"title":"1 stash item expiring within 1 day","priority":5}"#.to_string(),
+            ))
+            .create();
+
+        let notifier = PushNotifier::new(
+            PushService::Gotify,
+            format!("{}/message", server.url()),
+            Some("secret".to_string()),
+        );
+
+        notifier
+            .notify(&digest(ExpiryDateKind::BestBefore))
+            .unwrap();
+
+        mock.assert();
+    }
+
+    #[test]
+    fn test_notify_unreachable() {
+        let notifier = PushNotifier::new(
+            PushService::Ntfy,
+            "http://127.0.0.1:1/stash".to_string(),
+            None,
+        );
+
+        let result = notifier.notify(&digest(ExpiryDateKind::BestBefore));
+
+        assert!(matches!(result, Err(NotificationError::DeliveryError(_))));
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::domain::{
+    errors::NotificationError,
+    notifiers::Notifier,
+    value_objects::{ExpiryDigest, ExpiryDigestEntry},
+};
+
+/// A notifier posting digests as JSON to an HTTP endpoint
+pub struct WebhookNotifier {
+    /// URL to post the digests to
+    url: String,
+    /// HTTP client
+    agent: ureq::Agent,
+}
+
+impl WebhookNotifier {
+    /// Creates a new [`WebhookNotifier`]
+    ///
+    /// # Parameters
+    /// - `url`: URL to post the digests to
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn notify(&self, digest: &ExpiryDigest) -> Result<(), NotificationError> {
+        self.agent
+            .post(&self.url)
+            .send_json(WebhookPayload::from(digest))?;
+
+        Ok(())
+    }
+}
+
+/// Body of the requests to the webhook
+#[derive(Debug, Serialize)]
+struct WebhookPayload {
+    title: String,
+    window_days: u32,
+    stash_items: Vec<WebhookStashItem>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookStashItem {
+    product_id: String,
+    brand: String,
+    name: String,
+    stash_item_id: String,
+    quantity: String,
+    unit: String,
+    expiry_date: String,
+    status: String,
+}
+
+impl From<&ExpiryDigest> for WebhookPayload {
+    fn from(digest: &ExpiryDigest) -> Self {
+        Self {
+            title: digest.title(),
+            window_days: *digest.window_days(),
+            stash_items: digest
+                .entries()
+                .iter()
+                .map(WebhookStashItem::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&ExpiryDigestEntry> for WebhookStashItem {
+    fn from(entry: &ExpiryDigestEntry) -> Self {
+        Self {
+            product_id: entry.product_id().to_string(),
+            brand: entry.brand().to_string(),
+            name: entry.name().clone(),
+            stash_item_id: entry.stash_item_id().to_string(),
+            quantity: entry.quantity().to_string(),
+            unit: entry.unit().to_string(),
+            expiry_date: entry.expiry_date().to_string(),
+            status: entry.status().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    use crate::domain::entities::{FakeProduct, FakeStashItem};
+
+    use super::*;
+
+    fn digest() -> ExpiryDigest {
+        let stash_item = FakeStashItem::new()
+            .with_quantity(2.try_into().unwrap())
+            .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 11).unwrap())
+            .build();
+        let product = FakeProduct::new()
+            .with_id("7038010000737".parse().unwrap())
+            .with_brand("Tine".parse().unwrap())
+            .with_name("Milk".to_string())
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+
+        let entry = ExpiryDigestEntry::new(
+            &product,
+            &stash_item,
+            NaiveDate::from_ymd_opt(2023, 11, 10).unwrap(),
+        )
+        .unwrap();
+
+        ExpiryDigest::new(1, vec![entry])
+    }
+
+    #[test]
+    fn test_notify() {
+        let digest = digest();
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/hook")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({
+                "title": "1 stash item expiring within 1 day",
+                "window_days": 1,
+                "stash_items": [{
+                    "product_id": "7038010000737",
+                    "brand": "Tine",
+                    "name": "Milk",
+                    "stash_item_id": digest.entries()[0].stash_item_id().to_string(),
+                    "quantity": "2",
+                    "unit": "pieces",
+                    "expiry_date": "2023-11-11",
+                    "status": "fresh",
+                }],
+            })))
+            .create();
+
+        let notifier = WebhookNotifier::new(format!("{}/hook", server.url()));
+
+        notifier.notify(&digest).unwrap();
+
+        mock.assert();
+    }
+
+    #[test]
+    fn test_notify_error_status() {
+        let mut server = mockito::Server::new();
+        server.mock("POST", "/hook").with_status(500).create();
+
+        let notifier = WebhookNotifier::new(format!("{}/hook", server.url()));
+
+        let result = notifier.notify(&digest());
+
+        assert!(matches!(result, Err(NotificationError::DeliveryError(_))));
+    }
+}
@@ -0,0 +1,1851 @@
+//! The contract every [`ProductRepository`] implementation has to fulfil, as a suite of tests, so all backends behave
+//! the same. A backend is checked against it with one line in its test module:
+//!
+//! ```ignore
+//! product_repository_conformance_tests!(MyProductRepository::new());
+//! ```
+//!
+//! The expression makes an empty repository. It is evaluated in an async context once for every test, so it may
+//! `.await`. Outside this crate, the suite is available with the `testing` feature.
+//!
+//! The suite covers every method of the trait, including the errors a repository itself returns. Other variants of
+//! [`ProductRepositoryError`] come from the [`Product`] aggregate, and [`ProductRepositoryError::PersisteneError`]
+//! depends on the backend, so they are left to the tests of each backend
+//!
+//! A backend's [`UnitOfWork`] is checked the same way, given an expression making a repository and a unit of work on
+//! the same empty storage, with the unit of work dispatching the events of the products it saves to the named
+//! dispatcher:
+//!
+//! ```ignore
+//! unit_of_work_conformance_tests!(event_dispatcher => {
+//!     let storage = MyStorage::new();
+//!     (
+//!         MyProductRepository::new(storage.clone()),
+//!         MyUnitOfWork::new(storage).with_event_dispatcher(event_dispatcher),
+//!     )
+//! });
+//! ```
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::{FakeProduct, FakeStashItem, Product, StashItem},
+    errors::ProductRepositoryError,
+    events::{ProductEvent, ProductEventDispatcher, ProductEventHandler},
+    repositories::{ProductRepository, UnitOfWork, DEFAULT_UNDO_HISTORY_SIZE},
+    value_objects::{ExpiryDateKind, InverseOperation, Operation, ProductId, TrashEntry, Unit},
+};
+
+/// Generates a test for each of the conformance tests, in a `conformance` module
+///
+/// # Parameters
+/// - An expression making an empty repository. It is evaluated in an async test, so it may `.await`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __product_repository_conformance_tests {
+    ($repository:expr) => {
+        $crate::infrastructure::persistence::conformance::product_repository_conformance_tests!(
+            @tests $repository;
+            test_save_and_find_by_id,
+            test_save_fractional_quantity,
+            test_save_without_expiry_date,
+            test_find_by_id_by_barcode,
+            test_find_by_id_not_found,
+            test_find_by_ids,
+            test_find_by_ids_empty,
+            test_find_all_with_stash_items,
+            test_find_by_stash_item_id,
+            test_find_by_stash_item_id_not_found,
+            test_find_expiring_in_interval,
+            test_find_expiring_in_interval_opened,
+            test_find_expiring_in_interval_none,
+            test_find_in_interval_without_expiry_date,
+            test_find_unsafe_in_interval,
+            test_find_unsafe_in_interval_none,
+            test_exists_by_id,
+            test_save_update,
+            test_save_replaces_stash_item_with_same_dates,
+            test_save_all,
+            test_save_all_moved_stash_item,
+            test_save_moves_barcodes,
+            test_save_overrides_barcode,
+            test_merge_into,
+            test_merge_into_twice,
+            test_merge_into_moves_barcodes,
+            test_merge_into_missing_source,
+            test_delete_by_id,
+            test_delete_by_id_by_barcode,
+            test_delete_by_id_not_found,
+            test_delete_by_id_moves_to_trash,
+            test_delete_stash_item_by_id,
+            test_delete_stash_item_by_id_not_found,
+            test_trash_left_out_of_queries,
+            test_find_trash_most_recent_first,
+            test_save_replaces_trashed_product,
+            test_restore_by_id,
+            test_restore_by_id_not_found,
+            test_restore_by_id_taken,
+            test_restore_stash_item_by_id,
+            test_restore_stash_item_by_id_not_found,
+            test_restore_stash_item_by_id_duplicate_dates,
+            test_purge_trash
+        );
+    };
+    (@tests $repository:expr; $($test:ident),*) => {
+        mod conformance {
+            use super::*;
+
+            $(
+                #[test]
+                fn $test() {
+                    $crate::infrastructure::persistence::conformance::run(async {
+                        let repository = $repository;
+
+                        $crate::infrastructure::persistence::conformance::$test(&repository).await;
+                    });
+                }
+            )*
+        }
+    };
+}
+
+pub use crate::__product_repository_conformance_tests as product_repository_conformance_tests;
+
+/// Generates a test for each of the conformance tests of units of work, in a `unit_of_work_conformance` module
+///
+/// # Parameters
+/// - The name of the event dispatcher, followed by `=>` and an expression making a repository and a unit of work on
+///   the same empty storage. The unit of work is to dispatch to the event dispatcher. The expression is evaluated in an
+///   async test, so it may `.await`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __unit_of_work_conformance_tests {
+    ($event_dispatcher:ident => $backend:expr) => {
+        $crate::infrastructure::persistence::conformance::unit_of_work_conformance_tests!(
+            @tests $event_dispatcher => $backend;
+            test_run_commits,
+            test_run_rolls_back_on_error,
+            test_run_dispatches_events_once_committed,
+            test_run_dispatches_no_events_when_rolled_back,
+            test_run_exists_by_id,
+            test_run_find_by_stash_item_id,
+            test_run_delete_by_id,
+            test_run_delete_stash_item_by_id,
+            test_run_merge_into,
+            test_run_merge_into_dispatches_events_of_both_products,
+            test_run_restore_by_id,
+            test_run_restore_stash_item_by_id,
+            test_run_save_operation_and_find_by_id,
+            test_run_find_operation_by_id_not_found,
+            test_run_keeps_every_kind_of_operation,
+            test_run_delete_operation_by_id,
+            test_run_rolls_back_operations,
+            test_run_save_operation_forgets_oldest
+        );
+    };
+    (@tests $event_dispatcher:ident => $backend:expr; $($test:ident),*) => {
+        mod unit_of_work_conformance {
+            use super::*;
+
+            $(
+                #[test]
+                fn $test() {
+                    $crate::infrastructure::persistence::conformance::run(async {
+                        let (events, $event_dispatcher) =
+                            $crate::infrastructure::persistence::conformance::collecting_dispatcher();
+                        let (repository, unit_of_work) = $backend;
+
+                        $crate::infrastructure::persistence::conformance::$test(
+                            &repository,
+                            &unit_of_work,
+                            &events,
+                        )
+                        .await;
+                    });
+                }
+            )*
+        }
+    };
+}
+
+pub use crate::__unit_of_work_conformance_tests as unit_of_work_conformance_tests;
+
+/// Runs a test on the runtime the application uses, so the repository under test may rely on it
+pub fn run(test: impl Future<Output = ()>) {
+    actix_web::rt::System::new().block_on(test)
+}
+
+/// Handler collecting the events dispatched to it
+struct CollectingHandler(Arc<Mutex<Vec<ProductEvent>>>);
+
+impl ProductEventHandler for CollectingHandler {
+    fn handle(&self, event: &ProductEvent) {
+        self.0.lock().unwrap().push(event.clone());
+    }
+}
+
+/// Makes an event dispatcher collecting every event it dispatches
+///
+/// # Returns
+/// The events dispatched so far, and the dispatcher
+pub fn collecting_dispatcher() -> (Arc<Mutex<Vec<ProductEvent>>>, Arc<ProductEventDispatcher>) {
+    let events = Arc::new(Mutex::new(vec![]));
+
+    let mut event_dispatcher = ProductEventDispatcher::new();
+    event_dispatcher.register(Box::new(CollectingHandler(events.clone())));
+
+    (events, Arc::new(event_dispatcher))
+}
+
+/// A day in January 2023
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 1, day).unwrap()
+}
+
+/// A stash item expiring on a day in January 2023
+fn expiring(day: u32) -> StashItem {
+    FakeStashItem::new().with_expiry_date(date(day)).build()
+}
+
+/// A time a while from now, so everything in the trash was deleted before it
+fn later() -> NaiveDateTime {
+    Utc::now().naive_utc() + chrono::Duration::days(1)
+}
+
+/// Sorts products by ID, so lists can be compared no matter which order a repository returns them in
+fn sorted(mut products: Vec<Product>) -> Vec<Product> {
+    products.sort_by(|a, b| a.id().value().cmp(b.id().value()));
+    products
+}
+
+pub async fn test_save_and_find_by_id(repository: &impl ProductRepository) {
+    let product = FakeProduct::new()
+        .with_unit(Unit::Kilograms)
+        .with_shelf_life_after_opening_days(3)
+        .with_best_before_grace_period_days(7)
+        .with_barcodes(vec!["7038010000256".parse().unwrap()])
+        .with_stash_items(vec![
+            FakeStashItem::new()
+                .with_expiry_date(date(1))
+                .with_quantity("0.75".parse().unwrap())
+                .build(),
+            FakeStashItem::new()
+                .with_expiry_date(date(1))
+                .with_opened_on(date(2))
+                .with_expiry_date_kind(ExpiryDateKind::UseBy)
+                .build(),
+            FakeStashItem::new().without_expiry_date().build(),
+        ])
+        .build();
+
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_save_fractional_quantity(repository: &impl ProductRepository) {
+    let product = FakeProduct::new()
+        .with_unit(Unit::Kilograms)
+        .with_stash_items(vec![
+            FakeStashItem::new()
+                .with_expiry_date(date(1))
+                .with_quantity("0.1".parse().unwrap())
+                .build(),
+            FakeStashItem::new()
+                .with_expiry_date(date(2))
+                .with_quantity("1234567890.123456789".parse().unwrap())
+                .build(),
+        ])
+        .build();
+
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_save_without_expiry_date(repository: &impl ProductRepository) {
+    let product = FakeProduct::new()
+        .with_stash_items(vec![
+            FakeStashItem::new().without_expiry_date().build(),
+            FakeStashItem::new()
+                .without_expiry_date()
+                .with_opened_on(date(1))
+                .build(),
+        ])
+        .build();
+
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_find_by_id_by_barcode(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let product = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(&barcode).await.unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_find_by_id_not_found(repository: &impl ProductRepository) {
+    repository.save(FakeProduct::new().build()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(&"ID".parse().unwrap()).await.unwrap(),
+        None
+    );
+}
+
+pub async fn test_find_by_ids(repository: &impl ProductRepository) {
+    let first = FakeProduct::new().build();
+    let second = FakeProduct::new().with_stash_items(vec![]).build();
+    let third = FakeProduct::new().build();
+    repository
+        .save_all(vec![first.clone(), second.clone(), third.clone()])
+        .await
+        .unwrap();
+
+    let found = repository
+        .find_by_ids(&[
+            first.id().clone(),
+            second.id().clone(),
+            "ID".parse().unwrap(),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(sorted(found), sorted(vec![first, second]));
+}
+
+pub async fn test_find_by_ids_empty(repository: &impl ProductRepository) {
+    repository.save(FakeProduct::new().build()).await.unwrap();
+
+    assert_eq!(repository.find_by_ids(&[]).await.unwrap(), vec![]);
+}
+
+pub async fn test_find_all_with_stash_items(repository: &impl ProductRepository) {
+    let first = FakeProduct::new()
+        .with_stash_items(vec![expiring(1)])
+        .build();
+    let second = FakeProduct::new()
+        .with_stash_items(vec![FakeStashItem::new().without_expiry_date().build()])
+        .build();
+    let empty = FakeProduct::new().with_stash_items(vec![]).build();
+    repository
+        .save_all(vec![first.clone(), second.clone(), empty])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        sorted(repository.find_all_with_stash_items().await.unwrap()),
+        sorted(vec![first, second])
+    );
+}
+
+pub async fn test_find_by_stash_item_id(repository: &impl ProductRepository) {
+    let stash_item = expiring(1);
+    let product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone(), expiring(2)])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+    repository.save(FakeProduct::new().build()).await.unwrap();
+
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(stash_item.id())
+            .await
+            .unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_find_by_stash_item_id_not_found(repository: &impl ProductRepository) {
+    repository.save(FakeProduct::new().build()).await.unwrap();
+
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(&Uuid::new_v4())
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+pub async fn test_find_expiring_in_interval(repository: &impl ProductRepository) {
+    let first = FakeProduct::new()
+        .with_stash_items(vec![expiring(1)])
+        .build();
+    let fifth = FakeProduct::new()
+        .with_stash_items(vec![expiring(5)])
+        .build();
+    let tenth = FakeProduct::new()
+        .with_stash_items(vec![
+            expiring(10),
+            FakeStashItem::new().without_expiry_date().build(),
+        ])
+        .build();
+    let never = FakeProduct::new()
+        .with_stash_items(vec![FakeStashItem::new().without_expiry_date().build()])
+        .build();
+    repository
+        .save_all(vec![
+            first.clone(),
+            fifth.clone(),
+            tenth.clone(),
+            never.clone(),
+        ])
+        .await
+        .unwrap();
+
+    // The start of the interval is inclusive, the end exclusive
+    assert_eq!(
+        repository
+            .find_expiring_in_interval(Some(date(5)), Some(date(10)))
+            .await
+            .unwrap(),
+        vec![fifth.clone()]
+    );
+    assert_eq!(
+        repository
+            .find_expiring_in_interval(None, Some(date(5)))
+            .await
+            .unwrap(),
+        vec![first]
+    );
+    assert_eq!(
+        sorted(
+            repository
+                .find_expiring_in_interval(Some(date(5)), None)
+                .await
+                .unwrap()
+        ),
+        sorted(vec![fifth, tenth])
+    );
+}
+
+pub async fn test_find_expiring_in_interval_opened(repository: &impl ProductRepository) {
+    // Expires on the 4th, three days after being opened
+    let opened = FakeProduct::new()
+        .with_shelf_life_after_opening_days(3)
+        .with_stash_items(vec![FakeStashItem::new()
+            .with_expiry_date(date(20))
+            .with_opened_on(date(1))
+            .build()])
+        .build();
+    // Keeps its expiry date, since the product does not expire sooner once opened
+    let unaffected = FakeProduct::new()
+        .with_stash_items(vec![FakeStashItem::new()
+            .with_expiry_date(date(20))
+            .with_opened_on(date(1))
+            .build()])
+        .build();
+    // Expires on the 3rd, since opening only ever makes a stash item expire sooner
+    let unopened_sooner = FakeProduct::new()
+        .with_shelf_life_after_opening_days(30)
+        .with_stash_items(vec![FakeStashItem::new()
+            .with_expiry_date(date(3))
+            .with_opened_on(date(1))
+            .build()])
+        .build();
+    repository
+        .save_all(vec![
+            opened.clone(),
+            unaffected.clone(),
+            unopened_sooner.clone(),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        sorted(
+            repository
+                .find_expiring_in_interval(None, Some(date(5)))
+                .await
+                .unwrap()
+        ),
+        sorted(vec![opened, unopened_sooner])
+    );
+    assert_eq!(
+        repository
+            .find_expiring_in_interval(Some(date(5)), None)
+            .await
+            .unwrap(),
+        vec![unaffected]
+    );
+}
+
+pub async fn test_find_expiring_in_interval_none(repository: &impl ProductRepository) {
+    repository.save(FakeProduct::new().build()).await.unwrap();
+
+    assert_eq!(
+        repository.find_expiring_in_interval(None, None).await,
+        Err(ProductRepositoryError::InvalidDateInterval)
+    );
+}
+
+pub async fn test_find_in_interval_without_expiry_date(repository: &impl ProductRepository) {
+    repository
+        .save(
+            FakeProduct::new()
+                .with_best_before_grace_period_days(1)
+                .with_stash_items(vec![FakeStashItem::new().without_expiry_date().build()])
+                .build(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository
+            .find_expiring_in_interval(Some(date(1)), None)
+            .await
+            .unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        repository
+            .find_expiring_in_interval(None, Some(date(31)))
+            .await
+            .unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        repository
+            .find_unsafe_in_interval(Some(date(1)), Some(date(31)))
+            .await
+            .unwrap(),
+        vec![]
+    );
+}
+
+pub async fn test_find_unsafe_in_interval(repository: &impl ProductRepository) {
+    // Unsafe on the 5th
+    let use_by = FakeProduct::new()
+        .with_stash_items(vec![FakeStashItem::new()
+            .with_expiry_date(date(5))
+            .with_expiry_date_kind(ExpiryDateKind::UseBy)
+            .build()])
+        .build();
+    // Unsafe on the 8th, three days after the best before date
+    let grace_period = FakeProduct::new()
+        .with_best_before_grace_period_days(3)
+        .with_stash_items(vec![FakeStashItem::new()
+            .with_expiry_date(date(5))
+            .with_expiry_date_kind(ExpiryDateKind::BestBefore)
+            .build()])
+        .build();
+    // Never unsafe, as there is no grace period after the best before date
+    let best_before = FakeProduct::new()
+        .with_stash_items(vec![FakeStashItem::new()
+            .with_expiry_date(date(1))
+            .with_expiry_date_kind(ExpiryDateKind::BestBefore)
+            .build()])
+        .build();
+    repository
+        .save_all(vec![
+            use_by.clone(),
+            grace_period.clone(),
+            best_before.clone(),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository
+            .find_unsafe_in_interval(None, Some(date(6)))
+            .await
+            .unwrap(),
+        vec![use_by.clone()]
+    );
+    assert_eq!(
+        sorted(
+            repository
+                .find_unsafe_in_interval(None, Some(date(9)))
+                .await
+                .unwrap()
+        ),
+        sorted(vec![use_by, grace_period.clone()])
+    );
+    assert_eq!(
+        repository
+            .find_unsafe_in_interval(Some(date(6)), None)
+            .await
+            .unwrap(),
+        vec![grace_period]
+    );
+}
+
+pub async fn test_find_unsafe_in_interval_none(repository: &impl ProductRepository) {
+    repository.save(FakeProduct::new().build()).await.unwrap();
+
+    assert_eq!(
+        repository.find_unsafe_in_interval(None, None).await,
+        Err(ProductRepositoryError::InvalidDateInterval)
+    );
+}
+
+pub async fn test_exists_by_id(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let product = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    assert!(repository.exists_by_id(product.id()).await.unwrap());
+    assert!(repository.exists_by_id(&barcode).await.unwrap());
+    assert!(!repository
+        .exists_by_id(&"ID".parse().unwrap())
+        .await
+        .unwrap());
+}
+
+pub async fn test_save_update(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let to_remove = expiring(1);
+    let to_update = expiring(2);
+    let mut product = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .with_stash_items(vec![to_remove.clone(), to_update.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    product.set_name("NEW NAME".to_string());
+    product.set_brand("NEW BRAND".parse().unwrap());
+    product.set_unit(Unit::Litres);
+    product.set_shelf_life_after_opening_days(Some(5));
+    product.set_best_before_grace_period_days(Some(2));
+    product.detach_barcode(&barcode).unwrap();
+    product.attach_barcode("7038010000737".parse().unwrap());
+    product.remove_stash_item(to_remove.id()).unwrap();
+    product
+        .update_stash_item(StashItem::new(
+            *to_update.id(),
+            4.try_into().unwrap(),
+            Some(date(4)),
+        ))
+        .unwrap();
+    product.add_stash_item(expiring(1)).unwrap();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+    assert_eq!(repository.find_by_id(&barcode).await.unwrap(), None);
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(to_remove.id())
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+pub async fn test_save_replaces_stash_item_with_same_dates(repository: &impl ProductRepository) {
+    let old = expiring(1);
+    let mut product = FakeProduct::new()
+        .with_stash_items(vec![old.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    // Stash items are unique by their dates, which must not stop a new item from taking the place of a removed one
+    product.remove_stash_item(old.id()).unwrap();
+    let new = expiring(1);
+    product.add_stash_item(new.clone()).unwrap();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_stash_item_id(new.id()).await.unwrap(),
+        Some(product)
+    );
+    assert_eq!(
+        repository.find_by_stash_item_id(old.id()).await.unwrap(),
+        None
+    );
+}
+
+pub async fn test_save_all(repository: &impl ProductRepository) {
+    let first = FakeProduct::new().build();
+    let second = FakeProduct::new().build();
+
+    repository
+        .save_all(vec![first.clone(), second.clone()])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository.find_by_id(first.id()).await.unwrap(),
+        Some(first)
+    );
+    assert_eq!(
+        repository.find_by_id(second.id()).await.unwrap(),
+        Some(second)
+    );
+}
+
+pub async fn test_save_all_moved_stash_item(repository: &impl ProductRepository) {
+    let stash_item = expiring(1);
+    let mut source = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    let mut target = FakeProduct::new().with_stash_items(vec![]).build();
+    repository
+        .save_all(vec![source.clone(), target.clone()])
+        .await
+        .unwrap();
+
+    source
+        .move_stash_item(stash_item.id(), &mut target, false)
+        .unwrap();
+    repository
+        .save_all(vec![target.clone(), source.clone()])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository.find_by_id(source.id()).await.unwrap(),
+        Some(source)
+    );
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(stash_item.id())
+            .await
+            .unwrap(),
+        Some(target)
+    );
+}
+
+pub async fn test_save_moves_barcodes(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let mut first = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .build();
+    let mut second = FakeProduct::new().build();
+    repository.save(first.clone()).await.unwrap();
+
+    second.attach_barcode(barcode.clone());
+    repository.save(second.clone()).await.unwrap();
+
+    first.detach_barcode(&barcode).unwrap();
+    assert_eq!(
+        repository.find_by_id(first.id()).await.unwrap(),
+        Some(first)
+    );
+    assert_eq!(repository.find_by_id(&barcode).await.unwrap(), Some(second));
+}
+
+pub async fn test_save_overrides_barcode(repository: &impl ProductRepository) {
+    let source = FakeProduct::new().with_stash_items(vec![]).build();
+    let mut target = FakeProduct::new().with_stash_items(vec![]).build();
+    repository
+        .save_all(vec![source.clone(), target.clone()])
+        .await
+        .unwrap();
+    source.clone().merge_into(&mut target).unwrap();
+    repository
+        .merge_into(source.id(), target.clone())
+        .await
+        .unwrap();
+
+    // Saving a product with the ID of a barcode takes the barcode from the product it was attached to
+    repository.save(source.clone()).await.unwrap();
+
+    target.detach_barcode(source.id()).unwrap();
+    assert_eq!(
+        repository.find_by_id(source.id()).await.unwrap(),
+        Some(source)
+    );
+    assert_eq!(
+        repository.find_by_id(target.id()).await.unwrap(),
+        Some(target)
+    );
+}
+
+pub async fn test_merge_into(repository: &impl ProductRepository) {
+    let source = FakeProduct::new()
+        .with_barcodes(vec!["7038010000256".parse().unwrap()])
+        .with_stash_items(vec![
+            FakeStashItem::new()
+                .with_expiry_date(date(1))
+                .with_quantity(2.try_into().unwrap())
+                .build(),
+            expiring(2),
+        ])
+        .build();
+    let source_id = source.id().clone();
+    let mut target = FakeProduct::new()
+        .with_stash_items(vec![FakeStashItem::new()
+            .with_expiry_date(date(1))
+            .with_quantity(3.try_into().unwrap())
+            .build()])
+        .build();
+    repository
+        .save_all(vec![source.clone(), target.clone()])
+        .await
+        .unwrap();
+
+    source.merge_into(&mut target).unwrap();
+    repository
+        .merge_into(&source_id, target.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository.find_by_id(target.id()).await.unwrap(),
+        Some(target.clone())
+    );
+    assert_eq!(
+        repository.find_by_id(&source_id).await.unwrap(),
+        Some(target.clone())
+    );
+    assert_eq!(
+        repository
+            .find_by_id(&"7038010000256".parse().unwrap())
+            .await
+            .unwrap(),
+        Some(target.clone())
+    );
+    assert_eq!(
+        repository.find_all_with_stash_items().await.unwrap(),
+        vec![target]
+    );
+}
+
+pub async fn test_merge_into_twice(repository: &impl ProductRepository) {
+    let first = FakeProduct::new().with_stash_items(vec![]).build();
+    let mut second = FakeProduct::new().with_stash_items(vec![]).build();
+    let mut third = FakeProduct::new().with_stash_items(vec![]).build();
+    repository
+        .save_all(vec![first.clone(), second.clone(), third.clone()])
+        .await
+        .unwrap();
+
+    first.clone().merge_into(&mut second).unwrap();
+    repository
+        .merge_into(first.id(), second.clone())
+        .await
+        .unwrap();
+    // The barcodes the second product took over from the first move on with it
+    second.clone().merge_into(&mut third).unwrap();
+    repository
+        .merge_into(second.id(), third.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository.find_by_id(first.id()).await.unwrap(),
+        Some(third.clone())
+    );
+    assert_eq!(
+        repository.find_by_id(second.id()).await.unwrap(),
+        Some(third.clone())
+    );
+    assert_eq!(
+        repository
+            .find_by_ids(&[first.id().clone(), second.id().clone(), third.id().clone()])
+            .await
+            .unwrap(),
+        vec![third]
+    );
+}
+
+pub async fn test_merge_into_moves_barcodes(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let first = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .with_stash_items(vec![])
+        .build();
+    let mut second = FakeProduct::new().with_stash_items(vec![]).build();
+    let mut third = FakeProduct::new().with_stash_items(vec![]).build();
+    repository
+        .save_all(vec![first.clone(), second.clone(), third.clone()])
+        .await
+        .unwrap();
+
+    first.clone().merge_into(&mut second).unwrap();
+    repository
+        .merge_into(first.id(), second.clone())
+        .await
+        .unwrap();
+    second.clone().merge_into(&mut third).unwrap();
+    repository
+        .merge_into(second.id(), third.clone())
+        .await
+        .unwrap();
+
+    let found = repository.find_by_id(third.id()).await.unwrap().unwrap();
+    assert!(found.has_barcode(&barcode));
+    assert!(found.has_barcode(first.id()));
+    assert!(found.has_barcode(second.id()));
+    assert_eq!(repository.find_by_id(&barcode).await.unwrap(), Some(third));
+}
+
+pub async fn test_merge_into_missing_source(repository: &impl ProductRepository) {
+    let target = FakeProduct::new().build();
+
+    repository
+        .merge_into(&"ID".parse().unwrap(), target.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository.find_by_id(target.id()).await.unwrap(),
+        Some(target)
+    );
+}
+
+pub async fn test_delete_by_id(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let stash_item = expiring(1);
+    let product = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    let other = FakeProduct::new().build();
+    repository
+        .save_all(vec![product.clone(), other.clone()])
+        .await
+        .unwrap();
+
+    repository.delete_by_id(product.id()).await.unwrap();
+
+    assert!(!repository.exists_by_id(product.id()).await.unwrap());
+    assert!(!repository.exists_by_id(&barcode).await.unwrap());
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(stash_item.id())
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        repository.find_by_id(other.id()).await.unwrap(),
+        Some(other)
+    );
+}
+
+pub async fn test_delete_by_id_by_barcode(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let product = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    repository.delete_by_id(&barcode).await.unwrap();
+
+    assert!(!repository.exists_by_id(product.id()).await.unwrap());
+}
+
+pub async fn test_delete_by_id_not_found(repository: &impl ProductRepository) {
+    let product = FakeProduct::new().build();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.delete_by_id(&"ID".parse().unwrap()).await,
+        Ok(())
+    );
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_delete_by_id_moves_to_trash(repository: &impl ProductRepository) {
+    let product = FakeProduct::new()
+        .with_barcodes(vec!["7038010000256".parse().unwrap()])
+        .with_stash_items(vec![expiring(1), expiring(2)])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    repository.delete_by_id(product.id()).await.unwrap();
+
+    let trash = repository.find_trash().await.unwrap();
+    assert!(
+        matches!(&trash[..], [TrashEntry::Product { product: trashed, .. }] if *trashed == product),
+        "{:?}",
+        trash
+    );
+}
+
+pub async fn test_delete_stash_item_by_id(repository: &impl ProductRepository) {
+    let stash_item = expiring(1);
+    let mut product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone(), expiring(2)])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    repository
+        .delete_stash_item_by_id(stash_item.id())
+        .await
+        .unwrap();
+
+    product.remove_stash_item(stash_item.id()).unwrap();
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product.clone())
+    );
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(stash_item.id())
+            .await
+            .unwrap(),
+        None
+    );
+    let trash = repository.find_trash().await.unwrap();
+    assert!(
+        matches!(
+            &trash[..],
+            [TrashEntry::StashItem { product_id, stash_item: trashed, .. }]
+                if product_id == product.id() && *trashed == stash_item
+        ),
+        "{:?}",
+        trash
+    );
+}
+
+pub async fn test_delete_stash_item_by_id_not_found(repository: &impl ProductRepository) {
+    let product = FakeProduct::new().build();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.delete_stash_item_by_id(&Uuid::new_v4()).await,
+        Ok(())
+    );
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+    assert_eq!(repository.find_trash().await.unwrap(), vec![]);
+}
+
+pub async fn test_trash_left_out_of_queries(repository: &impl ProductRepository) {
+    let deleted = FakeProduct::new()
+        .with_stash_items(vec![expiring(1)])
+        .build();
+    let stash_item = expiring(2);
+    let mut kept = FakeProduct::new()
+        .with_stash_items(vec![
+            stash_item.clone(),
+            FakeStashItem::new()
+                .with_expiry_date(date(3))
+                .with_expiry_date_kind(ExpiryDateKind::UseBy)
+                .build(),
+        ])
+        .build();
+    repository
+        .save_all(vec![deleted.clone(), kept.clone()])
+        .await
+        .unwrap();
+
+    repository.delete_by_id(deleted.id()).await.unwrap();
+    repository
+        .delete_stash_item_by_id(stash_item.id())
+        .await
+        .unwrap();
+
+    kept.remove_stash_item(stash_item.id()).unwrap();
+    assert!(!repository.exists_by_id(deleted.id()).await.unwrap());
+    assert_eq!(
+        repository
+            .find_by_ids(&[deleted.id().clone(), kept.id().clone()])
+            .await
+            .unwrap(),
+        vec![kept.clone()]
+    );
+    assert_eq!(
+        repository.find_all_with_stash_items().await.unwrap(),
+        vec![kept.clone()]
+    );
+    assert_eq!(
+        repository
+            .find_expiring_in_interval(None, Some(date(31)))
+            .await
+            .unwrap(),
+        vec![kept.clone()]
+    );
+    assert_eq!(
+        repository
+            .find_unsafe_in_interval(None, Some(date(31)))
+            .await
+            .unwrap(),
+        vec![kept]
+    );
+}
+
+pub async fn test_find_trash_most_recent_first(repository: &impl ProductRepository) {
+    let first = FakeProduct::new().build();
+    let second = FakeProduct::new().build();
+    repository
+        .save_all(vec![first.clone(), second.clone()])
+        .await
+        .unwrap();
+
+    repository.delete_by_id(first.id()).await.unwrap();
+    // Keep the deletions apart, no matter how precisely a repository stores when they happened
+    thread::sleep(Duration::from_millis(10));
+    repository.delete_by_id(second.id()).await.unwrap();
+
+    let ids = repository
+        .find_trash()
+        .await
+        .unwrap()
+        .iter()
+        .map(TrashEntry::id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![second.id().to_string(), first.id().to_string()]);
+}
+
+pub async fn test_save_replaces_trashed_product(repository: &impl ProductRepository) {
+    let product = FakeProduct::new()
+        .with_barcodes(vec!["7038010000256".parse().unwrap()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+    repository.delete_by_id(product.id()).await.unwrap();
+
+    let replacement = FakeProduct::new().with_id(product.id().clone()).build();
+    repository.save(replacement.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(replacement)
+    );
+    assert_eq!(repository.find_trash().await.unwrap(), vec![]);
+}
+
+pub async fn test_restore_by_id(repository: &impl ProductRepository) {
+    let barcode: ProductId = "7038010000256".parse().unwrap();
+    let product = FakeProduct::new()
+        .with_barcodes(vec![barcode.clone()])
+        .with_stash_items(vec![expiring(1), expiring(2)])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+    repository.delete_by_id(product.id()).await.unwrap();
+
+    repository.restore_by_id(product.id()).await.unwrap();
+
+    assert_eq!(
+        repository.find_by_id(&barcode).await.unwrap(),
+        Some(product)
+    );
+    assert_eq!(repository.find_trash().await.unwrap(), vec![]);
+}
+
+pub async fn test_restore_by_id_not_found(repository: &impl ProductRepository) {
+    let product = FakeProduct::new().build();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.restore_by_id(&"ID".parse().unwrap()).await,
+        Err(ProductRepositoryError::ProductNotFound)
+    );
+    // Products which are not in the trash can't be restored either
+    assert_eq!(
+        repository.restore_by_id(product.id()).await,
+        Err(ProductRepositoryError::ProductNotFound)
+    );
+}
+
+pub async fn test_restore_by_id_taken(repository: &impl ProductRepository) {
+    let product = FakeProduct::new().build();
+    repository.save(product.clone()).await.unwrap();
+    repository.delete_by_id(product.id()).await.unwrap();
+
+    let other = FakeProduct::new()
+        .with_barcodes(vec![product.id().clone()])
+        .build();
+    repository.save(other.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.restore_by_id(product.id()).await,
+        Err(ProductRepositoryError::ProductAlreadyExists)
+    );
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(other)
+    );
+}
+
+pub async fn test_restore_stash_item_by_id(repository: &impl ProductRepository) {
+    let stash_item = expiring(1);
+    let product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone(), expiring(2)])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+    repository
+        .delete_stash_item_by_id(stash_item.id())
+        .await
+        .unwrap();
+
+    repository
+        .restore_stash_item_by_id(stash_item.id())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(stash_item.id())
+            .await
+            .unwrap(),
+        Some(product)
+    );
+    assert_eq!(repository.find_trash().await.unwrap(), vec![]);
+}
+
+pub async fn test_restore_stash_item_by_id_not_found(repository: &impl ProductRepository) {
+    let stash_item = expiring(1);
+    let product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.restore_stash_item_by_id(&Uuid::new_v4()).await,
+        Err(ProductRepositoryError::StashItemNotFound)
+    );
+    // Stash items deleted along with their product are only restored along with it
+    repository.delete_by_id(product.id()).await.unwrap();
+    assert_eq!(
+        repository.restore_stash_item_by_id(stash_item.id()).await,
+        Err(ProductRepositoryError::StashItemNotFound)
+    );
+}
+
+pub async fn test_restore_stash_item_by_id_duplicate_dates(repository: &impl ProductRepository) {
+    let stash_item = expiring(1);
+    let mut product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+    repository
+        .delete_stash_item_by_id(stash_item.id())
+        .await
+        .unwrap();
+
+    product.remove_stash_item(stash_item.id()).unwrap();
+    product.add_stash_item(expiring(1)).unwrap();
+    repository.save(product.clone()).await.unwrap();
+
+    assert_eq!(
+        repository.restore_stash_item_by_id(stash_item.id()).await,
+        Err(ProductRepositoryError::DuplicateExpiryDateError)
+    );
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_purge_trash(repository: &impl ProductRepository) {
+    let deleted = FakeProduct::new()
+        .with_stash_items(vec![expiring(1), expiring(2)])
+        .build();
+    let stash_item = expiring(1);
+    let mut kept = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    repository
+        .save_all(vec![deleted.clone(), kept.clone()])
+        .await
+        .unwrap();
+    repository.delete_by_id(deleted.id()).await.unwrap();
+    repository
+        .delete_stash_item_by_id(stash_item.id())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository
+            .purge_trash(date(1).and_hms_opt(0, 0, 0).unwrap())
+            .await,
+        Ok(0)
+    );
+    assert_eq!(repository.find_trash().await.unwrap().len(), 2);
+
+    assert_eq!(repository.purge_trash(later()).await, Ok(2));
+
+    kept.remove_stash_item(stash_item.id()).unwrap();
+    assert_eq!(repository.find_trash().await.unwrap(), vec![]);
+    assert_eq!(
+        repository.restore_by_id(deleted.id()).await,
+        Err(ProductRepositoryError::ProductNotFound)
+    );
+    assert_eq!(repository.find_by_id(kept.id()).await.unwrap(), Some(kept));
+}
+
+pub async fn test_run_commits(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let stash_item = FakeStashItem::new().build();
+    let product = FakeProduct::new().with_stash_items(vec![]).build();
+
+    let saved = product.clone();
+    let added = stash_item.clone();
+    let found = unit_of_work
+        .run(move |tx| {
+            tx.save(saved.clone())?;
+
+            // Later work in the transaction sees the changes of earlier work
+            let mut product = tx.find_by_id(saved.id())?.unwrap();
+            product.add_stash_item(added)?;
+            tx.save(product.clone())?;
+
+            Ok::<_, ProductRepositoryError>(product)
+        })
+        .await
+        .unwrap();
+
+    assert!(found.has_stash_item(stash_item.id()));
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(found)
+    );
+}
+
+pub async fn test_run_rolls_back_on_error(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let product = FakeProduct::new().build();
+
+    let saved = product.clone();
+    let result = unit_of_work
+        .run(move |tx| {
+            tx.save(saved)?;
+
+            Err::<(), _>(ProductRepositoryError::StashItemNotFound)
+        })
+        .await;
+
+    assert_eq!(result, Err(ProductRepositoryError::StashItemNotFound));
+    assert!(!repository.exists_by_id(product.id()).await.unwrap());
+}
+
+pub async fn test_run_dispatches_events_once_committed(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    events: &Mutex<Vec<ProductEvent>>,
+) {
+    let product = FakeProduct::new().with_stash_items(vec![]).build();
+    repository.save(product.clone()).await.unwrap();
+    let stash_item = FakeStashItem::new().build();
+
+    let product_id = product.id().clone();
+    let added = stash_item.clone();
+    unit_of_work
+        .run(move |tx| {
+            let mut product = tx.find_by_id(&product_id)?.unwrap();
+            product.add_stash_item(added)?;
+            tx.save(product)?;
+
+            Ok::<_, ProductRepositoryError>(())
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![ProductEvent::StashItemAdded {
+            product_id: product.id().clone(),
+            stash_item
+        }]
+    );
+}
+
+pub async fn test_run_dispatches_no_events_when_rolled_back(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    events: &Mutex<Vec<ProductEvent>>,
+) {
+    let product = FakeProduct::new().with_stash_items(vec![]).build();
+    repository.save(product.clone()).await.unwrap();
+
+    let product_id = product.id().clone();
+    let result = unit_of_work
+        .run(move |tx| {
+            let mut product = tx.find_by_id(&product_id)?.unwrap();
+            product.add_stash_item(FakeStashItem::new().build())?;
+            tx.save(product)?;
+
+            Err::<(), _>(ProductRepositoryError::StashItemNotFound)
+        })
+        .await;
+
+    assert_eq!(result, Err(ProductRepositoryError::StashItemNotFound));
+    assert!(events.lock().unwrap().is_empty());
+    assert_eq!(
+        repository.find_by_id(product.id()).await.unwrap(),
+        Some(product)
+    );
+}
+
+pub async fn test_run_exists_by_id(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let product = FakeProduct::new().build();
+    repository.save(product.clone()).await.unwrap();
+
+    let product_id = product.id().clone();
+    let exists = unit_of_work
+        .run(move |tx| {
+            let missing: ProductId = "missing".parse().unwrap();
+
+            Ok::<_, ProductRepositoryError>((
+                tx.exists_by_id(&product_id)?,
+                tx.exists_by_id(&missing)?,
+            ))
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(exists, (true, false));
+}
+
+pub async fn test_run_find_by_stash_item_id(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let stash_item = FakeStashItem::new().build();
+    let product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    let stash_item_id = *stash_item.id();
+    let found = unit_of_work
+        .run(move |tx| tx.find_by_stash_item_id(&stash_item_id))
+        .await
+        .unwrap();
+
+    assert_eq!(found, Some(product));
+}
+
+pub async fn test_run_delete_by_id(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let product = FakeProduct::new().build();
+    repository.save(product.clone()).await.unwrap();
+
+    let product_id = product.id().clone();
+    let exists = unit_of_work
+        .run(move |tx| {
+            tx.delete_by_id(&product_id)?;
+
+            // The deleted product is gone within the transaction
+            tx.exists_by_id(&product_id)
+        })
+        .await
+        .unwrap();
+
+    assert!(!exists);
+    assert!(!repository.exists_by_id(product.id()).await.unwrap());
+    assert_eq!(repository.find_trash().await.unwrap().len(), 1);
+}
+
+pub async fn test_run_delete_stash_item_by_id(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let stash_item = FakeStashItem::new().build();
+    let product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+
+    let stash_item_id = *stash_item.id();
+    unit_of_work
+        .run(move |tx| tx.delete_stash_item_by_id(&stash_item_id))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repository
+            .find_by_stash_item_id(stash_item.id())
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(repository.find_trash().await.unwrap().len(), 1);
+}
+
+pub async fn test_run_merge_into(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let source = FakeProduct::new().build();
+    let target = FakeProduct::new().build();
+    repository.save(source.clone()).await.unwrap();
+    repository.save(target.clone()).await.unwrap();
+
+    let mut merged = target.clone();
+    source.clone().merge_into(&mut merged).unwrap();
+
+    let source_id = source.id().clone();
+    let saved = merged.clone();
+    unit_of_work
+        .run(move |tx| tx.merge_into(&source_id, saved))
+        .await
+        .unwrap();
+
+    // The source's ID now leads to the target
+    assert_eq!(
+        repository.find_by_id(source.id()).await.unwrap(),
+        Some(merged)
+    );
+    assert!(repository.find_trash().await.unwrap().is_empty());
+}
+
+pub async fn test_run_merge_into_dispatches_events_of_both_products(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    events: &Mutex<Vec<ProductEvent>>,
+) {
+    let stash_item = FakeStashItem::new().build();
+    let source = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    let target = FakeProduct::new().with_stash_items(vec![]).build();
+    repository.save(source.clone()).await.unwrap();
+    repository.save(target.clone()).await.unwrap();
+
+    let source_id = source.id().clone();
+    let target_id = target.id().clone();
+    unit_of_work
+        .run(move |tx| {
+            let source = tx.find_by_id(&source_id)?.unwrap();
+            let mut target = tx.find_by_id(&target_id)?.unwrap();
+            source.merge_into(&mut target)?;
+            tx.merge_into(&source_id, target)
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ProductEvent::StashItemRemoved {
+                product_id: source.id().clone(),
+                stash_item: stash_item.clone()
+            },
+            ProductEvent::StashItemAdded {
+                product_id: target.id().clone(),
+                stash_item
+            }
+        ]
+    );
+}
+
+pub async fn test_run_restore_by_id(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let product = FakeProduct::new().build();
+    repository.save(product.clone()).await.unwrap();
+    repository.delete_by_id(product.id()).await.unwrap();
+
+    let product_id = product.id().clone();
+    let found = unit_of_work
+        .run(move |tx| {
+            tx.restore_by_id(&product_id)?;
+
+            // The restored product is seen within the transaction
+            tx.find_by_id(&product_id)
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(found, Some(product));
+    assert!(repository.find_trash().await.unwrap().is_empty());
+}
+
+pub async fn test_run_restore_stash_item_by_id(
+    repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let stash_item = FakeStashItem::new().build();
+    let product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone()])
+        .build();
+    repository.save(product.clone()).await.unwrap();
+    repository
+        .delete_stash_item_by_id(stash_item.id())
+        .await
+        .unwrap();
+
+    let stash_item_id = *stash_item.id();
+    let found = unit_of_work
+        .run(move |tx| {
+            tx.restore_stash_item_by_id(&stash_item_id)?;
+
+            // The restored stash item is seen within the transaction
+            tx.find_by_stash_item_id(&stash_item_id)
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(found, Some(product));
+    assert!(repository.find_trash().await.unwrap().is_empty());
+}
+
+/// Makes an operation restoring a random stash item
+fn operation() -> Operation {
+    Operation::new(InverseOperation::RestoreStashItem {
+        stash_item_id: Uuid::new_v4(),
+    })
+}
+
+pub async fn test_run_save_operation_and_find_by_id(
+    _repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let operation = self::operation();
+
+    let saved = operation.clone();
+    unit_of_work
+        .run(move |tx| {
+            tx.save_operation(saved)?;
+            tx.save_operation(self::operation())
+        })
+        .await
+        .unwrap();
+
+    let operation_id = *operation.id();
+    let found = unit_of_work
+        .run(move |tx| tx.find_operation_by_id(&operation_id))
+        .await;
+
+    assert_eq!(found, Ok(Some(operation)));
+}
+
+pub async fn test_run_find_operation_by_id_not_found(
+    _repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let found = unit_of_work
+        .run(move |tx| {
+            tx.save_operation(operation())?;
+
+            tx.find_operation_by_id(&Uuid::new_v4())
+        })
+        .await;
+
+    assert_eq!(found, Ok(None));
+}
+
+pub async fn test_run_keeps_every_kind_of_operation(
+    _repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let mut stash_item = FakeStashItem::new()
+        .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 26).unwrap())
+        .build();
+    stash_item.set_expiry_date_kind(ExpiryDateKind::UseBy);
+    stash_item.set_opened_on(NaiveDate::from_ymd_opt(2023, 11, 20));
+    let mut product = FakeProduct::new()
+        .with_stash_items(vec![stash_item.clone(), FakeStashItem::new().build()])
+        .with_barcodes(vec!["BARCODE".parse().unwrap()])
+        .build();
+    product.set_unit(Unit::Grams);
+    product.set_shelf_life_after_opening_days(Some(3));
+    product.set_best_before_grace_period_days(Some(7));
+    let inverses = vec![
+        InverseOperation::RemoveStashItem {
+            product_id: product.id().clone(),
+            stash_item: stash_item.clone(),
+        },
+        InverseOperation::RevertStashItem {
+            product_id: product.id().clone(),
+            before: stash_item.clone(),
+            after: FakeStashItem::new().with_id(*stash_item.id()).build(),
+        },
+        InverseOperation::RestoreStashItem {
+            stash_item_id: *stash_item.id(),
+        },
+        InverseOperation::RevertProduct {
+            before: Box::new(product.clone()),
+            after: Box::new(FakeProduct::new().with_id(product.id().clone()).build()),
+        },
+        InverseOperation::RestoreProduct {
+            product_id: product.id().clone(),
+        },
+        InverseOperation::DeleteProduct {
+            product: Box::new(product.clone()),
+        },
+        InverseOperation::Batch {
+            operations: vec![
+                InverseOperation::RestoreStashItem {
+                    stash_item_id: *stash_item.id(),
+                },
+                InverseOperation::RestoreProduct {
+                    product_id: product.id().clone(),
+                },
+            ],
+        },
+    ];
+
+    for inverse in inverses {
+        let operation = Operation::new(inverse);
+
+        let saved = operation.clone();
+        unit_of_work
+            .run(move |tx| tx.save_operation(saved))
+            .await
+            .unwrap();
+
+        let operation_id = *operation.id();
+        let found = unit_of_work
+            .run(move |tx| tx.find_operation_by_id(&operation_id))
+            .await;
+
+        assert_eq!(found, Ok(Some(operation)));
+    }
+}
+
+pub async fn test_run_delete_operation_by_id(
+    _repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let operation = self::operation();
+    let other = self::operation();
+
+    let (saved, saved_other) = (operation.clone(), other.clone());
+    unit_of_work
+        .run(move |tx| {
+            tx.save_operation(saved)?;
+            tx.save_operation(saved_other)
+        })
+        .await
+        .unwrap();
+
+    let (operation_id, other_id) = (*operation.id(), *other.id());
+    let found = unit_of_work
+        .run(move |tx| {
+            tx.delete_operation_by_id(&operation_id)?;
+
+            Ok::<_, ProductRepositoryError>((
+                tx.find_operation_by_id(&operation_id)?,
+                tx.find_operation_by_id(&other_id)?,
+            ))
+        })
+        .await;
+
+    assert_eq!(found, Ok((None, Some(other))));
+}
+
+pub async fn test_run_rolls_back_operations(
+    _repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let kept = operation();
+    let saved = kept.clone();
+    unit_of_work
+        .run(move |tx| tx.save_operation(saved))
+        .await
+        .unwrap();
+
+    // Neither the operation saved nor the one deleted by the failed work is changed
+    let added = operation();
+    let (added_id, kept_id) = (*added.id(), *kept.id());
+    let result = unit_of_work
+        .run(move |tx| {
+            tx.save_operation(added)?;
+            tx.delete_operation_by_id(&kept_id)?;
+
+            Err::<(), _>(ProductRepositoryError::StashItemNotFound)
+        })
+        .await;
+
+    assert_eq!(result, Err(ProductRepositoryError::StashItemNotFound));
+    let found = unit_of_work
+        .run(move |tx| {
+            Ok::<_, ProductRepositoryError>((
+                tx.find_operation_by_id(&added_id)?,
+                tx.find_operation_by_id(&kept_id)?,
+            ))
+        })
+        .await;
+    assert_eq!(found, Ok((None, Some(kept))));
+}
+
+pub async fn test_run_save_operation_forgets_oldest(
+    _repository: &impl ProductRepository,
+    unit_of_work: &(dyn UnitOfWork + 'static),
+    _events: &Mutex<Vec<ProductEvent>>,
+) {
+    let operations = (0..=DEFAULT_UNDO_HISTORY_SIZE)
+        .map(|_| operation())
+        .collect::<Vec<_>>();
+
+    let saved = operations.clone();
+    unit_of_work
+        .run(move |tx| {
+            for operation in saved {
+                tx.save_operation(operation)?;
+            }
+
+            Ok::<_, ProductRepositoryError>(())
+        })
+        .await
+        .unwrap();
+
+    let (oldest_id, second_id, newest_id) = (
+        *operations[0].id(),
+        *operations[1].id(),
+        *operations[DEFAULT_UNDO_HISTORY_SIZE].id(),
+    );
+    let found = unit_of_work
+        .run(move |tx| {
+            Ok::<_, ProductRepositoryError>((
+                tx.find_operation_by_id(&oldest_id)?,
+                tx.find_operation_by_id(&second_id)?,
+                tx.find_operation_by_id(&newest_id)?,
+            ))
+        })
+        .await;
+
+    assert_eq!(
+        found,
+        Ok((
+            None,
+            Some(operations[1].clone()),
+            Some(operations[DEFAULT_UNDO_HISTORY_SIZE].clone()),
+        ))
+    );
+}
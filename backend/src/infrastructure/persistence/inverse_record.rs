@@ -0,0 +1,247 @@
+//! How the databases store [`InverseOperation`]s, as JSON, so every database keeps operations the same way
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    entities::{Product, StashItem},
+    errors::ProductRepositoryError,
+    value_objects::InverseOperation,
+};
+
+/// Turns an [`InverseOperation`] into the JSON it is stored as
+pub(super) fn to_json(inverse: &InverseOperation) -> Result<String, ProductRepositoryError> {
+    serde_json::to_string(&InverseRecord::from(inverse)).map_err(invalid)
+}
+
+/// Turns stored JSON back into the [`InverseOperation`] it was made from
+pub(super) fn from_json(json: &str) -> Result<InverseOperation, ProductRepositoryError> {
+    serde_json::from_str::<InverseRecord>(json)
+        .map_err(invalid)?
+        .try_into()
+}
+
+/// Shortcut for turning errors in stored operations into [`ProductRepositoryError::PersisteneError`]s
+fn invalid(error: impl ToString) -> ProductRepositoryError {
+    ProductRepositoryError::PersisteneError(error.to_string())
+}
+
+/// How an [`InverseOperation`] is stored, as JSON
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum InverseRecord {
+    RemoveStashItem {
+        product_id: String,
+        stash_item: StashItemRecord,
+    },
+    RevertStashItem {
+        product_id: String,
+        before: StashItemRecord,
+        after: StashItemRecord,
+    },
+    RestoreStashItem {
+        stash_item_id: String,
+    },
+    RevertProduct {
+        before: ProductRecord,
+        after: ProductRecord,
+    },
+    RestoreProduct {
+        product_id: String,
+    },
+    DeleteProduct {
+        product: ProductRecord,
+    },
+    Batch {
+        operations: Vec<InverseRecord>,
+    },
+}
+
+impl From<&InverseOperation> for InverseRecord {
+    fn from(inverse: &InverseOperation) -> Self {
+        match inverse {
+            InverseOperation::RemoveStashItem {
+                product_id,
+                stash_item,
+            } => Self::RemoveStashItem {
+                product_id: product_id.to_string(),
+                stash_item: stash_item.into(),
+            },
+            InverseOperation::RevertStashItem {
+                product_id,
+                before,
+                after,
+            } => Self::RevertStashItem {
+                product_id: product_id.to_string(),
+                before: before.into(),
+                after: after.into(),
+            },
+            InverseOperation::RestoreStashItem { stash_item_id } => Self::RestoreStashItem {
+                stash_item_id: stash_item_id.to_string(),
+            },
+            InverseOperation::RevertProduct { before, after } => Self::RevertProduct {
+                before: before.as_ref().into(),
+                after: after.as_ref().into(),
+            },
+            InverseOperation::RestoreProduct { product_id } => Self::RestoreProduct {
+                product_id: product_id.to_string(),
+            },
+            InverseOperation::DeleteProduct { product } => Self::DeleteProduct {
+                product: product.as_ref().into(),
+            },
+            InverseOperation::Batch { operations } => Self::Batch {
+                operations: operations.iter().map(Into::into).collect(),
+            },
+        }
+    }
+}
+
+impl TryFrom<InverseRecord> for InverseOperation {
+    type Error = ProductRepositoryError;
+
+    fn try_from(record: InverseRecord) -> Result<Self, Self::Error> {
+        Ok(match record {
+            InverseRecord::RemoveStashItem {
+                product_id,
+                stash_item,
+            } => Self::RemoveStashItem {
+                product_id: product_id.parse().map_err(invalid)?,
+                stash_item: stash_item.try_into()?,
+            },
+            InverseRecord::RevertStashItem {
+                product_id,
+                before,
+                after,
+            } => Self::RevertStashItem {
+                product_id: product_id.parse().map_err(invalid)?,
+                before: before.try_into()?,
+                after: after.try_into()?,
+            },
+            InverseRecord::RestoreStashItem { stash_item_id } => Self::RestoreStashItem {
+                stash_item_id: stash_item_id.parse()?,
+            },
+            InverseRecord::RevertProduct { before, after } => Self::RevertProduct {
+                before: Box::new(before.try_into()?),
+                after: Box::new(after.try_into()?),
+            },
+            InverseRecord::RestoreProduct { product_id } => Self::RestoreProduct {
+                product_id: product_id.parse().map_err(invalid)?,
+            },
+            InverseRecord::DeleteProduct { product } => Self::DeleteProduct {
+                product: Box::new(product.try_into()?),
+            },
+            InverseRecord::Batch { operations } => Self::Batch {
+                operations: operations
+                    .into_iter()
+                    .map(InverseOperation::try_from)
+                    .collect::<Result<_, _>>()?,
+            },
+        })
+    }
+}
+
+/// How a [`StashItem`] is stored as part of an operation
+#[derive(Debug, Serialize, Deserialize)]
+struct StashItemRecord {
+    id: String,
+    quantity: String,
+    expiry_date: Option<String>,
+    expiry_date_kind: String,
+    opened_on: Option<String>,
+}
+
+impl From<&StashItem> for StashItemRecord {
+    fn from(stash_item: &StashItem) -> Self {
+        Self {
+            id: stash_item.id().to_string(),
+            quantity: stash_item.quantity().to_string(),
+            expiry_date: stash_item.expiry_date().map(|date| date.to_string()),
+            expiry_date_kind: stash_item.expiry_date_kind().to_string(),
+            opened_on: stash_item.opened_on().map(|date| date.to_string()),
+        }
+    }
+}
+
+impl TryFrom<StashItemRecord> for StashItem {
+    type Error = ProductRepositoryError;
+
+    fn try_from(record: StashItemRecord) -> Result<Self, Self::Error> {
+        let mut stash_item = StashItem::new(
+            record.id.parse()?,
+            record.quantity.parse().map_err(invalid)?,
+            record
+                .expiry_date
+                .map(|date| date.parse())
+                .transpose()
+                .map_err(invalid)?,
+        );
+        stash_item.set_expiry_date_kind(record.expiry_date_kind.parse().map_err(invalid)?);
+        stash_item.set_opened_on(
+            record
+                .opened_on
+                .map(|date| date.parse())
+                .transpose()
+                .map_err(invalid)?,
+        );
+
+        Ok(stash_item)
+    }
+}
+
+/// How a [`Product`] is stored as part of an operation
+#[derive(Debug, Serialize, Deserialize)]
+struct ProductRecord {
+    id: String,
+    brand: String,
+    name: String,
+    unit: String,
+    stash_items: Vec<StashItemRecord>,
+    barcodes: Vec<String>,
+    shelf_life_after_opening_days: Option<u32>,
+    best_before_grace_period_days: Option<u32>,
+}
+
+impl From<&Product> for ProductRecord {
+    fn from(product: &Product) -> Self {
+        Self {
+            id: product.id().to_string(),
+            brand: product.brand().to_string(),
+            name: product.name().to_string(),
+            unit: product.unit().to_string(),
+            stash_items: product.stash_items().into_iter().map(Into::into).collect(),
+            barcodes: product
+                .barcodes()
+                .into_iter()
+                .map(ToString::to_string)
+                .collect(),
+            shelf_life_after_opening_days: *product.shelf_life_after_opening_days(),
+            best_before_grace_period_days: *product.best_before_grace_period_days(),
+        }
+    }
+}
+
+impl TryFrom<ProductRecord> for Product {
+    type Error = ProductRepositoryError;
+
+    fn try_from(record: ProductRecord) -> Result<Self, Self::Error> {
+        let stash_items = record
+            .stash_items
+            .into_iter()
+            .map(StashItem::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut product = Product::new(
+            record.id.parse().map_err(invalid)?,
+            record.brand.parse().map_err(invalid)?,
+            record.name,
+            stash_items,
+        );
+        product.set_unit(record.unit.parse().map_err(invalid)?);
+        product.set_shelf_life_after_opening_days(record.shelf_life_after_opening_days);
+        product.set_best_before_grace_period_days(record.best_before_grace_period_days);
+        for barcode in record.barcodes {
+            product.attach_barcode(barcode.parse().map_err(invalid)?);
+        }
+
+        Ok(product)
+    }
+}
@@ -0,0 +1,5 @@
+mod product_repository;
+mod unit_of_work;
+
+pub use product_repository::ProductRepository;
+pub use unit_of_work::UnitOfWork;
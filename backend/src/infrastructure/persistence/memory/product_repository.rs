@@ -0,0 +1,613 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::{Product, StashItem},
+    errors::ProductRepositoryError,
+    events::ProductEventDispatcher,
+    repositories::ProductRepository as ProductRepositoryTrait,
+    value_objects::{Operation, ProductId, TrashEntry},
+};
+
+/// Everything the repository holds. Products are stored by an internal key, like the databases do, and found through
+/// indexes on their IDs, barcodes and stash items. The indexes cover products in the trash as well. Products are
+/// stored without the events they recorded, which would otherwise be dispatched again when the products are saved next.
+/// The operations which can still be undone are kept along with the products, so units of work change both at once
+#[derive(Debug, Default, Clone)]
+pub(super) struct Store {
+    /// Key the next new product gets. Keys only grow, so they keep products in the order they were created
+    next_key: u64,
+    /// Products by their key
+    products: BTreeMap<u64, Product>,
+    /// Products in the trash by their key, along with when they were deleted
+    trash: BTreeMap<u64, (Product, NaiveDateTime)>,
+    /// Stash items in the trash by their ID, along with the key of their product and when they were deleted
+    trashed_stash_items: HashMap<Uuid, (u64, StashItem, NaiveDateTime)>,
+    /// Keys of products by their ID
+    ids: HashMap<ProductId, u64>,
+    /// Keys of products by the barcodes attached to them
+    barcodes: HashMap<ProductId, u64>,
+    /// Keys of products by the IDs of their stash items
+    stash_items: HashMap<Uuid, u64>,
+    /// The operations which can still be undone, oldest first
+    operations: VecDeque<Operation>,
+}
+
+impl Store {
+    /// Finds the key of a product by its ID, or by a barcode attached to it. The ID of a product takes precedence.
+    /// Products in the trash are left out
+    pub(super) fn find_key(&self, id: &ProductId) -> Option<u64> {
+        [self.ids.get(id), self.barcodes.get(id)]
+            .into_iter()
+            .flatten()
+            .copied()
+            .find(|key| self.products.contains_key(key))
+    }
+
+    /// Finds a product by its ID, or by a barcode attached to it. Products in the trash are left out
+    pub(super) fn find_by_id(&self, id: &ProductId) -> Option<Product> {
+        self.find_key(id)
+            .and_then(|key| self.products.get(&key))
+            .cloned()
+    }
+
+    /// Finds the product a stash item belongs to. Products in the trash are left out
+    pub(super) fn find_by_stash_item_id(&self, stash_item_id: &Uuid) -> Option<Product> {
+        self.stash_items
+            .get(stash_item_id)
+            .and_then(|key| self.products.get(key))
+            .cloned()
+    }
+
+    /// Gets a product by its key, whether it is in the trash or not
+    fn product_mut(&mut self, key: u64) -> Option<&mut Product> {
+        match self.products.get_mut(&key) {
+            Some(product) => Some(product),
+            None => self.trash.get_mut(&key).map(|(product, _)| product),
+        }
+    }
+
+    /// Gets the products matching a predicate
+    ///
+    /// # Returns
+    /// The products that were found, in the order they were created
+    fn find_where(&self, predicate: impl Fn(&Product) -> bool) -> Vec<Product> {
+        self.products
+            .values()
+            .filter(|product| predicate(product))
+            .cloned()
+            .collect()
+    }
+
+    /// Finds all products with at least one stash item whose date, as given by a function, is within the given date
+    /// interval
+    ///
+    /// # Parameters
+    /// - `date`: Gets the date of a stash item of a product
+    /// - `after`: The start of the date range, inclusive
+    /// - `before`: The end of the date range, exclusive
+    ///
+    /// # Returns
+    /// A list of products with at least one stash item dated within the given date interval
+    fn find_in_interval(
+        &self,
+        date: impl Fn(&Product, &StashItem) -> Option<NaiveDate>,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        if after.is_none() && before.is_none() {
+            return Err(ProductRepositoryError::InvalidDateInterval);
+        }
+
+        Ok(self.find_where(|product| {
+            product.stash_items().into_iter().any(|stash_item| {
+                date(product, stash_item).is_some_and(|date| {
+                    after.is_none_or(|after| date >= after)
+                        && before.is_none_or(|before| date < before)
+                })
+            })
+        }))
+    }
+
+    /// Saves a [`Product`]. If the product already exists, it will be updated. Barcodes and stash items the product
+    /// has are taken away from any other product they belonged to
+    pub(super) fn save_product(&mut self, product: Product) -> Result<(), ProductRepositoryError> {
+        // Stash items are unique by their dates within a product, just like the unique index in the databases makes
+        // them
+        let mut dates = HashSet::new();
+        for stash_item in product.stash_items() {
+            if !dates.insert((stash_item.expiry_date(), stash_item.opened_on())) {
+                return Err(ProductRepositoryError::PersisteneError(format!(
+                    "Product {} has several stash items with the same dates",
+                    product.id()
+                )));
+            }
+        }
+
+        // A product in the trash with the same ID is replaced by this one
+        if let Some(key) = self.ids.get(product.id()).copied() {
+            if self.trash.contains_key(&key) {
+                self.delete_product_by_key(key);
+            }
+        }
+
+        // The ID of a product takes precedence over the same barcode attached to another product
+        if let Some(owner) = self.barcodes.remove(product.id()) {
+            self.take_barcode(owner, product.id());
+        }
+
+        let key = match self.ids.get(product.id()) {
+            Some(key) => *key,
+            None => {
+                let key = self.next_key;
+                self.next_key += 1;
+                self.ids.insert(product.id().clone(), key);
+                key
+            }
+        };
+
+        // Forget the barcodes and stash items the product no longer has
+        if let Some(previous) = self.products.remove(&key) {
+            for barcode in previous.barcodes() {
+                if !product.has_barcode(barcode) {
+                    self.barcodes.remove(barcode);
+                }
+            }
+            for stash_item in previous.stash_items() {
+                if !product.has_stash_item(stash_item.id()) {
+                    self.stash_items.remove(stash_item.id());
+                }
+            }
+        }
+
+        // Move barcodes and stash items from other products to this one
+        for barcode in product.barcodes() {
+            if let Some(owner) = self.barcodes.insert(barcode.clone(), key) {
+                if owner != key {
+                    self.take_barcode(owner, barcode);
+                }
+            }
+        }
+        for stash_item in product.stash_items() {
+            // Saving a stash item with the ID of one in the trash replaces that one
+            self.trashed_stash_items.remove(stash_item.id());
+
+            if let Some(owner) = self.stash_items.insert(*stash_item.id(), key) {
+                if owner != key {
+                    if let Some(owner) = self.products.get_mut(&owner) {
+                        owner.remove_stash_item(stash_item.id()).ok();
+                        owner.take_events();
+                    }
+                }
+            }
+        }
+
+        self.products.insert(key, product);
+
+        Ok(())
+    }
+
+    /// Saves the product a product is merged into, and permanently deletes the merged product
+    pub(super) fn merge_product(
+        &mut self,
+        source_id: &ProductId,
+        target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        // Find the source before its ID becomes a barcode of the target
+        let source_key = self.find_key(source_id);
+
+        self.save_product(target)?;
+
+        if let Some(source_key) = source_key {
+            self.delete_product_by_key(source_key);
+        }
+
+        Ok(())
+    }
+
+    /// Detaches a barcode from the product it was attached to, without touching the index of barcodes
+    fn take_barcode(&mut self, owner: u64, barcode: &ProductId) {
+        if let Some(owner) = self.product_mut(owner) {
+            owner.detach_barcode(barcode).ok();
+        }
+    }
+
+    /// Permanently deletes a product by its internal key, along with all its stash items and barcodes
+    fn delete_product_by_key(&mut self, key: u64) {
+        let product = match self.products.remove(&key) {
+            Some(product) => product,
+            None => match self.trash.remove(&key) {
+                Some((product, _)) => product,
+                None => return,
+            },
+        };
+
+        self.ids.remove(product.id());
+        self.barcodes.retain(|_, owner| *owner != key);
+        self.stash_items.retain(|_, owner| *owner != key);
+        self.trashed_stash_items
+            .retain(|_, (owner, _, _)| *owner != key);
+    }
+
+    /// Moves a product to the trash, along with its stash items. Its barcodes stay attached to it, unless other
+    /// products take them
+    pub(super) fn trash_product(&mut self, id: &ProductId, now: NaiveDateTime) {
+        if let Some(key) = self.find_key(id) {
+            if let Some(product) = self.products.remove(&key) {
+                self.trash.insert(key, (product, now));
+            }
+        }
+    }
+
+    /// Moves a stash item of a product which is not in the trash itself to the trash
+    pub(super) fn trash_stash_item(&mut self, stash_item_id: &Uuid, now: NaiveDateTime) {
+        let Some(key) = self.stash_items.get(stash_item_id).copied() else {
+            return;
+        };
+        let Some(product) = self.products.get_mut(&key) else {
+            return;
+        };
+
+        if let Ok(stash_item) = product.remove_stash_item(stash_item_id) {
+            product.take_events();
+            self.stash_items.remove(stash_item_id);
+            self.trashed_stash_items
+                .insert(*stash_item_id, (key, stash_item, now));
+        }
+    }
+
+    /// Gets everything in the trash, most recently deleted first
+    fn find_trash(&self) -> Vec<TrashEntry> {
+        let products = self
+            .trash
+            .values()
+            .map(|(product, deleted_at)| TrashEntry::Product {
+                product: product.clone(),
+                deleted_at: *deleted_at,
+            });
+        // Stash items of products in the trash are only restored along with their product
+        let stash_items =
+            self.trashed_stash_items
+                .values()
+                .filter_map(|(key, stash_item, deleted_at)| {
+                    Some(TrashEntry::StashItem {
+                        product_id: self.products.get(key)?.id().clone(),
+                        stash_item: stash_item.clone(),
+                        deleted_at: *deleted_at,
+                    })
+                });
+
+        let mut entries = products.chain(stash_items).collect::<Vec<_>>();
+        entries.sort_by(|a, b| {
+            b.deleted_at()
+                .cmp(a.deleted_at())
+                .then_with(|| a.id().cmp(&b.id()))
+        });
+
+        entries
+    }
+
+    /// Restores a product from the trash
+    pub(super) fn restore_product(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        let key = self
+            .ids
+            .get(id)
+            .copied()
+            .filter(|key| self.trash.contains_key(key))
+            .ok_or(ProductRepositoryError::ProductNotFound)?;
+
+        // Another product may have been given the ID as a barcode since
+        if self.find_key(id).is_some() {
+            return Err(ProductRepositoryError::ProductAlreadyExists);
+        }
+
+        if let Some((product, _)) = self.trash.remove(&key) {
+            self.products.insert(key, product);
+        }
+
+        Ok(())
+    }
+
+    /// Restores a stash item from the trash, as long as its product is not in the trash
+    pub(super) fn restore_stash_item(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let (key, stash_item, _) = self
+            .trashed_stash_items
+            .get(stash_item_id)
+            .filter(|(key, _, _)| self.products.contains_key(key))
+            .cloned()
+            .ok_or(ProductRepositoryError::StashItemNotFound)?;
+
+        // The product may have gotten another stash item with the same dates since
+        let product = self
+            .products
+            .get_mut(&key)
+            .ok_or(ProductRepositoryError::StashItemNotFound)?;
+        product
+            .add_stash_item(stash_item)
+            .map_err(|_| ProductRepositoryError::DuplicateExpiryDateError)?;
+        product.take_events();
+
+        self.trashed_stash_items.remove(stash_item_id);
+        self.stash_items.insert(*stash_item_id, key);
+
+        Ok(())
+    }
+
+    /// Records an operation, forgetting the oldest operations beyond the given capacity
+    pub(super) fn save_operation(&mut self, operation: Operation, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+
+        self.operations.push_back(operation);
+        while self.operations.len() > capacity {
+            self.operations.pop_front();
+        }
+    }
+
+    /// Finds an operation by its ID
+    pub(super) fn find_operation_by_id(&self, id: &Uuid) -> Option<Operation> {
+        self.operations
+            .iter()
+            .find(|operation| operation.id() == id)
+            .cloned()
+    }
+
+    /// Forgets an operation by its ID
+    pub(super) fn delete_operation_by_id(&mut self, id: &Uuid) {
+        self.operations.retain(|operation| operation.id() != id);
+    }
+
+    /// Permanently deletes the products and stash items which went to the trash before the given time
+    ///
+    /// # Returns
+    /// The number of products and stash items purged
+    fn purge_trash(&mut self, deleted_before: NaiveDateTime) -> usize {
+        let keys = self
+            .trash
+            .iter()
+            .filter(|(_, (_, deleted_at))| *deleted_at < deleted_before)
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        for key in &keys {
+            self.delete_product_by_key(*key);
+        }
+
+        let stash_items = self.trashed_stash_items.len();
+        self.trashed_stash_items
+            .retain(|_, (_, _, deleted_at)| *deleted_at >= deleted_before);
+
+        keys.len() + stash_items - self.trashed_stash_items.len()
+    }
+}
+
+/// A repository for [`Product`]s keeping them in memory. Nothing is persisted, so it is meant for tests and demos
+#[derive(Debug, Default)]
+pub struct ProductRepository {
+    /// The products and their indexes, shared with the units of work on this repository
+    pub(super) store: Arc<RwLock<Store>>,
+    /// Gets the events of saved products
+    event_dispatcher: Arc<ProductEventDispatcher>,
+}
+
+impl ProductRepository {
+    /// Creates a new, empty [`ProductRepository`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the repository dispatch the events of the products it saves, once they are saved
+    pub fn with_event_dispatcher(mut self, event_dispatcher: Arc<ProductEventDispatcher>) -> Self {
+        self.event_dispatcher = event_dispatcher;
+        self
+    }
+
+    /// Runs some work reading the store
+    fn read<T>(&self, work: impl FnOnce(&Store) -> T) -> Result<T, ProductRepositoryError> {
+        let store = self
+            .store
+            .read()
+            .map_err(|err| ProductRepositoryError::PersisteneError(err.to_string()))?;
+
+        Ok(work(&store))
+    }
+
+    /// Runs some work changing the store. The work is done on a copy, which replaces the store once the work
+    /// succeeds, so failed work leaves no trace like a rolled back transaction
+    fn update<T>(
+        &self,
+        work: impl FnOnce(&mut Store) -> Result<T, ProductRepositoryError>,
+    ) -> Result<T, ProductRepositoryError> {
+        let mut store = self
+            .store
+            .write()
+            .map_err(|err| ProductRepositoryError::PersisteneError(err.to_string()))?;
+
+        let mut copy = store.clone();
+        let result = work(&mut copy)?;
+        *store = copy;
+
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl ProductRepositoryTrait for ProductRepository {
+    async fn find_all_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.read(|store| store.find_where(|product| !product.stash_items().is_empty()))
+    }
+
+    async fn find_by_id(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
+        self.read(|store| store.find_by_id(id))
+    }
+
+    async fn find_by_ids(&self, ids: &[ProductId]) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.read(|store| store.find_where(|product| ids.contains(product.id())))
+    }
+
+    async fn find_by_stash_item_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<Option<Product>, ProductRepositoryError> {
+        self.read(|store| store.find_by_stash_item_id(stash_item_id))
+    }
+
+    async fn find_expiring_in_interval(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.read(|store| store.find_in_interval(Product::effective_expiry_date, after, before))?
+    }
+
+    async fn find_unsafe_in_interval(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.read(|store| store.find_in_interval(Product::unsafe_date, after, before))?
+    }
+
+    async fn exists_by_id(&self, id: &ProductId) -> Result<bool, ProductRepositoryError> {
+        self.read(|store| store.find_key(id).is_some())
+    }
+
+    async fn save(&self, mut product: Product) -> Result<(), ProductRepositoryError> {
+        let events = product.take_events();
+
+        self.update(|store| store.save_product(product))?;
+
+        self.event_dispatcher.dispatch(&events);
+        Ok(())
+    }
+
+    async fn save_all(&self, mut products: Vec<Product>) -> Result<(), ProductRepositoryError> {
+        let events = products
+            .iter_mut()
+            .flat_map(Product::take_events)
+            .collect::<Vec<_>>();
+
+        self.update(|store| {
+            products
+                .into_iter()
+                .try_for_each(|product| store.save_product(product))
+        })?;
+
+        self.event_dispatcher.dispatch(&events);
+        Ok(())
+    }
+
+    async fn merge_into(
+        &self,
+        source_id: &ProductId,
+        mut target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        let events = target.take_events();
+
+        self.update(|store| store.merge_product(source_id, target))?;
+
+        self.event_dispatcher.dispatch(&events);
+        Ok(())
+    }
+
+    async fn delete_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        self.update(|store| {
+            store.trash_product(id, chrono::Utc::now().naive_utc());
+
+            Ok(())
+        })
+    }
+
+    async fn delete_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        self.update(|store| {
+            store.trash_stash_item(stash_item_id, chrono::Utc::now().naive_utc());
+
+            Ok(())
+        })
+    }
+
+    async fn find_trash(&self) -> Result<Vec<TrashEntry>, ProductRepositoryError> {
+        self.read(Store::find_trash)
+    }
+
+    async fn restore_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        self.update(|store| store.restore_product(id))
+    }
+
+    async fn restore_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        self.update(|store| store.restore_stash_item(stash_item_id))
+    }
+
+    async fn purge_trash(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError> {
+        self.update(|store| Ok(store.purge_trash(deleted_before)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            entities::{FakeProduct, FakeStashItem},
+            events::ProductEvent,
+        },
+        infrastructure::persistence::conformance::product_repository_conformance_tests,
+    };
+
+    use super::*;
+
+    product_repository_conformance_tests!(ProductRepository::new());
+
+    #[actix_web::test]
+    async fn test_merge_into_dispatches_events_of_both_products() {
+        let (event_dispatcher, events) = ProductEventDispatcher::collecting();
+        let repository = ProductRepository::new().with_event_dispatcher(event_dispatcher);
+        let stash_item = FakeStashItem::new().build();
+        let source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        let mut target = FakeProduct::new().with_stash_items(vec![]).build();
+        repository
+            .save_all(vec![source.clone(), target.clone()])
+            .await
+            .unwrap();
+
+        let source_id = source.id().clone();
+        source.merge_into(&mut target).unwrap();
+        repository
+            .merge_into(&source_id, target.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ProductEvent::StashItemRemoved {
+                    product_id: source_id,
+                    stash_item: stash_item.clone()
+                },
+                ProductEvent::StashItemAdded {
+                    product_id: target.id().clone(),
+                    stash_item
+                }
+            ]
+        );
+    }
+}
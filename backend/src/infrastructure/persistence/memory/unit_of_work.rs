@@ -0,0 +1,226 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::Product,
+    errors::ProductRepositoryError,
+    events::{ProductEvent, ProductEventDispatcher},
+    repositories::{
+        ProductTransaction as ProductTransactionTrait, UnitOfWork as UnitOfWorkTrait, Work,
+        WorkResult, DEFAULT_UNDO_HISTORY_SIZE,
+    },
+    value_objects::{Operation, ProductId},
+};
+
+use super::product_repository::{ProductRepository, Store};
+
+/// The products as seen from within a unit of work, which works on a copy of the store
+struct ProductTransaction<'a> {
+    store: &'a mut Store,
+    /// How many operations are kept
+    undo_history_size: usize,
+    /// Events of the products saved, to be dispatched once the copy replaces the store
+    events: Vec<ProductEvent>,
+}
+
+impl ProductTransactionTrait for ProductTransaction<'_> {
+    fn find_by_id(&mut self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
+        Ok(self.store.find_by_id(id))
+    }
+
+    fn exists_by_id(&mut self, id: &ProductId) -> Result<bool, ProductRepositoryError> {
+        Ok(self.store.find_key(id).is_some())
+    }
+
+    fn find_by_stash_item_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<Option<Product>, ProductRepositoryError> {
+        Ok(self.store.find_by_stash_item_id(stash_item_id))
+    }
+
+    fn save(&mut self, mut product: Product) -> Result<(), ProductRepositoryError> {
+        let events = product.take_events();
+
+        self.store.save_product(product)?;
+
+        self.events.extend(events);
+        Ok(())
+    }
+
+    fn merge_into(
+        &mut self,
+        source_id: &ProductId,
+        mut target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        let events = target.take_events();
+
+        self.store.merge_product(source_id, target)?;
+
+        self.events.extend(events);
+        Ok(())
+    }
+
+    fn delete_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        self.store.trash_product(id, chrono::Utc::now().naive_utc());
+
+        Ok(())
+    }
+
+    fn delete_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        self.store
+            .trash_stash_item(stash_item_id, chrono::Utc::now().naive_utc());
+
+        Ok(())
+    }
+
+    fn restore_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        self.store.restore_product(id)
+    }
+
+    fn restore_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        self.store.restore_stash_item(stash_item_id)
+    }
+
+    fn save_operation(&mut self, operation: Operation) -> Result<(), ProductRepositoryError> {
+        self.store.save_operation(operation, self.undo_history_size);
+
+        Ok(())
+    }
+
+    fn find_operation_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Operation>, ProductRepositoryError> {
+        Ok(self.store.find_operation_by_id(id))
+    }
+
+    fn delete_operation_by_id(&mut self, id: &Uuid) -> Result<(), ProductRepositoryError> {
+        self.store.delete_operation_by_id(id);
+
+        Ok(())
+    }
+}
+
+/// Runs work on the products of an in-memory [`ProductRepository`]. The store stays locked while the work runs, and
+/// the work is done on a copy which replaces the store once the work succeeds
+#[derive(Debug)]
+pub struct UnitOfWork {
+    /// The store of the repository
+    store: Arc<RwLock<Store>>,
+    /// How many of the most recent operations are kept, so they can be undone
+    undo_history_size: usize,
+    /// Gets the events of the products saved by the work, once it is done
+    event_dispatcher: Arc<ProductEventDispatcher>,
+}
+
+impl UnitOfWork {
+    /// Creates a new [`UnitOfWork`] working on the products of a repository
+    pub fn new(repository: &ProductRepository) -> Self {
+        Self {
+            store: repository.store.clone(),
+            undo_history_size: DEFAULT_UNDO_HISTORY_SIZE,
+            event_dispatcher: Arc::default(),
+        }
+    }
+
+    /// Sets how many of the most recent operations are kept, so they can be undone
+    pub fn with_undo_history_size(mut self, undo_history_size: usize) -> Self {
+        self.undo_history_size = undo_history_size;
+        self
+    }
+
+    /// Makes the unit of work dispatch the events of the products it saves, once the work is done
+    pub fn with_event_dispatcher(mut self, event_dispatcher: Arc<ProductEventDispatcher>) -> Self {
+        self.event_dispatcher = event_dispatcher;
+        self
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkTrait for UnitOfWork {
+    async fn run_boxed(&self, work: Work) -> Result<WorkResult, ProductRepositoryError> {
+        let (result, events) = {
+            let mut store = self
+                .store
+                .write()
+                .map_err(|err| ProductRepositoryError::PersisteneError(err.to_string()))?;
+
+            let mut copy = store.clone();
+            let mut transaction = ProductTransaction {
+                store: &mut copy,
+                undo_history_size: self.undo_history_size,
+                events: vec![],
+            };
+            let result = work(&mut transaction);
+            let events = transaction.events;
+
+            if result.is_ok() {
+                *store = copy;
+            }
+
+            (result, events)
+        };
+
+        if result.is_ok() {
+            self.event_dispatcher.dispatch(&events);
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::value_objects::InverseOperation,
+        infrastructure::persistence::conformance::unit_of_work_conformance_tests,
+    };
+
+    use super::*;
+
+    unit_of_work_conformance_tests!(event_dispatcher => {
+        let repository = ProductRepository::new();
+        let unit_of_work = UnitOfWork::new(&repository).with_event_dispatcher(event_dispatcher);
+
+        (repository, unit_of_work)
+    });
+
+    #[actix_web::test]
+    async fn test_with_undo_history_size() {
+        let unit_of_work: &dyn UnitOfWorkTrait =
+            &UnitOfWork::new(&ProductRepository::new()).with_undo_history_size(1);
+        let (older, newer) = (
+            Operation::new(InverseOperation::RestoreStashItem {
+                stash_item_id: Uuid::new_v4(),
+            }),
+            Operation::new(InverseOperation::RestoreStashItem {
+                stash_item_id: Uuid::new_v4(),
+            }),
+        );
+
+        let (older_id, newer_id) = (*older.id(), *newer.id());
+        let saved_newer = newer.clone();
+        let found = unit_of_work
+            .run(move |tx| {
+                tx.save_operation(older)?;
+                tx.save_operation(saved_newer)?;
+
+                Ok::<_, ProductRepositoryError>((
+                    tx.find_operation_by_id(&older_id)?,
+                    tx.find_operation_by_id(&newer_id)?,
+                ))
+            })
+            .await;
+
+        assert_eq!(found, Ok((None, Some(newer))));
+    }
+}
@@ -1 +1,7 @@
+#[cfg(any(test, feature = "testing"))]
+pub mod conformance;
+mod inverse_record;
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod postgres;
 pub mod sqlite;
@@ -0,0 +1,104 @@
+use deadpool_postgres::PoolError;
+
+use crate::domain::errors::ProductRepositoryError;
+
+/// Migrations bringing the database schema up to date, in order. The schema version of the database is the highest
+/// version in `schema_versions`, which is the number of migrations applied to it
+const MIGRATIONS: &[&str] = &[
+    // 1: The schema as it is in SQLite at the time PostgreSQL support was added. Expiry and opening dates are part of
+    // the uniqueness of a stash item, with a missing date counting as a date of its own
+    "CREATE TABLE products (
+        key BIGSERIAL PRIMARY KEY,
+        id TEXT NOT NULL UNIQUE,
+        brand TEXT NOT NULL,
+        name TEXT NOT NULL,
+        unit TEXT NOT NULL DEFAULT 'pieces',
+        shelf_life_after_opening_days BIGINT,
+        best_before_grace_period_days BIGINT,
+        created_at TIMESTAMP NOT NULL,
+        updated_at TIMESTAMP
+    );
+
+    CREATE TABLE product_barcodes (
+        barcode TEXT PRIMARY KEY,
+        product_key BIGINT NOT NULL REFERENCES products(key),
+        created_at TIMESTAMP NOT NULL
+    );
+
+    CREATE TABLE stash_items (
+        id UUID PRIMARY KEY,
+        product_key BIGINT NOT NULL REFERENCES products(key),
+        quantity TEXT NOT NULL,
+        expiry_date DATE,
+        expiry_date_kind TEXT NOT NULL DEFAULT 'best_before',
+        opened_on DATE,
+        created_at TIMESTAMP NOT NULL,
+        updated_at TIMESTAMP
+    );
+
+    CREATE INDEX stash_items_product_key ON stash_items (product_key);
+
+    CREATE UNIQUE INDEX stash_items_product_key_expiry_date_opened_on
+        ON stash_items (product_key, COALESCE(expiry_date, 'infinity'), COALESCE(opened_on, 'infinity'));",
+    // 2: Deleted products and stash items go to the trash instead of being removed right away. Stash items in the
+    // trash no longer count towards the uniqueness of the dates of stash items
+    "ALTER TABLE products ADD COLUMN deleted_at TIMESTAMP;
+
+    ALTER TABLE stash_items ADD COLUMN deleted_at TIMESTAMP;
+
+    DROP INDEX stash_items_product_key_expiry_date_opened_on;
+
+    CREATE UNIQUE INDEX stash_items_product_key_expiry_date_opened_on
+        ON stash_items (product_key, COALESCE(expiry_date, 'infinity'), COALESCE(opened_on, 'infinity'))
+        WHERE deleted_at IS NULL;",
+    // 3: The operations which can still be undone, oldest first by key
+    "CREATE TABLE operations (
+        key BIGSERIAL PRIMARY KEY,
+        id UUID NOT NULL UNIQUE,
+        inverse TEXT NOT NULL
+    );",
+];
+
+/// Sets up the database, applying all migrations it has not yet seen. Several instances may set up the same database
+/// at once, in which case the first one applies the migrations and the others wait for it
+pub async fn setup_db(client: &mut tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+    client
+        .batch_execute("CREATE TABLE IF NOT EXISTS schema_versions (version INTEGER PRIMARY KEY)")
+        .await?;
+
+    let tx = client.transaction().await?;
+    tx.batch_execute("LOCK TABLE schema_versions IN EXCLUSIVE MODE")
+        .await?;
+
+    let version: i32 = tx
+        .query_one("SELECT COALESCE(MAX(version), 0) FROM schema_versions", &[])
+        .await?
+        .get(0);
+
+    for (index, migration) in MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(version.try_into().unwrap_or(0))
+    {
+        tx.batch_execute(migration).await?;
+        tx.execute(
+            "INSERT INTO schema_versions (version) VALUES ($1)",
+            &[&(index as i32 + 1)],
+        )
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+impl From<tokio_postgres::Error> for ProductRepositoryError {
+    fn from(error: tokio_postgres::Error) -> Self {
+        Self::PersisteneError(error.to_string())
+    }
+}
+
+impl From<PoolError> for ProductRepositoryError {
+    fn from(error: PoolError) -> Self {
+        Self::PersisteneError(error.to_string())
+    }
+}
@@ -0,0 +1,11 @@
+pub mod db;
+mod operations;
+pub mod pool;
+mod product_repository;
+#[cfg(test)]
+mod test_server;
+mod to_from_sql;
+mod unit_of_work;
+
+pub use product_repository::ProductRepository;
+pub use unit_of_work::UnitOfWork;
@@ -0,0 +1,91 @@
+//! The operations which can still be undone, kept in the same database as the products so they are recorded and
+//! undone in the same transaction as the changes they undo
+
+use deadpool_postgres::Transaction;
+use uuid::Uuid;
+
+use crate::{
+    domain::{errors::ProductRepositoryError, value_objects::Operation},
+    infrastructure::persistence::inverse_record,
+};
+
+/// Records an operation, forgetting the oldest operations beyond the given capacity
+///
+/// # Parameters
+/// - `tx`: The transaction to use
+/// - `operation`: The operation to record
+/// - `capacity`: How many operations to keep
+pub(super) async fn save(
+    tx: &Transaction<'_>,
+    operation: &Operation,
+    capacity: usize,
+) -> Result<(), ProductRepositoryError> {
+    if capacity == 0 {
+        return Ok(());
+    }
+
+    let stmt = tx
+        .prepare_cached("INSERT INTO operations (id, inverse) VALUES ($1, $2)")
+        .await?;
+    tx.execute(
+        &stmt,
+        &[
+            operation.id(),
+            &inverse_record::to_json(operation.inverse())?,
+        ],
+    )
+    .await?;
+
+    // Forget the oldest operations beyond the capacity
+    let stmt = tx
+        .prepare_cached(
+            "DELETE FROM operations WHERE key NOT IN (SELECT key FROM operations ORDER BY key DESC LIMIT $1)",
+        )
+        .await?;
+    tx.execute(&stmt, &[&i64::try_from(capacity).unwrap_or(i64::MAX)])
+        .await?;
+
+    Ok(())
+}
+
+/// Finds an operation by its ID
+///
+/// # Parameters
+/// - `tx`: The transaction to use
+/// - `id`: The ID of the operation
+pub(super) async fn find_by_id(
+    tx: &Transaction<'_>,
+    id: &Uuid,
+) -> Result<Option<Operation>, ProductRepositoryError> {
+    let stmt = tx
+        .prepare_cached("SELECT inverse FROM operations WHERE id = $1")
+        .await?;
+
+    tx.query_opt(&stmt, &[id])
+        .await?
+        .map(|row| {
+            let inverse: String = row.try_get(0)?;
+            Ok(Operation::with_id(
+                *id,
+                inverse_record::from_json(&inverse)?,
+            ))
+        })
+        .transpose()
+}
+
+/// Forgets an operation by its ID
+///
+/// # Parameters
+/// - `tx`: The transaction to use
+/// - `id`: The ID of the operation
+pub(super) async fn delete_by_id(
+    tx: &Transaction<'_>,
+    id: &Uuid,
+) -> Result<(), ProductRepositoryError> {
+    let stmt = tx
+        .prepare_cached("DELETE FROM operations WHERE id = $1")
+        .await?;
+    tx.execute(&stmt, &[id]).await?;
+
+    Ok(())
+}
@@ -0,0 +1,916 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use deadpool_postgres::Transaction;
+use tokio_postgres::{types::ToSql, Row};
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::{Product, StashItem},
+    errors::ProductRepositoryError,
+    events::ProductEventDispatcher,
+    repositories::ProductRepository as ProductRepositoryTrait,
+    value_objects::{ProductId, TrashEntry},
+};
+
+use super::pool::{Pool, PooledConnection};
+
+/// Parameters for a query
+type Params<'a> = [&'a (dyn ToSql + Sync)];
+
+/// SQL expression for the date a stash item actually expires, mirroring [`Product::effective_expiry_date`]. Expects
+/// the stash item as `s` and its product as `p`. Is `NULL` for stash items which do not expire
+const EFFECTIVE_EXPIRY_DATE: &str =
+    "CASE WHEN s.opened_on IS NOT NULL AND p.shelf_life_after_opening_days IS NOT NULL \
+    THEN LEAST(s.expiry_date, s.opened_on + p.shelf_life_after_opening_days::INTEGER) \
+    ELSE s.expiry_date END";
+
+/// SQL expression for the date a stash item is no longer safe, mirroring [`Product::unsafe_date`]. Expects the stash
+/// item as `s` and its product as `p`. Is `NULL` for stash items which never become unsafe
+fn unsafe_date() -> String {
+    format!(
+        "CASE WHEN s.expiry_date_kind = 'use_by' THEN {0} \
+        WHEN p.best_before_grace_period_days IS NOT NULL \
+        THEN {0} + p.best_before_grace_period_days::INTEGER \
+        ELSE NULL END",
+        EFFECTIVE_EXPIRY_DATE
+    )
+}
+
+/// Converts a number of days from the database, which PostgreSQL has no unsigned type for
+fn days_from_sql(days: Option<i64>) -> Result<Option<u32>, ProductRepositoryError> {
+    days.map(u32::try_from)
+        .transpose()
+        .map_err(|err| ProductRepositoryError::PersisteneError(err.to_string()))
+}
+
+/// A repository for [`Product`]s using PostgreSQL as the underlying storage.
+pub struct ProductRepository {
+    /// Connections to the database
+    pool: Pool,
+    /// Gets the events of saved products
+    event_dispatcher: Arc<ProductEventDispatcher>,
+}
+
+impl ProductRepository {
+    /// Creates a new [`ProductRepository`]
+    pub fn new(pool: Pool) -> Self {
+        Self {
+            pool,
+            event_dispatcher: Arc::default(),
+        }
+    }
+
+    /// Makes the repository dispatch the events of the products it saves, once they are saved
+    pub fn with_event_dispatcher(mut self, event_dispatcher: Arc<ProductEventDispatcher>) -> Self {
+        self.event_dispatcher = event_dispatcher;
+        self
+    }
+
+    /// Shortcut to get a connection to the database from the pool
+    async fn conn(&self) -> Result<PooledConnection, ProductRepositoryError> {
+        Ok(self.pool.get().await?)
+    }
+
+    /// Converts a raw database row into a [`Product`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the row contains invalid data, or if the data cannot be parsed into a
+    /// [`Product`]
+    fn row_to_product(row: &Row) -> Result<Product, ProductRepositoryError> {
+        let mut product = Product::new(
+            row.try_get("id")?,
+            row.try_get("brand")?,
+            row.try_get("name")?,
+            vec![],
+        );
+        product.set_unit(row.try_get("unit")?);
+        product.set_shelf_life_after_opening_days(days_from_sql(
+            row.try_get("shelf_life_after_opening_days")?,
+        )?);
+        product.set_best_before_grace_period_days(days_from_sql(
+            row.try_get("best_before_grace_period_days")?,
+        )?);
+
+        Ok(product)
+    }
+
+    /// Finds the internal key of a product by its ID, or by a barcode attached to it. Products in the trash are left
+    /// out
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `id`: The ID or barcode of the product
+    ///
+    /// # Returns
+    /// The key of the product, if found
+    pub(super) async fn find_key(
+        tx: &Transaction<'_>,
+        id: &ProductId,
+    ) -> Result<Option<i64>, ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached(
+                "SELECT COALESCE((SELECT key FROM products WHERE id = $1 AND deleted_at IS NULL), (SELECT b.product_key FROM product_barcodes b JOIN products p ON p.key = b.product_key WHERE b.barcode = $1 AND p.deleted_at IS NULL))",
+            )
+            .await?;
+
+        Ok(tx.query_one(&stmt, &[id]).await?.try_get(0)?)
+    }
+
+    /// Gets the products matching an SQL condition, along with their barcodes and stash items. Products in the trash
+    /// are left out
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
+    ///
+    /// # Returns
+    /// The products that were found, in the order they were created
+    async fn find_where(
+        tx: &Transaction<'_>,
+        condition: &str,
+        params: &Params<'_>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        Ok(ProductRepository::find_keyed_where(
+            tx,
+            &format!("deleted_at IS NULL AND ({})", condition),
+            params,
+        )
+        .await?
+        .into_iter()
+        .map(|(_, product)| product)
+        .collect())
+    }
+
+    /// Gets the products matching an SQL condition, along with their barcodes and stash items, whether they are in
+    /// the trash or not. Loads everything with three queries, no matter how many products match
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
+    ///
+    /// # Returns
+    /// The products that were found, along with their internal keys, in the order they were created
+    async fn find_keyed_where(
+        tx: &Transaction<'_>,
+        condition: &str,
+        params: &Params<'_>,
+    ) -> Result<Vec<(i64, Product)>, ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached(&format!(
+                "SELECT key, id, brand, name, unit, shelf_life_after_opening_days, best_before_grace_period_days FROM products WHERE {} ORDER BY key",
+                condition
+            ))
+            .await?;
+        let products = tx
+            .query(&stmt, params)
+            .await?
+            .iter()
+            .map(|row| Ok((row.try_get("key")?, ProductRepository::row_to_product(row)?)))
+            .collect::<Result<Vec<(i64, Product)>, ProductRepositoryError>>()?;
+
+        let mut barcodes = ProductRepository::get_barcodes(tx, condition, params).await?;
+        let mut stash_items = ProductRepository::get_stash_items(tx, condition, params).await?;
+
+        Ok(products
+            .into_iter()
+            .map(|(key, mut product)| {
+                for barcode in barcodes.remove(&key).unwrap_or_default() {
+                    product.attach_barcode(barcode);
+                }
+                for stash_item in stash_items.remove(&key).unwrap_or_default() {
+                    product.add_stash_item(stash_item).unwrap_or_else(|_| {
+                        panic!("Duplicate expiry dates in DB for product {}", product.id())
+                    });
+                }
+                // The stash items were there all along
+                product.take_events();
+
+                (key, product)
+            })
+            .collect())
+    }
+
+    /// Gets a product from the database by its ID, or by a barcode attached to it
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `id`: The ID or barcode of the product to get
+    ///
+    /// # Returns
+    /// The product, if found
+    pub(super) async fn find_by_id(
+        tx: &Transaction<'_>,
+        id: &ProductId,
+    ) -> Result<Option<Product>, ProductRepositoryError> {
+        match ProductRepository::find_key(tx, id).await? {
+            Some(key) => ProductRepository::find_where(tx, "key = $1", &[&key])
+                .await
+                .map(|mut products| products.pop()),
+            None => Ok(None),
+        }
+    }
+
+    /// Gets the product a stash item belongs to from the database
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `stash_item_id`: The ID of the stash item
+    ///
+    /// # Returns
+    /// The product, if found
+    pub(super) async fn find_by_stash_item_id(
+        tx: &Transaction<'_>,
+        stash_item_id: &Uuid,
+    ) -> Result<Option<Product>, ProductRepositoryError> {
+        Ok(ProductRepository::find_where(
+            tx,
+            "key = (SELECT product_key FROM stash_items WHERE id = $1 AND deleted_at IS NULL)",
+            &[stash_item_id],
+        )
+        .await?
+        .pop())
+    }
+
+    /// Finds all products with at least one stash item whose date, as given by an SQL expression, is within the given
+    /// date interval
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `date`: SQL expression for the date of a stash item, with the stash item as `s` and its product as `p`
+    /// - `after`: The start of the date range, inclusive
+    /// - `before`: The end of the date range, exclusive
+    ///
+    /// # Returns
+    /// A list of products with at least one stash item dated within the given date interval
+    async fn find_in_interval(
+        tx: &Transaction<'_>,
+        date: &str,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        let (condition, params): (String, Vec<&(dyn ToSql + Sync)>) = match (&after, &before) {
+            (Some(after), Some(before)) => {
+                (format!("{0} >= $1 AND {0} < $2", date), vec![after, before])
+            }
+            (Some(after), None) => (format!("{} >= $1", date), vec![after]),
+            (None, Some(before)) => (format!("{} < $1", date), vec![before]),
+            (None, None) => return Err(ProductRepositoryError::InvalidDateInterval),
+        };
+
+        ProductRepository::find_where(
+            tx,
+            &format!(
+                "key IN (SELECT s.product_key FROM stash_items s JOIN products p ON p.key = s.product_key WHERE s.deleted_at IS NULL AND {})",
+                condition
+            ),
+            &params,
+        )
+        .await
+    }
+
+    /// Saves a [`Product`] to the database. If the product already exists, it will be updated.
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `product`: The product to save
+    pub(super) async fn save_product(
+        tx: &Transaction<'_>,
+        product: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        let now = chrono::Utc::now().naive_utc();
+
+        // A product in the trash with the same ID is replaced by this one
+        let stmt = tx
+            .prepare_cached("SELECT key FROM products WHERE id = $1 AND deleted_at IS NOT NULL")
+            .await?;
+        if let Some(row) = tx.query_opt(&stmt, &[product.id()]).await? {
+            ProductRepository::delete_product_by_key(tx, row.try_get(0)?).await?;
+        }
+
+        // The ID of a product takes precedence over the same barcode attached to another product
+        let stmt = tx
+            .prepare_cached("DELETE FROM product_barcodes WHERE barcode = $1")
+            .await?;
+        tx.execute(&stmt, &[product.id()]).await?;
+
+        // Save the product itself
+        let stmt = tx
+            .prepare_cached(
+                "INSERT INTO products (id, brand, name, unit, shelf_life_after_opening_days, best_before_grace_period_days, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (id) DO UPDATE SET brand = $2, name = $3, unit = $4, shelf_life_after_opening_days = $5, best_before_grace_period_days = $6, updated_at = $7 RETURNING key",
+            )
+            .await?;
+        let key: i64 = tx
+            .query_one(
+                &stmt,
+                &[
+                    product.id(),
+                    product.brand(),
+                    product.name(),
+                    product.unit(),
+                    &product.shelf_life_after_opening_days().map(i64::from),
+                    &product.best_before_grace_period_days().map(i64::from),
+                    &now,
+                ],
+            )
+            .await?
+            .try_get("key")?;
+
+        // Attach and detach barcodes
+        let barcodes = product.barcodes().into_iter().collect::<Vec<_>>();
+        let stmt = tx
+            .prepare_cached(
+                "DELETE FROM product_barcodes WHERE product_key = $1 AND barcode <> ALL($2)",
+            )
+            .await?;
+        tx.execute(&stmt, &[&key, &barcodes]).await?;
+
+        let stmt = tx
+            .prepare_cached(
+                "INSERT INTO product_barcodes (barcode, product_key, created_at) VALUES ($1, $2, $3) ON CONFLICT (barcode) DO UPDATE SET product_key = $2",
+            )
+            .await?;
+        for barcode in barcodes {
+            tx.execute(&stmt, &[barcode, &key, &now]).await?;
+        }
+
+        // Delete all stash items no longer in the product
+        let stash_items = product.stash_items();
+        let stash_item_ids = stash_items
+            .iter()
+            .map(|stash_item| *stash_item.id())
+            .collect::<Vec<_>>();
+        let stmt = tx
+            .prepare_cached("DELETE FROM stash_items WHERE product_key = $1 AND deleted_at IS NULL AND id <> ALL($2)")
+            .await?;
+        tx.execute(&stmt, &[&key, &stash_item_ids]).await?;
+
+        // Create and update all stash items. Saving a stash item with the ID of one in the trash replaces that one
+        let stmt = tx
+            .prepare_cached(
+                "INSERT INTO stash_items (id, product_key, quantity, expiry_date, expiry_date_kind, opened_on, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (id) DO UPDATE SET product_key = $2, quantity = $3, expiry_date = $4, expiry_date_kind = $5, opened_on = $6, updated_at = $7, deleted_at = NULL",
+            )
+            .await?;
+        for stash_item in stash_items {
+            tx.execute(
+                &stmt,
+                &[
+                    stash_item.id(),
+                    &key,
+                    stash_item.quantity(),
+                    stash_item.expiry_date(),
+                    stash_item.expiry_date_kind(),
+                    stash_item.opened_on(),
+                    &now,
+                ],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Permanently deletes a product from the database by its internal key, along with all its stash items and barcodes
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `key`: Key of the product to delete
+    async fn delete_product_by_key(
+        tx: &Transaction<'_>,
+        key: i64,
+    ) -> Result<(), ProductRepositoryError> {
+        for query in [
+            "DELETE FROM stash_items WHERE product_key = $1",
+            "DELETE FROM product_barcodes WHERE product_key = $1",
+            "DELETE FROM products WHERE key = $1",
+        ] {
+            let stmt = tx.prepare_cached(query).await?;
+            tx.execute(&stmt, &[&key]).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the product a product is merged into, and permanently deletes the merged product
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `source_id`: The ID of the product being merged
+    /// - `target`: The product being merged into
+    pub(super) async fn merge_product(
+        tx: &Transaction<'_>,
+        source_id: &ProductId,
+        target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        // Find the source before its ID becomes a barcode of the target
+        let source_key = ProductRepository::find_key(tx, source_id).await?;
+
+        ProductRepository::save_product(tx, target).await?;
+
+        if let Some(source_key) = source_key {
+            ProductRepository::delete_product_by_key(tx, source_key).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves a product to the trash, along with its stash items. Its barcodes stay attached to it, unless other
+    /// products take them
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `id`: The ID or barcode of the product
+    /// - `now`: When the product is deleted
+    pub(super) async fn trash_product(
+        tx: &Transaction<'_>,
+        id: &ProductId,
+        now: NaiveDateTime,
+    ) -> Result<(), ProductRepositoryError> {
+        if let Some(key) = ProductRepository::find_key(tx, id).await? {
+            let stmt = tx
+                .prepare_cached("UPDATE products SET deleted_at = $2 WHERE key = $1")
+                .await?;
+            tx.execute(&stmt, &[&key, &now]).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves a stash item of a product which is not in the trash itself to the trash
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `stash_item_id`: The ID of the stash item
+    /// - `now`: When the stash item is deleted
+    pub(super) async fn trash_stash_item(
+        tx: &Transaction<'_>,
+        stash_item_id: &Uuid,
+        now: NaiveDateTime,
+    ) -> Result<(), ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached(
+                "UPDATE stash_items SET deleted_at = $2 WHERE id = $1 AND deleted_at IS NULL AND product_key IN (SELECT key FROM products WHERE deleted_at IS NULL)",
+            )
+            .await?;
+        tx.execute(&stmt, &[stash_item_id, &now]).await?;
+
+        Ok(())
+    }
+
+    /// Gets everything in the trash, most recently deleted first
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    async fn find_trash(tx: &Transaction<'_>) -> Result<Vec<TrashEntry>, ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached("SELECT key, deleted_at FROM products WHERE deleted_at IS NOT NULL")
+            .await?;
+        let mut deleted_at = tx
+            .query(&stmt, &[])
+            .await?
+            .iter()
+            .map(|row| Ok((row.try_get("key")?, row.try_get("deleted_at")?)))
+            .collect::<Result<HashMap<i64, NaiveDateTime>, ProductRepositoryError>>()?;
+
+        let mut entries = ProductRepository::find_keyed_where(tx, "deleted_at IS NOT NULL", &[])
+            .await?
+            .into_iter()
+            .filter_map(|(key, product)| {
+                Some(TrashEntry::Product {
+                    product,
+                    deleted_at: deleted_at.remove(&key)?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Stash items of products in the trash are only restored along with their product
+        let stmt = tx
+            .prepare_cached(
+                "SELECT s.id, s.quantity, s.expiry_date, s.expiry_date_kind, s.opened_on, s.deleted_at, p.id AS product_id FROM stash_items s JOIN products p ON p.key = s.product_key WHERE s.deleted_at IS NOT NULL AND p.deleted_at IS NULL",
+            )
+            .await?;
+        for row in tx.query(&stmt, &[]).await? {
+            entries.push(TrashEntry::StashItem {
+                product_id: row.try_get("product_id")?,
+                stash_item: ProductRepository::row_to_stash_item(&row)?,
+                deleted_at: row.try_get("deleted_at")?,
+            });
+        }
+
+        entries.sort_by(|a, b| {
+            b.deleted_at()
+                .cmp(a.deleted_at())
+                .then_with(|| a.id().cmp(&b.id()))
+        });
+
+        Ok(entries)
+    }
+
+    /// Restores a product from the trash
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `id`: ID of the product to restore
+    pub(super) async fn restore_product(
+        tx: &Transaction<'_>,
+        id: &ProductId,
+    ) -> Result<(), ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached("SELECT key FROM products WHERE id = $1 AND deleted_at IS NOT NULL")
+            .await?;
+        let key: i64 = tx
+            .query_opt(&stmt, &[id])
+            .await?
+            .ok_or(ProductRepositoryError::ProductNotFound)?
+            .try_get(0)?;
+
+        // Another product may have been given the ID as a barcode since
+        if ProductRepository::find_key(tx, id).await?.is_some() {
+            return Err(ProductRepositoryError::ProductAlreadyExists);
+        }
+
+        let stmt = tx
+            .prepare_cached("UPDATE products SET deleted_at = NULL, updated_at = $2 WHERE key = $1")
+            .await?;
+        tx.execute(&stmt, &[&key, &chrono::Utc::now().naive_utc()])
+            .await?;
+
+        Ok(())
+    }
+
+    /// Restores a stash item from the trash, as long as its product is not in the trash
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `stash_item_id`: ID of the stash item to restore
+    pub(super) async fn restore_stash_item(
+        tx: &Transaction<'_>,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached(
+                "SELECT 1 FROM stash_items s JOIN products p ON p.key = s.product_key WHERE s.id = $1 AND s.deleted_at IS NOT NULL AND p.deleted_at IS NULL",
+            )
+            .await?;
+        if tx.query_opt(&stmt, &[stash_item_id]).await?.is_none() {
+            return Err(ProductRepositoryError::StashItemNotFound);
+        }
+
+        // The product may have gotten another stash item with the same dates since
+        let stmt = tx
+            .prepare_cached(
+                "SELECT 1 FROM stash_items s JOIN stash_items t ON t.product_key = s.product_key AND t.expiry_date IS NOT DISTINCT FROM s.expiry_date AND t.opened_on IS NOT DISTINCT FROM s.opened_on WHERE s.id = $1 AND t.deleted_at IS NULL",
+            )
+            .await?;
+        if tx.query_opt(&stmt, &[stash_item_id]).await?.is_some() {
+            return Err(ProductRepositoryError::DuplicateExpiryDateError);
+        }
+
+        let stmt = tx
+            .prepare_cached(
+                "UPDATE stash_items SET deleted_at = NULL, updated_at = $2 WHERE id = $1",
+            )
+            .await?;
+        tx.execute(&stmt, &[stash_item_id, &chrono::Utc::now().naive_utc()])
+            .await?;
+
+        Ok(())
+    }
+
+    /// Permanently deletes the products and stash items which went to the trash before the given time
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `deleted_before`: Products and stash items deleted before this time are purged
+    ///
+    /// # Returns
+    /// The number of products and stash items purged
+    async fn purge_trash(
+        tx: &Transaction<'_>,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached("SELECT key FROM products WHERE deleted_at < $1")
+            .await?;
+        let keys = tx
+            .query(&stmt, &[&deleted_before])
+            .await?
+            .iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<Vec<i64>, _>>()?;
+
+        for key in &keys {
+            ProductRepository::delete_product_by_key(tx, *key).await?;
+        }
+
+        let stmt = tx
+            .prepare_cached("DELETE FROM stash_items WHERE deleted_at < $1")
+            .await?;
+        let stash_items = tx.execute(&stmt, &[&deleted_before]).await?;
+
+        Ok(keys.len() + stash_items as usize)
+    }
+
+    /// Converts a row into a [`StashItem`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the row contains invalid data, or if the data cannot be parsed into a
+    /// [`StashItem`]
+    fn row_to_stash_item(row: &Row) -> Result<StashItem, ProductRepositoryError> {
+        let mut stash_item = StashItem::new(
+            row.try_get("id")?,
+            row.try_get("quantity")?,
+            row.try_get("expiry_date")?,
+        );
+        stash_item.set_expiry_date_kind(row.try_get("expiry_date_kind")?);
+        stash_item.set_opened_on(row.try_get("opened_on")?);
+
+        Ok(stash_item)
+    }
+
+    /// Gets the [`StashItem`]s of all products matching an SQL condition
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
+    ///
+    /// # Returns
+    /// The stash items, by the key of the product they belong to
+    async fn get_stash_items(
+        tx: &Transaction<'_>,
+        condition: &str,
+        params: &Params<'_>,
+    ) -> Result<HashMap<i64, Vec<StashItem>>, ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached(&format!(
+                "SELECT product_key, id, quantity, expiry_date, expiry_date_kind, opened_on FROM stash_items WHERE deleted_at IS NULL AND product_key IN (SELECT key FROM products WHERE {}) ORDER BY product_key, expiry_date ASC NULLS LAST",
+                condition
+            ))
+            .await?;
+
+        let mut stash_items = HashMap::<_, Vec<_>>::new();
+        for row in tx.query(&stmt, params).await? {
+            stash_items
+                .entry(row.try_get::<_, i64>("product_key")?)
+                .or_default()
+                .push(ProductRepository::row_to_stash_item(&row)?);
+        }
+
+        Ok(stash_items)
+    }
+
+    /// Gets the barcodes attached to all products matching an SQL condition
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
+    ///
+    /// # Returns
+    /// The barcodes, by the key of the product they are attached to
+    async fn get_barcodes(
+        tx: &Transaction<'_>,
+        condition: &str,
+        params: &Params<'_>,
+    ) -> Result<HashMap<i64, Vec<ProductId>>, ProductRepositoryError> {
+        let stmt = tx
+            .prepare_cached(&format!(
+                "SELECT product_key, barcode FROM product_barcodes WHERE product_key IN (SELECT key FROM products WHERE {})",
+                condition
+            ))
+            .await?;
+
+        let mut barcodes = HashMap::<_, Vec<_>>::new();
+        for row in tx.query(&stmt, params).await? {
+            barcodes
+                .entry(row.try_get::<_, i64>("product_key")?)
+                .or_default()
+                .push(row.try_get("barcode")?);
+        }
+
+        Ok(barcodes)
+    }
+}
+
+#[async_trait]
+impl ProductRepositoryTrait for ProductRepository {
+    async fn find_all_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let products = ProductRepository::find_where(
+            &tx,
+            "key IN (SELECT product_key FROM stash_items WHERE deleted_at IS NULL)",
+            &[],
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(products)
+    }
+
+    async fn find_by_id(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let product = ProductRepository::find_by_id(&tx, id).await?;
+
+        tx.commit().await?;
+        Ok(product)
+    }
+
+    async fn find_by_ids(&self, ids: &[ProductId]) -> Result<Vec<Product>, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let products = ProductRepository::find_where(&tx, "id = ANY($1)", &[&ids]).await?;
+
+        tx.commit().await?;
+        Ok(products)
+    }
+
+    async fn find_by_stash_item_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<Option<Product>, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let product = ProductRepository::find_by_stash_item_id(&tx, stash_item_id).await?;
+
+        tx.commit().await?;
+        Ok(product)
+    }
+
+    async fn find_expiring_in_interval(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let products =
+            ProductRepository::find_in_interval(&tx, EFFECTIVE_EXPIRY_DATE, after, before).await?;
+
+        tx.commit().await?;
+        Ok(products)
+    }
+
+    async fn find_unsafe_in_interval(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let products =
+            ProductRepository::find_in_interval(&tx, &unsafe_date(), after, before).await?;
+
+        tx.commit().await?;
+        Ok(products)
+    }
+
+    async fn exists_by_id(&self, id: &ProductId) -> Result<bool, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let exists = ProductRepository::find_key(&tx, id).await?.is_some();
+
+        tx.commit().await?;
+        Ok(exists)
+    }
+
+    async fn save(&self, mut product: Product) -> Result<(), ProductRepositoryError> {
+        let events = product.take_events();
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        ProductRepository::save_product(&tx, product).await?;
+
+        tx.commit().await?;
+        self.event_dispatcher.dispatch(&events);
+        Ok(())
+    }
+
+    async fn save_all(&self, mut products: Vec<Product>) -> Result<(), ProductRepositoryError> {
+        let events = products
+            .iter_mut()
+            .flat_map(Product::take_events)
+            .collect::<Vec<_>>();
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        for product in products {
+            ProductRepository::save_product(&tx, product).await?;
+        }
+
+        tx.commit().await?;
+        self.event_dispatcher.dispatch(&events);
+        Ok(())
+    }
+
+    async fn merge_into(
+        &self,
+        source_id: &ProductId,
+        mut target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        let events = target.take_events();
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        ProductRepository::merge_product(&tx, source_id, target).await?;
+
+        tx.commit().await?;
+        self.event_dispatcher.dispatch(&events);
+        Ok(())
+    }
+
+    async fn delete_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        ProductRepository::trash_product(&tx, id, chrono::Utc::now().naive_utc()).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        ProductRepository::trash_stash_item(&tx, stash_item_id, chrono::Utc::now().naive_utc())
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find_trash(&self) -> Result<Vec<TrashEntry>, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let entries = ProductRepository::find_trash(&tx).await?;
+
+        tx.commit().await?;
+        Ok(entries)
+    }
+
+    async fn restore_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        ProductRepository::restore_product(&tx, id).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        ProductRepository::restore_stash_item(&tx, stash_item_id).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn purge_trash(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        let purged = ProductRepository::purge_trash(&tx, deleted_before).await?;
+
+        tx.commit().await?;
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::infrastructure::persistence::{
+        conformance::product_repository_conformance_tests, postgres::test_server::create_test_pool,
+    };
+
+    use super::*;
+
+    product_repository_conformance_tests!(ProductRepository::new(create_test_pool().await));
+}
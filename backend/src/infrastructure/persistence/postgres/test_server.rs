@@ -0,0 +1,115 @@
+//! A PostgreSQL server for tests to run against. Uses the server at `STASH_TEST_POSTGRES_URL` if it is set. Otherwise,
+//! a throwaway server is started with the PostgreSQL binaries on the `PATH`, or in `STASH_TEST_POSTGRES_BIN`, and
+//! removed again once the tests are done
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+
+use actix_web::rt;
+use tokio_postgres::NoTls;
+use uuid::Uuid;
+
+use super::{
+    db::setup_db,
+    pool::{create_pool, Pool},
+};
+
+/// How to connect to the server, without a database
+static SERVER: OnceLock<tokio_postgres::Config> = OnceLock::new();
+
+/// Gets the server, starting it if needed
+fn server() -> &'static tokio_postgres::Config {
+    SERVER.get_or_init(|| match std::env::var("STASH_TEST_POSTGRES_URL") {
+        Ok(url) => url.parse().expect("Invalid STASH_TEST_POSTGRES_URL"),
+        Err(_) => start_server(),
+    })
+}
+
+/// Path to a PostgreSQL binary
+fn binary(name: &str) -> PathBuf {
+    match std::env::var_os("STASH_TEST_POSTGRES_BIN") {
+        Some(dir) => Path::new(&dir).join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+/// Runs a command, panicking if it fails
+fn run(command: &mut Command) {
+    let output = command
+        .output()
+        .unwrap_or_else(|err| panic!("Failed to run {:?}: {}", command, err));
+
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        command,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Starts a server in a new directory in the temp directory. It only listens on a socket in that directory, so it
+/// never clashes with other servers
+// The process cleaning up after the server is meant to outlive this one, so it is never waited for
+#[allow(clippy::zombie_processes)]
+fn start_server() -> tokio_postgres::Config {
+    let dir = std::env::temp_dir().join(format!("stash-postgres-{}", Uuid::new_v4()));
+    let data = dir.join("data");
+
+    run(Command::new(binary("initdb"))
+        .arg("-D")
+        .arg(&data)
+        .args(["-U", "stash", "--auth=trust"]));
+    run(Command::new(binary("pg_ctl"))
+        .arg("-D")
+        .arg(&data)
+        .arg("-l")
+        .arg(dir.join("log"))
+        .arg("-o")
+        .arg(format!("-k '{}' -c listen_addresses=''", dir.display()))
+        .args(["-w", "start"]));
+
+    // Stop and remove the server once the tests are done, which is when this process is gone
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "while kill -0 {} 2>/dev/null; do sleep 1; done; '{}' -D '{}' -m immediate stop; rm -rf '{}'",
+            std::process::id(),
+            binary("pg_ctl").display(),
+            data.display(),
+            dir.display()
+        ))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to watch the PostgreSQL server");
+
+    let mut config = tokio_postgres::Config::new();
+    config.host_path(&dir).user("stash").dbname("postgres");
+
+    config
+}
+
+/// Creates a pool of connections to a new, empty database on the server. Every call gets a database of its own, so
+/// tests do not see each other's data. Databases are not dropped, which only matters for servers given by
+/// `STASH_TEST_POSTGRES_URL`
+pub async fn create_test_pool() -> Pool {
+    let mut config = server().clone();
+    let database = format!("stash_test_{}", Uuid::new_v4().simple());
+
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    rt::spawn(connection);
+    client
+        .batch_execute(&format!("CREATE DATABASE {}", database))
+        .await
+        .unwrap();
+
+    config.dbname(&database);
+    let pool = create_pool(config, 4).unwrap();
+    setup_db(&mut pool.get().await.unwrap()).await.unwrap();
+
+    pool
+}
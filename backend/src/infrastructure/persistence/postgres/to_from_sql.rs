@@ -0,0 +1,45 @@
+use std::error::Error;
+
+use bytes::BytesMut;
+use tokio_postgres::types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+use crate::domain::value_objects::{Brand, ExpiryDateKind, ProductId, Quantity, Unit};
+
+/// Stores a value object in a text column, as given by `$to_text`, and parses it back when reading the column
+macro_rules! text_column {
+    ($type:ty, $to_text:expr) => {
+        impl ToSql for $type {
+            fn to_sql(
+                &self,
+                ty: &Type,
+                out: &mut BytesMut,
+            ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+                let to_text: fn(&$type) -> String = $to_text;
+
+                to_text(self).to_sql(ty, out)
+            }
+
+            accepts!(TEXT, VARCHAR);
+
+            to_sql_checked!();
+        }
+
+        impl<'a> FromSql<'a> for $type {
+            fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+                let text = <&str>::from_sql(ty, raw)?;
+
+                text.parse()
+                    .map_err(|_| format!("Invalid {}: {}", stringify!($type), text).into())
+            }
+
+            accepts!(TEXT, VARCHAR);
+        }
+    };
+}
+
+text_column!(Brand, |brand| brand.value().clone());
+text_column!(ProductId, |id| id.value().clone());
+text_column!(Unit, |unit| unit.to_string());
+text_column!(ExpiryDateKind, |kind| kind.to_string());
+// Quantities are stored as text, so fractions are kept exactly as they are
+text_column!(Quantity, |quantity| quantity.value().to_string());
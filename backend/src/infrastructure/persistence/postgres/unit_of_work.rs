@@ -0,0 +1,270 @@
+use std::sync::Arc;
+
+use actix_web::rt::task::spawn_blocking;
+use async_trait::async_trait;
+use deadpool_postgres::Transaction;
+use tokio::runtime::Handle;
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::Product,
+    errors::ProductRepositoryError,
+    events::{ProductEvent, ProductEventDispatcher},
+    repositories::{
+        ProductTransaction as ProductTransactionTrait, UnitOfWork as UnitOfWorkTrait, Work,
+        WorkResult, DEFAULT_UNDO_HISTORY_SIZE,
+    },
+    value_objects::{Operation, ProductId},
+};
+
+use super::{operations, pool::Pool, ProductRepository};
+
+/// The products as seen from within a single PostgreSQL transaction. The work of a unit of work is synchronous, so it
+/// runs on a blocking thread which waits for each query on the runtime
+struct ProductTransaction<'a> {
+    tx: &'a Transaction<'a>,
+    /// The runtime the queries run on
+    handle: &'a Handle,
+    /// How many operations are kept
+    undo_history_size: usize,
+    /// Events of the products saved, to be dispatched once the transaction is committed
+    events: Vec<ProductEvent>,
+}
+
+impl ProductTransactionTrait for ProductTransaction<'_> {
+    fn find_by_id(&mut self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
+        self.handle
+            .block_on(ProductRepository::find_by_id(self.tx, id))
+    }
+
+    fn exists_by_id(&mut self, id: &ProductId) -> Result<bool, ProductRepositoryError> {
+        Ok(self
+            .handle
+            .block_on(ProductRepository::find_key(self.tx, id))?
+            .is_some())
+    }
+
+    fn find_by_stash_item_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<Option<Product>, ProductRepositoryError> {
+        self.handle
+            .block_on(ProductRepository::find_by_stash_item_id(
+                self.tx,
+                stash_item_id,
+            ))
+    }
+
+    fn save(&mut self, mut product: Product) -> Result<(), ProductRepositoryError> {
+        let events = product.take_events();
+
+        self.handle
+            .block_on(ProductRepository::save_product(self.tx, product))?;
+
+        self.events.extend(events);
+        Ok(())
+    }
+
+    fn merge_into(
+        &mut self,
+        source_id: &ProductId,
+        mut target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        let events = target.take_events();
+
+        self.handle
+            .block_on(ProductRepository::merge_product(self.tx, source_id, target))?;
+
+        self.events.extend(events);
+        Ok(())
+    }
+
+    fn delete_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        self.handle.block_on(ProductRepository::trash_product(
+            self.tx,
+            id,
+            chrono::Utc::now().naive_utc(),
+        ))
+    }
+
+    fn delete_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        self.handle.block_on(ProductRepository::trash_stash_item(
+            self.tx,
+            stash_item_id,
+            chrono::Utc::now().naive_utc(),
+        ))
+    }
+
+    fn restore_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        self.handle
+            .block_on(ProductRepository::restore_product(self.tx, id))
+    }
+
+    fn restore_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        self.handle.block_on(ProductRepository::restore_stash_item(
+            self.tx,
+            stash_item_id,
+        ))
+    }
+
+    fn save_operation(&mut self, operation: Operation) -> Result<(), ProductRepositoryError> {
+        self.handle.block_on(operations::save(
+            self.tx,
+            &operation,
+            self.undo_history_size,
+        ))
+    }
+
+    fn find_operation_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Operation>, ProductRepositoryError> {
+        self.handle.block_on(operations::find_by_id(self.tx, id))
+    }
+
+    fn delete_operation_by_id(&mut self, id: &Uuid) -> Result<(), ProductRepositoryError> {
+        self.handle.block_on(operations::delete_by_id(self.tx, id))
+    }
+}
+
+/// Runs work on the products in a PostgreSQL database, in one transaction on a single connection
+pub struct UnitOfWork {
+    /// Connections to the database
+    pool: Pool,
+    /// How many of the most recent operations are kept, so they can be undone
+    undo_history_size: usize,
+    /// Gets the events of the products saved by committed work
+    event_dispatcher: Arc<ProductEventDispatcher>,
+}
+
+impl UnitOfWork {
+    /// Creates a new [`UnitOfWork`]
+    pub fn new(pool: Pool) -> Self {
+        Self {
+            pool,
+            undo_history_size: DEFAULT_UNDO_HISTORY_SIZE,
+            event_dispatcher: Arc::default(),
+        }
+    }
+
+    /// Sets how many of the most recent operations are kept, so they can be undone
+    pub fn with_undo_history_size(mut self, undo_history_size: usize) -> Self {
+        self.undo_history_size = undo_history_size;
+        self
+    }
+
+    /// Makes the unit of work dispatch the events of the products it saves, once the work is committed
+    pub fn with_event_dispatcher(mut self, event_dispatcher: Arc<ProductEventDispatcher>) -> Self {
+        self.event_dispatcher = event_dispatcher;
+        self
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkTrait for UnitOfWork {
+    async fn run_boxed(&self, work: Work) -> Result<WorkResult, ProductRepositoryError> {
+        let mut conn = self.pool.get().await?;
+        let handle = Handle::current();
+        let undo_history_size = self.undo_history_size;
+
+        let (result, events) = spawn_blocking(move || {
+            let tx = handle.block_on(conn.transaction())?;
+
+            // Concurrent work waits for this work to be done instead of changing the products it read, like the write
+            // lock of SQLite. Reads do not wait for it
+            handle.block_on(tx.batch_execute("LOCK TABLE products IN SHARE ROW EXCLUSIVE MODE"))?;
+
+            let mut transaction = ProductTransaction {
+                tx: &tx,
+                handle: &handle,
+                undo_history_size,
+                events: vec![],
+            };
+            let result = work(&mut transaction);
+
+            // Dropping the transaction without committing it rolls it back, and the events with it
+            let events = match result {
+                Ok(_) => {
+                    let events = transaction.events;
+                    handle.block_on(tx.commit())?;
+                    events
+                }
+                Err(_) => vec![],
+            };
+
+            Ok::<_, ProductRepositoryError>((result, events))
+        })
+        .await??;
+
+        self.event_dispatcher.dispatch(&events);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            entities::{FakeProduct, FakeStashItem},
+            repositories::ProductRepository as _,
+        },
+        infrastructure::persistence::{
+            conformance::unit_of_work_conformance_tests, postgres::test_server::create_test_pool,
+        },
+    };
+
+    use super::*;
+
+    unit_of_work_conformance_tests!(event_dispatcher => {
+        let pool = create_test_pool().await;
+
+        (
+            ProductRepository::new(pool.clone()),
+            UnitOfWork::new(pool).with_event_dispatcher(event_dispatcher),
+        )
+    });
+
+    #[actix_web::test]
+    async fn test_concurrent_work_does_not_lose_updates() {
+        let pool = create_test_pool().await;
+        let repo = ProductRepository::new(pool.clone());
+        let unit_of_work = Arc::new(UnitOfWork::new(pool));
+        let product = FakeProduct::new().with_stash_items(vec![]).build();
+        repo.save(product.clone()).await.unwrap();
+
+        // Each adds a stash item to the product as it is when the work starts
+        let handles = (1..=8)
+            .map(|day| {
+                let unit_of_work = unit_of_work.clone();
+                let product_id = product.id().clone();
+                let stash_item = FakeStashItem::new()
+                    .with_expiry_date(chrono::NaiveDate::from_ymd_opt(2023, 11, day).unwrap())
+                    .build();
+
+                actix_web::rt::spawn(async move {
+                    let unit_of_work: &dyn UnitOfWorkTrait = unit_of_work.as_ref();
+                    unit_of_work
+                        .run(move |tx| {
+                            let mut product = tx.find_by_id(&product_id)?.unwrap();
+                            product.add_stash_item(stash_item)?;
+                            tx.save(product)
+                        })
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap(), Ok(()));
+        }
+
+        let product = repo.find_by_id(product.id()).await.unwrap().unwrap();
+        assert_eq!(product.stash_items().len(), 8);
+    }
+}
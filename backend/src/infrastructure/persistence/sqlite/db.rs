@@ -1,34 +1,231 @@
-use rusqlite::params;
+use actix_web::rt::task::JoinError;
 
-use crate::domain::errors::ProductRepositoryError;
+use crate::domain::errors::{IdempotencyError, NotificationError, ProductRepositoryError};
 
+/// Migrations bringing the database schema up to date, in order. The schema version of the database, stored in
+/// SQLite's `user_version`, is the number of migrations applied to it
+const MIGRATIONS: &[&str] = &[
+    // 1: The initial schema. Uses `IF NOT EXISTS` because databases from before migrations were introduced already
+    // have these tables
+    "CREATE TABLE IF NOT EXISTS products (
+        id TEXT PRIMARY KEY,
+        brand TEXT NOT NULL,
+        name TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS stash_items (
+        id TEXT PRIMARY KEY,
+        product_id TEXT NOT NULL,
+        quantity INTEGER NOT NULL,
+        expiry_date TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT,
+        FOREIGN KEY (product_id) REFERENCES products(id)
+        UNIQUE (product_id, expiry_date)
+    );
+
+    CREATE TABLE IF NOT EXISTS product_aliases (
+        alias TEXT PRIMARY KEY,
+        product_id TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        FOREIGN KEY (product_id) REFERENCES products(id)
+    );",
+    // 2: Products get an internal key, so they can have several barcodes. Aliases become barcodes. Foreign keys point
+    // at the new tables, and are updated by SQLite when they are renamed
+    "CREATE TABLE products_new (
+        key INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        brand TEXT NOT NULL,
+        name TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT
+    );
+
+    INSERT INTO products_new (id, brand, name, created_at, updated_at)
+        SELECT id, brand, name, created_at, updated_at FROM products;
+
+    CREATE TABLE product_barcodes (
+        barcode TEXT PRIMARY KEY,
+        product_key INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        FOREIGN KEY (product_key) REFERENCES products_new(key)
+    );
+
+    INSERT INTO product_barcodes (barcode, product_key, created_at)
+        SELECT a.alias, p.key, a.created_at FROM product_aliases a JOIN products_new p ON p.id = a.product_id;
+
+    CREATE TABLE stash_items_new (
+        id TEXT PRIMARY KEY,
+        product_key INTEGER NOT NULL,
+        quantity INTEGER NOT NULL,
+        expiry_date TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT,
+        FOREIGN KEY (product_key) REFERENCES products_new(key)
+        UNIQUE (product_key, expiry_date)
+    );
+
+    INSERT INTO stash_items_new (id, product_key, quantity, expiry_date, created_at, updated_at)
+        SELECT s.id, p.key, s.quantity, s.expiry_date, s.created_at, s.updated_at
+        FROM stash_items s JOIN products_new p ON p.id = s.product_id;
+
+    DROP TABLE product_aliases;
+    DROP TABLE stash_items;
+    DROP TABLE products;
+
+    ALTER TABLE products_new RENAME TO products;
+    ALTER TABLE stash_items_new RENAME TO stash_items;",
+    // 3: Stash items can be opened, and products can expire sooner once opened. Opened and unopened stash items may
+    // share an expiry date, so the uniqueness constraint moves to an index that takes the opened date into account
+    "ALTER TABLE products ADD COLUMN shelf_life_after_opening_days INTEGER;
+
+    CREATE TABLE stash_items_new (
+        id TEXT PRIMARY KEY,
+        product_key INTEGER NOT NULL,
+        quantity INTEGER NOT NULL,
+        expiry_date TEXT NOT NULL,
+        opened_on TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT,
+        FOREIGN KEY (product_key) REFERENCES products(key)
+    );
+
+    INSERT INTO stash_items_new (id, product_key, quantity, expiry_date, created_at, updated_at)
+        SELECT id, product_key, quantity, expiry_date, created_at, updated_at FROM stash_items;
+
+    DROP TABLE stash_items;
+
+    ALTER TABLE stash_items_new RENAME TO stash_items;
+
+    CREATE UNIQUE INDEX stash_items_product_key_expiry_date_opened_on
+        ON stash_items (product_key, expiry_date, COALESCE(opened_on, ''));",
+    // 4: Products have a unit, and quantities may be fractions. Quantities are stored as text to keep them exact
+    "ALTER TABLE products ADD COLUMN unit TEXT NOT NULL DEFAULT 'pieces';
+
+    CREATE TABLE stash_items_new (
+        id TEXT PRIMARY KEY,
+        product_key INTEGER NOT NULL,
+        quantity TEXT NOT NULL,
+        expiry_date TEXT NOT NULL,
+        opened_on TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT,
+        FOREIGN KEY (product_key) REFERENCES products(key)
+    );
+
+    INSERT INTO stash_items_new (id, product_key, quantity, expiry_date, opened_on, created_at, updated_at)
+        SELECT id, product_key, CAST(quantity AS TEXT), expiry_date, opened_on, created_at, updated_at FROM stash_items;
+
+    DROP TABLE stash_items;
+
+    ALTER TABLE stash_items_new RENAME TO stash_items;
+
+    CREATE UNIQUE INDEX stash_items_product_key_expiry_date_opened_on
+        ON stash_items (product_key, expiry_date, COALESCE(opened_on, ''));",
+    // 5: Expiry dates are either best-before or use-by dates, and products may have a grace period for best-before
+    "ALTER TABLE stash_items ADD COLUMN expiry_date_kind TEXT NOT NULL DEFAULT 'best_before';
+
+    ALTER TABLE products ADD COLUMN best_before_grace_period_days INTEGER;",
+    // 6: Stash items may have no expiry date
+    "CREATE TABLE stash_items_new (
+        id TEXT PRIMARY KEY,
+        product_key INTEGER NOT NULL,
+        quantity TEXT NOT NULL,
+        expiry_date TEXT,
+        expiry_date_kind TEXT NOT NULL DEFAULT 'best_before',
+        opened_on TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT,
+        FOREIGN KEY (product_key) REFERENCES products(key)
+    );
+
+    INSERT INTO stash_items_new
+        (id, product_key, quantity, expiry_date, expiry_date_kind, opened_on, created_at, updated_at)
+        SELECT id, product_key, quantity, expiry_date, expiry_date_kind, opened_on, created_at, updated_at
+        FROM stash_items;
+
+    DROP TABLE stash_items;
+
+    ALTER TABLE stash_items_new RENAME TO stash_items;
+
+    CREATE UNIQUE INDEX stash_items_product_key_expiry_date_opened_on
+        ON stash_items (product_key, COALESCE(expiry_date, ''), COALESCE(opened_on, ''));",
+    // 7: Keep track of which stash items have been notified about, so they are only notified about once per window
+    "CREATE TABLE stash_item_notifications (
+        stash_item_id TEXT NOT NULL,
+        window_days INTEGER NOT NULL,
+        notified_at TEXT NOT NULL,
+        PRIMARY KEY (stash_item_id, window_days)
+    );",
+    // 8: Deleted products and stash items go to the trash instead of being removed right away. Stash items in the
+    // trash no longer count towards the uniqueness of the dates of stash items
+    "ALTER TABLE products ADD COLUMN deleted_at TEXT;
+
+    ALTER TABLE stash_items ADD COLUMN deleted_at TEXT;
+
+    DROP INDEX stash_items_product_key_expiry_date_opened_on;
+
+    CREATE UNIQUE INDEX stash_items_product_key_expiry_date_opened_on
+        ON stash_items (product_key, COALESCE(expiry_date, ''), COALESCE(opened_on, ''))
+        WHERE deleted_at IS NULL;",
+    // 9: Remember the responses to requests made with an idempotency key, to replay them when the requests are
+    // retried. The response is missing while the request is being handled
+    "CREATE TABLE idempotency_keys (
+        key TEXT PRIMARY KEY,
+        fingerprint TEXT NOT NULL,
+        status INTEGER,
+        headers TEXT,
+        body BLOB,
+        created_at TEXT NOT NULL
+    );
+
+    CREATE INDEX idempotency_keys_created_at ON idempotency_keys (created_at);",
+    // 10: Keep the operations which can be undone, so they can still be undone after a restart. The key keeps them in
+    // the order they were made, so the oldest can be forgotten
+    "CREATE TABLE operations (
+        key INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        inverse TEXT NOT NULL
+    );",
+    // 11: Keep track of what each notifier delivered, so one failing neither holds up the others nor has them deliver
+    // again. What was notified about so far was delivered by every notifier, which is recorded without a notifier
+    "CREATE TABLE stash_item_notifications_new (
+        stash_item_id TEXT NOT NULL,
+        notifier TEXT NOT NULL,
+        window_days INTEGER NOT NULL,
+        notified_at TEXT NOT NULL,
+        PRIMARY KEY (stash_item_id, notifier, window_days)
+    );
+
+    INSERT INTO stash_item_notifications_new (stash_item_id, notifier, window_days, notified_at)
+        SELECT stash_item_id, '', window_days, notified_at FROM stash_item_notifications;
+
+    DROP TABLE stash_item_notifications;
+
+    ALTER TABLE stash_item_notifications_new RENAME TO stash_item_notifications;",
+    // 12: Remember the token each idempotency key was claimed with, so a request whose claim went stale cannot
+    // complete or release the key once a retry has claimed it again
+    "ALTER TABLE idempotency_keys ADD COLUMN token TEXT;",
+];
+
+/// Sets up the database, applying all migrations it has not yet seen
 pub fn setup_db(connection: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
-    connection.execute(
-        "CREATE TABLE IF NOT EXISTS products (
-            id TEXT PRIMARY KEY,
-            brand TEXT NOT NULL,
-            name TEXT NOT NULL,
-            created_at TEXT NOT NULL,
-            updated_at TEXT
-        )",
-        params![],
-    )?;
-
-    connection.execute(
-        "CREATE TABLE IF NOT EXISTS stash_items (
-            id TEXT PRIMARY KEY,
-            product_id TEXT NOT NULL,
-            quantity INTEGER NOT NULL,
-            expiry_date TEXT NOT NULL,
-            created_at TEXT NOT NULL,
-            updated_at TEXT,
-            FOREIGN KEY (product_id) REFERENCES products(id)
-            UNIQUE (product_id, expiry_date)
-        )",
-        params![],
-    )?;
-
-    Ok(())
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let foreign_keys: bool = connection.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+
+    // Migrations rebuild tables others refer to, which foreign key enforcement would get in the way of
+    connection.pragma_update(None, "foreign_keys", false)?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = connection.unchecked_transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+
+    connection.pragma_update(None, "foreign_keys", foreign_keys)
 }
 
 impl From<rusqlite::Error> for ProductRepositoryError {
@@ -36,3 +233,162 @@ impl From<rusqlite::Error> for ProductRepositoryError {
         Self::PersisteneError(error.to_string())
     }
 }
+
+impl From<rusqlite::Error> for NotificationError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::PersistenceError(error.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for IdempotencyError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::PersistenceError(error.to_string())
+    }
+}
+
+impl From<r2d2::Error> for ProductRepositoryError {
+    fn from(error: r2d2::Error) -> Self {
+        Self::PersisteneError(error.to_string())
+    }
+}
+
+impl From<r2d2::Error> for NotificationError {
+    fn from(error: r2d2::Error) -> Self {
+        Self::PersistenceError(error.to_string())
+    }
+}
+
+impl From<r2d2::Error> for IdempotencyError {
+    fn from(error: r2d2::Error) -> Self {
+        Self::PersistenceError(error.to_string())
+    }
+}
+
+impl From<JoinError> for ProductRepositoryError {
+    fn from(error: JoinError) -> Self {
+        Self::PersisteneError(error.to_string())
+    }
+}
+
+impl From<JoinError> for NotificationError {
+    fn from(error: JoinError) -> Self {
+        Self::PersistenceError(error.to_string())
+    }
+}
+
+impl From<JoinError> for IdempotencyError {
+    fn from(error: JoinError) -> Self {
+        Self::PersistenceError(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{params, Connection};
+
+    use super::*;
+
+    fn user_version(connection: &Connection) -> usize {
+        connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_setup_db_applies_all_migrations() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        setup_db(&connection).unwrap();
+
+        assert_eq!(user_version(&connection), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_setup_db_is_idempotent() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        setup_db(&connection).unwrap();
+        setup_db(&connection).unwrap();
+
+        assert_eq!(user_version(&connection), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_setup_db_migrates_unversioned_db() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO products (id, brand, name, created_at) VALUES ('1', 'BRAND', 'NAME', '2023-01-01');
+                INSERT INTO products (id, brand, name, created_at) VALUES ('2', 'BRAND', 'NAME', '2023-01-01');
+                INSERT INTO stash_items (id, product_id, quantity, expiry_date, created_at)
+                    VALUES ('a', '1', 3, '2023-02-01', '2023-01-01');
+                INSERT INTO product_aliases (alias, product_id, created_at) VALUES ('3', '1', '2023-01-01');",
+            )
+            .unwrap();
+
+        setup_db(&connection).unwrap();
+
+        let key: i64 = connection
+            .query_row("SELECT key FROM products WHERE id = '1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        let stash_item_key: i64 = connection
+            .query_row(
+                "SELECT product_key FROM stash_items WHERE id = 'a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let barcode_key: i64 = connection
+            .query_row(
+                "SELECT product_key FROM product_barcodes WHERE barcode = ?",
+                params!["3"],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let quantity: String = connection
+            .query_row(
+                "SELECT quantity FROM stash_items WHERE id = 'a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let product_count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM products", [], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(stash_item_key, key);
+        assert_eq!(barcode_key, key);
+        assert_eq!(quantity, "3");
+        assert_eq!(product_count, 2);
+    }
+
+    #[test]
+    fn test_setup_db_keeps_notifications_for_every_notifier() {
+        let connection = Connection::open_in_memory().unwrap();
+        for migration in &MIGRATIONS[..10] {
+            connection.execute_batch(migration).unwrap();
+        }
+        connection.pragma_update(None, "user_version", 10).unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO stash_item_notifications (stash_item_id, window_days, notified_at)
+                    VALUES ('a', 1, '2023-11-10');",
+            )
+            .unwrap();
+
+        setup_db(&connection).unwrap();
+
+        // Notified about before the notifiers were told apart, so delivered by every notifier
+        let notifier: String = connection
+            .query_row(
+                "SELECT notifier FROM stash_item_notifications WHERE stash_item_id = 'a' AND window_days = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(notifier, "");
+    }
+}
@@ -0,0 +1,612 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use rusqlite::{named_params, OptionalExtension, TransactionBehavior};
+use uuid::Uuid;
+
+use crate::domain::{
+    errors::IdempotencyError,
+    repositories::IdempotencyRepository as IdempotencyRepositoryTrait,
+    value_objects::{IdempotencyRecord, IdempotentResponse},
+};
+
+use super::pool::{with_connection, Pool};
+
+/// A repository keeping track of requests made with an idempotency key, using SQLite as the underlying storage.
+pub struct IdempotencyRepository {
+    /// Connections to the database
+    pool: Pool,
+}
+
+impl IdempotencyRepository {
+    /// Creates a new [`IdempotencyRepository`]
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepositoryTrait for IdempotencyRepository {
+    async fn claim(
+        &self,
+        key: &str,
+        token: &Uuid,
+        fingerprint: &str,
+        now: NaiveDateTime,
+        expired_before: NaiveDateTime,
+        stale_before: NaiveDateTime,
+    ) -> Result<Option<IdempotencyRecord>, IdempotencyError> {
+        let key = key.to_string();
+        let token = token.to_string();
+        let fingerprint = fingerprint.to_string();
+
+        with_connection(&self.pool, move |conn| {
+            // Take the write lock right away, so a retry racing the original request cannot claim the key as well
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+            tx.execute(
+                "DELETE FROM idempotency_keys WHERE created_at < :expired_before OR (status IS NULL AND created_at < :stale_before)",
+                named_params! {
+                    ":expired_before": expired_before,
+                    ":stale_before": stale_before,
+                },
+            )?;
+
+            let record = tx
+                .query_row(
+                    "SELECT fingerprint, status, headers, body, created_at FROM idempotency_keys WHERE key = :key",
+                    named_params! { ":key": key },
+                    |row| {
+                        let status: Option<u16> = row.get("status")?;
+                        let headers: Option<String> = row.get("headers")?;
+                        let body: Option<Vec<u8>> = row.get("body")?;
+
+                        Ok((
+                            row.get::<_, String>("fingerprint")?,
+                            status.map(|status| (status, headers.unwrap_or_default(), body.unwrap_or_default())),
+                            row.get::<_, NaiveDateTime>("created_at")?,
+                        ))
+                    },
+                )
+                .optional()?;
+
+            if let Some((fingerprint, response, created_at)) = record {
+                let response = response
+                    .map(|(status, headers, body)| {
+                        serde_json::from_str(&headers)
+                            .map(|headers| IdempotentResponse::new(status, headers, body))
+                            .map_err(|error| IdempotencyError::PersistenceError(error.to_string()))
+                    })
+                    .transpose()?;
+
+                // Keep the expired keys purged, even though the key is not claimed
+                tx.commit()?;
+
+                return Ok(Some(IdempotencyRecord::new(
+                    key,
+                    fingerprint,
+                    response,
+                    created_at,
+                )));
+            }
+
+            tx.execute(
+                "INSERT INTO idempotency_keys (key, token, fingerprint, created_at) VALUES (:key, :token, :fingerprint, :now)",
+                named_params! {
+                    ":key": key,
+                    ":token": token,
+                    ":fingerprint": fingerprint,
+                    ":now": now,
+                },
+            )?;
+
+            tx.commit()?;
+            Ok(None)
+        })
+        .await
+    }
+
+    async fn complete(
+        &self,
+        key: &str,
+        token: &Uuid,
+        response: IdempotentResponse,
+    ) -> Result<(), IdempotencyError> {
+        let key = key.to_string();
+        let token = token.to_string();
+        let headers = serde_json::to_string(response.headers())
+            .map_err(|error| IdempotencyError::PersistenceError(error.to_string()))?;
+
+        with_connection(&self.pool, move |conn| {
+            // The key may have been claimed again for a retry, if the lease on it ran out while handling the request
+            let completed = conn.execute(
+                "UPDATE idempotency_keys SET status = :status, headers = :headers, body = :body WHERE key = :key AND token = :token AND status IS NULL",
+                named_params! {
+                    ":key": key,
+                    ":token": token,
+                    ":status": response.status(),
+                    ":headers": headers,
+                    ":body": response.body(),
+                },
+            )?;
+
+            if completed == 0 {
+                return Err(IdempotencyError::ClaimLost);
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn release(&self, key: &str, token: &Uuid) -> Result<(), IdempotencyError> {
+        let key = key.to_string();
+        let token = token.to_string();
+
+        with_connection(&self.pool, move |conn| {
+            conn.execute(
+                "DELETE FROM idempotency_keys WHERE key = :key AND token = :token AND status IS NULL",
+                named_params! { ":key": key, ":token": token },
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    use crate::infrastructure::persistence::sqlite::{
+        db::setup_db,
+        pool::{create_pool, ConnectionManager},
+    };
+
+    /// Token the keys are claimed with
+    const TOKEN: Uuid = Uuid::from_u128(1);
+
+    fn get_repo() -> IdempotencyRepository {
+        let pool = create_pool(ConnectionManager::memory(), 1).unwrap();
+
+        setup_db(&pool.get().unwrap()).unwrap();
+
+        IdempotencyRepository::new(pool)
+    }
+
+    fn now() -> NaiveDateTime {
+        chrono::Utc::now().naive_utc()
+    }
+
+    /// When the oldest keys still being handled at a time were claimed
+    fn stale_before(now: NaiveDateTime) -> NaiveDateTime {
+        now - Duration::minutes(1)
+    }
+
+    fn response() -> IdempotentResponse {
+        IdempotentResponse::new(
+            201,
+            vec![("content-type".to_string(), "application/json".to_string())],
+            br#"{"id":"ID"}"#.to_vec(),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_claim_new_key() {
+        let repo = get_repo();
+        let now = now();
+
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "fingerprint",
+                now,
+                now - Duration::hours(1),
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(claimed, None);
+    }
+
+    #[actix_web::test]
+    async fn test_claim_key_in_progress() {
+        let repo = get_repo();
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "other",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            claimed,
+            Some(IdempotencyRecord::new(
+                "key".to_string(),
+                "fingerprint".to_string(),
+                None,
+                now
+            ))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_claim_completed_key() {
+        let repo = get_repo();
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        repo.complete("key", &TOKEN, response()).await.unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "fingerprint",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(claimed.unwrap().response(), &Some(response()));
+    }
+
+    #[actix_web::test]
+    async fn test_claim_stale_key() {
+        let repo = get_repo();
+        let then = now() - Duration::minutes(2);
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        // The request the key was claimed for never completed
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            then,
+            expired_before,
+            stale_before(then),
+        )
+        .await
+        .unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "fingerprint",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(claimed, None);
+    }
+
+    #[actix_web::test]
+    async fn test_claim_completed_key_does_not_go_stale() {
+        let repo = get_repo();
+        let then = now() - Duration::minutes(2);
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            then,
+            expired_before,
+            stale_before(then),
+        )
+        .await
+        .unwrap();
+        repo.complete("key", &TOKEN, response()).await.unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "fingerprint",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(claimed.unwrap().response(), &Some(response()));
+    }
+
+    #[actix_web::test]
+    async fn test_claim_expired_key() {
+        let repo = get_repo();
+        let then = now() - Duration::hours(2);
+        let now = now();
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            then,
+            then - Duration::hours(1),
+            stale_before(then),
+        )
+        .await
+        .unwrap();
+        repo.complete("key", &TOKEN, response()).await.unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "other",
+                now,
+                now - Duration::hours(1),
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(claimed, None);
+    }
+
+    #[actix_web::test]
+    async fn test_claim_purges_expired_keys_when_key_exists() {
+        let repo = get_repo();
+        let then = now() - Duration::hours(2);
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        repo.claim(
+            "expired",
+            &TOKEN,
+            "fingerprint",
+            then,
+            then - Duration::hours(1),
+            stale_before(then),
+        )
+        .await
+        .unwrap();
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+
+        let count: usize = repo
+            .pool
+            .get()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM idempotency_keys", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_release() {
+        let repo = get_repo();
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        repo.release("key", &TOKEN).await.unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "other",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(claimed, None);
+    }
+
+    #[actix_web::test]
+    async fn test_release_keeps_completed_key() {
+        let repo = get_repo();
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        repo.complete("key", &TOKEN, response()).await.unwrap();
+        repo.release("key", &TOKEN).await.unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "fingerprint",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert!(claimed.is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_complete_after_claimed_again() {
+        let repo = get_repo();
+        let then = now() - Duration::minutes(2);
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+        let retry = Uuid::from_u128(2);
+
+        // The request took longer than the lease on the key, so a retry claimed it again
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            then,
+            expired_before,
+            stale_before(then),
+        )
+        .await
+        .unwrap();
+        repo.claim(
+            "key",
+            &retry,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        let completed = repo.complete("key", &TOKEN, response()).await;
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "fingerprint",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(completed, Err(IdempotencyError::ClaimLost));
+        assert_eq!(claimed.unwrap().response(), &None);
+        assert_eq!(repo.complete("key", &retry, response()).await, Ok(()));
+    }
+
+    #[actix_web::test]
+    async fn test_release_after_claimed_again() {
+        let repo = get_repo();
+        let then = now() - Duration::minutes(2);
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            then,
+            expired_before,
+            stale_before(then),
+        )
+        .await
+        .unwrap();
+        repo.claim(
+            "key",
+            &Uuid::from_u128(2),
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        repo.release("key", &TOKEN).await.unwrap();
+        let claimed = repo
+            .claim(
+                "key",
+                &TOKEN,
+                "other",
+                now,
+                expired_before,
+                stale_before(now),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(claimed.unwrap().fingerprint(), "fingerprint");
+    }
+
+    #[actix_web::test]
+    async fn test_complete_completed_key() {
+        let repo = get_repo();
+        let now = now();
+        let expired_before = now - Duration::hours(1);
+
+        repo.claim(
+            "key",
+            &TOKEN,
+            "fingerprint",
+            now,
+            expired_before,
+            stale_before(now),
+        )
+        .await
+        .unwrap();
+        repo.complete("key", &TOKEN, response()).await.unwrap();
+
+        assert_eq!(
+            repo.complete("key", &TOKEN, response()).await,
+            Err(IdempotencyError::ClaimLost)
+        );
+    }
+}
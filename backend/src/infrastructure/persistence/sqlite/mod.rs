@@ -1,5 +1,23 @@
 pub mod db;
+mod idempotency_repository;
+mod notification_repository;
+mod operations;
+pub mod pool;
 mod product_repository;
+mod stash_item_query;
 mod to_from_sql;
+mod unit_of_work;
 
+pub use idempotency_repository::IdempotencyRepository;
+pub use notification_repository::NotificationRepository;
 pub use product_repository::ProductRepository;
+pub use stash_item_query::StashItemQuery;
+pub use unit_of_work::UnitOfWork;
+
+/// Largest number of variables put in a single statement. Older SQLite versions allow no more than 999
+const MAX_VARIABLES: usize = 999;
+
+/// Makes placeholders for a list of variables in a query. This becomes "?, ?, ?, ..."
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use rusqlite::{named_params, ToSql};
+use uuid::Uuid;
+
+use crate::domain::{
+    errors::NotificationError, repositories::NotificationRepository as NotificationRepositoryTrait,
+};
+
+use super::{
+    placeholders,
+    pool::{with_connection, Pool},
+    MAX_VARIABLES,
+};
+
+/// A repository keeping track of expiry notifications, using SQLite as the underlying storage.
+pub struct NotificationRepository {
+    /// Connections to the database
+    pool: Pool,
+}
+
+impl NotificationRepository {
+    /// Creates a new [`NotificationRepository`]
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl NotificationRepositoryTrait for NotificationRepository {
+    async fn find_notified_windows(
+        &self,
+        notifier: &str,
+        stash_item_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, u32>, NotificationError> {
+        let notifier = notifier.to_string();
+        let stash_item_ids = stash_item_ids
+            .iter()
+            .map(Uuid::to_string)
+            .collect::<Vec<_>>();
+
+        with_connection(&self.pool, move |conn| {
+            let mut windows = HashMap::new();
+
+            // What was notified about without a notifier was delivered by every notifier
+            for stash_item_ids in stash_item_ids.chunks(MAX_VARIABLES - 1) {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT stash_item_id, MIN(window_days) FROM stash_item_notifications WHERE notifier IN (?, '') AND stash_item_id IN ({}) GROUP BY stash_item_id",
+                    placeholders(stash_item_ids.len())
+                ))?;
+                let params = std::iter::once(&notifier as &dyn ToSql)
+                    .chain(stash_item_ids.iter().map(|id| id as &dyn ToSql))
+                    .collect::<Vec<_>>();
+                let rows = stmt.query_map(params.as_slice(), |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+                })?;
+
+                for row in rows {
+                    let (stash_item_id, window_days) = row?;
+                    let stash_item_id = stash_item_id
+                        .parse()
+                        .map_err(|err: uuid::Error| NotificationError::PersistenceError(err.to_string()))?;
+                    windows.insert(stash_item_id, window_days);
+                }
+            }
+
+            Ok(windows)
+        })
+        .await
+    }
+
+    async fn mark_notified(
+        &self,
+        notifier: &str,
+        stash_item_ids: &[Uuid],
+        window_days: u32,
+    ) -> Result<(), NotificationError> {
+        let notifier = notifier.to_string();
+        let stash_item_ids = stash_item_ids.to_vec();
+
+        with_connection(&self.pool, move |conn| {
+            let tx = conn.transaction()?;
+
+            {
+                let mut stmt = tx.prepare_cached(
+                    "INSERT INTO stash_item_notifications (stash_item_id, notifier, window_days, notified_at) VALUES (:stash_item_id, :notifier, :window_days, :now) ON CONFLICT(stash_item_id, notifier, window_days) DO UPDATE SET notified_at = :now",
+                )?;
+                for stash_item_id in stash_item_ids {
+                    stmt.execute(named_params! {
+                        ":stash_item_id": stash_item_id.to_string(),
+                        ":notifier": notifier,
+                        ":window_days": window_days,
+                        ":now": chrono::Utc::now().naive_utc(),
+                    })?;
+                }
+            }
+
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::infrastructure::persistence::sqlite::{
+        db::setup_db,
+        pool::{create_pool, ConnectionManager},
+    };
+
+    fn get_repo() -> NotificationRepository {
+        let pool = create_pool(ConnectionManager::memory(), 1).unwrap();
+
+        setup_db(&pool.get().unwrap()).unwrap();
+
+        NotificationRepository::new(pool)
+    }
+
+    #[actix_web::test]
+    async fn test_find_notified_windows_not_notified() {
+        let repo = get_repo();
+
+        let windows = repo
+            .find_notified_windows("webhook", &[Uuid::new_v4()])
+            .await
+            .unwrap();
+
+        assert!(windows.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_find_notified_windows_narrowest() {
+        let repo = get_repo();
+        let stash_item_id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
+
+        repo.mark_notified("webhook", &[stash_item_id, other_id], 7)
+            .await
+            .unwrap();
+        repo.mark_notified("webhook", &[stash_item_id], 1)
+            .await
+            .unwrap();
+        repo.mark_notified("webhook", &[Uuid::new_v4()], 0)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.find_notified_windows("webhook", &[stash_item_id, other_id])
+                .await
+                .unwrap(),
+            HashMap::from([(stash_item_id, 1), (other_id, 7)])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_find_notified_windows_of_notifier() {
+        let repo = get_repo();
+        let stash_item_id = Uuid::new_v4();
+
+        repo.mark_notified("webhook", &[stash_item_id], 1)
+            .await
+            .unwrap();
+        repo.mark_notified("email", &[stash_item_id], 7)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.find_notified_windows("email", &[stash_item_id])
+                .await
+                .unwrap(),
+            HashMap::from([(stash_item_id, 7)])
+        );
+        assert!(repo
+            .find_notified_windows("push", &[stash_item_id])
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_find_notified_windows_many() {
+        let repo = get_repo();
+        let stash_item_ids = (0..MAX_VARIABLES + 10)
+            .map(|_| Uuid::new_v4())
+            .collect::<Vec<_>>();
+
+        repo.mark_notified("webhook", &stash_item_ids, 3)
+            .await
+            .unwrap();
+
+        let windows = repo
+            .find_notified_windows("webhook", &stash_item_ids)
+            .await
+            .unwrap();
+        assert_eq!(windows.len(), MAX_VARIABLES + 10);
+    }
+
+    #[actix_web::test]
+    async fn test_mark_notified_twice() {
+        let repo = get_repo();
+        let stash_item_id = Uuid::new_v4();
+
+        repo.mark_notified("webhook", &[stash_item_id], 3)
+            .await
+            .unwrap();
+        repo.mark_notified("webhook", &[stash_item_id], 3)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.find_notified_windows("webhook", &[stash_item_id])
+                .await
+                .unwrap(),
+            HashMap::from([(stash_item_id, 3)])
+        );
+    }
+}
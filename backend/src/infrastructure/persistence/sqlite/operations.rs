@@ -0,0 +1,85 @@
+//! The operations which can still be undone, kept in the same database as the products so they are recorded and
+//! undone in the same transaction as the changes they undo
+
+use rusqlite::{named_params, OptionalExtension, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    domain::{errors::ProductRepositoryError, value_objects::Operation},
+    infrastructure::persistence::inverse_record,
+};
+
+/// Records an operation, forgetting the oldest operations beyond the given capacity
+///
+/// # Parameters
+/// - `tx`: The transaction to use
+/// - `operation`: The operation to record
+/// - `capacity`: How many operations to keep
+pub(super) fn save(
+    tx: &Transaction,
+    operation: &Operation,
+    capacity: usize,
+) -> Result<(), ProductRepositoryError> {
+    if capacity == 0 {
+        return Ok(());
+    }
+
+    tx.prepare_cached("INSERT INTO operations (id, inverse) VALUES (:id, :inverse)")?
+        .execute(named_params! {
+            ":id": operation.id().to_string(),
+            ":inverse": inverse_record::to_json(operation.inverse())?,
+        })?;
+
+    // Forget the oldest operations beyond the capacity
+    tx.prepare_cached(
+        "DELETE FROM operations WHERE key NOT IN (SELECT key FROM operations ORDER BY key DESC LIMIT :capacity)",
+    )?
+    .execute(named_params! {
+        ":capacity": capacity,
+    })?;
+
+    Ok(())
+}
+
+/// Finds an operation by its ID
+///
+/// # Parameters
+/// - `tx`: The transaction to use
+/// - `id`: The ID of the operation
+pub(super) fn find_by_id(
+    tx: &Transaction,
+    id: &Uuid,
+) -> Result<Option<Operation>, ProductRepositoryError> {
+    let inverse = tx
+        .prepare_cached("SELECT inverse FROM operations WHERE id = :id")?
+        .query_row(
+            named_params! {
+                ":id": id.to_string(),
+            },
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+
+    inverse
+        .map(|inverse| {
+            Ok(Operation::with_id(
+                *id,
+                inverse_record::from_json(&inverse)?,
+            ))
+        })
+        .transpose()
+}
+
+/// Forgets an operation by its ID
+///
+/// # Parameters
+/// - `tx`: The transaction to use
+/// - `id`: The ID of the operation
+pub(super) fn delete_by_id(tx: &Transaction, id: &Uuid) -> Result<(), ProductRepositoryError> {
+    tx.prepare_cached("DELETE FROM operations WHERE id = :id")?
+        .execute(named_params! {
+            ":id": id.to_string(),
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use std::{path::PathBuf, time::Duration};
+
+use actix_web::rt::task::{spawn_blocking, JoinError};
+use rusqlite::Connection;
+
+/// A pool of connections to an SQLite database
+pub type Pool = r2d2::Pool<ConnectionManager>;
+
+/// A connection borrowed from a [`Pool`]. Goes back to the pool when dropped
+pub type PooledConnection = r2d2::PooledConnection<ConnectionManager>;
+
+/// How long a connection waits for another to release a lock before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Opens and configures connections for a [`Pool`]
+#[derive(Debug, Clone)]
+pub struct ConnectionManager {
+    /// Path to the database file. `None` for an in-memory database
+    path: Option<PathBuf>,
+}
+
+impl ConnectionManager {
+    /// Creates a manager for the database file at the given path
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// Creates a manager for an in-memory database
+    pub fn memory() -> Self {
+        Self { path: None }
+    }
+}
+
+impl r2d2::ManageConnection for ConnectionManager {
+    type Connection = Connection;
+    type Error = rusqlite::Error;
+
+    fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let connection = match &self.path {
+            Some(path) => {
+                let connection = Connection::open(path)?;
+                // Readers see the last commit instead of waiting for writers to finish
+                connection.pragma_update(None, "journal_mode", "WAL")?;
+                connection
+            }
+            None => Connection::open_in_memory()?,
+        };
+
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+
+        Ok(connection)
+    }
+
+    fn is_valid(&self, connection: &mut Self::Connection) -> Result<(), Self::Error> {
+        connection.execute_batch("")
+    }
+
+    fn has_broken(&self, _: &mut Self::Connection) -> bool {
+        false
+    }
+}
+
+/// Creates a pool of connections to an SQLite database
+///
+/// # Parameters
+/// - `manager`: Opens the connections of the pool
+/// - `size`: Largest number of connections in the pool. Ignored for in-memory databases, which live and die with their
+///   only connection
+pub fn create_pool(manager: ConnectionManager, size: u32) -> Result<Pool, r2d2::Error> {
+    let builder = Pool::builder();
+
+    let builder = match manager.path {
+        Some(_) => builder.max_size(size),
+        None => builder
+            .max_size(1)
+            .min_idle(Some(1))
+            .idle_timeout(None)
+            .max_lifetime(None),
+    };
+
+    builder.build(manager)
+}
+
+/// Runs work against a connection from the pool on the thread pool for blocking work, so waiting for a connection and
+/// for the database does not hold up the async workers
+///
+/// # Errors
+/// Returns the error of the work, or the error of getting a connection or of running the blocking task
+pub async fn with_connection<F, T, E>(pool: &Pool, work: F) -> Result<T, E>
+where
+    F: FnOnce(&mut Connection) -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: From<r2d2::Error> + From<JoinError> + Send + 'static,
+{
+    let pool = pool.clone();
+
+    spawn_blocking(move || {
+        let mut conn = pool.get()?;
+        work(&mut conn)
+    })
+    .await?
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::{
+        sync::{mpsc, Arc, Barrier},
+        thread,
+    };
+
+    use actix_web::rt;
+    use uuid::Uuid;
+
+    use crate::{
+        domain::{entities::FakeProduct, repositories::ProductRepository as _},
+        infrastructure::persistence::sqlite::{db::setup_db, ProductRepository},
+    };
+
+    use super::*;
+
+    /// How long a test waits for something that should not be blocked
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// A database file in the temp directory, removed along with its WAL files when dropped
+    pub(crate) struct TempDb(PathBuf);
+
+    impl TempDb {
+        pub(crate) fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("stash-{}.db", Uuid::new_v4())))
+        }
+
+        pub(crate) fn pool(&self, size: u32) -> Pool {
+            let pool = create_pool(ConnectionManager::file(&self.0), size).unwrap();
+            setup_db(&pool.get().unwrap()).unwrap();
+            pool
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let mut path = self.0.clone().into_os_string();
+                path.push(suffix);
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_connections_are_configured() {
+        let db = TempDb::new();
+        let pool = db.pool(2);
+        let connection = pool.get().unwrap();
+
+        let journal_mode: String = connection
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        let foreign_keys: bool = connection
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .unwrap();
+        let busy_timeout: u64 = connection
+            .query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(journal_mode, "wal");
+        assert!(foreign_keys);
+        assert_eq!(busy_timeout, BUSY_TIMEOUT.as_millis() as u64);
+    }
+
+    #[test]
+    fn test_readers_do_not_block_each_other() {
+        let db = TempDb::new();
+        let pool = db.pool(4);
+        let readers = 4;
+
+        // Every reader holds a read transaction open until all of them have one. With a single shared connection, the
+        // first reader would keep the others out and this would never finish
+        let barrier = Arc::new(Barrier::new(readers));
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..readers {
+            let pool = pool.clone();
+            let barrier = barrier.clone();
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                let mut connection = pool.get().unwrap();
+                let tx = connection.transaction().unwrap();
+                let count: i64 = tx
+                    .query_row("SELECT COUNT(*) FROM products", [], |row| row.get(0))
+                    .unwrap();
+                barrier.wait();
+                tx.commit().unwrap();
+
+                sender.send(count).unwrap();
+            });
+        }
+
+        for _ in 0..readers {
+            assert_eq!(receiver.recv_timeout(TIMEOUT), Ok(0));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_readers_are_not_blocked_by_writer() {
+        let db = TempDb::new();
+        let pool = db.pool(4);
+        let repository = Arc::new(ProductRepository::new(pool.clone()));
+        repository.save(FakeProduct::new().build()).await.unwrap();
+
+        // Keep a write transaction open while reading
+        let mut writer = pool.get().unwrap();
+        let tx = writer
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+            .unwrap();
+        tx.execute(
+            "INSERT INTO products (id, brand, name, created_at) VALUES ('1', 'Brand', 'Name', '2023-01-01')",
+            [],
+        )
+        .unwrap();
+
+        // Load the repository from several tasks at once
+        let readers = (0..8)
+            .map(|_| {
+                let repository = repository.clone();
+
+                rt::spawn(async move {
+                    for _ in 0..25 {
+                        let products = repository
+                            .find_by_ids(&["1".parse().unwrap()])
+                            .await
+                            .unwrap();
+                        assert!(products.is_empty());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for reader in readers {
+            rt::time::timeout(TIMEOUT, reader).await.unwrap().unwrap();
+        }
+
+        tx.commit().unwrap();
+        assert_eq!(
+            repository
+                .find_by_ids(&["1".parse().unwrap()])
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -1,31 +1,84 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
-use chrono::NaiveDate;
-use rusqlite::{named_params, Connection, ToSql, Transaction};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use rusqlite::{named_params, OptionalExtension, ToSql, Transaction};
 use uuid::Uuid;
 
 use crate::domain::{
     entities::{Product, StashItem},
     errors::ProductRepositoryError,
+    events::ProductEventDispatcher,
     repositories::ProductRepository as ProductRepositoryTrait,
-    value_objects::{Brand, ProductId, Quantity},
+    value_objects::{Brand, ExpiryDateKind, ProductId, Quantity, TrashEntry, Unit},
+};
+
+use super::{
+    placeholders,
+    pool::{with_connection, Pool},
+    MAX_VARIABLES,
 };
 
+/// SQL expression for the date a stash item actually expires, mirroring [`Product::effective_expiry_date`]. Expects
+/// the stash item as `s` and its product as `p`. Is `NULL` for stash items which do not expire
+pub(super) const EFFECTIVE_EXPIRY_DATE: &str =
+    "CASE WHEN s.opened_on IS NOT NULL AND p.shelf_life_after_opening_days IS NOT NULL \
+    THEN MIN(COALESCE(s.expiry_date, '9999-12-31'), date(s.opened_on, '+' || p.shelf_life_after_opening_days || ' days')) \
+    ELSE s.expiry_date END";
+
+/// SQL expression for the date a stash item is no longer safe, mirroring [`Product::unsafe_date`]. Expects the stash
+/// item as `s` and its product as `p`. Is `NULL` for stash items which never become unsafe
+fn unsafe_date() -> String {
+    format!(
+        "CASE WHEN s.expiry_date_kind = 'use_by' THEN {0} \
+        WHEN p.best_before_grace_period_days IS NOT NULL \
+        THEN date({0}, '+' || p.best_before_grace_period_days || ' days') \
+        ELSE NULL END",
+        EFFECTIVE_EXPIRY_DATE
+    )
+}
+
 /// A repository for [`Product`]s using SQLite as the underlying storage.
 pub struct ProductRepository {
-    /// Connection to the database
-    connection: Arc<Mutex<Connection>>,
+    /// Connections to the database
+    pool: Pool,
+    /// Gets the events of saved products
+    event_dispatcher: Arc<ProductEventDispatcher>,
 }
 
 impl ProductRepository {
     /// Creates a new [`ProductRepository`]
-    pub fn new(connection: Arc<Mutex<Connection>>) -> Self {
-        Self { connection }
+    pub fn new(pool: Pool) -> Self {
+        Self {
+            pool,
+            event_dispatcher: Arc::default(),
+        }
     }
 
-    /// Shortcut to get the connection to the database
-    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
-        self.connection.lock().unwrap()
+    /// Makes the repository dispatch the events of the products it saves, once they are saved
+    pub fn with_event_dispatcher(mut self, event_dispatcher: Arc<ProductEventDispatcher>) -> Self {
+        self.event_dispatcher = event_dispatcher;
+        self
+    }
+
+    /// Runs work in a transaction on a connection from the pool, without blocking the async runtime. The transaction is
+    /// committed if the work succeeds, and rolled back otherwise
+    async fn in_transaction<F, T>(&self, work: F) -> Result<T, ProductRepositoryError>
+    where
+        F: FnOnce(&Transaction) -> Result<T, ProductRepositoryError> + Send + 'static,
+        T: Send + 'static,
+    {
+        with_connection(&self.pool, move |conn| {
+            let tx = conn.transaction()?;
+            let result = work(&tx)?;
+            tx.commit()?;
+
+            Ok(result)
+        })
+        .await
     }
 
     /// Converts a raw database row into a [`Product`]
@@ -38,71 +91,182 @@ impl ProductRepository {
         let id = row.get::<_, ProductId>("id")?;
         let brand = row.get::<_, Brand>("brand")?;
         let name = row.get::<_, String>("name")?;
+        let unit = row.get::<_, Unit>("unit")?;
+        let shelf_life_after_opening_days =
+            row.get::<_, Option<u32>>("shelf_life_after_opening_days")?;
+        let best_before_grace_period_days =
+            row.get::<_, Option<u32>>("best_before_grace_period_days")?;
+
+        let mut product = Product::new(id, brand, name, vec![]);
+        product.set_unit(unit);
+        product.set_shelf_life_after_opening_days(shelf_life_after_opening_days);
+        product.set_best_before_grace_period_days(best_before_grace_period_days);
 
-        Ok(Product::new(id, brand, name, vec![]))
+        Ok(product)
     }
 
-    fn find_product_ids_from_all_stash_items(
+    /// Finds the internal key of a product by its ID, or by a barcode attached to it. Products in the trash are left
+    /// out
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `id`: The ID or barcode of the product
+    ///
+    /// # Returns
+    /// The key of the product, if found
+    pub(super) fn find_key(
         tx: &Transaction,
-    ) -> Result<Vec<ProductId>, ProductRepositoryError> {
-        let mut stmt = tx.prepare("SELECT DISTINCT product_id FROM stash_items")?;
-        let mut rows = stmt.query([])?;
+        id: &ProductId,
+    ) -> Result<Option<i64>, ProductRepositoryError> {
+        let mut stmt = tx.prepare_cached(
+            "SELECT key FROM products WHERE id = :id AND deleted_at IS NULL UNION ALL SELECT b.product_key FROM product_barcodes b JOIN products p ON p.key = b.product_key WHERE b.barcode = :id AND p.deleted_at IS NULL LIMIT 1",
+        )?;
+        let mut rows = stmt.query(named_params! { ":id": id })?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get::<_, i64>(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Gets the products matching an SQL condition, along with their barcodes and stash items. Products in the trash
+    /// are left out
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
+    ///
+    /// # Returns
+    /// The products that were found
+    fn find_where(
+        tx: &Transaction,
+        condition: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        Ok(ProductRepository::find_keyed_where(
+            tx,
+            &format!("deleted_at IS NULL AND ({})", condition),
+            params,
+        )?
+        .into_iter()
+        .map(|(_, product)| product)
+        .collect())
+    }
 
-        let mut product_ids = vec![];
+    /// Gets the products matching an SQL condition, along with their barcodes and stash items, whether they are in
+    /// the trash or not. Loads everything with three queries, no matter how many products match
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
+    ///
+    /// # Returns
+    /// The products that were found, along with their internal keys
+    fn find_keyed_where(
+        tx: &Transaction,
+        condition: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<Vec<(i64, Product)>, ProductRepositoryError> {
+        let mut stmt = tx.prepare_cached(&format!(
+            "SELECT key, id, brand, name, unit, shelf_life_after_opening_days, best_before_grace_period_days FROM products WHERE {}",
+            condition
+        ))?;
+        let mut rows = stmt.query(params)?;
+
+        let mut products = vec![];
         while let Some(row) = rows.next()? {
-            let product_id = row.get::<_, ProductId>("product_id")?;
-            product_ids.push(product_id);
+            let key = row.get::<_, i64>("key")?;
+            products.push((key, ProductRepository::row_to_product(row)?));
         }
 
-        Ok(product_ids)
+        let mut barcodes = ProductRepository::get_barcodes(tx, condition, params)?;
+        let mut stash_items = ProductRepository::get_stash_items(tx, condition, params)?;
+
+        Ok(products
+            .into_iter()
+            .map(|(key, mut product)| {
+                for barcode in barcodes.remove(&key).unwrap_or_default() {
+                    product.attach_barcode(barcode);
+                }
+                for stash_item in stash_items.remove(&key).unwrap_or_default() {
+                    product.add_stash_item(stash_item).unwrap_or_else(|_| {
+                        panic!("Duplicate expiry dates in DB for product {}", product.id())
+                    });
+                }
+                // The stash items were there all along
+                product.take_events();
+
+                (key, product)
+            })
+            .collect())
     }
 
-    fn find_by_ids(
+    /// Gets products from the database by their internal keys
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `keys`: The keys of the products to get
+    ///
+    /// # Returns
+    /// The products that were found
+    fn find_by_keys(
         tx: &Transaction,
-        ids: &[ProductId],
+        keys: &[i64],
     ) -> Result<Vec<Product>, ProductRepositoryError> {
-        // Create placeholders for the query. This becomes "?, ?, ?, ..."
-        let placeholders = ids.iter().map(|_| "?").collect::<Vec<_>>();
-
-        // TODO Clean up this mess
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-        for id in ids {
-            let id = Box::new(id.to_string());
-            params.push(id);
-        }
-        let params = params.iter().map(|param| &**param).collect::<Vec<_>>();
+        let mut products = vec![];
 
-        let mut stmt = tx.prepare(&format!(
-            "SELECT id, brand, name FROM products WHERE id IN ({})",
-            placeholders.join(", ")
-        ))?;
+        for keys in keys.chunks(MAX_VARIABLES) {
+            let params = keys.iter().map(|key| key as &dyn ToSql).collect::<Vec<_>>();
 
-        // TODO Somehow use query_map or query_and_then here?
-        let mut rows = stmt.query(&params[..])?;
+            products.extend(ProductRepository::find_where(
+                tx,
+                &format!("key IN ({})", placeholders(keys.len())),
+                &params,
+            )?);
+        }
+
+        Ok(products)
+    }
 
+    fn find_by_ids(
+        tx: &Transaction,
+        ids: &[ProductId],
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
         let mut products = vec![];
-        while let Some(row) = rows.next()? {
-            let product = ProductRepository::row_to_product(row)?;
-            let product = ProductRepository::add_stash_items(tx, product)?;
-            products.push(product);
+
+        for ids in ids.chunks(MAX_VARIABLES) {
+            let params = ids.iter().map(|id| id as &dyn ToSql).collect::<Vec<_>>();
+
+            products.extend(ProductRepository::find_where(
+                tx,
+                &format!("id IN ({})", placeholders(ids.len())),
+                &params,
+            )?);
         }
 
         Ok(products)
     }
 
-    /// Gets a product from the database by its ID
+    /// Gets a product from the database by its ID, or by a barcode attached to it
     ///
     /// # Parameters
-    /// - `id`: The ID of the product to get
+    /// - `id`: The ID or barcode of the product to get
     ///
     /// # Returns
     /// The product, if found
     /// An error if the product could not be found
-    fn find_by_id(
+    pub(super) fn find_by_id(
         tx: &Transaction,
         id: &ProductId,
     ) -> Result<Option<Product>, ProductRepositoryError> {
-        ProductRepository::find_by_ids(tx, &[id.clone()]).map(|mut products| products.pop())
+        match ProductRepository::find_key(tx, id)? {
+            Some(key) => {
+                ProductRepository::find_by_keys(tx, &[key]).map(|mut products| products.pop())
+            }
+            None => Ok(None),
+        }
     }
 
     /// Finds a product by the ID of one of its [`StashItem`]s
@@ -113,54 +277,54 @@ impl ProductRepository {
     ///
     /// # Returns
     /// The product, if found
-    fn find_by_stash_item_id(
+    pub(super) fn find_by_stash_item_id(
         tx: &Transaction,
         stash_item_id: &Uuid,
     ) -> Result<Option<Product>, ProductRepositoryError> {
-        let mut stmt =
-            tx.prepare("SELECT product_id FROM stash_items WHERE id = :stash_item_id LIMIT 1")?;
-        let mut rows = stmt.query(named_params! { ":stash_item_id": stash_item_id.to_string() })?;
-
-        if let Some(row) = rows.next()? {
-            let product_id = row.get::<_, ProductId>("product_id")?;
-
-            ProductRepository::find_by_id(tx, &product_id)
-        } else {
-            Ok(None)
-        }
+        ProductRepository::find_where(
+            tx,
+            "key = (SELECT product_key FROM stash_items WHERE id = ? AND deleted_at IS NULL LIMIT 1)",
+            &[&stash_item_id.to_string()],
+        )
+        .map(|mut products| products.pop())
     }
 
-    /// Finds all products with at least one stash item expiring within the given date interval
+    /// Finds all products with at least one stash item whose date, as given by an SQL expression, is within the given
+    /// date interval
     ///
     /// # Parameters
     /// - `tx`: The transaction to use
+    /// - `date`: SQL expression for the date of a stash item, with the stash item as `s` and its product as `p`
     /// - `after`: The start of the date range, inclusive
     /// - `before`: The end of the date range, exclusive
     ///
     /// # Returns
-    /// A list of products with at least one stash item expiring within the given date interval
-    fn find_expiring_in_interval(
+    /// A list of products with at least one stash item dated within the given date interval
+    fn find_in_interval(
         tx: &Transaction,
+        date: &str,
         after: Option<NaiveDate>,
         before: Option<NaiveDate>,
     ) -> Result<Vec<Product>, ProductRepositoryError> {
         // Hold the query and args for it outside of the match to ensure their lifetime is long enough
-        let mut query = String::from("SELECT DISTINCT product_id FROM stash_items WHERE ");
+        let mut query = String::from(
+            "SELECT s.product_key FROM stash_items s JOIN products p ON p.key = s.product_key WHERE s.deleted_at IS NULL AND ",
+        );
         let mut args: Vec<Box<dyn ToSql>> = Vec::new();
 
         // Build the query
         match (after, before) {
             (Some(after), Some(before)) => {
-                query.push_str("expiry_date >= ? AND expiry_date < ?");
+                query.push_str(&format!("{0} >= ? AND {0} < ?", date));
                 args.push(Box::new(after.to_string()));
                 args.push(Box::new(before.to_string()));
             }
             (Some(after), None) => {
-                query.push_str("expiry_date >= ?");
+                query.push_str(&format!("{} >= ?", date));
                 args.push(Box::new(after.to_string()));
             }
             (None, Some(before)) => {
-                query.push_str("expiry_date < ?");
+                query.push_str(&format!("{} < ?", date));
                 args.push(Box::new(before.to_string()));
             }
             (None, None) => {
@@ -171,16 +335,7 @@ impl ProductRepository {
         // Convert the args to something the query can use
         let args = args.iter().map(|arg| &**arg).collect::<Vec<_>>();
 
-        let product_ids = tx
-            .prepare(&query)?
-            .query_map(&args[..], |row| {
-                let product_id = row.get::<_, ProductId>("product_id")?;
-                Ok(product_id)
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-
-        // Get the products
-        ProductRepository::find_by_ids(tx, &product_ids)
+        ProductRepository::find_where(tx, &format!("key IN ({})", query), &args)
     }
 
     /// Saves a [`Product`] to the database. If the product already exists, it will be updated.
@@ -188,88 +343,404 @@ impl ProductRepository {
     /// # Parameters
     /// - `tx`: The transaction to use
     /// - `product`: The product to save
-    fn save_product(tx: &Transaction, product: Product) -> Result<(), ProductRepositoryError> {
-        // Save the product itself
+    pub(super) fn save_product(
+        tx: &Transaction,
+        product: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        // A product in the trash with the same ID is replaced by this one
+        let trashed_key = tx
+            .query_row(
+                "SELECT key FROM products WHERE id = :id AND deleted_at IS NOT NULL",
+                named_params! {
+                    ":id": product.id(),
+                },
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        if let Some(key) = trashed_key {
+            ProductRepository::delete_product_by_key(tx, key)?;
+        }
+
+        // The ID of a product takes precedence over the same barcode attached to another product
         tx.execute(
-            "INSERT INTO products (id, brand, name, created_at) VALUES (:id, :brand, :name, :now) ON CONFLICT(id) DO UPDATE SET brand = :brand, name = :name, updated_at = :now",
+            "DELETE FROM product_barcodes WHERE barcode = :id",
+            named_params! {
+                ":id": product.id(),
+            },
+        )?;
+
+        // Save the product itself
+        let key = tx.query_row(
+            "INSERT INTO products (id, brand, name, unit, shelf_life_after_opening_days, best_before_grace_period_days, created_at) VALUES (:id, :brand, :name, :unit, :shelf_life_after_opening_days, :best_before_grace_period_days, :now) ON CONFLICT(id) DO UPDATE SET brand = :brand, name = :name, unit = :unit, shelf_life_after_opening_days = :shelf_life_after_opening_days, best_before_grace_period_days = :best_before_grace_period_days, updated_at = :now RETURNING key",
             named_params! {
                 ":id": product.id(),
                 ":brand": product.brand(),
                 ":name": product.name(),
+                ":unit": product.unit(),
+                ":shelf_life_after_opening_days": product.shelf_life_after_opening_days(),
+                ":best_before_grace_period_days": product.best_before_grace_period_days(),
                 ":now": chrono::Utc::now().naive_utc(),
             },
+            |row| row.get::<_, i64>("key"),
         )?;
 
+        // Attach and detach barcodes
+        ProductRepository::save_barcodes(tx, &product, key)?;
+
         // Delete all stash items no longer in the product
-        ProductRepository::delete_deleted_stash_items(tx, &product)?;
+        ProductRepository::delete_deleted_stash_items(tx, &product, key)?;
 
         // Create and update all stash items
-        ProductRepository::save_stash_items(tx, &product)?;
+        ProductRepository::save_stash_items(tx, &product, key)?;
+
+        Ok(())
+    }
+
+    /// Moves a product to the trash, along with its stash items. Its barcodes stay attached to it, unless other
+    /// products take them
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `product_id`: ID of the product to delete, or a barcode attached to it
+    pub(super) fn trash_product(
+        tx: &Transaction,
+        product_id: &ProductId,
+    ) -> Result<(), ProductRepositoryError> {
+        if let Some(key) = ProductRepository::find_key(tx, product_id)? {
+            tx.execute(
+                "UPDATE products SET deleted_at = :now WHERE key = :key",
+                named_params! {
+                    ":key": key,
+                    ":now": chrono::Utc::now().naive_utc(),
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves a stash item of a product which is not in the trash itself to the trash
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `stash_item_id`: ID of the stash item to delete
+    pub(super) fn trash_stash_item(
+        tx: &Transaction,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        tx.execute(
+            "UPDATE stash_items SET deleted_at = :now WHERE id = :id AND deleted_at IS NULL AND product_key IN (SELECT key FROM products WHERE deleted_at IS NULL)",
+            named_params! {
+                ":id": stash_item_id.to_string(),
+                ":now": chrono::Utc::now().naive_utc(),
+            },
+        )?;
 
         Ok(())
     }
 
-    /// Deletes a product from the database, along with all its stash items
+    /// Gets everything in the trash, most recently deleted first
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    fn find_trash(tx: &Transaction) -> Result<Vec<TrashEntry>, ProductRepositoryError> {
+        let mut deleted_at = HashMap::new();
+        let mut stmt =
+            tx.prepare_cached("SELECT key, deleted_at FROM products WHERE deleted_at IS NOT NULL")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            deleted_at.insert(
+                row.get::<_, i64>("key")?,
+                row.get::<_, NaiveDateTime>("deleted_at")?,
+            );
+        }
+
+        let mut entries = ProductRepository::find_keyed_where(tx, "deleted_at IS NOT NULL", &[])?
+            .into_iter()
+            .filter_map(|(key, product)| {
+                Some(TrashEntry::Product {
+                    product,
+                    deleted_at: deleted_at.remove(&key)?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Stash items of products in the trash are only restored along with their product
+        let mut stmt = tx.prepare_cached(
+            "SELECT s.id, s.quantity, s.expiry_date, s.expiry_date_kind, s.opened_on, s.deleted_at, p.id AS product_id FROM stash_items s JOIN products p ON p.key = s.product_key WHERE s.deleted_at IS NOT NULL AND p.deleted_at IS NULL",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            entries.push(TrashEntry::StashItem {
+                product_id: row.get::<_, ProductId>("product_id")?,
+                stash_item: ProductRepository::row_to_stash_item(row)?,
+                deleted_at: row.get::<_, NaiveDateTime>("deleted_at")?,
+            });
+        }
+
+        entries.sort_by(|a, b| {
+            b.deleted_at()
+                .cmp(a.deleted_at())
+                .then_with(|| a.id().cmp(&b.id()))
+        });
+
+        Ok(entries)
+    }
+
+    /// Restores a product from the trash
     ///
     /// # Parameters
     /// - `tx`: The transaction to use
-    /// - `product_id`: ID of the product to delete
-    fn delete_product(
+    /// - `product_id`: ID of the product to restore
+    pub(super) fn restore_product(
         tx: &Transaction,
         product_id: &ProductId,
     ) -> Result<(), ProductRepositoryError> {
+        let key = tx
+            .query_row(
+                "SELECT key FROM products WHERE id = :id AND deleted_at IS NOT NULL",
+                named_params! {
+                    ":id": product_id,
+                },
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .ok_or(ProductRepositoryError::ProductNotFound)?;
+
+        // Another product may have been given the ID as a barcode since
+        if ProductRepository::find_key(tx, product_id)?.is_some() {
+            return Err(ProductRepositoryError::ProductAlreadyExists);
+        }
+
+        tx.execute(
+            "UPDATE products SET deleted_at = NULL, updated_at = :now WHERE key = :key",
+            named_params! {
+                ":key": key,
+                ":now": chrono::Utc::now().naive_utc(),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Restores a stash item from the trash, as long as its product is not in the trash
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `stash_item_id`: ID of the stash item to restore
+    pub(super) fn restore_stash_item(
+        tx: &Transaction,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let id = stash_item_id.to_string();
+
+        let trashed = tx
+            .query_row(
+                "SELECT 1 FROM stash_items s JOIN products p ON p.key = s.product_key WHERE s.id = :id AND s.deleted_at IS NOT NULL AND p.deleted_at IS NULL",
+                named_params! {
+                    ":id": id,
+                },
+                |_| Ok(()),
+            )
+            .optional()?;
+        if trashed.is_none() {
+            return Err(ProductRepositoryError::StashItemNotFound);
+        }
+
+        // The product may have gotten another stash item with the same dates since
+        let taken = tx
+            .query_row(
+                "SELECT 1 FROM stash_items s JOIN stash_items t ON t.product_key = s.product_key AND COALESCE(t.expiry_date, '') = COALESCE(s.expiry_date, '') AND COALESCE(t.opened_on, '') = COALESCE(s.opened_on, '') WHERE s.id = :id AND t.deleted_at IS NULL",
+                named_params! {
+                    ":id": id,
+                },
+                |_| Ok(()),
+            )
+            .optional()?;
+        if taken.is_some() {
+            return Err(ProductRepositoryError::DuplicateExpiryDateError);
+        }
+
+        tx.execute(
+            "UPDATE stash_items SET deleted_at = NULL, updated_at = :now WHERE id = :id",
+            named_params! {
+                ":id": id,
+                ":now": chrono::Utc::now().naive_utc(),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Permanently deletes the products and stash items which went to the trash before the given time
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `deleted_before`: Products and stash items deleted before this time are purged
+    ///
+    /// # Returns
+    /// The number of products and stash items purged
+    fn purge_trash(
+        tx: &Transaction,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError> {
+        let mut stmt = tx.prepare_cached("SELECT key FROM products WHERE deleted_at < :before")?;
+        let keys = stmt
+            .query_map(named_params! { ":before": deleted_before }, |row| {
+                row.get::<_, i64>(0)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for key in &keys {
+            ProductRepository::delete_product_by_key(tx, *key)?;
+        }
+
+        let stash_items = tx.execute(
+            "DELETE FROM stash_items WHERE deleted_at < :before",
+            named_params! {
+                ":before": deleted_before,
+            },
+        )?;
+
+        Ok(keys.len() + stash_items)
+    }
+
+    /// Permanently deletes a product from the database by its internal key, along with all its stash items and barcodes
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `key`: Key of the product to delete
+    fn delete_product_by_key(tx: &Transaction, key: i64) -> Result<(), ProductRepositoryError> {
         // Delete all stash items related to the product
         tx.execute(
-            "DELETE FROM stash_items WHERE product_id = :product_id",
+            "DELETE FROM stash_items WHERE product_key = :product_key",
+            named_params! {
+                ":product_key": key,
+            },
+        )?;
+
+        // Delete all barcodes attached to the product
+        tx.execute(
+            "DELETE FROM product_barcodes WHERE product_key = :product_key",
             named_params! {
-                ":product_id": product_id,
+                ":product_key": key,
             },
         )?;
 
         // Delete the product itself
         tx.execute(
-            "DELETE FROM products WHERE id = :id",
+            "DELETE FROM products WHERE key = :key",
             named_params! {
-                ":id": product_id,
+                ":key": key,
             },
         )?;
 
         Ok(())
     }
 
-    /// Deletes all [`StashItem`]s from the database that are no longer in the passed [`Product`]
+    /// Merges a product into another. The target is saved, which moves over the stash items and barcodes it took from
+    /// the source, and the source is deleted along with any stash items still belonging to it
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `source_id`: ID of the product being merged
+    /// - `target`: The product being merged into, with the source's stash items and barcodes already added
+    pub(super) fn merge_product(
+        tx: &Transaction,
+        source_id: &ProductId,
+        target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        // Find the source before its ID becomes a barcode of the target
+        let source_key = ProductRepository::find_key(tx, source_id)?;
+
+        ProductRepository::save_product(tx, target)?;
+
+        if let Some(source_key) = source_key {
+            ProductRepository::delete_product_by_key(tx, source_key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the barcodes attached to a [`Product`], detaching those no longer attached to it. Barcodes attached to
+    /// other products are moved to this one
+    ///
+    /// # Parameters
+    /// - `tx`: The transaction to use
+    /// - `product`: The product to save the barcodes for
+    /// - `key`: Key of the product
+    fn save_barcodes(
+        tx: &Transaction,
+        product: &Product,
+        key: i64,
+    ) -> Result<(), ProductRepositoryError> {
+        // Detach all barcodes no longer attached to the product. The barcodes to keep are compared here rather than
+        // in the query, as there may be more of them than SQLite allows variables
+        let barcodes = product
+            .barcodes()
+            .iter()
+            .map(|barcode| barcode.to_string())
+            .collect::<HashSet<_>>();
+        let detached = tx
+            .prepare_cached("SELECT barcode FROM product_barcodes WHERE product_key = ?")?
+            .query_map([key], |row| row.get::<_, String>(0))?
+            .filter(|barcode| {
+                barcode
+                    .as_ref()
+                    .map_or(true, |barcode| !barcodes.contains(barcode))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = tx.prepare_cached("DELETE FROM product_barcodes WHERE barcode = ?")?;
+        for barcode in detached {
+            stmt.execute([barcode])?;
+        }
+
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO product_barcodes (barcode, product_key, created_at) VALUES (:barcode, :product_key, :now) ON CONFLICT(barcode) DO UPDATE SET product_key = :product_key",
+        )?;
+        for barcode in product.barcodes() {
+            stmt.execute(named_params! {
+                ":barcode": barcode,
+                ":product_key": key,
+                ":now": chrono::Utc::now().naive_utc(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes all [`StashItem`]s from the database that are no longer in the passed [`Product`]. Those in the trash
+    /// are left there
     ///
     /// # Parameters
     /// - `tx`: The transaction to use
     /// - `product`: The product to delete the stash items from
+    /// - `key`: Key of the product
     fn delete_deleted_stash_items(
         tx: &Transaction,
         product: &Product,
+        key: i64,
     ) -> Result<(), ProductRepositoryError> {
-        // Create placeholders for the query. This becomes "?, ?, ?, ..."
-        let placeholders = product
+        // Delete all stash items no longer in the product. The stash items to keep are compared here rather than in
+        // the query, as there may be more of them than SQLite allows variables
+        let ids = product
             .stash_items()
             .iter()
-            .map(|_| "?")
-            .collect::<Vec<_>>();
+            .map(|stash_item| stash_item.id().to_string())
+            .collect::<HashSet<_>>();
+        let deleted = tx
+            .prepare_cached(
+                "SELECT id FROM stash_items WHERE product_key = ? AND deleted_at IS NULL",
+            )?
+            .query_map([key], |row| row.get::<_, String>(0))?
+            .filter(|id| id.as_ref().map_or(true, |id| !ids.contains(id)))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Make the list of parameters to pass to the query. This becomes [product_id, stash_item_id1, stash_item_id2, ...]
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-        params.push(Box::new(product.id()));
-        for stash_item in product.stash_items() {
-            let id = Box::new(stash_item.id().to_string());
-            params.push(id);
+        let mut stmt = tx.prepare_cached("DELETE FROM stash_items WHERE id = ?")?;
+        for id in deleted {
+            stmt.execute([id])?;
         }
-        let params = params.iter().map(|param| &**param).collect::<Vec<_>>();
-
-        // Delete all stash items no longer in the product
-        tx.execute(
-            &format!(
-                "DELETE FROM stash_items WHERE product_id = ? AND id NOT IN ({})",
-                placeholders.join(", ")
-            ),
-            &params[..],
-        )?;
 
         Ok(())
     }
@@ -280,15 +751,24 @@ impl ProductRepository {
     /// # Parameters
     /// - `tx`: The transaction to use
     /// - `product`: The product to save the stash items for
-    fn save_stash_items(tx: &Transaction, product: &Product) -> Result<(), ProductRepositoryError> {
+    /// - `key`: Key of the product
+    fn save_stash_items(
+        tx: &Transaction,
+        product: &Product,
+        key: i64,
+    ) -> Result<(), ProductRepositoryError> {
+        // Saving a stash item with the ID of one in the trash replaces that one
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO stash_items (id, product_key, quantity, expiry_date, expiry_date_kind, opened_on, created_at) VALUES (:id, :product_key, :quantity, :expiry_date, :expiry_date_kind, :opened_on, :now) ON CONFLICT(id) DO UPDATE SET product_key = :product_key, quantity = :quantity, expiry_date = :expiry_date, expiry_date_kind = :expiry_date_kind, opened_on = :opened_on, updated_at = :now, deleted_at = NULL",
+        )?;
         for stash_item in product.stash_items() {
-            tx.execute(
-            "INSERT INTO stash_items (id, product_id, quantity, expiry_date, created_at) VALUES (:id, :product_id, :quantity, :expiry_date, :now) ON CONFLICT(id) DO UPDATE SET quantity = :quantity, expiry_date = :expiry_date, updated_at = :now"
-            , named_params! {
+            stmt.execute(named_params! {
                 ":id": stash_item.id().to_string(),
-                ":product_id": product.id(),
+                ":product_key": key,
                 ":quantity": stash_item.quantity(),
                 ":expiry_date": stash_item.expiry_date(),
+                ":expiry_date_kind": stash_item.expiry_date_kind(),
+                ":opened_on": stash_item.opened_on(),
                 ":now": chrono::Utc::now().naive_utc(),
             })?;
         }
@@ -302,557 +782,429 @@ impl ProductRepository {
     ///
     /// This function will return an error if the row contains invalid data, or if the data cannot be parsed into a
     /// [`StashItem`]
-    fn row_to_stash_item(row: &rusqlite::Row) -> Result<StashItem, ProductRepositoryError> {
+    pub(super) fn row_to_stash_item(
+        row: &rusqlite::Row,
+    ) -> Result<StashItem, ProductRepositoryError> {
         let id = row.get::<_, String>("id")?;
         let quantity = row.get::<_, Quantity>("quantity")?;
-        let expiry_date = row.get::<_, NaiveDate>("expiry_date")?;
+        let expiry_date = row.get::<_, Option<NaiveDate>>("expiry_date")?;
+        let expiry_date_kind = row.get::<_, ExpiryDateKind>("expiry_date_kind")?;
+        let opened_on = row.get::<_, Option<NaiveDate>>("opened_on")?;
+
+        let mut stash_item = StashItem::new(Uuid::parse_str(&id)?, quantity, expiry_date);
+        stash_item.set_expiry_date_kind(expiry_date_kind);
+        stash_item.set_opened_on(opened_on);
 
-        Ok(StashItem::new(Uuid::parse_str(&id)?, quantity, expiry_date))
+        Ok(stash_item)
     }
 
-    /// Gets all [`StashItem`]s for a given [`Product`]
+    /// Gets the [`StashItem`]s of all products matching an SQL condition
     ///
     /// # Parameters
     /// - `tx`: The transaction to use
-    /// - `product_id`: The ID of the product to get the stash items for
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
     ///
     /// # Returns
-    /// A vector of all [`StashItem`]s for the given [`Product`]
+    /// The stash items, by the key of the product they belong to
     fn get_stash_items(
         tx: &Transaction,
-        product_id: &ProductId,
-    ) -> Result<Vec<StashItem>, ProductRepositoryError> {
-        let mut stmt = tx.prepare(
-            "SELECT id, quantity, expiry_date FROM stash_items WHERE product_id = :product_id ORDER BY expiry_date ASC",
-        )?;
-        let mut rows = stmt.query(named_params! { ":product_id": product_id })?;
-
-        let mut stash_items = vec![];
+        condition: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<HashMap<i64, Vec<StashItem>>, ProductRepositoryError> {
+        let mut stmt = tx.prepare_cached(&format!(
+            "SELECT product_key, id, quantity, expiry_date, expiry_date_kind, opened_on FROM stash_items WHERE deleted_at IS NULL AND product_key IN (SELECT key FROM products WHERE {}) ORDER BY product_key, expiry_date IS NULL, expiry_date ASC",
+            condition
+        ))?;
+        let mut rows = stmt.query(params)?;
 
+        let mut stash_items = HashMap::<_, Vec<_>>::new();
         while let Some(row) = rows.next()? {
-            stash_items.push(ProductRepository::row_to_stash_item(row)?);
+            stash_items
+                .entry(row.get::<_, i64>("product_key")?)
+                .or_default()
+                .push(ProductRepository::row_to_stash_item(row)?);
         }
 
         Ok(stash_items)
     }
 
-    /// Fetches and adds all [`StashItem`]s for a given [`Product`] to the [`Product`]
+    /// Gets the barcodes attached to all products matching an SQL condition
     ///
     /// # Parameters
     /// - `tx`: The transaction to use
-    /// - `product`: The product to add the stash items to
+    /// - `condition`: SQL condition on the `products` table, which must not alias it
+    /// - `params`: Parameters for the condition
     ///
     /// # Returns
-    /// The product with the stash items added
-    fn add_stash_items(
+    /// The barcodes, by the key of the product they are attached to
+    fn get_barcodes(
         tx: &Transaction,
-        mut product: Product,
-    ) -> Result<Product, ProductRepositoryError> {
-        let stash_items = ProductRepository::get_stash_items(&tx, product.id())?;
+        condition: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<HashMap<i64, Vec<ProductId>>, ProductRepositoryError> {
+        let mut stmt = tx.prepare_cached(&format!(
+            "SELECT product_key, barcode FROM product_barcodes WHERE product_key IN (SELECT key FROM products WHERE {})",
+            condition
+        ))?;
+        let mut rows = stmt.query(params)?;
 
-        stash_items.into_iter().for_each(|stash_item| {
-            product.add_stash_item(stash_item).expect(
-                format!("Duplicate expiry dates in DB for product {}", product.id()).as_str(),
-            );
-        });
+        let mut barcodes = HashMap::<_, Vec<_>>::new();
+        while let Some(row) = rows.next()? {
+            barcodes
+                .entry(row.get::<_, i64>("product_key")?)
+                .or_default()
+                .push(row.get::<_, ProductId>("barcode")?);
+        }
 
-        Ok(product)
+        Ok(barcodes)
     }
 }
 
+#[async_trait]
 impl ProductRepositoryTrait for ProductRepository {
-    fn find_all_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
-
-        // Find all product IDs in the stash item table
-        let product_ids = ProductRepository::find_product_ids_from_all_stash_items(&tx)?;
-
-        // Get the products
-        let products = ProductRepository::find_by_ids(&tx, &product_ids)?;
-
-        tx.commit()?;
-
-        Ok(products)
+    async fn find_all_with_stash_items(&self) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.in_transaction(|tx| {
+            ProductRepository::find_where(
+                tx,
+                "key IN (SELECT product_key FROM stash_items WHERE deleted_at IS NULL)",
+                &[],
+            )
+        })
+        .await
     }
 
-    fn find_by_id(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
+    async fn find_by_id(&self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
+        let id = id.clone();
 
-        let product = ProductRepository::find_by_id(&tx, id)?;
-
-        tx.commit()?;
-        Ok(product)
+        self.in_transaction(move |tx| ProductRepository::find_by_id(tx, &id))
+            .await
     }
 
-    fn find_by_ids(&self, ids: &[ProductId]) -> Result<Vec<Product>, ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
+    async fn find_by_ids(&self, ids: &[ProductId]) -> Result<Vec<Product>, ProductRepositoryError> {
+        let ids = ids.to_vec();
 
-        let products = ProductRepository::find_by_ids(&tx, ids)?;
-
-        tx.commit()?;
-        Ok(products)
+        self.in_transaction(move |tx| ProductRepository::find_by_ids(tx, &ids))
+            .await
     }
 
-    fn find_by_stash_item_id(
+    async fn find_by_stash_item_id(
         &self,
         stash_item_id: &Uuid,
     ) -> Result<Option<Product>, ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
-
-        let result = ProductRepository::find_by_stash_item_id(&tx, stash_item_id)?;
+        let stash_item_id = *stash_item_id;
 
-        tx.commit()?;
-        Ok(result)
+        self.in_transaction(move |tx| ProductRepository::find_by_stash_item_id(tx, &stash_item_id))
+            .await
     }
 
-    fn find_expiring_in_interval(
+    async fn find_expiring_in_interval(
         &self,
         after: Option<NaiveDate>,
         before: Option<NaiveDate>,
     ) -> Result<Vec<Product>, ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
-
-        let products = ProductRepository::find_expiring_in_interval(&tx, after, before)?;
-
-        tx.commit()?;
-        Ok(products)
+        self.in_transaction(move |tx| {
+            ProductRepository::find_in_interval(tx, EFFECTIVE_EXPIRY_DATE, after, before)
+        })
+        .await
     }
 
-    fn exists_by_id(&self, id: &ProductId) -> Result<bool, ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
+    async fn find_unsafe_in_interval(
+        &self,
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+    ) -> Result<Vec<Product>, ProductRepositoryError> {
+        self.in_transaction(move |tx| {
+            ProductRepository::find_in_interval(tx, &unsafe_date(), after, before)
+        })
+        .await
+    }
 
-        let exists = match ProductRepository::find_by_id(&tx, id)? {
-            Some(_) => Ok(true),
-            None => Ok(false),
-        };
+    async fn exists_by_id(&self, id: &ProductId) -> Result<bool, ProductRepositoryError> {
+        let id = id.clone();
 
-        tx.commit()?;
-        exists
+        self.in_transaction(move |tx| Ok(ProductRepository::find_key(tx, &id)?.is_some()))
+            .await
     }
 
-    fn save(&self, product: Product) -> Result<(), ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
-
-        ProductRepository::save_product(&tx, product)?;
+    async fn save(&self, mut product: Product) -> Result<(), ProductRepositoryError> {
+        let events = product.take_events();
 
-        tx.commit()?;
+        self.in_transaction(move |tx| ProductRepository::save_product(tx, product))
+            .await?;
 
+        self.event_dispatcher.dispatch(&events);
         Ok(())
     }
 
-    fn delete_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
-        let mut conn = self.conn();
-        let tx = conn.transaction()?;
+    async fn save_all(&self, mut products: Vec<Product>) -> Result<(), ProductRepositoryError> {
+        let events = products
+            .iter_mut()
+            .flat_map(Product::take_events)
+            .collect::<Vec<_>>();
 
-        ProductRepository::delete_product(&tx, id)?;
+        self.in_transaction(move |tx| {
+            for product in products {
+                ProductRepository::save_product(tx, product)?;
+            }
 
-        // Commit the transaction
-        tx.commit()?;
+            Ok(())
+        })
+        .await?;
 
+        self.event_dispatcher.dispatch(&events);
         Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        domain::{
-            entities::{FakeProduct, FakeStashItem},
-            value_objects::ProductId,
-        },
-        infrastructure::persistence::sqlite::db::setup_db,
-    };
 
-    fn get_repo() -> ProductRepository {
-        // Create an in-memory database
-        let connection = Connection::open_in_memory().unwrap();
+    async fn merge_into(
+        &self,
+        source_id: &ProductId,
+        mut target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        let source_id = source_id.clone();
+        let events = target.take_events();
 
-        // Create the tables in the database
-        setup_db(&connection).unwrap();
+        self.in_transaction(move |tx| ProductRepository::merge_product(tx, &source_id, target))
+            .await?;
 
-        ProductRepository::new(Arc::new(Mutex::new(connection)))
+        self.event_dispatcher.dispatch(&events);
+        Ok(())
     }
 
-    #[test]
-    fn test_find_all_with_stash_items() {
-        let repo = get_repo();
-
-        let product1 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new().build()])
-            .build();
-        let product2 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new().build()])
-            .build();
-        let product3 = FakeProduct::new().with_stash_items(vec![]).build();
-        repo.save(product1.clone()).unwrap();
-        repo.save(product2.clone()).unwrap();
-        repo.save(product3.clone()).unwrap();
-
-        let found_products = repo.find_all_with_stash_items().unwrap();
+    async fn delete_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        let id = id.clone();
 
-        assert_eq!(found_products.len(), 2);
-        assert!(found_products.contains(&product1));
-        assert!(found_products.contains(&product2));
-        assert!(!found_products.contains(&product3));
+        self.in_transaction(move |tx| ProductRepository::trash_product(tx, &id))
+            .await
     }
 
-    #[test]
-    fn test_find_by_id() {
-        let repo = get_repo();
-
-        let product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-        repo.save(product.clone()).unwrap();
-
-        let found_product = repo.find_by_id(&product_id).unwrap().unwrap();
+    async fn delete_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let stash_item_id = *stash_item_id;
 
-        assert_eq!(found_product, product);
+        self.in_transaction(move |tx| ProductRepository::trash_stash_item(tx, &stash_item_id))
+            .await
     }
 
-    #[test]
-    fn test_find_by_ids() {
-        let repo = get_repo();
-
-        let product1 = FakeProduct::new().build();
-        let product1_id = product1.id().clone();
-        let product2 = FakeProduct::new().build();
-        let product2_id = product2.id().clone();
-        let product3 = FakeProduct::new().build();
-        repo.save(product1.clone()).unwrap();
-        repo.save(product2.clone()).unwrap();
-        repo.save(product3.clone()).unwrap();
-
-        let found_products = repo
-            .find_by_ids(&[product1_id.clone(), product2_id.clone()])
-            .unwrap();
-
-        assert_eq!(found_products.len(), 2);
-        assert!(found_products.contains(&product1));
-        assert!(found_products.contains(&product2));
+    async fn find_trash(&self) -> Result<Vec<TrashEntry>, ProductRepositoryError> {
+        self.in_transaction(ProductRepository::find_trash).await
     }
 
-    #[test]
-    fn test_find_by_id_not_found() {
-        let repo = get_repo();
-
-        let product_id: ProductId = "ID".parse().unwrap();
+    async fn restore_by_id(&self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        let id = id.clone();
 
-        let found_product = repo.find_by_id(&product_id).unwrap();
-
-        assert!(found_product.is_none());
+        self.in_transaction(move |tx| ProductRepository::restore_product(tx, &id))
+            .await
     }
 
-    #[test]
-    fn test_find_by_stash_item_id() {
-        let repo = get_repo();
-
-        let stash_item = FakeStashItem::new().build();
-        let stash_item_id = stash_item.id().clone();
-        let product = FakeProduct::new()
-            .with_stash_items(vec![stash_item])
-            .build();
-        repo.save(product.clone()).unwrap();
-
-        let found_product = repo.find_by_stash_item_id(&stash_item_id).unwrap().unwrap();
+    async fn restore_stash_item_by_id(
+        &self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        let stash_item_id = *stash_item_id;
 
-        assert_eq!(found_product, product);
+        self.in_transaction(move |tx| ProductRepository::restore_stash_item(tx, &stash_item_id))
+            .await
     }
 
-    #[test]
-    fn test_find_expiring_in_interval_after() {
-        let repo = get_repo();
-
-        let product_1 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
-                .build()])
-            .build();
-        let product_2 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())
-                .build()])
-            .build();
-        let product_3 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 3).unwrap())
-                .build()])
-            .build();
-
-        repo.save(product_1.clone()).unwrap();
-        repo.save(product_2.clone()).unwrap();
-        repo.save(product_3.clone()).unwrap();
-
-        let found_products = repo
-            .find_expiring_in_interval(Some(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap()), None)
-            .unwrap();
-
-        assert_eq!(found_products.len(), 2);
-        assert!(found_products.contains(&product_2));
-        assert!(found_products.contains(&product_3));
+    async fn purge_trash(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, ProductRepositoryError> {
+        self.in_transaction(move |tx| ProductRepository::purge_trash(tx, deleted_before))
+            .await
     }
+}
 
-    #[test]
-    fn test_find_expiring_in_interval_before() {
-        let repo = get_repo();
-
-        let product_1 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
-                .build()])
-            .build();
-        let product_2 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())
-                .build()])
-            .build();
-        let product_3 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 3).unwrap())
-                .build()])
-            .build();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            entities::{FakeProduct, FakeStashItem},
+            events::ProductEvent,
+        },
+        infrastructure::persistence::{
+            conformance::product_repository_conformance_tests,
+            sqlite::{
+                db::setup_db,
+                pool::{create_pool, ConnectionManager},
+            },
+        },
+    };
 
-        repo.save(product_1.clone()).unwrap();
-        repo.save(product_2.clone()).unwrap();
-        repo.save(product_3.clone()).unwrap();
+    fn get_repo() -> ProductRepository {
+        // Create an in-memory database
+        let pool = create_pool(ConnectionManager::memory(), 1).unwrap();
 
-        let found_products = repo
-            .find_expiring_in_interval(None, Some(NaiveDate::from_ymd_opt(2023, 1, 3).unwrap()))
-            .unwrap();
+        // Create the tables in the database
+        setup_db(&pool.get().unwrap()).unwrap();
 
-        assert_eq!(found_products.len(), 2);
-        assert!(found_products.contains(&product_1));
-        assert!(found_products.contains(&product_2));
+        ProductRepository::new(pool)
     }
 
-    #[test]
-    fn test_find_expiring_in_interval_both() {
-        let repo = get_repo();
+    product_repository_conformance_tests!(get_repo());
 
-        let product_1 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
-                .build()])
-            .build();
-        let product_2 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())
-                .build()])
-            .build();
-        let product_3 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 3).unwrap())
-                .build()])
-            .build();
-
-        repo.save(product_1.clone()).unwrap();
-        repo.save(product_2.clone()).unwrap();
-        repo.save(product_3.clone()).unwrap();
+    #[actix_web::test]
+    async fn test_save_dispatches_events() {
+        let (event_dispatcher, events) = ProductEventDispatcher::collecting();
+        let repo = get_repo().with_event_dispatcher(event_dispatcher);
+        let mut product = FakeProduct::new().with_stash_items(vec![]).build();
+        repo.save(product.clone()).await.unwrap();
 
-        let found_products = repo
-            .find_expiring_in_interval(
-                Some(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap()),
-                Some(NaiveDate::from_ymd_opt(2023, 1, 3).unwrap()),
-            )
-            .unwrap();
+        let stash_item = FakeStashItem::new().build();
+        product.add_stash_item(stash_item.clone()).unwrap();
+        repo.save(product.clone()).await.unwrap();
 
-        assert_eq!(found_products.len(), 1);
-        assert!(found_products.contains(&product_2));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![ProductEvent::StashItemAdded {
+                product_id: product.id().clone(),
+                stash_item
+            }]
+        );
+        assert!(repo
+            .find_by_id(product.id())
+            .await
+            .unwrap()
+            .unwrap()
+            .events()
+            .is_empty());
     }
 
-    #[test]
-    fn test_find_expiring_in_interval_none() {
-        let repo = get_repo();
-
-        let product_1 = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new()
-                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
-                .build()])
+    #[actix_web::test]
+    async fn test_save_all_dispatches_events_of_every_product() {
+        let (event_dispatcher, events) = ProductEventDispatcher::collecting();
+        let repo = get_repo().with_event_dispatcher(event_dispatcher);
+        let stash_item = FakeStashItem::new().build();
+        let mut source = FakeProduct::new()
+            .with_stash_items(vec![stash_item.clone()])
             .build();
+        let mut target = FakeProduct::new().with_stash_items(vec![]).build();
+        repo.save_all(vec![source.clone(), target.clone()])
+            .await
+            .unwrap();
 
-        repo.save(product_1).unwrap();
-
-        let result = repo.find_expiring_in_interval(None, None);
+        source
+            .move_stash_item(stash_item.id(), &mut target, false)
+            .unwrap();
+        repo.save_all(vec![source.clone(), target.clone()])
+            .await
+            .unwrap();
 
         assert_eq!(
-            result.unwrap_err(),
-            ProductRepositoryError::InvalidDateInterval
+            *events.lock().unwrap(),
+            vec![
+                ProductEvent::StashItemRemoved {
+                    product_id: source.id().clone(),
+                    stash_item: stash_item.clone()
+                },
+                ProductEvent::StashItemAdded {
+                    product_id: target.id().clone(),
+                    stash_item
+                }
+            ]
         );
     }
 
-    #[test]
-    fn test_exists_by_id_true() {
+    #[actix_web::test]
+    async fn test_find_by_ids_more_than_variable_limit() {
         let repo = get_repo();
 
-        let product_id: ProductId = "ID".parse().unwrap();
-
-        repo.save(Product::new(
-            product_id.clone(),
-            "BRAND".parse().unwrap(),
-            "NAME".to_string(),
-            vec![],
-        ))
-        .unwrap();
-
-        let exists = repo.exists_by_id(&product_id).unwrap();
-
-        assert!(exists);
-    }
-
-    #[test]
-    fn test_exists_by_id_false() {
-        let repo = get_repo();
-
-        let product_id: ProductId = "ID".parse().unwrap();
-
-        let exists = repo.exists_by_id(&product_id).unwrap();
-
-        assert!(!exists);
-    }
-
-    #[test]
-    fn test_save_new() {
-        let repo = get_repo();
-
-        let product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-        repo.save(product.clone()).unwrap();
-
-        let found_product = repo.find_by_id(&product_id).unwrap().unwrap();
-
-        assert_eq!(found_product, product);
-    }
-
-    #[test]
-    fn test_save_update() {
-        let repo = get_repo();
-
-        let mut product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-        repo.save(product.clone()).unwrap();
-
-        product.set_name("NEW NAME".to_string());
-
-        repo.save(product.clone()).unwrap();
-
-        let found_product = repo.find_by_id(&product_id).unwrap().unwrap();
-
-        assert_eq!(found_product, product);
-    }
-
-    #[test]
-    fn test_save_update_add_stash_item() {
-        let repo = get_repo();
-
-        let mut product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-        repo.save(product.clone()).unwrap();
-
-        product
-            .add_stash_item(StashItem::new(
-                Uuid::new_v4(),
-                2.try_into().unwrap(),
-                NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
-            ))
-            .unwrap();
-
-        repo.save(product.clone()).unwrap();
+        let products = (0..MAX_VARIABLES + 10)
+            .map(|i| {
+                FakeProduct::new()
+                    .with_id(format!("{:013}", i).parse().unwrap())
+                    .with_stash_items(vec![])
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let ids = products
+            .iter()
+            .map(|product| product.id().clone())
+            .collect::<Vec<_>>();
+        repo.save_all(products).await.unwrap();
 
-        let found_product = repo.find_by_id(&product_id).unwrap().unwrap();
+        let found_products = repo.find_by_ids(&ids).await.unwrap();
 
-        assert_eq!(found_product, product);
+        assert_eq!(found_products.len(), MAX_VARIABLES + 10);
     }
 
-    #[test]
-    fn test_save_update_remove_stash_item() {
+    #[actix_web::test]
+    async fn test_save_more_than_variable_limit() {
         let repo = get_repo();
 
-        let stash_item_id = Uuid::new_v4();
+        let stash_items = (0..MAX_VARIABLES + 10)
+            .map(|i| {
+                FakeStashItem::new()
+                    .with_expiry_date(
+                        NaiveDate::from_ymd_opt(2023, 1, 1).unwrap() + chrono::Days::new(i as u64),
+                    )
+                    .build()
+            })
+            .collect::<Vec<_>>();
         let mut product = FakeProduct::new()
-            .with_stash_items(vec![FakeStashItem::new().with_id(stash_item_id).build()])
+            .with_stash_items(stash_items.clone())
             .build();
-        let product_id = product.id().clone();
-
-        repo.save(product.clone()).unwrap();
-
-        product.remove_stash_item(&stash_item_id).unwrap();
-
-        repo.save(product.clone()).unwrap();
+        let barcodes = (0..MAX_VARIABLES + 10)
+            .map(|i| format!("{:013}", i).parse::<ProductId>().unwrap())
+            .collect::<Vec<_>>();
+        for barcode in &barcodes {
+            product.attach_barcode(barcode.clone());
+        }
+        repo.save(product.clone()).await.unwrap();
 
-        let found_product = repo.find_by_id(&product_id).unwrap().unwrap();
+        product.detach_barcode(&barcodes[0]).unwrap();
+        product.remove_stash_item(stash_items[0].id()).unwrap();
+        repo.save(product.clone()).await.unwrap();
 
-        assert_eq!(found_product, product);
+        let found_product = repo.find_by_id(product.id()).await.unwrap().unwrap();
+        assert_eq!(found_product.barcodes().len(), MAX_VARIABLES + 9);
+        assert!(!found_product.has_barcode(&barcodes[0]));
+        assert_eq!(found_product.stash_items().len(), MAX_VARIABLES + 9);
+        assert!(!found_product.has_stash_item(stash_items[0].id()));
     }
 
-    #[test]
-    fn test_save_all() {
+    #[actix_web::test]
+    async fn test_stash_items_without_expiry_date_sorted_last() {
         let repo = get_repo();
 
-        let stash_item_to_remove = Uuid::new_v4();
-        let stash_item_to_update = Uuid::new_v4();
-        let mut product = FakeProduct::new()
+        let product = FakeProduct::new()
             .with_stash_items(vec![
-                FakeStashItem::new().with_id(stash_item_to_remove).build(),
-                FakeStashItem::new().with_id(stash_item_to_update).build(),
+                FakeStashItem::new().without_expiry_date().build(),
+                FakeStashItem::new()
+                    .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 2).unwrap())
+                    .build(),
+                FakeStashItem::new()
+                    .with_expiry_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+                    .build(),
             ])
             .build();
-        let product_id = product.id().clone();
-
-        repo.save(product.clone()).unwrap();
-
-        product.set_name("NEW NAME".to_string());
-        product.set_brand("NEW BRAND".parse().unwrap());
-        product
-            .add_stash_item(StashItem::new(
-                Uuid::new_v4(),
-                3.try_into().unwrap(),
-                NaiveDate::from_ymd_opt(2021, 1, 3).unwrap(),
-            ))
-            .unwrap();
-        product
-            .update_stash_item(StashItem::new(
-                stash_item_to_update,
-                4.try_into().unwrap(),
-                NaiveDate::from_ymd_opt(2021, 1, 4).unwrap(),
-            ))
-            .unwrap();
-        product.remove_stash_item(&stash_item_to_remove).unwrap();
-
-        repo.save(product.clone()).unwrap();
 
-        let found_product = repo.find_by_id(&product_id).unwrap().unwrap();
+        repo.save(product.clone()).await.unwrap();
 
-        assert_eq!(found_product, product);
-    }
-
-    #[test]
-    fn test_delete_by_id() {
-        let repo = get_repo();
-
-        let product = FakeProduct::new().build();
-        let product_id = product.id().clone();
-        repo.save(product.clone()).unwrap();
-
-        repo.delete_by_id(&product_id).unwrap();
-
-        let found_product = repo.find_by_id(&product_id).unwrap();
-
-        assert!(found_product.is_none());
-    }
-
-    #[test]
-    fn test_delete_by_id_not_found() {
-        let repo = get_repo();
-
-        let product_id: ProductId = "ID".parse().unwrap();
+        let mut conn = repo.pool.get().unwrap();
+        let tx = conn.transaction().unwrap();
+        let key = ProductRepository::find_key(&tx, product.id())
+            .unwrap()
+            .unwrap();
+        let expiry_dates = ProductRepository::get_stash_items(&tx, "key = ?", &[&key])
+            .unwrap()
+            .remove(&key)
+            .unwrap()
+            .into_iter()
+            .map(|stash_item| *stash_item.expiry_date())
+            .collect::<Vec<_>>();
 
-        repo.delete_by_id(&product_id).unwrap();
+        assert_eq!(
+            expiry_dates,
+            vec![
+                NaiveDate::from_ymd_opt(2023, 1, 1),
+                NaiveDate::from_ymd_opt(2023, 1, 2),
+                None
+            ]
+        );
     }
 }
@@ -0,0 +1,477 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rusqlite::ToSql;
+
+use crate::{
+    application::queries::{
+        StashItemCursor, StashItemFilter, StashItemPage, StashItemQuery as StashItemQueryTrait,
+        StashItemSort, StashItemView,
+    },
+    domain::{
+        errors::ProductRepositoryError,
+        value_objects::{Brand, ProductId, Unit},
+    },
+};
+
+use super::{
+    pool::{with_connection, Pool},
+    product_repository::{ProductRepository, EFFECTIVE_EXPIRY_DATE},
+};
+
+/// A [`StashItemQuery`](StashItemQueryTrait) reading stash items and their products in one go, using SQLite as the
+/// underlying storage.
+pub struct StashItemQuery {
+    /// Connections to the database
+    pool: Pool,
+}
+
+impl StashItemQuery {
+    /// Creates a new [`StashItemQuery`]
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    /// SQL expression for the sort key of a stash item. Expects the stash item as `s` and its product as `p`. Is never
+    /// `NULL`, so it can be compared with the key of a cursor
+    fn sort_key(sort: StashItemSort) -> String {
+        match sort {
+            StashItemSort::ExpiryDate => {
+                format!("COALESCE({}, '9999-12-31')", EFFECTIVE_EXPIRY_DATE)
+            }
+            StashItemSort::Name => "p.name".to_string(),
+            StashItemSort::Brand => "p.brand".to_string(),
+        }
+    }
+
+    /// Converts a raw database row into a [`StashItemView`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the row contains invalid data
+    fn row_to_view(row: &rusqlite::Row) -> Result<StashItemView, ProductRepositoryError> {
+        Ok(StashItemView::new(
+            row.get::<_, ProductId>("product_id")?,
+            row.get::<_, Brand>("brand")?,
+            row.get::<_, String>("name")?,
+            row.get::<_, Unit>("unit")?,
+            ProductRepository::row_to_stash_item(row)?,
+            row.get::<_, Option<NaiveDate>>("effective_expiry_date")?,
+        ))
+    }
+}
+
+#[async_trait]
+impl StashItemQueryTrait for StashItemQuery {
+    async fn find_stash_items(
+        &self,
+        filter: &StashItemFilter,
+        sort: StashItemSort,
+        cursor: Option<StashItemCursor>,
+        limit: usize,
+    ) -> Result<StashItemPage, ProductRepositoryError> {
+        let sort_key = StashItemQuery::sort_key(sort);
+
+        // Hold the conditions and args for them outside of the query so their lifetime is long enough
+        // Stash items in the trash, or whose product is, are left out
+        let mut conditions = vec!["s.deleted_at IS NULL AND p.deleted_at IS NULL".to_string()];
+        let mut args: Vec<Box<dyn ToSql + Send>> = Vec::new();
+
+        if let Some(product_id) = &filter.product_id {
+            conditions.push(
+                "p.key = (SELECT key FROM products WHERE id = ?1 AND deleted_at IS NULL UNION ALL SELECT b.product_key FROM product_barcodes b JOIN products bp ON bp.key = b.product_key WHERE b.barcode = ?1 AND bp.deleted_at IS NULL LIMIT 1)"
+                    .replace("?1", &format!("?{}", args.len() + 1)),
+            );
+            args.push(Box::new(product_id.clone()));
+        }
+        if let Some(brand) = &filter.brand {
+            conditions.push(format!("p.brand = ?{}", args.len() + 1));
+            args.push(Box::new(brand.clone()));
+        }
+        if let Some(after) = filter.expiring_after {
+            conditions.push(format!("{} >= ?{}", EFFECTIVE_EXPIRY_DATE, args.len() + 1));
+            args.push(Box::new(after.to_string()));
+        }
+        if let Some(before) = filter.expiring_before {
+            conditions.push(format!("{} < ?{}", EFFECTIVE_EXPIRY_DATE, args.len() + 1));
+            args.push(Box::new(before.to_string()));
+        }
+        if let Some(cursor) = cursor {
+            conditions.push(format!(
+                "({0} > ?{1} OR ({0} = ?{1} AND s.id > ?{2}))",
+                sort_key,
+                args.len() + 1,
+                args.len() + 2
+            ));
+            args.push(Box::new(cursor.key().clone()));
+            args.push(Box::new(cursor.id().to_string()));
+        }
+
+        // Fetch one stash item more than asked for, to know whether there is another page
+        let query = format!(
+            "SELECT s.id, s.quantity, s.expiry_date, s.expiry_date_kind, s.opened_on, \
+            p.id AS product_id, p.brand, p.name, p.unit, \
+            {} AS effective_expiry_date, {} AS sort_key \
+            FROM stash_items s JOIN products p ON p.key = s.product_key \
+            WHERE {} ORDER BY sort_key, s.id LIMIT {}",
+            EFFECTIVE_EXPIRY_DATE,
+            sort_key,
+            conditions.join(" AND "),
+            limit + 1
+        );
+
+        with_connection(&self.pool, move |conn| {
+            // Convert the args to something the query can use
+            let args = args
+                .iter()
+                .map(|arg| &**arg as &dyn ToSql)
+                .collect::<Vec<_>>();
+
+            let mut stmt = conn.prepare(&query)?;
+            let mut rows = stmt.query(&args[..])?;
+
+            let mut stash_items = vec![];
+            let mut last_key = None;
+            while let Some(row) = rows.next()? {
+                if stash_items.len() == limit {
+                    let next_cursor = last_key.zip(
+                        stash_items
+                            .last()
+                            .map(|view: &StashItemView| *view.stash_item().id()),
+                    );
+
+                    return Ok(StashItemPage::new(
+                        stash_items,
+                        next_cursor.map(|(key, id)| StashItemCursor::new(key, id)),
+                    ));
+                }
+
+                stash_items.push(StashItemQuery::row_to_view(row)?);
+                last_key = Some(row.get::<_, String>("sort_key")?);
+            }
+
+            Ok(StashItemPage::new(stash_items, None))
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            entities::{FakeProduct, FakeStashItem},
+            repositories::ProductRepository as ProductRepositoryTrait,
+        },
+        infrastructure::persistence::sqlite::{
+            db::setup_db,
+            pool::{create_pool, ConnectionManager},
+        },
+    };
+
+    use super::*;
+
+    fn get_query() -> (ProductRepository, StashItemQuery) {
+        // Create an in-memory database
+        let pool = create_pool(ConnectionManager::memory(), 1).unwrap();
+
+        // Create the tables in the database
+        setup_db(&pool.get().unwrap()).unwrap();
+
+        (
+            ProductRepository::new(pool.clone()),
+            StashItemQuery::new(pool),
+        )
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 11, day).unwrap()
+    }
+
+    fn expiring(day: u32) -> crate::domain::entities::StashItem {
+        FakeStashItem::new().with_expiry_date(date(day)).build()
+    }
+
+    fn expiry_dates(page: &StashItemPage) -> Vec<Option<NaiveDate>> {
+        page.stash_items()
+            .iter()
+            .map(|view| *view.effective_expiry_date())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_find_stash_items() {
+        let (repo, query) = get_query();
+
+        let stash_item = FakeStashItem::new()
+            .with_expiry_date(date(10))
+            .with_opened_on(date(1))
+            .build();
+        let product = FakeProduct::new()
+            .with_id("7038010000737".parse().unwrap())
+            .with_brand("Tine".parse().unwrap())
+            .with_name("Milk".to_string())
+            .with_shelf_life_after_opening_days(4)
+            .with_stash_items(vec![stash_item.clone()])
+            .build();
+        repo.save(product).await.unwrap();
+
+        let page = query
+            .find_stash_items(
+                &StashItemFilter::default(),
+                StashItemSort::default(),
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            page,
+            StashItemPage::new(
+                vec![StashItemView::new(
+                    "7038010000737".parse().unwrap(),
+                    "Tine".parse().unwrap(),
+                    "Milk".to_string(),
+                    Unit::default(),
+                    stash_item,
+                    Some(date(5)),
+                )],
+                None
+            )
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_find_stash_items_sorted_by_expiry_date() {
+        let (repo, query) = get_query();
+
+        repo.save(
+            FakeProduct::new()
+                .with_stash_items(vec![expiring(20), expiring(5)])
+                .build(),
+        )
+        .await
+        .unwrap();
+        repo.save(
+            FakeProduct::new()
+                .with_stash_items(vec![
+                    FakeStashItem::new().without_expiry_date().build(),
+                    expiring(12),
+                ])
+                .build(),
+        )
+        .await
+        .unwrap();
+
+        let page = query
+            .find_stash_items(
+                &StashItemFilter::default(),
+                StashItemSort::ExpiryDate,
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            expiry_dates(&page),
+            vec![Some(date(5)), Some(date(12)), Some(date(20)), None]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_find_stash_items_sorted_by_name() {
+        let (repo, query) = get_query();
+
+        for name in ["Milk", "Butter", "Cheese"] {
+            repo.save(
+                FakeProduct::new()
+                    .with_name(name.to_string())
+                    .with_stash_items(vec![FakeStashItem::new().build()])
+                    .build(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let page = query
+            .find_stash_items(&StashItemFilter::default(), StashItemSort::Name, None, 10)
+            .await
+            .unwrap();
+
+        let names = page
+            .stash_items()
+            .iter()
+            .map(|view| view.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Butter", "Cheese", "Milk"]);
+    }
+
+    #[actix_web::test]
+    async fn test_find_stash_items_filtered() {
+        let (repo, query) = get_query();
+
+        let product = FakeProduct::new()
+            .with_brand("Tine".parse().unwrap())
+            .with_barcodes(vec!["7038010000737".parse().unwrap()])
+            .with_stash_items(vec![expiring(5), expiring(10), expiring(15)])
+            .build();
+        repo.save(product.clone()).await.unwrap();
+        repo.save(
+            FakeProduct::new()
+                .with_brand("Q".parse().unwrap())
+                .with_stash_items(vec![expiring(10)])
+                .build(),
+        )
+        .await
+        .unwrap();
+
+        let by_brand = StashItemFilter {
+            brand: Some("Tine".parse().unwrap()),
+            expiring_after: Some(date(10)),
+            expiring_before: Some(date(15)),
+            ..Default::default()
+        };
+        let page = query
+            .find_stash_items(&by_brand, StashItemSort::default(), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(expiry_dates(&page), vec![Some(date(10))]);
+        assert_eq!(page.stash_items()[0].product_id(), product.id());
+
+        let by_barcode = StashItemFilter {
+            product_id: Some("7038010000737".parse().unwrap()),
+            ..Default::default()
+        };
+        let page = query
+            .find_stash_items(&by_barcode, StashItemSort::default(), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            expiry_dates(&page),
+            vec![Some(date(5)), Some(date(10)), Some(date(15))]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_find_stash_items_paginated() {
+        let (repo, query) = get_query();
+
+        // Two stash items on the same date make sure ties are paginated through as well
+        repo.save(
+            FakeProduct::new()
+                .with_stash_items(vec![expiring(1), expiring(2), expiring(3)])
+                .build(),
+        )
+        .await
+        .unwrap();
+        repo.save(
+            FakeProduct::new()
+                .with_stash_items(vec![
+                    expiring(2),
+                    FakeStashItem::new().without_expiry_date().build(),
+                ])
+                .build(),
+        )
+        .await
+        .unwrap();
+
+        let filter = StashItemFilter::default();
+        let mut dates = vec![];
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let page = query
+                .find_stash_items(&filter, StashItemSort::default(), cursor.clone(), 2)
+                .await
+                .unwrap();
+            dates.extend(expiry_dates(&page));
+            pages += 1;
+
+            match page.next_cursor() {
+                Some(next_cursor) => cursor = Some(next_cursor.clone()),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(
+            dates,
+            vec![
+                Some(date(1)),
+                Some(date(2)),
+                Some(date(2)),
+                Some(date(3)),
+                None
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_find_stash_items_last_page_has_no_cursor() {
+        let (repo, query) = get_query();
+
+        repo.save(
+            FakeProduct::new()
+                .with_stash_items(vec![expiring(1), expiring(2)])
+                .build(),
+        )
+        .await
+        .unwrap();
+
+        let page = query
+            .find_stash_items(
+                &StashItemFilter::default(),
+                StashItemSort::default(),
+                None,
+                2,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(page.stash_items().len(), 2);
+        assert_eq!(page.next_cursor(), &None);
+    }
+
+    #[actix_web::test]
+    async fn test_find_stash_items_leaves_out_trash() {
+        let (repo, query) = get_query();
+
+        let deleted = FakeProduct::new()
+            .with_barcodes(vec!["7038010000737".parse().unwrap()])
+            .with_stash_items(vec![expiring(5)])
+            .build();
+        let stash_item = expiring(10);
+        repo.save_all(vec![
+            deleted.clone(),
+            FakeProduct::new()
+                .with_stash_items(vec![stash_item.clone(), expiring(15)])
+                .build(),
+        ])
+        .await
+        .unwrap();
+        repo.delete_by_id(deleted.id()).await.unwrap();
+        repo.delete_stash_item_by_id(stash_item.id()).await.unwrap();
+
+        let page = query
+            .find_stash_items(
+                &StashItemFilter::default(),
+                StashItemSort::default(),
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(expiry_dates(&page), vec![Some(date(15))]);
+
+        let by_barcode = StashItemFilter {
+            product_id: Some("7038010000737".parse().unwrap()),
+            ..Default::default()
+        };
+        let page = query
+            .find_stash_items(&by_barcode, StashItemSort::default(), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(expiry_dates(&page), vec![]);
+    }
+}
@@ -1,13 +1,13 @@
 use rusqlite::{
-    types::{FromSql, ToSqlOutput},
+    types::{FromSql, ToSqlOutput, ValueRef},
     ToSql,
 };
 
-use crate::domain::value_objects::{Brand, ProductId, Quantity};
+use crate::domain::value_objects::{Brand, ExpiryDateKind, ProductId, Quantity, Unit};
 
 impl ToSql for Brand {
     fn to_sql(&self) -> Result<rusqlite::types::ToSqlOutput<'_>, rusqlite::Error> {
-        Ok(self.value().to_sql()?)
+        self.value().to_sql()
     }
 }
 
@@ -24,7 +24,7 @@ impl FromSql for Brand {
 
 impl ToSql for ProductId {
     fn to_sql(&self) -> Result<rusqlite::types::ToSqlOutput<'_>, rusqlite::Error> {
-        Ok(self.value().to_sql()?)
+        self.value().to_sql()
     }
 }
 
@@ -39,31 +39,65 @@ impl FromSql for ProductId {
     }
 }
 
-impl ToSql for Quantity {
+impl ToSql for Unit {
     fn to_sql(&self) -> Result<rusqlite::types::ToSqlOutput<'_>, rusqlite::Error> {
-        let val = self.value();
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
 
-        if val > i64::MAX as u64 {
-            return Err(rusqlite::Error::ToSqlConversionFailure(
-                format!("Quantity {} is too large to fit in an i64", val).into(),
-            ));
-        }
+impl FromSql for Unit {
+    fn column_result(
+        value: rusqlite::types::ValueRef<'_>,
+    ) -> Result<Self, rusqlite::types::FromSqlError> {
+        let str = value.as_str()?;
+
+        str.parse()
+            .map_err(|_| rusqlite::types::FromSqlError::InvalidType)
+    }
+}
 
-        Ok(ToSqlOutput::from(val as i64))
+impl ToSql for ExpiryDateKind {
+    fn to_sql(&self) -> Result<rusqlite::types::ToSqlOutput<'_>, rusqlite::Error> {
+        Ok(ToSqlOutput::from(self.to_string()))
     }
 }
 
-impl FromSql for Quantity {
+impl FromSql for ExpiryDateKind {
     fn column_result(
         value: rusqlite::types::ValueRef<'_>,
     ) -> Result<Self, rusqlite::types::FromSqlError> {
-        let val = value.as_i64()?;
+        let str = value.as_str()?;
 
-        if val < 0 {
-            return Err(rusqlite::types::FromSqlError::InvalidType);
-        }
+        str.parse()
+            .map_err(|_| rusqlite::types::FromSqlError::InvalidType)
+    }
+}
 
-        Ok(Quantity::new(val as u64).map_err(|_| rusqlite::types::FromSqlError::InvalidType)?)
+/// Quantities are stored as text, so fractions are kept exactly as they are
+impl ToSql for Quantity {
+    fn to_sql(&self) -> Result<rusqlite::types::ToSqlOutput<'_>, rusqlite::Error> {
+        Ok(ToSqlOutput::from(self.value().to_string()))
+    }
+}
+
+impl FromSql for Quantity {
+    fn column_result(
+        value: rusqlite::types::ValueRef<'_>,
+    ) -> Result<Self, rusqlite::types::FromSqlError> {
+        match value {
+            ValueRef::Text(_) => value
+                .as_str()?
+                .parse()
+                .map_err(|_| rusqlite::types::FromSqlError::InvalidType),
+            ValueRef::Integer(val) => {
+                if val < 0 {
+                    return Err(rusqlite::types::FromSqlError::InvalidType);
+                }
+
+                Quantity::new(val as u64).map_err(|_| rusqlite::types::FromSqlError::InvalidType)
+            }
+            _ => Err(rusqlite::types::FromSqlError::InvalidType),
+        }
     }
 }
 
@@ -121,13 +155,53 @@ mod tests {
         assert_eq!(id, id_from_sql);
     }
 
+    #[test]
+    fn test_unit_to_from_sql() {
+        let unit = Unit::Kilograms;
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        connection
+            .execute("CREATE TABLE test (unit TEXT NOT NULL)", params![])
+            .unwrap();
+
+        connection
+            .execute("INSERT INTO test (unit) VALUES (?)", params![unit])
+            .unwrap();
+
+        let unit_from_sql: Unit = connection
+            .query_row("SELECT unit FROM test LIMIT 1", params![], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(unit, unit_from_sql);
+    }
+
+    #[test]
+    fn test_expiry_date_kind_to_from_sql() {
+        let kind = ExpiryDateKind::UseBy;
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        connection
+            .execute("CREATE TABLE test (kind TEXT NOT NULL)", params![])
+            .unwrap();
+
+        connection
+            .execute("INSERT INTO test (kind) VALUES (?)", params![kind])
+            .unwrap();
+
+        let kind_from_sql: ExpiryDateKind = connection
+            .query_row("SELECT kind FROM test LIMIT 1", params![], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(kind, kind_from_sql);
+    }
+
     #[test]
     fn test_quantity_to_from_sql() {
-        let quantity = Quantity::new(1).unwrap();
+        let quantity: Quantity = "0.75".parse().unwrap();
 
         let connection = rusqlite::Connection::open_in_memory().unwrap();
         connection
-            .execute("CREATE TABLE test (quantity INTEGER NOT NULL)", params![])
+            .execute("CREATE TABLE test (quantity TEXT NOT NULL)", params![])
             .unwrap();
 
         connection
@@ -146,18 +220,25 @@ mod tests {
     }
 
     #[test]
-    fn test_quantity_to_sql_overflow() {
-        let quantity = Quantity::new(u64::MAX).unwrap();
-
+    fn test_quantity_from_sql_integer() {
         let connection = rusqlite::Connection::open_in_memory().unwrap();
         connection
             .execute("CREATE TABLE test (quantity INTEGER NOT NULL)", params![])
             .unwrap();
 
-        let result =
-            connection.execute("INSERT INTO test (quantity) VALUES (?)", params![quantity]);
+        connection
+            .execute("INSERT INTO test (quantity) VALUES (3)", params![])
+            .unwrap();
+
+        let mut statement = connection
+            .prepare("SELECT quantity FROM test LIMIT 1")
+            .unwrap();
+        let mut rows = statement.query(params![]).unwrap();
+
+        let row = rows.next().unwrap().unwrap();
+        let quantity_from_sql: Quantity = row.get(0).unwrap();
 
-        assert!(result.is_err());
+        assert_eq!(quantity_from_sql, Quantity::new(3).unwrap());
     }
 
     #[test]
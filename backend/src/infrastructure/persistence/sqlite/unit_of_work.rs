@@ -0,0 +1,298 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rusqlite::{Transaction, TransactionBehavior};
+use uuid::Uuid;
+
+use crate::domain::{
+    entities::Product,
+    errors::ProductRepositoryError,
+    events::{ProductEvent, ProductEventDispatcher},
+    repositories::{
+        ProductTransaction as ProductTransactionTrait, UnitOfWork as UnitOfWorkTrait, Work,
+        WorkResult, DEFAULT_UNDO_HISTORY_SIZE,
+    },
+    value_objects::{Operation, ProductId},
+};
+
+use super::{
+    operations,
+    pool::{with_connection, Pool},
+    ProductRepository,
+};
+
+/// The products as seen from within a single SQLite transaction
+struct ProductTransaction<'a> {
+    tx: &'a Transaction<'a>,
+    /// How many operations are kept
+    undo_history_size: usize,
+    /// Events of the products saved, to be dispatched once the transaction is committed
+    events: Vec<ProductEvent>,
+}
+
+impl ProductTransactionTrait for ProductTransaction<'_> {
+    fn find_by_id(&mut self, id: &ProductId) -> Result<Option<Product>, ProductRepositoryError> {
+        ProductRepository::find_by_id(self.tx, id)
+    }
+
+    fn exists_by_id(&mut self, id: &ProductId) -> Result<bool, ProductRepositoryError> {
+        Ok(ProductRepository::find_key(self.tx, id)?.is_some())
+    }
+
+    fn find_by_stash_item_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<Option<Product>, ProductRepositoryError> {
+        ProductRepository::find_by_stash_item_id(self.tx, stash_item_id)
+    }
+
+    fn save(&mut self, mut product: Product) -> Result<(), ProductRepositoryError> {
+        let events = product.take_events();
+
+        ProductRepository::save_product(self.tx, product)?;
+
+        self.events.extend(events);
+        Ok(())
+    }
+
+    fn merge_into(
+        &mut self,
+        source_id: &ProductId,
+        mut target: Product,
+    ) -> Result<(), ProductRepositoryError> {
+        let events = target.take_events();
+
+        ProductRepository::merge_product(self.tx, source_id, target)?;
+
+        self.events.extend(events);
+        Ok(())
+    }
+
+    fn delete_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        ProductRepository::trash_product(self.tx, id)
+    }
+
+    fn delete_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        ProductRepository::trash_stash_item(self.tx, stash_item_id)
+    }
+
+    fn restore_by_id(&mut self, id: &ProductId) -> Result<(), ProductRepositoryError> {
+        ProductRepository::restore_product(self.tx, id)
+    }
+
+    fn restore_stash_item_by_id(
+        &mut self,
+        stash_item_id: &Uuid,
+    ) -> Result<(), ProductRepositoryError> {
+        ProductRepository::restore_stash_item(self.tx, stash_item_id)
+    }
+
+    fn save_operation(&mut self, operation: Operation) -> Result<(), ProductRepositoryError> {
+        operations::save(self.tx, &operation, self.undo_history_size)
+    }
+
+    fn find_operation_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Operation>, ProductRepositoryError> {
+        operations::find_by_id(self.tx, id)
+    }
+
+    fn delete_operation_by_id(&mut self, id: &Uuid) -> Result<(), ProductRepositoryError> {
+        operations::delete_by_id(self.tx, id)
+    }
+}
+
+/// Runs work in a single SQLite transaction, on a connection from the pool
+pub struct UnitOfWork {
+    pool: Pool,
+    /// How many of the most recent operations are kept in the database, so they can still be undone after a restart
+    undo_history_size: usize,
+    /// Gets the events of the products saved by committed work
+    event_dispatcher: Arc<ProductEventDispatcher>,
+}
+
+impl UnitOfWork {
+    pub fn new(pool: Pool) -> Self {
+        Self {
+            pool,
+            undo_history_size: DEFAULT_UNDO_HISTORY_SIZE,
+            event_dispatcher: Arc::default(),
+        }
+    }
+
+    /// Sets how many of the most recent operations are kept, so they can be undone
+    pub fn with_undo_history_size(mut self, undo_history_size: usize) -> Self {
+        self.undo_history_size = undo_history_size;
+        self
+    }
+
+    /// Makes committed work dispatch the events of the products it saved
+    pub fn with_event_dispatcher(mut self, event_dispatcher: Arc<ProductEventDispatcher>) -> Self {
+        self.event_dispatcher = event_dispatcher;
+        self
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkTrait for UnitOfWork {
+    async fn run_boxed(&self, work: Work) -> Result<WorkResult, ProductRepositoryError> {
+        let undo_history_size = self.undo_history_size;
+
+        let (result, events) = with_connection(&self.pool, move |conn| {
+            // Take the write lock right away, so concurrent work waits for it instead of failing when it reads a
+            // snapshot another transaction then writes to
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let mut transaction = ProductTransaction {
+                tx: &tx,
+                undo_history_size,
+                events: vec![],
+            };
+            let result = work(&mut transaction);
+
+            // Dropping the transaction without committing it rolls it back, and the events with it
+            let events = match result {
+                Ok(_) => {
+                    let events = transaction.events;
+                    tx.commit()?;
+                    events
+                }
+                Err(_) => vec![],
+            };
+
+            Ok::<_, ProductRepositoryError>((result, events))
+        })
+        .await?;
+
+        self.event_dispatcher.dispatch(&events);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            entities::{FakeProduct, FakeStashItem},
+            repositories::ProductRepository as _,
+            value_objects::InverseOperation,
+        },
+        infrastructure::persistence::{
+            conformance::unit_of_work_conformance_tests,
+            sqlite::{
+                db::setup_db,
+                pool::{create_pool, tests::TempDb, ConnectionManager},
+            },
+        },
+    };
+
+    use super::*;
+
+    unit_of_work_conformance_tests!(event_dispatcher => {
+        // Create an in-memory database
+        let pool = create_pool(ConnectionManager::memory(), 1).unwrap();
+
+        // Create the tables in the database
+        setup_db(&pool.get().unwrap()).unwrap();
+
+        (
+            ProductRepository::new(pool.clone()),
+            UnitOfWork::new(pool).with_event_dispatcher(event_dispatcher),
+        )
+    });
+
+    #[actix_web::test]
+    async fn test_concurrent_work_does_not_lose_updates() {
+        let db = TempDb::new();
+        let pool = db.pool(4);
+        let repo = ProductRepository::new(pool.clone());
+        let unit_of_work = std::sync::Arc::new(UnitOfWork::new(pool));
+        let product = FakeProduct::new().with_stash_items(vec![]).build();
+        repo.save(product.clone()).await.unwrap();
+
+        // Each adds a stash item to the product as it is when the work starts
+        let handles = (1..=8)
+            .map(|day| {
+                let unit_of_work = unit_of_work.clone();
+                let product_id = product.id().clone();
+                let stash_item = FakeStashItem::new()
+                    .with_expiry_date(chrono::NaiveDate::from_ymd_opt(2023, 11, day).unwrap())
+                    .build();
+
+                actix_web::rt::spawn(async move {
+                    let unit_of_work: &dyn UnitOfWorkTrait = unit_of_work.as_ref();
+                    unit_of_work
+                        .run(move |tx| {
+                            let mut product = tx.find_by_id(&product_id)?.unwrap();
+                            product.add_stash_item(stash_item)?;
+                            tx.save(product)
+                        })
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap(), Ok(()));
+        }
+
+        let product = repo.find_by_id(product.id()).await.unwrap().unwrap();
+        assert_eq!(product.stash_items().len(), 8);
+    }
+
+    #[actix_web::test]
+    async fn test_operations_survive_restart() {
+        let db = TempDb::new();
+        let operation = Operation::new(InverseOperation::RestoreStashItem {
+            stash_item_id: Uuid::new_v4(),
+        });
+        let saved = operation.clone();
+        let unit_of_work: &dyn UnitOfWorkTrait = &UnitOfWork::new(db.pool(1));
+        unit_of_work
+            .run(move |tx| tx.save_operation(saved))
+            .await
+            .unwrap();
+
+        let unit_of_work: &dyn UnitOfWorkTrait = &UnitOfWork::new(db.pool(1));
+        let operation_id = *operation.id();
+        let found = unit_of_work
+            .run(move |tx| tx.find_operation_by_id(&operation_id))
+            .await;
+
+        assert_eq!(found, Ok(Some(operation)));
+    }
+
+    #[actix_web::test]
+    async fn test_with_undo_history_size() {
+        let db = TempDb::new();
+        let unit_of_work: &dyn UnitOfWorkTrait =
+            &UnitOfWork::new(db.pool(1)).with_undo_history_size(1);
+        let (older, newer) = (
+            Operation::new(InverseOperation::RestoreStashItem {
+                stash_item_id: Uuid::new_v4(),
+            }),
+            Operation::new(InverseOperation::RestoreStashItem {
+                stash_item_id: Uuid::new_v4(),
+            }),
+        );
+
+        let (older_id, newer_id) = (*older.id(), *newer.id());
+        let saved_newer = newer.clone();
+        let found = unit_of_work
+            .run(move |tx| {
+                tx.save_operation(older)?;
+                tx.save_operation(saved_newer)?;
+
+                Ok::<_, ProductRepositoryError>((
+                    tx.find_operation_by_id(&older_id)?,
+                    tx.find_operation_by_id(&newer_id)?,
+                ))
+            })
+            .await;
+
+        assert_eq!(found, Ok((None, Some(newer))));
+    }
+}
@@ -0,0 +1,29 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::rt;
+
+use crate::application::{
+    services::ExpiryNotificationService, use_cases::NotifyExpiringStashItems,
+};
+
+/// Sends expiry notifications at a fixed interval, starting right away. Must be called from within the actix runtime,
+/// which the notifications then run in the background of
+///
+/// # Parameters
+/// - `service`: The service sending the notifications
+/// - `interval`: Time between each time notifications are sent
+pub fn schedule_expiry_notifications(service: Arc<ExpiryNotificationService>, interval: Duration) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            let today = chrono::Local::now().date_naive();
+            match service.notify_expiring_stash_items(today).await {
+                Ok(notified) => println!("Notified about {} expiring stash items", notified),
+                Err(err) => println!("Error: {}", err),
+            }
+        }
+    });
+}
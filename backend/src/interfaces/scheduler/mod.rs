@@ -0,0 +1,5 @@
+mod expiry_notifications;
+mod trash_purge;
+
+pub use expiry_notifications::schedule_expiry_notifications;
+pub use trash_purge::schedule_trash_purge;
@@ -0,0 +1,30 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::rt;
+
+use crate::application::{services::ProductService, use_cases::PurgeTrash};
+
+/// Purges what has been in the trash for longer than the retention period at a fixed interval, starting right away.
+/// Must be called from within the actix runtime, which the purges then run in the background of
+///
+/// # Parameters
+/// - `service`: The service purging the trash
+/// - `retention`: How long deleted products and stash items are kept in the trash
+/// - `interval`: Time between each purge
+pub fn schedule_trash_purge(service: Arc<ProductService>, retention: Duration, interval: Duration) {
+    let retention = chrono::Duration::from_std(retention).expect("Trash retention out of range");
+
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            let deleted_before = chrono::Utc::now().naive_utc() - retention;
+            match service.purge_trash(deleted_before).await {
+                Ok(purged) => println!("Purged {} entries from the trash", purged),
+                Err(err) => println!("Error: {}", err),
+            }
+        }
+    });
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// DTO for a request to attach a barcode to a product
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BarcodeDTO {
+    /// The barcode to attach
+    pub barcode: String,
+}
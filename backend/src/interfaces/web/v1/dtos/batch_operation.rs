@@ -0,0 +1,200 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        entities::{Product, StashItem},
+        value_objects::{BatchOperation, Quantity, Unit},
+    },
+    interfaces::web::v1::errors::BatchOperationParseError,
+};
+
+use super::{ProductDTO, StashItemDTO};
+
+/// DTO for one of the operations in a batch. The kind of operation is given by its `type`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchOperationDTO {
+    CreateProduct {
+        product: ProductDTO,
+    },
+    AddStashItem {
+        product_id: String,
+        stash_item: StashItemDTO,
+    },
+    UpdateStashItem {
+        product_id: String,
+        stash_item: StashItemDTO,
+    },
+    DeleteStashItem {
+        product_id: String,
+        stash_item_id: String,
+    },
+    ConsumeStashItem {
+        product_id: String,
+        stash_item_id: String,
+        #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+        amount: Decimal,
+        /// Unit of the amount. Defaults to the unit of the product
+        #[serde(default)]
+        unit: Option<String>,
+    },
+}
+
+impl TryFrom<BatchOperationDTO> for BatchOperation {
+    type Error = BatchOperationParseError;
+
+    fn try_from(dto: BatchOperationDTO) -> Result<Self, Self::Error> {
+        Ok(match dto {
+            BatchOperationDTO::CreateProduct { product } => Self::CreateProduct {
+                product: Box::new(Product::try_from(product)?),
+            },
+            BatchOperationDTO::AddStashItem {
+                product_id,
+                stash_item,
+            } => Self::AddStashItem {
+                product_id: product_id.parse()?,
+                stash_item: StashItem::try_from(stash_item)?,
+            },
+            BatchOperationDTO::UpdateStashItem {
+                product_id,
+                stash_item,
+            } => Self::UpdateStashItem {
+                product_id: product_id.parse()?,
+                stash_item: StashItem::try_from(stash_item)?,
+            },
+            BatchOperationDTO::DeleteStashItem {
+                product_id,
+                stash_item_id,
+            } => Self::DeleteStashItem {
+                product_id: product_id.parse()?,
+                stash_item_id: stash_item_id.parse()?,
+            },
+            BatchOperationDTO::ConsumeStashItem {
+                product_id,
+                stash_item_id,
+                amount,
+                unit,
+            } => Self::ConsumeStashItem {
+                product_id: product_id.parse()?,
+                stash_item_id: stash_item_id.parse()?,
+                amount: Quantity::try_from(amount)?,
+                unit: unit.map(|unit| unit.parse::<Unit>()).transpose()?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem},
+        errors::{ProductIdError, QuantityError},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_create_product_from_json() {
+        let json = r#"{"type":"create_product","product":{"id":"ID","brand":"Brand","name":"Name","stash_items":[]}}"#;
+
+        let dto: BatchOperationDTO = serde_json::from_str(json).unwrap();
+        let operation = BatchOperation::try_from(dto).unwrap();
+
+        assert_eq!(
+            operation,
+            BatchOperation::CreateProduct {
+                product: Box::new(Product::new(
+                    "ID".parse().unwrap(),
+                    "Brand".parse().unwrap(),
+                    "Name".to_string(),
+                    vec![]
+                ))
+            }
+        );
+    }
+
+    #[test]
+    fn test_add_stash_item_from_dto() {
+        let product = FakeProduct::new().build();
+        let stash_item = FakeStashItem::new().build();
+
+        let dto = BatchOperationDTO::AddStashItem {
+            product_id: product.id().to_string(),
+            stash_item: StashItemDTO::from(stash_item.clone()),
+        };
+
+        assert_eq!(
+            BatchOperation::try_from(dto),
+            Ok(BatchOperation::AddStashItem {
+                product_id: product.id().clone(),
+                stash_item
+            })
+        );
+    }
+
+    #[test]
+    fn test_consume_stash_item_from_json() {
+        let stash_item_id = Uuid::new_v4();
+        let json = format!(
+            r#"{{"type":"consume_stash_item","product_id":"ID","stash_item_id":"{}","amount":0.5}}"#,
+            stash_item_id
+        );
+
+        let dto: BatchOperationDTO = serde_json::from_str(&json).unwrap();
+        let operation = BatchOperation::try_from(dto).unwrap();
+
+        assert_eq!(
+            operation,
+            BatchOperation::ConsumeStashItem {
+                product_id: "ID".parse().unwrap(),
+                stash_item_id,
+                amount: "0.5".parse().unwrap(),
+                unit: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_operation_from_json() {
+        let json = r#"{"type":"eat_product","product_id":"ID"}"#;
+
+        let result = serde_json::from_str::<BatchOperationDTO>(json);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_delete_stash_item_from_dto_invalid_product_id() {
+        let dto = BatchOperationDTO::DeleteStashItem {
+            product_id: "".to_string(),
+            stash_item_id: Uuid::new_v4().to_string(),
+        };
+
+        assert_eq!(
+            BatchOperation::try_from(dto),
+            Err(BatchOperationParseError::ProductIdError(
+                ProductIdError::EmptyStringError
+            ))
+        );
+    }
+
+    #[test]
+    fn test_consume_stash_item_from_dto_invalid_amount() {
+        let dto = BatchOperationDTO::ConsumeStashItem {
+            product_id: "ID".to_string(),
+            stash_item_id: Uuid::new_v4().to_string(),
+            amount: 0.into(),
+            unit: None,
+        };
+
+        assert_eq!(
+            BatchOperation::try_from(dto),
+            Err(BatchOperationParseError::QuantityError(
+                QuantityError::ZeroError
+            ))
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entities::Product;
+
+use super::ProductDTO;
+
+/// DTO for the result of one of the operations in a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchResultDTO {
+    /// `ok` if the operation was applied, `failed` if it failed, `rolled_back` if it succeeded but another operation
+    /// failed, and `skipped` if it was not tried because an earlier operation failed
+    pub status: String,
+    /// The product the operation was applied to, as it is right after the operation. Only set for applied operations
+    pub product: Option<ProductDTO>,
+    /// Why the operation failed. Only set for the failed operation
+    pub error: Option<String>,
+}
+
+impl BatchResultDTO {
+    /// Result of an operation that was applied
+    pub fn ok(product: Product) -> Self {
+        Self {
+            status: "ok".to_string(),
+            product: Some(ProductDTO::from(product)),
+            error: None,
+        }
+    }
+
+    /// Results of the operations in a batch in which one failed, so none of them were applied
+    ///
+    /// # Parameters
+    /// - `len`: Number of operations in the batch
+    /// - `index`: Index of the failed operation
+    /// - `error`: Why the operation failed
+    pub fn failed(len: usize, index: usize, error: String) -> Vec<Self> {
+        let mut error = Some(error);
+
+        (0..len)
+            .map(|i| Self {
+                status: match i.cmp(&index) {
+                    std::cmp::Ordering::Less => "rolled_back",
+                    std::cmp::Ordering::Equal => "failed",
+                    std::cmp::Ordering::Greater => "skipped",
+                }
+                .to_string(),
+                product: None,
+                error: if i == index { error.take() } else { None },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::entities::{FakeProduct, FakeStashItem};
+
+    use super::*;
+
+    #[test]
+    fn test_ok() {
+        // With a single stash item, as they come in any order
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new().build()])
+            .build();
+
+        let dto = BatchResultDTO::ok(product.clone());
+
+        assert_eq!(
+            dto,
+            BatchResultDTO {
+                status: "ok".to_string(),
+                product: Some(ProductDTO::from(product)),
+                error: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_failed() {
+        let dtos = BatchResultDTO::failed(3, 1, "Product not found".to_string());
+
+        assert_eq!(
+            dtos.iter()
+                .map(|dto| dto.status.as_str())
+                .collect::<Vec<_>>(),
+            vec!["rolled_back", "failed", "skipped"]
+        );
+        assert_eq!(
+            dtos.iter().map(|dto| dto.error.clone()).collect::<Vec<_>>(),
+            vec![None, Some("Product not found".to_string()), None]
+        );
+        assert!(dtos.iter().all(|dto| dto.product.is_none()));
+    }
+}
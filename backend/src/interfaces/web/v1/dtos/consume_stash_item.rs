@@ -0,0 +1,13 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// DTO for a request to consume an amount of a stash item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsumeStashItemDTO {
+    /// Amount to consume
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub amount: Decimal,
+    /// Unit of the amount. Defaults to the unit of the product
+    #[serde(default)]
+    pub unit: Option<String>,
+}
@@ -0,0 +1,141 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entities::{Product, StashItem};
+
+/// DTO for a stash item along with the product it belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiringStashItemDTO {
+    pub product_id: String,
+    pub brand: String,
+    pub name: String,
+    pub unit: String,
+    pub id: String,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub quantity: Decimal,
+    pub expiry_date: Option<String>,
+    pub expiry_date_kind: String,
+    pub opened_on: Option<String>,
+    /// Date the stash item actually expires, taking opening into account
+    pub effective_expiry_date: String,
+    /// Whether the stash item is fresh, past its best-before date, or unsafe
+    pub status: String,
+}
+
+impl ExpiringStashItemDTO {
+    /// Creates DTOs for the stash items of the given products, sorted by when they expire. Stash items which do not
+    /// expire are left out
+    ///
+    /// # Parameters
+    /// - `products`: The products whose stash items to include
+    /// - `today`: The date the statuses of the stash items are given for
+    pub fn from_products(products: &[Product], today: NaiveDate) -> Vec<Self> {
+        let mut stash_items = products
+            .iter()
+            .flat_map(|product| {
+                product
+                    .stash_items()
+                    .into_iter()
+                    .filter_map(move |stash_item| {
+                        let date = product.effective_expiry_date(stash_item)?;
+                        Some((date, Self::new(product, stash_item, date, today)))
+                    })
+            })
+            .collect::<Vec<_>>();
+        stash_items
+            .sort_by(|(a_date, a), (b_date, b)| a_date.cmp(b_date).then_with(|| a.id.cmp(&b.id)));
+
+        stash_items.into_iter().map(|(_, dto)| dto).collect()
+    }
+
+    fn new(product: &Product, stash_item: &StashItem, date: NaiveDate, today: NaiveDate) -> Self {
+        Self {
+            product_id: product.id().to_string(),
+            brand: product.brand().to_string(),
+            name: product.name().to_string(),
+            unit: product.unit().to_string(),
+            id: stash_item.id().to_string(),
+            quantity: stash_item.quantity().value(),
+            expiry_date: stash_item.expiry_date().map(|date| date.to_string()),
+            expiry_date_kind: stash_item.expiry_date_kind().to_string(),
+            opened_on: stash_item.opened_on().map(|date| date.to_string()),
+            effective_expiry_date: date.to_string(),
+            status: product.expiry_status(stash_item, today).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem},
+        value_objects::ExpiryDateKind,
+    };
+
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 11, day).unwrap()
+    }
+
+    #[test]
+    fn test_from_products() {
+        let stash_item_id = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let product = FakeProduct::new()
+            .with_id("7038010000737".parse().unwrap())
+            .with_brand("Tine".parse().unwrap())
+            .with_name("Milk".to_string())
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_id(stash_item_id)
+                .with_quantity(2.try_into().unwrap())
+                .with_expiry_date(date(9))
+                .with_expiry_date_kind(ExpiryDateKind::UseBy)
+                .build()])
+            .build();
+
+        let dtos = ExpiringStashItemDTO::from_products(&[product], date(10));
+
+        assert_eq!(
+            dtos,
+            vec![ExpiringStashItemDTO {
+                product_id: "7038010000737".to_string(),
+                brand: "Tine".to_string(),
+                name: "Milk".to_string(),
+                unit: "pieces".to_string(),
+                id: stash_item_id.to_string(),
+                quantity: 2.into(),
+                expiry_date: Some("2023-11-09".to_string()),
+                expiry_date_kind: "use_by".to_string(),
+                opened_on: None,
+                effective_expiry_date: "2023-11-09".to_string(),
+                status: "unsafe".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_from_products_sorted_by_expiry() {
+        let expiring = |day| FakeStashItem::new().with_expiry_date(date(day)).build();
+        let products = [
+            FakeProduct::new()
+                .with_stash_items(vec![expiring(20), expiring(5)])
+                .build(),
+            FakeProduct::new()
+                .with_stash_items(vec![
+                    expiring(12),
+                    FakeStashItem::new().without_expiry_date().build(),
+                ])
+                .build(),
+        ];
+
+        let dates = ExpiringStashItemDTO::from_products(&products, date(10))
+            .into_iter()
+            .map(|dto| dto.effective_expiry_date)
+            .collect::<Vec<_>>();
+
+        assert_eq!(dates, vec!["2023-11-05", "2023-11-12", "2023-11-20"]);
+    }
+}
@@ -0,0 +1,147 @@
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::interfaces::web::v1::errors::ExpiringQueryParseError;
+
+/// Query parameters for listing expiring stash items
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiringStashItemsQueryDTO {
+    /// Only include stash items expiring on or after this date
+    pub after: Option<String>,
+    /// Only include stash items expiring before this date
+    pub before: Option<String>,
+    /// Only include stash items expiring within this many days from today, today included
+    pub within_days: Option<u32>,
+    /// Only include stash items which have expired (`true`) or have not (`false`)
+    pub expired: Option<bool>,
+}
+
+impl ExpiringStashItemsQueryDTO {
+    /// Turns the query into a date interval
+    ///
+    /// # Parameters
+    /// - `today`: The date relative dates are counted from
+    ///
+    /// # Returns
+    /// The start of the interval, inclusive, and its end, exclusive
+    pub fn interval(
+        &self,
+        today: NaiveDate,
+    ) -> Result<(Option<NaiveDate>, Option<NaiveDate>), ExpiringQueryParseError> {
+        let parse_date = |date: &Option<String>| {
+            date.as_ref()
+                .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+                .transpose()
+                .map_err(|_| ExpiringQueryParseError::InvalidDate)
+        };
+
+        let mut after = parse_date(&self.after)?;
+        let mut before = match (parse_date(&self.before)?, self.within_days) {
+            (Some(_), Some(_)) => return Err(ExpiringQueryParseError::ConflictingBounds),
+            (before, None) => before,
+            (None, Some(days)) => today.checked_add_days(Days::new(u64::from(days) + 1)),
+        };
+
+        // Stash items expiring today have not expired yet
+        match self.expired {
+            Some(true) => before = Some(before.map_or(today, |before| before.min(today))),
+            Some(false) => after = Some(after.map_or(today, |after| after.max(today))),
+            None => (),
+        }
+
+        Ok((after, before))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 11, day).unwrap()
+    }
+
+    #[test]
+    fn test_interval_unbounded() {
+        let query = ExpiringStashItemsQueryDTO::default();
+
+        assert_eq!(query.interval(date(10)), Ok((None, None)));
+    }
+
+    #[test]
+    fn test_interval_dates() {
+        let query = ExpiringStashItemsQueryDTO {
+            after: Some("2023-11-01".to_string()),
+            before: Some("2023-11-20".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            query.interval(date(10)),
+            Ok((Some(date(1)), Some(date(20))))
+        );
+    }
+
+    #[test]
+    fn test_interval_within_days() {
+        let query = ExpiringStashItemsQueryDTO {
+            within_days: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(query.interval(date(10)), Ok((None, Some(date(14)))));
+    }
+
+    #[test]
+    fn test_interval_expired() {
+        let query = ExpiringStashItemsQueryDTO {
+            before: Some("2023-11-20".to_string()),
+            expired: Some(true),
+            ..Default::default()
+        };
+
+        assert_eq!(query.interval(date(10)), Ok((None, Some(date(10)))));
+    }
+
+    #[test]
+    fn test_interval_not_expired() {
+        let query = ExpiringStashItemsQueryDTO {
+            after: Some("2023-11-01".to_string()),
+            within_days: Some(7),
+            expired: Some(false),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            query.interval(date(10)),
+            Ok((Some(date(10)), Some(date(18))))
+        );
+    }
+
+    #[test]
+    fn test_interval_conflicting_bounds() {
+        let query = ExpiringStashItemsQueryDTO {
+            before: Some("2023-11-20".to_string()),
+            within_days: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            query.interval(date(10)),
+            Err(ExpiringQueryParseError::ConflictingBounds)
+        );
+    }
+
+    #[test]
+    fn test_interval_invalid_date() {
+        let query = ExpiringStashItemsQueryDTO {
+            after: Some("tomorrow".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            query.interval(date(10)),
+            Err(ExpiringQueryParseError::InvalidDate)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::{Days, NaiveDate, NaiveDateTime};
+
+use crate::domain::{entities::Product, value_objects::ExpiryDateKind};
+
+/// Longest a content line may be, in octets, before it must be folded
+const MAX_LINE_LENGTH: usize = 75;
+
+/// An RFC 5545 calendar with one all-day event per stash item expiry date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiryCalendarDTO {
+    /// Time the calendar was made
+    created_at: NaiveDateTime,
+    events: Vec<ExpiryEventDTO>,
+}
+
+/// An all-day event on the day a stash item expires
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExpiryEventDTO {
+    uid: String,
+    date: NaiveDate,
+    summary: String,
+    description: String,
+}
+
+impl ExpiryCalendarDTO {
+    /// Creates a calendar of the expiry dates of the stash items of the given products
+    ///
+    /// # Parameters
+    /// - `products`: The products whose stash items to include
+    /// - `after`: Only include stash items expiring on or after this date
+    /// - `before`: Only include stash items expiring before this date
+    /// - `created_at`: Time the calendar is made, in UTC
+    pub fn new(
+        products: &[Product],
+        after: Option<NaiveDate>,
+        before: Option<NaiveDate>,
+        created_at: NaiveDateTime,
+    ) -> Self {
+        let mut events = products
+            .iter()
+            .flat_map(|product| {
+                product.stash_items().into_iter().filter_map(|stash_item| {
+                    let date = product.effective_expiry_date(stash_item)?;
+                    if after.is_some_and(|after| date < after)
+                        || before.is_some_and(|before| date >= before)
+                    {
+                        return None;
+                    }
+
+                    let kind = match stash_item.expiry_date_kind() {
+                        ExpiryDateKind::BestBefore => "Best before",
+                        ExpiryDateKind::UseBy => "Use by",
+                    };
+
+                    Some(ExpiryEventDTO {
+                        uid: format!("{}@rsstash", stash_item.id()),
+                        date,
+                        summary: format!(
+                            "{} ({}), {} {}",
+                            product.name(),
+                            product.brand(),
+                            stash_item.quantity(),
+                            product.unit()
+                        ),
+                        description: format!("{} {}\nProduct ID: {}", kind, date, product.id()),
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        events.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.uid.cmp(&b.uid)));
+
+        Self { created_at, events }
+    }
+}
+
+/// Renders the calendar as an iCalendar file
+impl std::fmt::Display for ExpiryCalendarDTO {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let created_at = self.created_at.format("%Y%m%dT%H%M%SZ").to_string();
+
+        write_line(f, "BEGIN:VCALENDAR")?;
+        write_line(f, "VERSION:2.0")?;
+        write_line(f, "PRODID:-//rsstash//Stash expiry dates//EN")?;
+        write_line(f, "CALSCALE:GREGORIAN")?;
+        write_line(f, "X-WR-CALNAME:Stash expiry dates")?;
+
+        for event in &self.events {
+            // All-day events end, exclusively, on the day after they start
+            let end = event
+                .date
+                .checked_add_days(Days::new(1))
+                .unwrap_or(event.date);
+
+            write_line(f, "BEGIN:VEVENT")?;
+            write_line(f, &format!("UID:{}", escape(&event.uid)))?;
+            write_line(f, &format!("DTSTAMP:{}", created_at))?;
+            write_line(
+                f,
+                &format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")),
+            )?;
+            write_line(f, &format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")))?;
+            write_line(f, &format!("SUMMARY:{}", escape(&event.summary)))?;
+            write_line(f, &format!("DESCRIPTION:{}", escape(&event.description)))?;
+            write_line(f, "TRANSP:TRANSPARENT")?;
+            write_line(f, "END:VEVENT")?;
+        }
+
+        write_line(f, "END:VCALENDAR")
+    }
+}
+
+/// Escapes text so it can be used as a property value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Writes a content line, folding it so no line is longer than [`MAX_LINE_LENGTH`] octets. Lines are never split
+/// within a character
+fn write_line(f: &mut std::fmt::Formatter<'_>, line: &str) -> std::fmt::Result {
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            // The space starting the continuation line counts towards its length
+            write!(f, "\r\n ")?;
+            length = 1;
+        }
+
+        write!(f, "{}", c)?;
+        length += c.len_utf8();
+    }
+
+    write!(f, "\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Datelike;
+    use uuid::Uuid;
+
+    use crate::domain::entities::{FakeProduct, FakeStashItem};
+
+    use super::*;
+
+    fn created_at() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 11, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_calendar() {
+        let stash_item_id = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let product = FakeProduct::new()
+            .with_id("7038010000737".parse().unwrap())
+            .with_brand("Tine".parse().unwrap())
+            .with_name("Milk".to_string())
+            .with_stash_items(vec![FakeStashItem::new()
+                .with_id(stash_item_id)
+                .with_quantity(2.try_into().unwrap())
+                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, 10).unwrap())
+                .with_expiry_date_kind(ExpiryDateKind::UseBy)
+                .build()])
+            .build();
+
+        let calendar = ExpiryCalendarDTO::new(&[product], None, None, created_at());
+
+        assert_eq!(
+            calendar.to_string(),
+            "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            PRODID:-//rsstash//Stash expiry dates//EN\r\n\
+            CALSCALE:GREGORIAN\r\n\
+            X-WR-CALNAME:Stash expiry dates\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:67e55044-10b1-426f-9247-bb680e5fe0c8@rsstash\r\n\
+            DTSTAMP:20231101T120000Z\r\n\
+            DTSTART;VALUE=DATE:20231110\r\n\
+            DTEND;VALUE=DATE:20231111\r\n\
+            SUMMARY:Milk (Tine)\\, 2 pieces\r\n\
+            DESCRIPTION:Use by 2023-11-10\\nProduct ID: 7038010000737\r\n\
+            TRANSP:TRANSPARENT\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n"
+        );
+    }
+
+    #[test]
+    fn test_calendar_date_window() {
+        let expiring = |day| {
+            FakeStashItem::new()
+                .with_expiry_date(NaiveDate::from_ymd_opt(2023, 11, day).unwrap())
+                .build()
+        };
+        let product = FakeProduct::new()
+            .with_stash_items(vec![
+                expiring(9),
+                expiring(10),
+                expiring(19),
+                expiring(20),
+                FakeStashItem::new().without_expiry_date().build(),
+            ])
+            .build();
+
+        let calendar = ExpiryCalendarDTO::new(
+            &[product],
+            NaiveDate::from_ymd_opt(2023, 11, 10),
+            NaiveDate::from_ymd_opt(2023, 11, 20),
+            created_at(),
+        );
+
+        let dates = calendar
+            .events
+            .iter()
+            .map(|event| event.date.day())
+            .collect::<Vec<_>>();
+        assert_eq!(dates, vec![10, 19]);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("Salt; coarse, \\ fine\nline"),
+            "Salt\\; coarse\\, \\\\ fine\\nline"
+        );
+    }
+
+    #[test]
+    fn test_line_folding() {
+        let product = FakeProduct::new()
+            .with_name("Æ".repeat(100))
+            .with_stash_items(vec![FakeStashItem::new().build()])
+            .build();
+
+        let calendar = ExpiryCalendarDTO::new(&[product], None, None, created_at()).to_string();
+
+        let lines = calendar.split("\r\n").collect::<Vec<_>>();
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(lines.iter().any(|line| line.starts_with(' ')));
+
+        // Unfolding gives back the original line
+        let unfolded = calendar.replace("\r\n ", "");
+        assert!(unfolded.contains(&format!("SUMMARY:{}", "Æ".repeat(100))));
+    }
+}
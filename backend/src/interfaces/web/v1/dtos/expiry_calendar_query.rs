@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for the expiry calendar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiryCalendarQueryDTO {
+    /// Only include stash items expiring on or after this date
+    pub after: Option<String>,
+    /// Only include stash items expiring before this date
+    pub before: Option<String>,
+}
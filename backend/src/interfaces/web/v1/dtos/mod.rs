@@ -1,5 +1,37 @@
+mod barcode;
+mod batch_operation;
+mod batch_result;
+mod consume_stash_item;
+mod expiring_stash_item;
+mod expiring_stash_items_query;
+mod expiry_calendar;
+mod expiry_calendar_query;
+mod move_stash_item;
+mod open_stash_item;
 mod product;
+mod split_stash_item;
+mod stash_event;
 mod stash_item;
+mod stash_item_cursor;
+mod stash_item_page;
+mod stash_items_query;
+mod trash_entry;
 
+pub use barcode::BarcodeDTO;
+pub use batch_operation::BatchOperationDTO;
+pub use batch_result::BatchResultDTO;
+pub use consume_stash_item::ConsumeStashItemDTO;
+pub use expiring_stash_item::ExpiringStashItemDTO;
+pub use expiring_stash_items_query::ExpiringStashItemsQueryDTO;
+pub use expiry_calendar::ExpiryCalendarDTO;
+pub use expiry_calendar_query::ExpiryCalendarQueryDTO;
+pub use move_stash_item::MoveStashItemDTO;
+pub use open_stash_item::OpenStashItemDTO;
 pub use product::ProductDTO;
+pub use split_stash_item::SplitStashItemDTO;
+pub use stash_event::StashEventDTO;
 pub use stash_item::StashItemDTO;
+pub use stash_item_cursor::StashItemCursorDTO;
+pub use stash_item_page::{StashItemPageDTO, StashItemViewDTO};
+pub use stash_items_query::{StashItemsQuery, StashItemsQueryDTO};
+pub use trash_entry::TrashEntryDTO;
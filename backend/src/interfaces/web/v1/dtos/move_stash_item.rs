@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// DTO for a request to move a stash item to another product
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveStashItemDTO {
+    /// ID of the product to move the stash item to
+    pub product_id: String,
+    /// Whether to merge the stash item into a stash item with the same expiry date on the target product
+    #[serde(default)]
+    pub merge: bool,
+}
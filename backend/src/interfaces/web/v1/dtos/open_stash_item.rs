@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// DTO for a request to open a unit of a stash item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenStashItemDTO {
+    /// Date the unit was opened. Defaults to today
+    #[serde(default)]
+    pub opened_on: Option<String>,
+}
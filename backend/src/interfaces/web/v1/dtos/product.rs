@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    domain::entities::{Product, StashItem},
+    domain::{
+        entities::{Product, StashItem},
+        value_objects::Unit,
+    },
     interfaces::web::v1::errors::ProductParseError,
 };
 
@@ -13,20 +16,48 @@ pub struct ProductDTO {
     pub id: String,
     pub brand: String,
     pub name: String,
+    /// Unit the quantities of the stash items are in. Defaults to pieces
+    #[serde(default = "default_unit")]
+    pub unit: String,
     pub stash_items: Vec<StashItemDTO>,
+    /// Barcodes attached to the product besides its ID. Only informative; barcodes are attached and detached through
+    /// their own endpoints
+    #[serde(default)]
+    pub barcodes: Vec<String>,
+    /// Number of days the product keeps after being opened
+    #[serde(default)]
+    pub shelf_life_after_opening_days: Option<u32>,
+    /// Number of days stash items are still safe after their best-before date
+    #[serde(default)]
+    pub best_before_grace_period_days: Option<u32>,
+}
+
+fn default_unit() -> String {
+    Unit::default().to_string()
 }
 
 impl From<Product> for ProductDTO {
     fn from(product: Product) -> Self {
+        let mut barcodes = product
+            .barcodes()
+            .into_iter()
+            .map(|barcode| barcode.to_string())
+            .collect::<Vec<_>>();
+        barcodes.sort();
+
         Self {
             id: product.id().to_string(),
             brand: product.brand().to_string(),
             name: product.name().to_string(),
+            unit: product.unit().to_string(),
             stash_items: product
                 .stash_items()
                 .into_iter()
                 .map(|item| StashItemDTO::from(item.clone()))
                 .collect(),
+            barcodes,
+            shelf_life_after_opening_days: *product.shelf_life_after_opening_days(),
+            best_before_grace_period_days: *product.best_before_grace_period_days(),
         }
     }
 }
@@ -35,15 +66,20 @@ impl TryFrom<ProductDTO> for Product {
     type Error = ProductParseError;
 
     fn try_from(dto: ProductDTO) -> Result<Self, Self::Error> {
-        Ok(Self::new(
+        let mut product = Self::try_new(
             dto.id.parse()?,
             dto.brand.parse()?,
             dto.name,
             dto.stash_items
                 .into_iter()
-                .map(|item| StashItem::try_from(item))
+                .map(StashItem::try_from)
                 .collect::<Result<Vec<_>, _>>()?,
-        ))
+        )?;
+        product.set_unit(dto.unit.parse()?);
+        product.set_shelf_life_after_opening_days(dto.shelf_life_after_opening_days);
+        product.set_best_before_grace_period_days(dto.best_before_grace_period_days);
+
+        Ok(product)
     }
 }
 
@@ -61,18 +97,26 @@ mod tests {
             id: "1".to_string(),
             brand: "brand".to_string(),
             name: "name".to_string(),
+            unit: "pieces".to_string(),
             stash_items: vec![
                 StashItemDTO {
                     id: Uuid::new_v4().to_string(),
-                    quantity: 3,
-                    expiry_date: "2021-01-01".to_string(),
+                    quantity: 3.into(),
+                    expiry_date: Some("2021-01-01".to_string()),
+                    expiry_date_kind: "best_before".to_string(),
+                    opened_on: None,
                 },
                 StashItemDTO {
                     id: Uuid::new_v4().to_string(),
-                    quantity: 5,
-                    expiry_date: "2021-01-02".to_string(),
+                    quantity: 5.into(),
+                    expiry_date: Some("2021-01-02".to_string()),
+                    expiry_date_kind: "best_before".to_string(),
+                    opened_on: None,
                 },
             ],
+            barcodes: vec![],
+            shelf_life_after_opening_days: None,
+            best_before_grace_period_days: None,
         };
 
         let product = Product::new(
@@ -86,7 +130,7 @@ mod tests {
                     StashItem::new(
                         item.id.parse().unwrap(),
                         item.quantity.try_into().unwrap(),
-                        item.expiry_date.parse().unwrap(),
+                        item.expiry_date.as_ref().map(|date| date.parse().unwrap()),
                     )
                 })
                 .collect(),
@@ -116,7 +160,29 @@ mod tests {
             id: "".to_string(),
             brand: "brand".to_string(),
             name: "name".to_string(),
+            unit: "pieces".to_string(),
+            stash_items: vec![],
+            barcodes: vec![],
+            shelf_life_after_opening_days: None,
+            best_before_grace_period_days: None,
+        };
+
+        let product = Product::try_from(dto);
+
+        assert!(product.is_err());
+    }
+
+    #[test]
+    fn test_product_try_from_dto_with_invalid_unit() {
+        let dto = ProductDTO {
+            id: "1".to_string(),
+            brand: "brand".to_string(),
+            name: "name".to_string(),
+            unit: "bushels".to_string(),
             stash_items: vec![],
+            barcodes: vec![],
+            shelf_life_after_opening_days: None,
+            best_before_grace_period_days: None,
         };
 
         let product = Product::try_from(dto);
@@ -130,11 +196,49 @@ mod tests {
             id: "1".to_string(),
             brand: "".to_string(),
             name: "name".to_string(),
+            unit: "pieces".to_string(),
             stash_items: vec![],
+            barcodes: vec![],
+            shelf_life_after_opening_days: None,
+            best_before_grace_period_days: None,
         };
 
         let product = Product::try_from(dto);
 
         assert!(product.is_err());
     }
+
+    #[test]
+    fn test_product_try_from_dto_with_duplicate_stash_items() {
+        let stash_item = StashItemDTO {
+            id: Uuid::new_v4().to_string(),
+            quantity: 1.into(),
+            expiry_date: None,
+            expiry_date_kind: "best_before".to_string(),
+            opened_on: None,
+        };
+        let dto = ProductDTO {
+            id: "1".to_string(),
+            brand: "brand".to_string(),
+            name: "name".to_string(),
+            unit: "pieces".to_string(),
+            stash_items: vec![
+                stash_item.clone(),
+                StashItemDTO {
+                    id: Uuid::new_v4().to_string(),
+                    ..stash_item
+                },
+            ],
+            barcodes: vec![],
+            shelf_life_after_opening_days: None,
+            best_before_grace_period_days: None,
+        };
+
+        let product = Product::try_from(dto);
+
+        assert!(matches!(
+            product,
+            Err(ProductParseError::StashItemExists(_))
+        ));
+    }
 }
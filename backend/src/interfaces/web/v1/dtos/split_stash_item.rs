@@ -0,0 +1,109 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    domain::{entities::StashItem, value_objects::ExpiryDateKind},
+    interfaces::web::v1::errors::StashItemParseError,
+};
+
+/// DTO for a request to split part of a stash item off into a new stash item. The ID of the new stash item is
+/// generated by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitStashItemDTO {
+    /// Quantity to split off
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub quantity: Decimal,
+    /// Date the new stash item expires. Left out for stash items which do not expire
+    #[serde(default)]
+    pub expiry_date: Option<String>,
+    /// Kind of the expiry date of the new stash item. Defaults to best before
+    #[serde(default)]
+    pub expiry_date_kind: Option<String>,
+}
+
+impl TryFrom<SplitStashItemDTO> for StashItem {
+    type Error = StashItemParseError;
+
+    fn try_from(dto: SplitStashItemDTO) -> Result<Self, Self::Error> {
+        let mut stash_item = Self::new(
+            Uuid::new_v4(),
+            dto.quantity.try_into()?,
+            dto.expiry_date.map(|date| date.parse()).transpose()?,
+        );
+        stash_item.set_expiry_date_kind(
+            dto.expiry_date_kind
+                .map(|kind| kind.parse())
+                .transpose()?
+                .unwrap_or(ExpiryDateKind::default()),
+        );
+
+        Ok(stash_item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_from_json() {
+        let json = r#"{"quantity":2,"expiry_date":"2021-06-01"}"#;
+
+        let dto: SplitStashItemDTO = serde_json::from_str(json).unwrap();
+        let item = StashItem::try_from(dto).unwrap();
+
+        assert_eq!(item.quantity(), &2.try_into().unwrap());
+        assert_eq!(item.expiry_date(), &Some("2021-06-01".parse().unwrap()));
+        assert_eq!(item.expiry_date_kind(), &ExpiryDateKind::BestBefore);
+    }
+
+    #[test]
+    fn test_split_generates_id() {
+        let dto = SplitStashItemDTO {
+            quantity: 2.into(),
+            expiry_date: None,
+            expiry_date_kind: None,
+        };
+
+        let first = StashItem::try_from(dto.clone()).unwrap();
+        let second = StashItem::try_from(dto).unwrap();
+
+        assert_ne!(first.id(), second.id());
+    }
+
+    #[test]
+    fn test_split_use_by() {
+        let dto = SplitStashItemDTO {
+            quantity: 2.into(),
+            expiry_date: Some("2021-06-01".to_string()),
+            expiry_date_kind: Some("use_by".to_string()),
+        };
+
+        let item = StashItem::try_from(dto).unwrap();
+
+        assert_eq!(item.expiry_date_kind(), &ExpiryDateKind::UseBy);
+    }
+
+    #[test]
+    fn test_split_invalid_expiry_date() {
+        let dto = SplitStashItemDTO {
+            quantity: 2.into(),
+            expiry_date: Some("2021-13-01".to_string()),
+            expiry_date_kind: None,
+        };
+
+        assert!(StashItem::try_from(dto).is_err());
+    }
+
+    #[test]
+    fn test_split_negative_quantity() {
+        let dto = SplitStashItemDTO {
+            quantity: (-2).into(),
+            expiry_date: None,
+            expiry_date_kind: None,
+        };
+
+        assert!(StashItem::try_from(dto).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::application::events::StashEvent;
+
+use super::{ProductDTO, StashItemDTO};
+
+/// DTO for something which changed in the stash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StashEventDTO {
+    ProductCreated {
+        product: ProductDTO,
+    },
+    ProductUpdated {
+        product: ProductDTO,
+    },
+    ProductDeleted {
+        product_id: String,
+    },
+    StashItemAdded {
+        product_id: String,
+        stash_item: StashItemDTO,
+    },
+    StashItemRemoved {
+        product_id: String,
+        stash_item_id: String,
+    },
+    QuantityChanged {
+        product_id: String,
+        stash_item_id: String,
+        #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+        from: Decimal,
+        #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+        to: Decimal,
+    },
+    ExpiryDateChanged {
+        product_id: String,
+        stash_item_id: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    ExpiryDateKindChanged {
+        product_id: String,
+        stash_item_id: String,
+        from: String,
+        to: String,
+    },
+    OpenedOnChanged {
+        product_id: String,
+        stash_item_id: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+impl From<StashEvent> for StashEventDTO {
+    fn from(event: StashEvent) -> Self {
+        match event {
+            StashEvent::ProductCreated { product } => Self::ProductCreated {
+                product: ProductDTO::from(*product),
+            },
+            StashEvent::ProductUpdated { product } => Self::ProductUpdated {
+                product: ProductDTO::from(*product),
+            },
+            StashEvent::ProductDeleted { product_id } => Self::ProductDeleted {
+                product_id: product_id.to_string(),
+            },
+            StashEvent::StashItemAdded {
+                product_id,
+                stash_item,
+            } => Self::StashItemAdded {
+                product_id: product_id.to_string(),
+                stash_item: StashItemDTO::from(stash_item),
+            },
+            StashEvent::StashItemRemoved {
+                product_id,
+                stash_item_id,
+            } => Self::StashItemRemoved {
+                product_id: product_id.to_string(),
+                stash_item_id: stash_item_id.to_string(),
+            },
+            StashEvent::QuantityChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::QuantityChanged {
+                product_id: product_id.to_string(),
+                stash_item_id: stash_item_id.to_string(),
+                from: from.value(),
+                to: to.value(),
+            },
+            StashEvent::ExpiryDateChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::ExpiryDateChanged {
+                product_id: product_id.to_string(),
+                stash_item_id: stash_item_id.to_string(),
+                from: from.map(|date| date.to_string()),
+                to: to.map(|date| date.to_string()),
+            },
+            StashEvent::ExpiryDateKindChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::ExpiryDateKindChanged {
+                product_id: product_id.to_string(),
+                stash_item_id: stash_item_id.to_string(),
+                from: from.to_string(),
+                to: to.to_string(),
+            },
+            StashEvent::OpenedOnChanged {
+                product_id,
+                stash_item_id,
+                from,
+                to,
+            } => Self::OpenedOnChanged {
+                product_id: product_id.to_string(),
+                stash_item_id: stash_item_id.to_string(),
+                from: from.map(|date| date.to_string()),
+                to: to.map(|date| date.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::{
+        entities::{FakeProduct, FakeStashItem},
+        value_objects::{ExpiryDateKind, ProductId},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_from_product_created() {
+        // With a single stash item, as they come in any order
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new().build()])
+            .build();
+
+        let dto = StashEventDTO::from(StashEvent::ProductCreated {
+            product: Box::new(product.clone()),
+        });
+
+        assert_eq!(
+            dto,
+            StashEventDTO::ProductCreated {
+                product: ProductDTO::from(product)
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_stash_item_added() {
+        let product_id: ProductId = "7038010000737".parse().unwrap();
+        let stash_item = FakeStashItem::new().build();
+
+        let dto = StashEventDTO::from(StashEvent::StashItemAdded {
+            product_id,
+            stash_item: stash_item.clone(),
+        });
+
+        assert_eq!(
+            dto,
+            StashEventDTO::StashItemAdded {
+                product_id: "7038010000737".to_string(),
+                stash_item: StashItemDTO::from(stash_item),
+            }
+        );
+    }
+
+    #[test]
+    fn test_serialize_expiry_date_changed() {
+        let stash_item_id = Uuid::new_v4();
+        let dto = StashEventDTO::from(StashEvent::ExpiryDateChanged {
+            product_id: "7038010000737".parse().unwrap(),
+            stash_item_id,
+            from: None,
+            to: Some("2024-01-31".parse().unwrap()),
+        });
+
+        let json = serde_json::to_value(dto).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "expiry_date_changed",
+                "product_id": "7038010000737",
+                "stash_item_id": stash_item_id.to_string(),
+                "from": null,
+                "to": "2024-01-31",
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize_expiry_date_kind_changed() {
+        let stash_item_id = Uuid::new_v4();
+        let dto = StashEventDTO::from(StashEvent::ExpiryDateKindChanged {
+            product_id: "7038010000737".parse().unwrap(),
+            stash_item_id,
+            from: ExpiryDateKind::BestBefore,
+            to: ExpiryDateKind::UseBy,
+        });
+
+        let json = serde_json::to_value(dto).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "expiry_date_kind_changed",
+                "product_id": "7038010000737",
+                "stash_item_id": stash_item_id.to_string(),
+                "from": "best_before",
+                "to": "use_by",
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let stash_item_id = Uuid::new_v4();
+        let dto = StashEventDTO::from(StashEvent::StashItemRemoved {
+            product_id: "7038010000737".parse().unwrap(),
+            stash_item_id,
+        });
+
+        let json = serde_json::to_value(dto).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "stash_item_removed",
+                "product_id": "7038010000737",
+                "stash_item_id": stash_item_id.to_string(),
+            })
+        );
+    }
+}
@@ -1,13 +1,28 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use crate::{domain::entities::StashItem, interfaces::web::v1::errors::StashItemParseError};
+use crate::{
+    domain::{entities::StashItem, value_objects::ExpiryDateKind},
+    interfaces::web::v1::errors::StashItemParseError,
+};
 
 /// DTO for a stash item
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StashItemDTO {
     pub id: String,
-    pub quantity: u64,
-    pub expiry_date: String,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub quantity: Decimal,
+    /// Date the stash item expires. Left out for stash items which do not expire
+    #[serde(default)]
+    pub expiry_date: Option<String>,
+    #[serde(default = "default_expiry_date_kind")]
+    pub expiry_date_kind: String,
+    #[serde(default)]
+    pub opened_on: Option<String>,
+}
+
+fn default_expiry_date_kind() -> String {
+    ExpiryDateKind::default().to_string()
 }
 
 impl From<StashItem> for StashItemDTO {
@@ -15,7 +30,9 @@ impl From<StashItem> for StashItemDTO {
         Self {
             id: item.id().to_string(),
             quantity: item.quantity().value(),
-            expiry_date: item.expiry_date().to_string(),
+            expiry_date: item.expiry_date().map(|date| date.to_string()),
+            expiry_date_kind: item.expiry_date_kind().to_string(),
+            opened_on: item.opened_on().map(|date| date.to_string()),
         }
     }
 }
@@ -24,11 +41,15 @@ impl TryFrom<StashItemDTO> for StashItem {
     type Error = StashItemParseError;
 
     fn try_from(dto: StashItemDTO) -> Result<Self, Self::Error> {
-        Ok(Self::new(
+        let mut stash_item = Self::new(
             dto.id.parse()?,
             dto.quantity.try_into()?,
-            dto.expiry_date.parse()?,
-        ))
+            dto.expiry_date.map(|date| date.parse()).transpose()?,
+        );
+        stash_item.set_expiry_date_kind(dto.expiry_date_kind.parse()?);
+        stash_item.set_opened_on(dto.opened_on.map(|date| date.parse()).transpose()?);
+
+        Ok(stash_item)
     }
 }
 
@@ -36,20 +57,27 @@ impl TryFrom<StashItemDTO> for StashItem {
 mod tests {
     use uuid::Uuid;
 
+    use crate::domain::errors::ExpiryDateKindError;
+
     use super::*;
 
     #[test]
     fn test_dto_from_stash_item() {
         let expected_dto = StashItemDTO {
             id: Uuid::new_v4().to_string(),
-            quantity: 3,
-            expiry_date: "2021-01-01".to_string(),
+            quantity: 3.into(),
+            expiry_date: Some("2021-01-01".to_string()),
+            expiry_date_kind: "best_before".to_string(),
+            opened_on: None,
         };
 
         let item = StashItem::new(
             expected_dto.id.parse().unwrap(),
             expected_dto.quantity.try_into().unwrap(),
-            expected_dto.expiry_date.parse().unwrap(),
+            expected_dto
+                .expiry_date
+                .as_ref()
+                .map(|date| date.parse().unwrap()),
         );
 
         let dto = StashItemDTO::from(item);
@@ -62,7 +90,7 @@ mod tests {
         let expected_item = StashItem::new(
             Uuid::new_v4(),
             3.try_into().unwrap(),
-            "2021-01-01".parse().unwrap(),
+            Some("2021-01-01".parse().unwrap()),
         );
 
         let dto = StashItemDTO::from(expected_item.clone());
@@ -76,8 +104,10 @@ mod tests {
     fn test_stash_item_from_dto_invalid_id() {
         let dto = StashItemDTO {
             id: "".to_string(),
-            quantity: 3,
-            expiry_date: "2021-01-01".to_string(),
+            quantity: 3.into(),
+            expiry_date: Some("2021-01-01".to_string()),
+            expiry_date_kind: "best_before".to_string(),
+            opened_on: None,
         };
 
         let result = StashItem::try_from(dto);
@@ -89,8 +119,10 @@ mod tests {
     fn test_stash_item_from_dto_invalid_quantity() {
         let dto = StashItemDTO {
             id: Uuid::new_v4().to_string(),
-            quantity: 0,
-            expiry_date: "2021-01-01".to_string(),
+            quantity: 0.into(),
+            expiry_date: Some("2021-01-01".to_string()),
+            expiry_date_kind: "best_before".to_string(),
+            opened_on: None,
         };
 
         let result = StashItem::try_from(dto);
@@ -98,12 +130,106 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_stash_item_from_dto_negative_quantity() {
+        let dto = StashItemDTO {
+            id: Uuid::new_v4().to_string(),
+            quantity: (-1).into(),
+            expiry_date: Some("2021-01-01".to_string()),
+            expiry_date_kind: "best_before".to_string(),
+            opened_on: None,
+        };
+
+        let result = StashItem::try_from(dto);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stash_item_from_json_fractional_quantity() {
+        let json = r#"{"id":"67e55044-10b1-426f-9247-bb680e5fe0c8","quantity":0.75,"expiry_date":"2021-01-01"}"#;
+
+        let dto: StashItemDTO = serde_json::from_str(json).unwrap();
+        let item = StashItem::try_from(dto.clone()).unwrap();
+
+        assert_eq!(item.quantity(), &"0.75".parse().unwrap());
+        assert!(serde_json::to_string(&dto)
+            .unwrap()
+            .contains(r#""quantity":0.75"#));
+    }
+
+    #[test]
+    fn test_stash_item_json_round_trip_keeps_whole_quantity() {
+        let json = r#"{"id":"67e55044-10b1-426f-9247-bb680e5fe0c8","quantity":2,"expiry_date":null,"expiry_date_kind":"best_before","opened_on":null}"#;
+
+        let dto: StashItemDTO = serde_json::from_str(json).unwrap();
+
+        assert_eq!(serde_json::to_string(&dto).unwrap(), json);
+    }
+
+    #[test]
+    fn test_stash_item_json_round_trip_keeps_precision() {
+        let json = r#"{"id":"67e55044-10b1-426f-9247-bb680e5fe0c8","quantity":1234567890.123456789,"expiry_date":null,"expiry_date_kind":"best_before","opened_on":null}"#;
+
+        let dto: StashItemDTO = serde_json::from_str(json).unwrap();
+
+        assert_eq!(dto.quantity, "1234567890.123456789".parse().unwrap());
+        assert_eq!(serde_json::to_string(&dto).unwrap(), json);
+    }
+
+    #[test]
+    fn test_stash_item_from_json_without_expiry_date() {
+        let json = r#"{"id":"67e55044-10b1-426f-9247-bb680e5fe0c8","quantity":1}"#;
+
+        let dto: StashItemDTO = serde_json::from_str(json).unwrap();
+        let item = StashItem::try_from(dto).unwrap();
+
+        assert_eq!(item.expiry_date(), &None);
+    }
+
+    #[test]
+    fn test_stash_item_from_dto_use_by() {
+        let dto = StashItemDTO {
+            id: Uuid::new_v4().to_string(),
+            quantity: 3.into(),
+            expiry_date: Some("2021-01-01".to_string()),
+            expiry_date_kind: "use_by".to_string(),
+            opened_on: None,
+        };
+
+        let item = StashItem::try_from(dto).unwrap();
+
+        assert_eq!(item.expiry_date_kind(), &ExpiryDateKind::UseBy);
+    }
+
+    #[test]
+    fn test_stash_item_from_dto_invalid_expiry_date_kind() {
+        let dto = StashItemDTO {
+            id: Uuid::new_v4().to_string(),
+            quantity: 3.into(),
+            expiry_date: Some("2021-01-01".to_string()),
+            expiry_date_kind: "sell_by".to_string(),
+            opened_on: None,
+        };
+
+        let result = StashItem::try_from(dto);
+
+        assert_eq!(
+            result,
+            Err(StashItemParseError::ExpiryDateKindError(
+                ExpiryDateKindError::UnknownKindError
+            ))
+        );
+    }
+
     #[test]
     fn test_stash_item_from_dto_invalid_expiry_date() {
         let dto = StashItemDTO {
             id: Uuid::new_v4().to_string(),
-            quantity: 3,
-            expiry_date: "".to_string(),
+            quantity: 3.into(),
+            expiry_date: Some("".to_string()),
+            expiry_date_kind: "best_before".to_string(),
+            opened_on: None,
         };
 
         let result = StashItem::try_from(dto);
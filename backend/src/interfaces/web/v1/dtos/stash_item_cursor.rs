@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    application::queries::StashItemCursor, interfaces::web::v1::errors::StashItemsQueryParseError,
+};
+
+/// DTO for a cursor into a list of stash items. Opaque to clients; it is the hex encoded sort key and the stash item
+/// ID, separated by a dot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StashItemCursorDTO(pub String);
+
+impl From<&StashItemCursor> for StashItemCursorDTO {
+    fn from(cursor: &StashItemCursor) -> Self {
+        let key = cursor
+            .key()
+            .bytes()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        Self(format!("{}.{}", key, cursor.id()))
+    }
+}
+
+impl TryFrom<StashItemCursorDTO> for StashItemCursor {
+    type Error = StashItemsQueryParseError;
+
+    fn try_from(dto: StashItemCursorDTO) -> Result<Self, Self::Error> {
+        let (key, id) = dto
+            .0
+            .split_once('.')
+            .ok_or(StashItemsQueryParseError::InvalidCursor)?;
+
+        if key.len() % 2 != 0 || !key.is_ascii() {
+            return Err(StashItemsQueryParseError::InvalidCursor);
+        }
+        let key = (0..key.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&key[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| StashItemsQueryParseError::InvalidCursor)?;
+
+        Ok(StashItemCursor::new(
+            String::from_utf8(key).map_err(|_| StashItemsQueryParseError::InvalidCursor)?,
+            id.parse()
+                .map_err(|_| StashItemsQueryParseError::InvalidCursor)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = StashItemCursor::new("Smør, 500 g".to_string(), Uuid::new_v4());
+
+        let dto = StashItemCursorDTO::from(&cursor);
+
+        assert!(dto
+            .0
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-'));
+        assert_eq!(StashItemCursor::try_from(dto), Ok(cursor));
+    }
+
+    #[test]
+    fn test_cursor_invalid() {
+        for cursor in [
+            "",
+            "2023",
+            "zz.67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "32.nope",
+        ] {
+            assert_eq!(
+                StashItemCursor::try_from(StashItemCursorDTO(cursor.to_string())),
+                Err(StashItemsQueryParseError::InvalidCursor)
+            );
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::application::queries::{StashItemPage, StashItemView};
+
+use super::StashItemCursorDTO;
+
+/// DTO for a page of stash items
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StashItemPageDTO {
+    pub stash_items: Vec<StashItemViewDTO>,
+    /// Cursor to pass to get the next page. Left out on the last page
+    pub next_cursor: Option<StashItemCursorDTO>,
+}
+
+/// DTO for a stash item along with the product it belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StashItemViewDTO {
+    pub product_id: String,
+    pub brand: String,
+    pub name: String,
+    pub unit: String,
+    pub id: String,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub quantity: Decimal,
+    pub expiry_date: Option<String>,
+    pub expiry_date_kind: String,
+    pub opened_on: Option<String>,
+    /// Date the stash item actually expires, taking opening into account
+    pub effective_expiry_date: Option<String>,
+}
+
+impl From<StashItemPage> for StashItemPageDTO {
+    fn from(page: StashItemPage) -> Self {
+        Self {
+            next_cursor: page.next_cursor().as_ref().map(StashItemCursorDTO::from),
+            stash_items: page
+                .stash_items()
+                .iter()
+                .map(StashItemViewDTO::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&StashItemView> for StashItemViewDTO {
+    fn from(view: &StashItemView) -> Self {
+        let stash_item = view.stash_item();
+
+        Self {
+            product_id: view.product_id().to_string(),
+            brand: view.brand().to_string(),
+            name: view.name().clone(),
+            unit: view.unit().to_string(),
+            id: stash_item.id().to_string(),
+            quantity: stash_item.quantity().value(),
+            expiry_date: stash_item.expiry_date().map(|date| date.to_string()),
+            expiry_date_kind: stash_item.expiry_date_kind().to_string(),
+            opened_on: stash_item.opened_on().map(|date| date.to_string()),
+            effective_expiry_date: view.effective_expiry_date().map(|date| date.to_string()),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    application::queries::{StashItemCursor, StashItemFilter, StashItemSort},
+    interfaces::web::v1::errors::StashItemsQueryParseError,
+};
+
+use super::StashItemCursorDTO;
+
+/// Number of stash items on a page when no limit is given
+const DEFAULT_LIMIT: usize = 50;
+/// Largest number of stash items a page may have
+const MAX_LIMIT: usize = 500;
+
+/// Query parameters for listing stash items
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StashItemsQueryDTO {
+    /// Only include stash items of the product with this ID or barcode
+    pub product_id: Option<String>,
+    /// Only include stash items of products of this brand
+    pub brand: Option<String>,
+    /// Only include stash items expiring on or after this date
+    pub after: Option<String>,
+    /// Only include stash items expiring before this date
+    pub before: Option<String>,
+    /// Order of the stash items. One of `expiry_date` (default), `name` and `brand`
+    pub sort: Option<String>,
+    /// Cursor from the previous page
+    pub cursor: Option<StashItemCursorDTO>,
+    /// Maximum number of stash items on the page
+    pub limit: Option<usize>,
+}
+
+/// A parsed [`StashItemsQueryDTO`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashItemsQuery {
+    pub filter: StashItemFilter,
+    pub sort: StashItemSort,
+    pub cursor: Option<StashItemCursor>,
+    pub limit: usize,
+}
+
+impl TryFrom<StashItemsQueryDTO> for StashItemsQuery {
+    type Error = StashItemsQueryParseError;
+
+    fn try_from(dto: StashItemsQueryDTO) -> Result<Self, Self::Error> {
+        let parse_date = |date: Option<String>| {
+            date.map(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d"))
+                .transpose()
+                .map_err(|_| StashItemsQueryParseError::InvalidDate)
+        };
+
+        let filter = StashItemFilter {
+            product_id: dto.product_id.map(|id| id.parse()).transpose()?,
+            brand: dto.brand.map(|brand| brand.parse()).transpose()?,
+            expiring_after: parse_date(dto.after)?,
+            expiring_before: parse_date(dto.before)?,
+        };
+
+        let sort = match dto.sort.as_deref() {
+            None | Some("expiry_date") => StashItemSort::ExpiryDate,
+            Some("name") => StashItemSort::Name,
+            Some("brand") => StashItemSort::Brand,
+            Some(_) => return Err(StashItemsQueryParseError::UnknownSort),
+        };
+
+        let limit = dto.limit.unwrap_or(DEFAULT_LIMIT);
+        if limit == 0 || limit > MAX_LIMIT {
+            return Err(StashItemsQueryParseError::InvalidLimit);
+        }
+
+        Ok(Self {
+            filter,
+            sort,
+            cursor: dto.cursor.map(StashItemCursor::try_from).transpose()?,
+            limit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_query_defaults() {
+        let query = StashItemsQuery::try_from(StashItemsQueryDTO::default()).unwrap();
+
+        assert_eq!(
+            query,
+            StashItemsQuery {
+                filter: StashItemFilter::default(),
+                sort: StashItemSort::ExpiryDate,
+                cursor: None,
+                limit: DEFAULT_LIMIT,
+            }
+        );
+    }
+
+    #[test]
+    fn test_query() {
+        let cursor = StashItemCursor::new("Milk".to_string(), Uuid::new_v4());
+        let dto = StashItemsQueryDTO {
+            product_id: Some("7038010000737".to_string()),
+            brand: Some("Tine".to_string()),
+            after: Some("2023-11-01".to_string()),
+            before: Some("2023-12-01".to_string()),
+            sort: Some("name".to_string()),
+            cursor: Some(StashItemCursorDTO::from(&cursor)),
+            limit: Some(10),
+        };
+
+        let query = StashItemsQuery::try_from(dto).unwrap();
+
+        assert_eq!(
+            query,
+            StashItemsQuery {
+                filter: StashItemFilter {
+                    product_id: Some("7038010000737".parse().unwrap()),
+                    brand: Some("Tine".parse().unwrap()),
+                    expiring_after: NaiveDate::from_ymd_opt(2023, 11, 1),
+                    expiring_before: NaiveDate::from_ymd_opt(2023, 12, 1),
+                },
+                sort: StashItemSort::Name,
+                cursor: Some(cursor),
+                limit: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_invalid() {
+        let invalid = [
+            (
+                StashItemsQueryDTO {
+                    after: Some("yesterday".to_string()),
+                    ..Default::default()
+                },
+                StashItemsQueryParseError::InvalidDate,
+            ),
+            (
+                StashItemsQueryDTO {
+                    sort: Some("quantity".to_string()),
+                    ..Default::default()
+                },
+                StashItemsQueryParseError::UnknownSort,
+            ),
+            (
+                StashItemsQueryDTO {
+                    limit: Some(0),
+                    ..Default::default()
+                },
+                StashItemsQueryParseError::InvalidLimit,
+            ),
+            (
+                StashItemsQueryDTO {
+                    limit: Some(MAX_LIMIT + 1),
+                    ..Default::default()
+                },
+                StashItemsQueryParseError::InvalidLimit,
+            ),
+        ];
+
+        for (dto, error) in invalid {
+            assert_eq!(StashItemsQuery::try_from(dto), Err(error));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::value_objects::TrashEntry;
+
+use super::{ProductDTO, StashItemDTO};
+
+/// DTO for something in the trash. Either `product` or `stash_item` is set, depending on the kind of the entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashEntryDTO {
+    /// ID to restore the entry by
+    pub id: String,
+    /// Either `product` or `stash_item`
+    pub kind: String,
+    /// When the entry was deleted, in UTC
+    pub deleted_at: String,
+    /// ID of the product, or of the product the stash item belongs to
+    pub product_id: String,
+    pub product: Option<ProductDTO>,
+    pub stash_item: Option<StashItemDTO>,
+}
+
+impl From<TrashEntry> for TrashEntryDTO {
+    fn from(entry: TrashEntry) -> Self {
+        let id = entry.id();
+        let deleted_at = entry
+            .deleted_at()
+            .and_utc()
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        match entry {
+            TrashEntry::Product { product, .. } => Self {
+                id,
+                kind: "product".to_string(),
+                deleted_at,
+                product_id: product.id().to_string(),
+                product: Some(ProductDTO::from(product)),
+                stash_item: None,
+            },
+            TrashEntry::StashItem {
+                product_id,
+                stash_item,
+                ..
+            } => Self {
+                id,
+                kind: "stash_item".to_string(),
+                deleted_at,
+                product_id: product_id.to_string(),
+                product: None,
+                stash_item: Some(StashItemDTO::from(stash_item)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::domain::entities::{FakeProduct, FakeStashItem};
+
+    use super::*;
+
+    #[test]
+    fn test_from_stash_item_entry() {
+        let stash_item = FakeStashItem::new().build();
+        let entry = TrashEntry::StashItem {
+            product_id: "7038010000737".parse().unwrap(),
+            stash_item: stash_item.clone(),
+            deleted_at: NaiveDate::from_ymd_opt(2023, 11, 9)
+                .unwrap()
+                .and_hms_opt(18, 30, 0)
+                .unwrap(),
+        };
+
+        assert_eq!(
+            TrashEntryDTO::from(entry),
+            TrashEntryDTO {
+                id: stash_item.id().to_string(),
+                kind: "stash_item".to_string(),
+                deleted_at: "2023-11-09T18:30:00Z".to_string(),
+                product_id: "7038010000737".to_string(),
+                product: None,
+                stash_item: Some(StashItemDTO::from(stash_item)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_product_entry() {
+        let product = FakeProduct::new()
+            .with_stash_items(vec![FakeStashItem::new().build()])
+            .build();
+        let entry = TrashEntry::Product {
+            product: product.clone(),
+            deleted_at: NaiveDate::from_ymd_opt(2023, 11, 9)
+                .unwrap()
+                .and_hms_opt(18, 30, 0)
+                .unwrap(),
+        };
+
+        let dto = TrashEntryDTO::from(entry);
+
+        assert_eq!(dto.id, product.id().to_string());
+        assert_eq!(dto.kind, "product");
+        assert_eq!(dto.product_id, product.id().to_string());
+        assert_eq!(dto.product, Some(ProductDTO::from(product)));
+        assert_eq!(dto.stash_item, None);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::domain::errors::{ProductIdError, QuantityError, UnitError};
+
+use super::{ProductParseError, StashItemParseError};
+
+/// Errors that can occur when parsing a BatchOperation from a BatchOperationDTO
+#[derive(Debug, PartialEq, Eq)]
+pub enum BatchOperationParseError {
+    /// Parsing the product to create failed
+    ProductParseError(ProductParseError),
+    /// Parsing the stash item to add or update failed
+    StashItemParseError(StashItemParseError),
+    /// Parsing the product ID failed
+    ProductIdError(ProductIdError),
+    /// Parsing the stash item ID failed
+    StashItemIdError(uuid::Error),
+    /// Parsing the amount to consume failed
+    QuantityError(QuantityError),
+    /// Parsing the unit of the amount to consume failed
+    UnitError(UnitError),
+}
+
+impl std::fmt::Display for BatchOperationParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ProductParseError(error) => write!(f, "Invalid product: {}", error),
+            Self::StashItemParseError(error) => write!(f, "Invalid stash item: {}", error),
+            Self::ProductIdError(error) => write!(f, "Invalid product id: {}", error),
+            Self::StashItemIdError(error) => write!(f, "Invalid stash item id: {}", error),
+            Self::QuantityError(error) => write!(f, "Invalid amount: {}", error),
+            Self::UnitError(error) => write!(f, "Invalid unit: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for BatchOperationParseError {}
+
+impl From<ProductParseError> for BatchOperationParseError {
+    fn from(error: ProductParseError) -> Self {
+        Self::ProductParseError(error)
+    }
+}
+
+impl From<StashItemParseError> for BatchOperationParseError {
+    fn from(error: StashItemParseError) -> Self {
+        Self::StashItemParseError(error)
+    }
+}
+
+impl From<ProductIdError> for BatchOperationParseError {
+    fn from(error: ProductIdError) -> Self {
+        Self::ProductIdError(error)
+    }
+}
+
+impl From<uuid::Error> for BatchOperationParseError {
+    fn from(error: uuid::Error) -> Self {
+        Self::StashItemIdError(error)
+    }
+}
+
+impl From<QuantityError> for BatchOperationParseError {
+    fn from(error: QuantityError) -> Self {
+        Self::QuantityError(error)
+    }
+}
+
+impl From<UnitError> for BatchOperationParseError {
+    fn from(error: UnitError) -> Self {
+        Self::UnitError(error)
+    }
+}
@@ -0,0 +1,19 @@
+/// Errors that can occur when parsing the query for expiring stash items
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExpiringQueryParseError {
+    /// A date is not on the form YYYY-MM-DD
+    InvalidDate,
+    /// Both `before` and `within_days` are given
+    ConflictingBounds,
+}
+
+impl std::fmt::Display for ExpiringQueryParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDate => write!(f, "Invalid date format. Date must be on form YYYY-MM-DD"),
+            Self::ConflictingBounds => write!(f, "Only one of before and within_days can be given"),
+        }
+    }
+}
+
+impl std::error::Error for ExpiringQueryParseError {}
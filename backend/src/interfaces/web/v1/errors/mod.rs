@@ -1,5 +1,11 @@
+mod batch_operation_parse_error;
+mod expiring_query_parse_error;
 mod product_parse_error;
 mod stash_item_parse_error;
+mod stash_items_query_parse_error;
 
+pub use batch_operation_parse_error::BatchOperationParseError;
+pub use expiring_query_parse_error::ExpiringQueryParseError;
 pub use product_parse_error::ProductParseError;
 pub use stash_item_parse_error::StashItemParseError;
+pub use stash_items_query_parse_error::StashItemsQueryParseError;
@@ -1,4 +1,4 @@
-use crate::domain::errors::{BrandError, ProductIdError};
+use crate::domain::errors::{BrandError, ProductIdError, StashItemExistsError, UnitError};
 
 use super::StashItemParseError;
 
@@ -9,8 +9,12 @@ pub enum ProductParseError {
     ProductIdError(ProductIdError),
     /// Parsing the brand failed
     BrandError(BrandError),
+    /// Parsing the unit failed
+    UnitError(UnitError),
     /// Parsing stash items failed
     StashItemParseError(StashItemParseError),
+    /// Two of the stash items have the same ID or the same dates
+    StashItemExists(StashItemExistsError),
 }
 
 impl std::fmt::Display for ProductParseError {
@@ -18,7 +22,9 @@ impl std::fmt::Display for ProductParseError {
         match self {
             Self::ProductIdError(error) => error.fmt(f),
             Self::BrandError(error) => error.fmt(f),
+            Self::UnitError(error) => error.fmt(f),
             Self::StashItemParseError(error) => error.fmt(f),
+            Self::StashItemExists(error) => error.fmt(f),
         }
     }
 }
@@ -37,8 +43,20 @@ impl From<BrandError> for ProductParseError {
     }
 }
 
+impl From<UnitError> for ProductParseError {
+    fn from(error: UnitError) -> Self {
+        Self::UnitError(error)
+    }
+}
+
 impl From<StashItemParseError> for ProductParseError {
     fn from(error: StashItemParseError) -> Self {
         Self::StashItemParseError(error)
     }
 }
+
+impl From<StashItemExistsError> for ProductParseError {
+    fn from(error: StashItemExistsError) -> Self {
+        Self::StashItemExists(error)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::domain::errors::{ProductIdError, QuantityError};
+use crate::domain::errors::{ExpiryDateKindError, ProductIdError, QuantityError};
 
 /// Errors that can occur when parsing a StashItem from a StashItemDTO
 #[derive(Debug, PartialEq, Eq)]
@@ -11,6 +11,8 @@ pub enum StashItemParseError {
     QuantityError(QuantityError),
     /// Parsing the expiry date failed
     ExpiryDateError(chrono::ParseError),
+    /// Parsing the expiry date kind failed
+    ExpiryDateKindError(ExpiryDateKindError),
 }
 
 impl std::fmt::Display for StashItemParseError {
@@ -20,6 +22,7 @@ impl std::fmt::Display for StashItemParseError {
             Self::ProductIdError(error) => error.fmt(f),
             Self::QuantityError(error) => error.fmt(f),
             Self::ExpiryDateError(error) => write!(f, "Expiry date error: {}", error),
+            Self::ExpiryDateKindError(error) => error.fmt(f),
         }
     }
 }
@@ -49,3 +52,9 @@ impl From<chrono::ParseError> for StashItemParseError {
         Self::ExpiryDateError(error)
     }
 }
+
+impl From<ExpiryDateKindError> for StashItemParseError {
+    fn from(error: ExpiryDateKindError) -> Self {
+        Self::ExpiryDateKindError(error)
+    }
+}
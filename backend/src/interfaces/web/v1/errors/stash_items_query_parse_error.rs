@@ -0,0 +1,45 @@
+use crate::domain::errors::{BrandError, ProductIdError};
+
+/// Errors that can occur when parsing the query for listing stash items
+#[derive(Debug, PartialEq, Eq)]
+pub enum StashItemsQueryParseError {
+    /// Parsing the product ID failed
+    ProductIdError(ProductIdError),
+    /// Parsing the brand failed
+    BrandError(BrandError),
+    /// A date is not on the form YYYY-MM-DD
+    InvalidDate,
+    /// The sort order is not one of the known ones
+    UnknownSort,
+    /// The cursor is not one given out with a page
+    InvalidCursor,
+    /// The limit is zero or too large
+    InvalidLimit,
+}
+
+impl std::fmt::Display for StashItemsQueryParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ProductIdError(error) => error.fmt(f),
+            Self::BrandError(error) => error.fmt(f),
+            Self::InvalidDate => write!(f, "Invalid date format. Date must be on form YYYY-MM-DD"),
+            Self::UnknownSort => write!(f, "Unknown sort. Must be expiry_date, name or brand"),
+            Self::InvalidCursor => write!(f, "Invalid cursor"),
+            Self::InvalidLimit => write!(f, "Invalid limit"),
+        }
+    }
+}
+
+impl std::error::Error for StashItemsQueryParseError {}
+
+impl From<ProductIdError> for StashItemsQueryParseError {
+    fn from(error: ProductIdError) -> Self {
+        Self::ProductIdError(error)
+    }
+}
+
+impl From<BrandError> for StashItemsQueryParseError {
+    fn from(error: BrandError) -> Self {
+        Self::BrandError(error)
+    }
+}
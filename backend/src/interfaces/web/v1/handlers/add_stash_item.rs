@@ -6,6 +6,8 @@ use crate::{
     interfaces::web::v1::dtos::StashItemDTO,
 };
 
+use super::OPERATION_ID_HEADER;
+
 pub async fn add_stash_item(
     product_service: web::Data<ProductService>,
     path: web::Path<String>,
@@ -21,9 +23,14 @@ pub async fn add_stash_item(
         Err(err) => return HttpResponse::BadRequest().body(format!("Invalid stash item: {}", err)),
     };
 
-    match product_service.add_stash_item(&product_id, stash_item) {
+    match product_service
+        .add_stash_item(&product_id, stash_item)
+        .await
+    {
         // TODO Return 201 Created and the stash item
-        Ok(_) => HttpResponse::NoContent().finish(),
+        Ok(operation_id) => HttpResponse::NoContent()
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
+            .finish(),
         Err(ProductRepositoryError::ProductNotFound) => {
             HttpResponse::NotFound().body("Product not found")
         }
@@ -0,0 +1,37 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::{services::ProductService, use_cases::AttachBarcode},
+    domain::{errors::ProductRepositoryError, value_objects::ProductId},
+    interfaces::web::v1::dtos::{BarcodeDTO, ProductDTO},
+};
+
+pub async fn attach_barcode(
+    product_service: web::Data<ProductService>,
+    path: web::Path<String>,
+    barcode_dto: web::Json<BarcodeDTO>,
+) -> HttpResponse {
+    let product_id = match path.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
+    };
+
+    let barcode = match barcode_dto.into_inner().barcode.parse::<ProductId>() {
+        Ok(barcode) => barcode,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid barcode: {}", err)),
+    };
+
+    match product_service.attach_barcode(&product_id, barcode).await {
+        Ok(product) => HttpResponse::Ok().json(ProductDTO::from(product)),
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Product not found")
+        }
+        Err(ProductRepositoryError::ProductAlreadyExists) => {
+            HttpResponse::Conflict().body("Barcode belongs to another product")
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
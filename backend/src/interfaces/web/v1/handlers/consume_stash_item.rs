@@ -0,0 +1,75 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    application::{services::ProductService, use_cases::ConsumeStashItem},
+    domain::{
+        errors::ProductRepositoryError,
+        value_objects::{ProductId, Quantity, Unit},
+    },
+    interfaces::web::v1::dtos::{ConsumeStashItemDTO, StashItemDTO},
+};
+
+use super::OPERATION_ID_HEADER;
+
+pub async fn consume_stash_item(
+    product_service: web::Data<ProductService>,
+    path: web::Path<(String, String)>,
+    consume_dto: web::Json<ConsumeStashItemDTO>,
+) -> HttpResponse {
+    let product_id = match path.0.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
+    };
+
+    let stash_item_id = match Uuid::parse_str(path.1.as_str()) {
+        Ok(stash_item_id) => stash_item_id,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(format!("Invalid stash item id: {}", err))
+        }
+    };
+
+    let consume_dto = consume_dto.into_inner();
+
+    let amount = match Quantity::try_from(consume_dto.amount) {
+        Ok(amount) => amount,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid amount: {}", err)),
+    };
+
+    let unit = match consume_dto
+        .unit
+        .map(|unit| unit.parse::<Unit>())
+        .transpose()
+    {
+        Ok(unit) => unit,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid unit: {}", err)),
+    };
+
+    match product_service
+        .consume_stash_item(&product_id, &stash_item_id, amount, unit)
+        .await
+    {
+        Ok((Some(stash_item), operation_id)) => HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
+            .json(StashItemDTO::from(stash_item)),
+        Ok((None, operation_id)) => HttpResponse::NoContent()
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
+            .finish(),
+        Err(ProductRepositoryError::StashItemNotFound) => {
+            HttpResponse::NotFound().body("Stash item not found")
+        }
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Product not found")
+        }
+        Err(ProductRepositoryError::UnitError(err)) => {
+            HttpResponse::BadRequest().body(format!("Invalid unit: {}", err))
+        }
+        Err(ProductRepositoryError::QuantityError(err)) => {
+            HttpResponse::BadRequest().body(format!("Invalid amount: {}", err))
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
@@ -6,21 +6,28 @@ use actix_web::{
 use crate::{
     application::{services::ProductService, use_cases::CreateProduct},
     domain::{entities::Product, errors::ProductRepositoryError},
-    interfaces::web::v1::dtos::ProductDTO,
+    interfaces::web::v1::{dtos::ProductDTO, errors::ProductParseError},
 };
 
+use super::OPERATION_ID_HEADER;
+
 pub async fn create_product(
     product_service: web::Data<ProductService>,
     product_dto: web::Json<ProductDTO>,
 ) -> HttpResponse {
     let product = match Product::try_from(product_dto.into_inner()) {
         Ok(product) => product,
+        // The stash items are valid on their own, but can not be told apart
+        Err(ProductParseError::StashItemExists(err)) => {
+            return HttpResponse::Conflict().body(format!("{}", err))
+        }
         Err(err) => return HttpResponse::BadRequest().body(format!("{}", err)),
     };
 
-    match product_service.create_product(product) {
-        Ok(product) => HttpResponse::Created()
+    match product_service.create_product(product).await {
+        Ok((product, operation_id)) => HttpResponse::Created()
             .append_header(("Location", format!("/products/{}", product.id())))
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
             .json(ProductDTO::from(product)),
         Err(ProductRepositoryError::ProductAlreadyExists) => {
             HttpResponse::Conflict().body("Product already exists")
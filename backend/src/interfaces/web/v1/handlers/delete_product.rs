@@ -5,6 +5,8 @@ use crate::{
     domain::value_objects::ProductId,
 };
 
+use super::OPERATION_ID_HEADER;
+
 pub async fn delete_product(
     product_service: web::Data<ProductService>,
     path: web::Path<String>,
@@ -14,8 +16,11 @@ pub async fn delete_product(
         Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
     };
 
-    match product_service.delete_product(&product_id) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+    match product_service.delete_product(&product_id).await {
+        Ok(Some(operation_id)) => HttpResponse::NoContent()
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
+            .finish(),
+        Ok(None) => HttpResponse::NoContent().finish(),
         Err(err) => {
             println!("Error: {}", err);
             HttpResponse::InternalServerError().body("Internal server error")
@@ -6,6 +6,8 @@ use crate::{
     domain::{errors::ProductRepositoryError, value_objects::ProductId},
 };
 
+use super::OPERATION_ID_HEADER;
+
 pub async fn delete_stash_item(
     product_service: web::Data<ProductService>,
     path: web::Path<(String, String)>,
@@ -22,8 +24,13 @@ pub async fn delete_stash_item(
         }
     };
 
-    match product_service.delete_stash_item(&product_id, &stash_item_id) {
-        Ok(_) => HttpResponse::NoContent().finish(),
+    match product_service
+        .delete_stash_item(&product_id, &stash_item_id)
+        .await
+    {
+        Ok(operation_id) => HttpResponse::NoContent()
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
+            .finish(),
         Err(ProductRepositoryError::ProductNotFound) => {
             HttpResponse::NotFound().body("Product not found")
         }
@@ -0,0 +1,40 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::{services::ProductService, use_cases::DetachBarcode},
+    domain::{errors::ProductRepositoryError, value_objects::ProductId},
+};
+
+pub async fn detach_barcode(
+    product_service: web::Data<ProductService>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (product_id, barcode) = path.into_inner();
+
+    let product_id = match product_id.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
+    };
+
+    let barcode = match barcode.parse::<ProductId>() {
+        Ok(barcode) => barcode,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid barcode: {}", err)),
+    };
+
+    match product_service.detach_barcode(&product_id, &barcode).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Product not found")
+        }
+        Err(ProductRepositoryError::BarcodeNotFound) => {
+            HttpResponse::NotFound().body("Barcode not found")
+        }
+        Err(ProductRepositoryError::CannotDetachPrimaryBarcode) => {
+            HttpResponse::Conflict().body("The ID of a product can not be detached from it")
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
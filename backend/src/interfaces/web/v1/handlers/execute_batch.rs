@@ -0,0 +1,71 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::{services::ProductService, use_cases::ExecuteBatch},
+    domain::{
+        errors::{BatchError, ProductRepositoryError},
+        value_objects::BatchOperation,
+    },
+    interfaces::web::v1::dtos::{BatchOperationDTO, BatchResultDTO},
+};
+
+use super::OPERATION_ID_HEADER;
+
+pub async fn execute_batch(
+    product_service: web::Data<ProductService>,
+    operation_dtos: web::Json<Vec<BatchOperationDTO>>,
+) -> HttpResponse {
+    let operation_dtos = operation_dtos.into_inner();
+    let len = operation_dtos.len();
+
+    let mut operations = Vec::with_capacity(len);
+    for (index, operation_dto) in operation_dtos.into_iter().enumerate() {
+        match BatchOperation::try_from(operation_dto) {
+            Ok(operation) => operations.push(operation),
+            Err(err) => {
+                return HttpResponse::BadRequest()
+                    .body(format!("Invalid operation {}: {}", index, err))
+            }
+        }
+    }
+
+    match product_service.execute_batch(operations).await {
+        Ok((products, operation_id)) => {
+            let mut response = HttpResponse::Ok();
+            // An empty batch did nothing, so there is nothing to undo
+            if let Some(operation_id) = operation_id {
+                response.insert_header((OPERATION_ID_HEADER, operation_id.to_string()));
+            }
+
+            response.json(
+                products
+                    .into_iter()
+                    .map(BatchResultDTO::ok)
+                    .collect::<Vec<_>>(),
+            )
+        }
+        Err(BatchError::OperationFailed { index, error }) => {
+            let mut response = match error {
+                ProductRepositoryError::ProductNotFound
+                | ProductRepositoryError::StashItemNotFound => HttpResponse::NotFound(),
+                ProductRepositoryError::ProductAlreadyExists
+                | ProductRepositoryError::StashItemExists
+                | ProductRepositoryError::DuplicateExpiryDateError
+                | ProductRepositoryError::StashItemAlreadyOpened => HttpResponse::Conflict(),
+                ProductRepositoryError::QuantityError(_) | ProductRepositoryError::UnitError(_) => {
+                    HttpResponse::BadRequest()
+                }
+                err => {
+                    println!("Error: {}", err);
+                    return HttpResponse::InternalServerError().body("Internal Server Error");
+                }
+            };
+
+            response.json(BatchResultDTO::failed(len, index, error.to_string()))
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
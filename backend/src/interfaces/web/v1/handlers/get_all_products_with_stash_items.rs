@@ -8,7 +8,7 @@ use crate::{
 pub async fn get_all_products_with_stash_items(
     product_service: web::Data<ProductService>,
 ) -> HttpResponse {
-    match product_service.get_all_products_with_stash_items() {
+    match product_service.get_all_products_with_stash_items().await {
         Ok(products) => HttpResponse::Ok().json(
             products
                 .into_iter()
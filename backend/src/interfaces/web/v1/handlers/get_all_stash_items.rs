@@ -0,0 +1,27 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::queries::StashItemQuery,
+    interfaces::web::v1::dtos::{StashItemPageDTO, StashItemsQuery, StashItemsQueryDTO},
+};
+
+pub async fn get_all_stash_items(
+    stash_item_query: web::Data<dyn StashItemQuery>,
+    query: web::Query<StashItemsQueryDTO>,
+) -> HttpResponse {
+    let query = match StashItemsQuery::try_from(query.into_inner()) {
+        Ok(query) => query,
+        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+    };
+
+    match stash_item_query
+        .find_stash_items(&query.filter, query.sort, query.cursor, query.limit)
+        .await
+    {
+        Ok(page) => HttpResponse::Ok().json(StashItemPageDTO::from(page)),
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
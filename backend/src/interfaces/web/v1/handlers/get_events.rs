@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use actix_web::{rt::time::timeout, web, web::Bytes, HttpRequest, HttpResponse};
+use futures_util::stream;
+use serde::Deserialize;
+
+use crate::{
+    application::events::{Delivery, EventBus},
+    interfaces::web::v1::dtos::StashEventDTO,
+};
+
+/// Header clients reconnecting to the event stream tell the ID of the last event they got in
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+
+/// How long to go without sending anything before sending a comment, to keep the connection from timing out
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// ID of the last event seen, for clients which can not set the `Last-Event-ID` header
+    last_event_id: Option<u64>,
+}
+
+/// Streams changes to the stash as server-sent events. Every event has an ID, which the stream can be resumed from.
+/// If events were missed, a `reset` event is sent, telling the client to reload what it displays
+pub async fn get_events(
+    event_bus: web::Data<EventBus>,
+    request: HttpRequest,
+    query: web::Query<EventsQuery>,
+) -> HttpResponse {
+    let last_event_id = match request.headers().get(LAST_EVENT_ID_HEADER) {
+        Some(header) => match header.to_str().ok().and_then(|id| id.parse().ok()) {
+            Some(id) => Some(id),
+            None => return HttpResponse::BadRequest().body("Invalid Last-Event-ID"),
+        },
+        None => query.last_event_id,
+    };
+
+    let subscription = event_bus.subscribe(last_event_id);
+    let events = stream::unfold(subscription, |mut subscription| async move {
+        let message = match timeout(KEEP_ALIVE_INTERVAL, subscription.next()).await {
+            Ok(Some(Delivery::Event(published))) => {
+                let dto = StashEventDTO::from(published.event().clone());
+                match serde_json::to_string(&dto) {
+                    Ok(json) => format!("id: {}\ndata: {}\n\n", published.id(), json),
+                    Err(err) => {
+                        println!("Error: {}", err);
+                        return None;
+                    }
+                }
+            }
+            Ok(Some(Delivery::Missed)) => "event: reset\ndata: {}\n\n".to_string(),
+            Ok(None) => return None,
+            Err(_) => ": keep-alive\n\n".to_string(),
+        };
+
+        Some((
+            Ok::<_, actix_web::Error>(Bytes::from(message)),
+            subscription,
+        ))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
@@ -0,0 +1,28 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::{services::ProductService, use_cases::GetExpiringStashItems},
+    interfaces::web::v1::dtos::{ExpiringStashItemDTO, ExpiringStashItemsQueryDTO},
+};
+
+pub async fn get_expiring_stash_items(
+    product_service: web::Data<ProductService>,
+    query: web::Query<ExpiringStashItemsQueryDTO>,
+) -> HttpResponse {
+    let today = chrono::Local::now().date_naive();
+
+    let (after, before) = match query.interval(today) {
+        Ok(interval) => interval,
+        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+    };
+
+    match product_service.expiring_stash_items(after, before).await {
+        Ok(products) => {
+            HttpResponse::Ok().json(ExpiringStashItemDTO::from_products(&products, today))
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+
+use crate::{
+    application::{services::ProductService, use_cases::GetProductsExpiringInInterval},
+    interfaces::web::v1::dtos::{ExpiryCalendarDTO, ExpiryCalendarQueryDTO},
+};
+
+pub async fn get_expiry_calendar(
+    product_service: web::Data<ProductService>,
+    query: web::Query<ExpiryCalendarQueryDTO>,
+) -> HttpResponse {
+    let query = query.into_inner();
+
+    let parse_date = |date: Option<String>| {
+        date.map(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d"))
+            .transpose()
+    };
+    let (after, before) = match (parse_date(query.after), parse_date(query.before)) {
+        (Ok(after), Ok(before)) => (after, before),
+        _ => {
+            return HttpResponse::BadRequest()
+                .body("Invalid date format. Date must be on form YYYY-MM-DD")
+        }
+    };
+
+    match product_service
+        .products_expiring_in_interval(after, before)
+        .await
+    {
+        Ok(products) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .body(
+                ExpiryCalendarDTO::new(&products, after, before, chrono::Utc::now().naive_utc())
+                    .to_string(),
+            ),
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
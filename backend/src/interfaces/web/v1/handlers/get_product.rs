@@ -15,7 +15,7 @@ pub async fn get_product(
         Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
     };
 
-    match product_service.get_product(&product_id) {
+    match product_service.get_product(&product_id).await {
         Ok(None) => HttpResponse::NotFound().body("Product not found"),
         Ok(Some(product)) => {
             let product_dto = ProductDTO::from(product);
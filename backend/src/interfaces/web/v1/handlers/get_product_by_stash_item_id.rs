@@ -14,7 +14,9 @@ pub async fn get_product_by_stash_item_id(
         Ok(id) => id,
         Err(_) => return HttpResponse::BadRequest().finish(),
     };
-    let product = product_service.get_product_by_stash_item_id(&stash_item_id);
+    let product = product_service
+        .get_product_by_stash_item_id(&stash_item_id)
+        .await;
 
     match product {
         Ok(None) => HttpResponse::NotFound().finish(),
@@ -18,7 +18,7 @@ pub async fn get_products_expiring_before(
         }
     };
 
-    match product_service.products_expiring_before(date) {
+    match product_service.products_expiring_before(date).await {
         Ok(products) => HttpResponse::Ok().json(
             products
                 .into_iter()
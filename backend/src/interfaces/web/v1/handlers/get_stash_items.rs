@@ -15,7 +15,7 @@ pub async fn get_stash_items(
         Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
     };
 
-    match product_service.get_stash_items(&product_id) {
+    match product_service.get_stash_items(&product_id).await {
         Ok(stash_items) => HttpResponse::Ok().json(
             stash_items
                 .into_iter()
@@ -0,0 +1,21 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::{services::ProductService, use_cases::GetTrash},
+    interfaces::web::v1::dtos::TrashEntryDTO,
+};
+
+pub async fn get_trash(product_service: web::Data<ProductService>) -> HttpResponse {
+    match product_service.get_trash().await {
+        Ok(entries) => HttpResponse::Ok().json(
+            entries
+                .into_iter()
+                .map(TrashEntryDTO::from)
+                .collect::<Vec<_>>(),
+        ),
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::{services::ProductService, use_cases::MergeProducts},
+    domain::{errors::ProductRepositoryError, value_objects::ProductId},
+    interfaces::web::v1::dtos::ProductDTO,
+};
+
+pub async fn merge_products(
+    product_service: web::Data<ProductService>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let source_id = match path.0.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
+    };
+
+    let target_id = match path.1.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(format!("Invalid target product id: {}", err))
+        }
+    };
+
+    match product_service.merge_products(&source_id, &target_id).await {
+        Ok(product) => HttpResponse::Ok().json(ProductDTO::from(product)),
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Product not found")
+        }
+        Err(ProductRepositoryError::StashItemExists) => {
+            HttpResponse::Conflict().body("Stash item already exists")
+        }
+        Err(ProductRepositoryError::QuantityError(err)) => {
+            HttpResponse::BadRequest().body(format!("Invalid quantity: {}", err))
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
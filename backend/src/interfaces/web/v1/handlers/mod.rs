@@ -1,23 +1,58 @@
 mod add_stash_item;
+mod attach_barcode;
+mod consume_stash_item;
 mod create_product;
 mod delete_product;
 mod delete_stash_item;
+mod detach_barcode;
+mod execute_batch;
 mod get_all_products_with_stash_items;
+mod get_all_stash_items;
+mod get_events;
+mod get_expiring_stash_items;
+mod get_expiry_calendar;
 mod get_product;
 mod get_product_by_stash_item_id;
 mod get_products_expiring_before;
+mod get_products_unsafe_before;
 mod get_stash_items;
+mod get_trash;
+mod merge_products;
+mod move_stash_item;
+mod open_stash_item;
+mod restore_from_trash;
+mod split_stash_item;
+mod undo_operation;
 mod update_product;
 mod update_stash_item;
 
 pub use add_stash_item::add_stash_item;
+pub use attach_barcode::attach_barcode;
+pub use consume_stash_item::consume_stash_item;
 pub use create_product::create_product;
 pub use delete_product::delete_product;
 pub use delete_stash_item::delete_stash_item;
+pub use detach_barcode::detach_barcode;
+pub use execute_batch::execute_batch;
 pub use get_all_products_with_stash_items::get_all_products_with_stash_items;
+pub use get_all_stash_items::get_all_stash_items;
+pub use get_events::get_events;
+pub use get_expiring_stash_items::get_expiring_stash_items;
+pub use get_expiry_calendar::get_expiry_calendar;
 pub use get_product::get_product;
 pub use get_product_by_stash_item_id::get_product_by_stash_item_id;
 pub use get_products_expiring_before::get_products_expiring_before;
+pub use get_products_unsafe_before::get_products_unsafe_before;
 pub use get_stash_items::get_stash_items;
+pub use get_trash::get_trash;
+pub use merge_products::merge_products;
+pub use move_stash_item::move_stash_item;
+pub use open_stash_item::open_stash_item;
+pub use restore_from_trash::restore_from_trash;
+pub use split_stash_item::split_stash_item;
+pub use undo_operation::undo_operation;
 pub use update_product::update_product;
 pub use update_stash_item::update_stash_item;
+
+/// Header the ID of an operation is returned in by the endpoints making undoable changes, to undo them by
+pub const OPERATION_ID_HEADER: &str = "X-Operation-Id";
@@ -0,0 +1,54 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    application::{services::ProductService, use_cases::MoveStashItem},
+    domain::{errors::ProductRepositoryError, value_objects::ProductId},
+    interfaces::web::v1::dtos::{MoveStashItemDTO, StashItemDTO},
+};
+
+pub async fn move_stash_item(
+    product_service: web::Data<ProductService>,
+    path: web::Path<String>,
+    move_dto: web::Json<MoveStashItemDTO>,
+) -> HttpResponse {
+    let stash_item_id = match Uuid::parse_str(path.as_str()) {
+        Ok(stash_item_id) => stash_item_id,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(format!("Invalid stash item id: {}", err))
+        }
+    };
+
+    let move_dto = move_dto.into_inner();
+
+    let target_product_id = match move_dto.product_id.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
+    };
+
+    match product_service
+        .move_stash_item(&stash_item_id, &target_product_id, move_dto.merge)
+        .await
+    {
+        Ok(stash_item) => HttpResponse::Ok().json(StashItemDTO::from(stash_item)),
+        Err(ProductRepositoryError::StashItemNotFound) => {
+            HttpResponse::NotFound().body("Stash item not found")
+        }
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Product not found")
+        }
+        Err(ProductRepositoryError::StashItemExists) => {
+            HttpResponse::Conflict().body("Stash item already exists")
+        }
+        Err(ProductRepositoryError::DuplicateExpiryDateError) => {
+            HttpResponse::Conflict().body("Duplicate expiry date")
+        }
+        Err(ProductRepositoryError::QuantityError(err)) => {
+            HttpResponse::BadRequest().body(format!("Invalid quantity: {}", err))
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::{
+    application::{services::ProductService, use_cases::OpenStashItem},
+    domain::{errors::ProductRepositoryError, value_objects::ProductId},
+    interfaces::web::v1::dtos::{OpenStashItemDTO, StashItemDTO},
+};
+
+pub async fn open_stash_item(
+    product_service: web::Data<ProductService>,
+    path: web::Path<(String, String)>,
+    open_dto: web::Json<OpenStashItemDTO>,
+) -> HttpResponse {
+    let product_id = match path.0.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
+    };
+
+    let stash_item_id = match Uuid::parse_str(path.1.as_str()) {
+        Ok(stash_item_id) => stash_item_id,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(format!("Invalid stash item id: {}", err))
+        }
+    };
+
+    let opened_on = match open_dto.into_inner().opened_on {
+        Some(opened_on) => match opened_on.parse::<NaiveDate>() {
+            Ok(opened_on) => opened_on,
+            Err(err) => {
+                return HttpResponse::BadRequest().body(format!("Invalid opened date: {}", err))
+            }
+        },
+        None => chrono::Local::now().date_naive(),
+    };
+
+    match product_service
+        .open_stash_item(&product_id, &stash_item_id, opened_on)
+        .await
+    {
+        Ok(stash_item) => HttpResponse::Ok().json(StashItemDTO::from(stash_item)),
+        Err(ProductRepositoryError::StashItemNotFound) => {
+            HttpResponse::NotFound().body("Stash item not found")
+        }
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Product not found")
+        }
+        Err(ProductRepositoryError::StashItemAlreadyOpened) => {
+            HttpResponse::Conflict().body("Stash item is already opened")
+        }
+        Err(ProductRepositoryError::QuantityError(err)) => {
+            HttpResponse::BadRequest().body(format!("Invalid quantity: {}", err))
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
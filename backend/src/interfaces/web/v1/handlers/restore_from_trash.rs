@@ -0,0 +1,27 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    application::{services::ProductService, use_cases::RestoreFromTrash},
+    domain::errors::ProductRepositoryError,
+};
+
+pub async fn restore_from_trash(
+    product_service: web::Data<ProductService>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    match product_service.restore_from_trash(&path).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Nothing with that id in the trash")
+        }
+        Err(ProductRepositoryError::ProductAlreadyExists) => {
+            HttpResponse::Conflict().body("Another product has taken the product id")
+        }
+        Err(ProductRepositoryError::DuplicateExpiryDateError) => HttpResponse::Conflict()
+            .body("The product already has a stash item with the same dates"),
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
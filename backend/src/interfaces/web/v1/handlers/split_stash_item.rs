@@ -0,0 +1,57 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    application::{services::ProductService, use_cases::SplitStashItem},
+    domain::{entities::StashItem, errors::ProductRepositoryError, value_objects::ProductId},
+    interfaces::web::v1::dtos::{ProductDTO, SplitStashItemDTO},
+};
+
+pub async fn split_stash_item(
+    product_service: web::Data<ProductService>,
+    path: web::Path<(String, String)>,
+    split_dto: web::Json<SplitStashItemDTO>,
+) -> HttpResponse {
+    let product_id = match path.0.parse::<ProductId>() {
+        Ok(product_id) => product_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid product id: {}", err)),
+    };
+
+    let stash_item_id = match Uuid::parse_str(path.1.as_str()) {
+        Ok(stash_item_id) => stash_item_id,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(format!("Invalid stash item id: {}", err))
+        }
+    };
+
+    let split = match StashItem::try_from(split_dto.into_inner()) {
+        Ok(stash_item) => stash_item,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid stash item: {}", err)),
+    };
+
+    match product_service
+        .split_stash_item(&product_id, &stash_item_id, split)
+        .await
+    {
+        Ok(product) => HttpResponse::Ok().json(ProductDTO::from(product)),
+        Err(ProductRepositoryError::StashItemNotFound) => {
+            HttpResponse::NotFound().body("Stash item not found")
+        }
+        Err(ProductRepositoryError::ProductNotFound) => {
+            HttpResponse::NotFound().body("Product not found")
+        }
+        Err(ProductRepositoryError::StashItemExists) => {
+            HttpResponse::Conflict().body("Stash item already exists")
+        }
+        Err(ProductRepositoryError::DuplicateExpiryDateError) => {
+            HttpResponse::Conflict().body("Duplicate expiry date")
+        }
+        Err(ProductRepositoryError::QuantityError(err)) => {
+            HttpResponse::BadRequest().body(format!("Invalid quantity: {}", err))
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    application::{services::ProductService, use_cases::UndoOperation},
+    domain::errors::ProductRepositoryError,
+};
+
+pub async fn undo_operation(
+    product_service: web::Data<ProductService>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let operation_id = match Uuid::parse_str(path.as_str()) {
+        Ok(operation_id) => operation_id,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(format!("Invalid operation id: {}", err))
+        }
+    };
+
+    match product_service.undo_operation(&operation_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(ProductRepositoryError::OperationNotFound) => {
+            HttpResponse::NotFound().body("Operation not found")
+        }
+        Err(ProductRepositoryError::UndoConflict) => {
+            HttpResponse::Conflict().body("The operation has been overtaken by later changes")
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
@@ -3,9 +3,11 @@ use actix_web::{web, HttpResponse};
 use crate::{
     application::{services::ProductService, use_cases::UpdateProduct},
     domain::{entities::Product, errors::ProductRepositoryError, value_objects::ProductId},
-    interfaces::web::v1::dtos::ProductDTO,
+    interfaces::web::v1::{dtos::ProductDTO, errors::ProductParseError},
 };
 
+use super::OPERATION_ID_HEADER;
+
 pub async fn update_product(
     product_service: web::Data<ProductService>,
     path: web::Path<String>,
@@ -18,6 +20,10 @@ pub async fn update_product(
 
     let product = match Product::try_from(product_dto.into_inner()) {
         Ok(product) => product,
+        // The stash items are valid on their own, but can not be told apart
+        Err(ProductParseError::StashItemExists(err)) => {
+            return HttpResponse::Conflict().body(format!("{}", err))
+        }
         Err(err) => return HttpResponse::BadRequest().body(format!("{}", err)),
     };
 
@@ -25,8 +31,10 @@ pub async fn update_product(
         return HttpResponse::BadRequest().body("Product id mismatch");
     }
 
-    match product_service.update_product(&product_id, product) {
-        Ok(product) => HttpResponse::Ok().json(ProductDTO::from(product)),
+    match product_service.update_product(&product_id, product).await {
+        Ok((product, operation_id)) => HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
+            .json(ProductDTO::from(product)),
         Err(ProductRepositoryError::ProductNotFound) => {
             HttpResponse::NotFound().body("Product not found")
         }
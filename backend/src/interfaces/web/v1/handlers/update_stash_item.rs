@@ -7,6 +7,8 @@ use crate::{
     interfaces::web::v1::dtos::StashItemDTO,
 };
 
+use super::OPERATION_ID_HEADER;
+
 pub async fn update_stash_item(
     product_service: web::Data<ProductService>,
     path: web::Path<(String, String)>,
@@ -33,8 +35,13 @@ pub async fn update_stash_item(
         return HttpResponse::BadRequest().body("Stash item id does not match");
     }
 
-    match product_service.update_stash_item(&product_id, stash_item) {
-        Ok(stash_item) => HttpResponse::Ok().json(StashItemDTO::from(stash_item)),
+    match product_service
+        .update_stash_item(&product_id, stash_item)
+        .await
+    {
+        Ok((stash_item, operation_id)) => HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, operation_id.to_string()))
+            .json(StashItemDTO::from(stash_item)),
         Err(ProductRepositoryError::StashItemNotFound) => {
             HttpResponse::NotFound().body("Stash item not found")
         }
@@ -0,0 +1,424 @@
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+};
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::PayloadError,
+    http::{Method, StatusCode},
+    web, Error, HttpResponse,
+};
+use futures_util::future::LocalBoxFuture;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    application::{
+        services::IdempotencyService,
+        use_cases::{ClaimIdempotencyKey, RecordIdempotentResponse, ReleaseIdempotencyKey},
+    },
+    domain::value_objects::{IdempotencyClaim, IdempotentResponse},
+};
+
+/// Header clients send a key in to make a request idempotent
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Header marking a response as replayed for a retried request
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// Longest idempotency key accepted
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Middleware making POST, PUT and DELETE requests sent with an idempotency key idempotent. The response to the first
+/// request with a key is recorded, and replayed when the request is retried, while reusing the key for another request
+/// is rejected. Needs an [`IdempotencyService`] in the app data; without one, requests pass through untouched
+pub struct Idempotency;
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = IdempotencyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            if !matches!(*req.method(), Method::POST | Method::PUT | Method::DELETE) {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_boxed_body);
+            }
+
+            let idempotency_service = req.app_data::<web::Data<IdempotencyService>>().cloned();
+            let key = req.headers().get(IDEMPOTENCY_KEY_HEADER).cloned();
+
+            let (Some(idempotency_service), Some(key)) = (idempotency_service, key) else {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_boxed_body);
+            };
+
+            let key = match key.to_str() {
+                Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+                _ => {
+                    return Ok(req
+                        .into_response(HttpResponse::BadRequest().body("Invalid idempotency key")))
+                }
+            };
+
+            // Read the body to fingerprint the request, then put it back for the handler. Reading it as bytes applies
+            // the app's `PayloadConfig`, so bodies over its limit are rejected with 413 before anything is buffered
+            let body = match req.extract::<web::Bytes>().await {
+                Ok(body) => body,
+                Err(err) => return Ok(req.error_response(err)),
+            };
+            let fingerprint = fingerprint(&req, &body);
+            req.set_payload(Payload::Stream {
+                payload: Box::pin(futures_util::stream::once(async move {
+                    Ok::<_, PayloadError>(body)
+                })),
+            });
+
+            let token = match idempotency_service
+                .claim_idempotency_key(&key, &fingerprint)
+                .await
+            {
+                Ok(IdempotencyClaim::Claimed(token)) => token,
+                Ok(IdempotencyClaim::Replay(response)) => {
+                    return Ok(req.into_response(replay(response)))
+                }
+                Ok(IdempotencyClaim::InProgress) => {
+                    return Ok(req.into_response(
+                        HttpResponse::Conflict()
+                            .body("A request with this idempotency key is still being handled"),
+                    ))
+                }
+                Ok(IdempotencyClaim::Mismatch) => {
+                    return Ok(req.into_response(
+                        HttpResponse::UnprocessableEntity()
+                            .body("Idempotency key was already used for another request"),
+                    ))
+                }
+                Err(err) => {
+                    println!("Error: {}", err);
+                    return Ok(req.into_response(
+                        HttpResponse::InternalServerError().body("Internal Server Error"),
+                    ));
+                }
+            };
+
+            let res = match service.call(req).await {
+                Ok(res) => res,
+                Err(err) => {
+                    release(&idempotency_service, &key, &token).await;
+                    return Err(err);
+                }
+            };
+
+            // Server errors may well not happen again, so let the request be retried instead of replaying them
+            if res.status().is_server_error() {
+                release(&idempotency_service, &key, &token).await;
+                return Ok(res.map_into_boxed_body());
+            }
+
+            let (req, res) = res.into_parts();
+            let (res, body) = res.into_parts();
+            let Ok(body) = body::to_bytes(body).await else {
+                release(&idempotency_service, &key, &token).await;
+                return Ok(ServiceResponse::new(
+                    req,
+                    HttpResponse::InternalServerError().body("Internal Server Error"),
+                ));
+            };
+
+            let response = IdempotentResponse::new(
+                res.status().as_u16(),
+                res.headers()
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+                body.to_vec(),
+            );
+            if let Err(err) = idempotency_service
+                .record_idempotent_response(&key, &token, response)
+                .await
+            {
+                println!("Error: {}", err);
+                release(&idempotency_service, &key, &token).await;
+            }
+
+            Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+        })
+    }
+}
+
+/// Fingerprints a request by its method, path, query and body
+fn fingerprint(req: &ServiceRequest, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b" ");
+    hasher.update(
+        req.uri()
+            .path_and_query()
+            .map(|path_and_query| path_and_query.as_str())
+            .unwrap_or_default(),
+    );
+    hasher.update(b"\n");
+    hasher.update(body);
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Rebuilds a recorded response to replay it
+fn replay(response: IdempotentResponse) -> HttpResponse {
+    let status =
+        StatusCode::from_u16(*response.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    let mut builder = HttpResponse::build(status);
+    for (name, value) in response.headers() {
+        builder.append_header((name.as_str(), value.as_str()));
+    }
+
+    builder
+        .insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"))
+        .body(response.body().clone())
+}
+
+/// Releases an idempotency key, so the request it was claimed for can be retried
+async fn release(idempotency_service: &IdempotencyService, key: &str, token: &Uuid) {
+    if let Err(err) = idempotency_service
+        .release_idempotency_key(key, token)
+        .await
+    {
+        println!("Error: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use actix_web::{test, App};
+
+    use crate::{
+        domain::repositories::IdempotencyRepository as IdempotencyRepositoryTrait,
+        infrastructure::persistence::sqlite::{
+            db::setup_db,
+            pool::{create_pool, ConnectionManager},
+            IdempotencyRepository,
+        },
+    };
+
+    use super::*;
+
+    fn idempotency_service() -> web::Data<IdempotencyService> {
+        let pool = create_pool(ConnectionManager::memory(), 1).unwrap();
+        setup_db(&pool.get().unwrap()).unwrap();
+
+        let repository: Box<dyn IdempotencyRepositoryTrait> =
+            Box::new(IdempotencyRepository::new(pool));
+
+        web::Data::new(IdempotencyService::new(
+            Arc::new(repository),
+            Duration::from_secs(60 * 60),
+            Duration::from_secs(60),
+        ))
+    }
+
+    /// Makes an app counting the requests which get through to it, and echoing their bodies
+    macro_rules! app {
+        ($calls:expr) => {{
+            let calls = $calls.clone();
+            test::init_service(
+                App::new()
+                    .app_data(idempotency_service())
+                    .wrap(Idempotency)
+                    .default_service(web::to(move |body: web::Bytes| {
+                        let calls = calls.clone();
+                        async move {
+                            let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                            HttpResponse::Created()
+                                .insert_header(("X-Call", call.to_string()))
+                                .body(body)
+                        }
+                    })),
+            )
+        }};
+    }
+
+    fn post(key: &str, body: &'static str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri("/v1/products")
+            .insert_header((IDEMPOTENCY_KEY_HEADER, key))
+            .set_payload(body)
+    }
+
+    #[actix_web::test]
+    async fn test_retry_is_replayed() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(calls).await;
+
+        let first = test::call_service(&app, post("key", "body").to_request()).await;
+        let retry = test::call_service(&app, post("key", "body").to_request()).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        assert_eq!(retry.headers().get("X-Call").unwrap(), "1");
+        assert_eq!(
+            retry.headers().get(IDEMPOTENT_REPLAYED_HEADER).unwrap(),
+            "true"
+        );
+        assert_eq!(test::read_body(first).await, "body");
+        assert_eq!(test::read_body(retry).await, "body");
+    }
+
+    #[actix_web::test]
+    async fn test_key_reused_for_another_request() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(calls).await;
+
+        test::call_service(&app, post("key", "body").to_request()).await;
+        let reused = test::call_service(&app, post("key", "other").to_request()).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[actix_web::test]
+    async fn test_different_keys_are_handled() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(calls).await;
+
+        test::call_service(&app, post("key", "body").to_request()).await;
+        test::call_service(&app, post("other", "body").to_request()).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_requests_without_key_are_handled() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(calls).await;
+
+        for _ in 0..2 {
+            let req = test::TestRequest::post()
+                .uri("/v1/products")
+                .set_payload("body")
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_get_requests_are_handled() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(calls).await;
+
+        for _ in 0..2 {
+            let req = test::TestRequest::get()
+                .uri("/v1/products/ID")
+                .insert_header((IDEMPOTENCY_KEY_HEADER, "key"))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_get_requests_with_invalid_key_are_handled() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(calls).await;
+
+        let req = test::TestRequest::get()
+            .uri("/v1/products/ID")
+            .insert_header((IDEMPOTENCY_KEY_HEADER, ""))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    #[actix_web::test]
+    async fn test_body_over_payload_limit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = test::init_service(
+            App::new()
+                .app_data(idempotency_service())
+                .app_data(web::PayloadConfig::new(4))
+                .wrap(Idempotency)
+                .default_service(web::to({
+                    let calls = calls.clone();
+                    move || {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        HttpResponse::Created()
+                    }
+                })),
+        )
+        .await;
+
+        let res = test::call_service(&app, post("key", "too long").to_request()).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_key() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app!(calls).await;
+
+        let res = test::call_service(&app, post("", "body").to_request()).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,3 @@
+mod idempotency;
+
+pub use idempotency::{Idempotency, IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
@@ -1,4 +1,5 @@
 pub mod dtos;
 pub mod errors;
 pub mod handlers;
+pub mod middleware;
 pub mod router;
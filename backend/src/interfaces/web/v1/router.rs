@@ -1,36 +1,88 @@
 use actix_web::web;
 
-use super::handlers::{
-    add_stash_item, create_product, delete_product, delete_stash_item,
-    get_all_products_with_stash_items, get_product, get_product_by_stash_item_id,
-    get_products_expiring_before, get_stash_items, update_product, update_stash_item,
+use super::{
+    handlers::{
+        add_stash_item, attach_barcode, consume_stash_item, create_product, delete_product,
+        delete_stash_item, detach_barcode, execute_batch, get_all_products_with_stash_items,
+        get_all_stash_items, get_events, get_expiring_stash_items, get_expiry_calendar,
+        get_product, get_product_by_stash_item_id, get_products_expiring_before,
+        get_products_unsafe_before, get_stash_items, get_trash, merge_products, move_stash_item,
+        open_stash_item, restore_from_trash, split_stash_item, undo_operation, update_product,
+        update_stash_item,
+    },
+    middleware::Idempotency,
 };
 
+/// Largest request body accepted, whether read as JSON or buffered to make a request idempotent
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// Configures the routes of the API. POST, PUT and DELETE requests are made idempotent by sending an idempotency key
+/// along with them
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::PayloadConfig::new(MAX_BODY_SIZE))
+        .app_data(web::JsonConfig::default().limit(MAX_BODY_SIZE));
     cfg.service(
-        web::scope("/v1/products")
-            .route("", web::post().to(create_product))
-            .route(
-                "/with_stash_items",
-                web::get().to(get_all_products_with_stash_items),
-            )
-            .route(
-                "/by_stash_item_id/{stash_item_id}",
-                web::get().to(get_product_by_stash_item_id),
+        web::scope("/v1")
+            .wrap(Idempotency)
+            .service(
+                web::scope("/products")
+                    .route("", web::post().to(create_product))
+                    .route(
+                        "/with_stash_items",
+                        web::get().to(get_all_products_with_stash_items),
+                    )
+                    .route(
+                        "/by_stash_item_id/{stash_item_id}",
+                        web::get().to(get_product_by_stash_item_id),
+                    )
+                    .route(
+                        "/expiring_before/{date}",
+                        web::get().to(get_products_expiring_before),
+                    )
+                    .route(
+                        "/unsafe_before/{date}",
+                        web::get().to(get_products_unsafe_before),
+                    )
+                    .route("/{product_id}", web::get().to(get_product))
+                    .route("/{product_id}", web::put().to(update_product))
+                    .route("/{product_id}", web::delete().to(delete_product))
+                    .route(
+                        "/{product_id}/merge_into/{target_id}",
+                        web::post().to(merge_products),
+                    )
+                    .route("/{product_id}/barcodes", web::post().to(attach_barcode))
+                    .route(
+                        "/{product_id}/barcodes/{barcode}",
+                        web::delete().to(detach_barcode),
+                    )
+                    .service(
+                        web::scope("/{product_id}/stash_items")
+                            .route("", web::post().to(add_stash_item))
+                            .route("", web::get().to(get_stash_items))
+                            .route("/{stash_item_id}", web::put().to(update_stash_item))
+                            .route("/{stash_item_id}", web::delete().to(delete_stash_item))
+                            .route("/{stash_item_id}/split", web::post().to(split_stash_item))
+                            .route("/{stash_item_id}/open", web::post().to(open_stash_item))
+                            .route(
+                                "/{stash_item_id}/consume",
+                                web::post().to(consume_stash_item),
+                            ),
+                    ),
             )
-            .route(
-                "/expiring_before/{date}",
-                web::get().to(get_products_expiring_before),
+            .route("/calendar.ics", web::get().to(get_expiry_calendar))
+            .service(
+                web::scope("/stash_items")
+                    .route("", web::get().to(get_all_stash_items))
+                    .route("/expiring", web::get().to(get_expiring_stash_items))
+                    .route("/{stash_item_id}/move", web::post().to(move_stash_item)),
             )
-            .route("/{product_id}", web::get().to(get_product))
-            .route("/{product_id}", web::put().to(update_product))
-            .route("/{product_id}", web::delete().to(delete_product))
             .service(
-                web::scope("/{product_id}/stash_items")
-                    .route("", web::post().to(add_stash_item))
-                    .route("", web::get().to(get_stash_items))
-                    .route("/{stash_item_id}", web::put().to(update_stash_item))
-                    .route("/{stash_item_id}", web::delete().to(delete_stash_item)),
-            ),
+                web::scope("/trash")
+                    .route("", web::get().to(get_trash))
+                    .route("/{id}/restore", web::post().to(restore_from_trash)),
+            )
+            .route("/events", web::get().to(get_events))
+            .route("/batch", web::post().to(execute_batch))
+            .route("/undo/{operation_id}", web::post().to(undo_operation)),
     );
 }
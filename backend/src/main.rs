@@ -1,53 +1,263 @@
-use std::sync::{Arc, Mutex};
+use std::{sync::Arc, time::Duration};
 
 use actix_web::{web::Data, App, HttpServer};
 use rsstash::{
-    application::services::ProductService,
-    domain::repositories::ProductRepository as ProductRepositoryTrait,
-    infrastructure::persistence::sqlite::{db::setup_db, ProductRepository},
-    interfaces::web::v1::router::configure_routes,
+    application::{
+        events::{EventBus, ProductEventForwarder},
+        queries::StashItemQuery as StashItemQueryTrait,
+        services::{ExpiryNotificationService, IdempotencyService, ProductService},
+    },
+    domain::{
+        events::ProductEventDispatcher,
+        notifiers::Notifier,
+        repositories::{
+            IdempotencyRepository as IdempotencyRepositoryTrait,
+            NotificationRepository as NotificationRepositoryTrait,
+            ProductRepository as ProductRepositoryTrait, UnitOfWork as UnitOfWorkTrait,
+            DEFAULT_UNDO_HISTORY_SIZE,
+        },
+    },
+    infrastructure::{
+        notifiers::{EmailNotifier, PushNotifier, PushService, SmtpSecurity, WebhookNotifier},
+        persistence::sqlite::{
+            db::setup_db,
+            pool::{create_pool, ConnectionManager},
+            IdempotencyRepository, NotificationRepository, ProductRepository, StashItemQuery,
+            UnitOfWork,
+        },
+    },
+    interfaces::{
+        scheduler::{schedule_expiry_notifications, schedule_trash_purge},
+        web::v1::router::configure_routes,
+    },
 };
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Create the database connection
-    let connection = match std::env::var("STASH_DB_PATH") {
+    // Create the database connection pool
+    let manager = match std::env::var("STASH_DB_PATH") {
         Ok(path) => {
             println!("Using database at {}", path);
-            rusqlite::Connection::open(path).unwrap()
+            ConnectionManager::file(path)
         }
         Err(_) => {
             eprintln!("No database path provided, using in-memory database");
-            rusqlite::Connection::open_in_memory().unwrap()
+            ConnectionManager::memory()
         }
     };
+    let pool_size = env_parse("STASH_DB_POOL_SIZE", 8)?;
+    let pool = create_pool(manager, pool_size).unwrap();
 
     // Setup the database
-    setup_db(&connection).unwrap();
+    setup_db(&pool.get().unwrap()).unwrap();
 
-    // Make the connection shareable
-    let shared_connection = Arc::new(Mutex::new(connection));
+    // Changes are published to clients following them, which can resume from any of the most recent events
+    let event_history_size = env_parse("STASH_EVENT_HISTORY_SIZE", 1000)?;
+    let event_bus = Arc::new(EventBus::new(event_history_size));
+
+    // Handlers of what happens to the stash items of products are registered with the dispatcher, which gets the
+    // events once the changes are saved
+    let mut product_event_dispatcher = ProductEventDispatcher::new();
+    product_event_dispatcher.register(Box::new(ProductEventForwarder::new(event_bus.clone())));
+    let product_event_dispatcher = Arc::new(product_event_dispatcher);
 
     // Create the repositories
-    let product_repository: Box<dyn ProductRepositoryTrait> =
-        Box::new(ProductRepository::new(shared_connection.clone()));
+    let product_repository: Box<dyn ProductRepositoryTrait> = Box::new(
+        ProductRepository::new(pool.clone())
+            .with_event_dispatcher(product_event_dispatcher.clone()),
+    );
+    let notification_repository: Box<dyn NotificationRepositoryTrait> =
+        Box::new(NotificationRepository::new(pool.clone()));
+    let idempotency_repository: Box<dyn IdempotencyRepositoryTrait> =
+        Box::new(IdempotencyRepository::new(pool.clone()));
+
+    // The most recent operations are kept in the database along with the changes they undo, so they can still be
+    // undone after a restart
+    let undo_history_size = env_parse("STASH_UNDO_HISTORY_SIZE", DEFAULT_UNDO_HISTORY_SIZE)?;
+    let unit_of_work: Box<dyn UnitOfWorkTrait> = Box::new(
+        UnitOfWork::new(pool.clone())
+            .with_undo_history_size(undo_history_size)
+            .with_event_dispatcher(product_event_dispatcher),
+    );
+
+    // Create the read models
+    let stash_item_query: Arc<dyn StashItemQueryTrait> =
+        Arc::new(StashItemQuery::new(pool.clone()));
 
     // Make the repositories shareable
     let product_repository = Arc::new(product_repository);
+    let notification_repository = Arc::new(notification_repository);
+    let unit_of_work = Arc::new(unit_of_work);
+    let idempotency_repository = Arc::new(idempotency_repository);
 
     // Create the services
-    let product_service = ProductService::new(product_repository.clone());
+    let product_service = Arc::new(ProductService::new(
+        product_repository.clone(),
+        unit_of_work,
+        event_bus.clone(),
+    ));
+
+    // Responses to requests made with an idempotency key are replayed when the requests are retried within the window
+    let idempotency_window_hours: u64 = env_parse("STASH_IDEMPOTENCY_WINDOW_HOURS", 24)?;
+    // A request still being handled after its lease is taken to have never completed, like when the server stopped,
+    // so it can be retried
+    let idempotency_lease_seconds = env_parse("STASH_IDEMPOTENCY_LEASE_SECONDS", 60)?;
+    let idempotency_service = IdempotencyService::new(
+        idempotency_repository,
+        Duration::from_secs(idempotency_window_hours * 60 * 60),
+        Duration::from_secs(idempotency_lease_seconds),
+    );
+
+    // Send expiry notifications in the background, if anywhere to send them is configured
+    let notifiers = notifiers_from_env()?;
+    if notifiers.is_empty() {
+        eprintln!("No notifiers configured, not sending expiry notifications");
+    } else {
+        let expiry_notification_service = ExpiryNotificationService::new(
+            product_repository.clone(),
+            notification_repository.clone(),
+            notifiers,
+            env_list("STASH_NOTIFY_WINDOWS", "1")
+                .iter()
+                .map(|window| {
+                    window
+                        .parse()
+                        .map_err(|err| invalid_env("STASH_NOTIFY_WINDOWS", err))
+                })
+                .collect::<std::io::Result<_>>()?,
+        );
+        let interval = env_parse("STASH_NOTIFY_INTERVAL_SECONDS", 24 * 60 * 60)?;
+
+        schedule_expiry_notifications(
+            Arc::new(expiry_notification_service),
+            Duration::from_secs(interval),
+        );
+    }
+
+    // Purge deleted products and stash items from the trash once they have been there for the retention period
+    let retention_days: u64 = env_parse("STASH_TRASH_RETENTION_DAYS", 30)?;
+    let interval = env_parse("STASH_TRASH_PURGE_INTERVAL_SECONDS", 60 * 60)?;
+    schedule_trash_purge(
+        product_service.clone(),
+        Duration::from_secs(retention_days * 24 * 60 * 60),
+        Duration::from_secs(interval),
+    );
 
     // Create the web server state
-    let product_service = Data::new(product_service);
+    let product_service = Data::from(product_service);
+    let stash_item_query = Data::from(stash_item_query);
+    let idempotency_service = Data::new(idempotency_service);
+    let event_bus = Data::from(event_bus);
 
     // Spin up the web server
     HttpServer::new(move || {
         App::new()
             .app_data(product_service.clone())
+            .app_data(stash_item_query.clone())
+            .app_data(idempotency_service.clone())
+            .app_data(event_bus.clone())
             .configure(configure_routes)
     })
     .bind("0.0.0.0:8080")?
     .run()
     .await
 }
+
+/// Creates the notifiers configured through environment variables
+///
+/// # Returns
+/// The notifiers, or an error naming the variable which is missing or invalid
+fn notifiers_from_env() -> std::io::Result<Vec<Box<dyn Notifier>>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = vec![];
+
+    if let Ok(url) = std::env::var("STASH_WEBHOOK_URL") {
+        println!("Sending expiry notifications to webhook at {}", url);
+        notifiers.push(Box::new(WebhookNotifier::new(url)));
+    }
+
+    if let Ok(url) = std::env::var("STASH_PUSH_URL") {
+        let service = match std::env::var("STASH_PUSH_SERVICE") {
+            Ok(service) => service
+                .parse()
+                .map_err(|err| invalid_env("STASH_PUSH_SERVICE", err))?,
+            Err(_) => PushService::default(),
+        };
+
+        println!(
+            "Sending expiry notifications as push notifications to {}",
+            url
+        );
+        notifiers.push(Box::new(PushNotifier::new(
+            service,
+            url,
+            std::env::var("STASH_PUSH_TOKEN").ok(),
+        )));
+    }
+
+    if let Ok(host) = std::env::var("STASH_SMTP_HOST") {
+        let port = std::env::var("STASH_SMTP_PORT")
+            .ok()
+            .map(|port| port.parse())
+            .transpose()
+            .map_err(|err| invalid_env("STASH_SMTP_PORT", err))?;
+        let security = match std::env::var("STASH_SMTP_SECURITY") {
+            Ok(security) => security
+                .parse()
+                .map_err(|err| invalid_env("STASH_SMTP_SECURITY", err))?,
+            Err(_) => SmtpSecurity::default(),
+        };
+        let credentials = std::env::var("STASH_SMTP_USERNAME")
+            .ok()
+            .zip(std::env::var("STASH_SMTP_PASSWORD").ok());
+        let from = std::env::var("STASH_SMTP_FROM")
+            .map_err(|_| invalid_env("STASH_SMTP_FROM", "required when STASH_SMTP_HOST is set"))?;
+        let email_notifier = EmailNotifier::new(
+            &host,
+            port,
+            security,
+            credentials,
+            &from,
+            &env_list("STASH_SMTP_TO", ""),
+        )
+        .map_err(|err| invalid_env("STASH_SMTP_HOST, STASH_SMTP_FROM or STASH_SMTP_TO", err))?;
+
+        println!("Sending expiry notifications by email through {}", host);
+        notifiers.push(Box::new(email_notifier));
+    }
+
+    Ok(notifiers)
+}
+
+/// Makes the error for an environment variable with an invalid value
+fn invalid_env(name: &str, error: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("Invalid {}: {}", name, error),
+    )
+}
+
+/// Reads a setting from an environment variable, or the default if it is not set
+///
+/// # Returns
+/// The setting, or an error naming the variable if its value is invalid
+fn env_parse<T>(name: &str, default: T) -> std::io::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value.parse().map_err(|err| invalid_env(name, err)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Reads a comma separated list from an environment variable
+fn env_list(name: &str, default: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_else(|_| default.to_string())
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}